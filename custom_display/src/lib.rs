@@ -13,7 +13,6 @@ use syn::{parse_macro_input, Data, DeriveInput, Fields};
 ///
 /// Remember, `OP_BLOCK(_, nested block)`
 /// we are not interested in the nested block
-
 #[proc_macro_derive(CustomDisplay)]
 pub fn custom_display(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
@@ -1,46 +1,59 @@
-use crate::engine::import_resolver::{Import, ImportResolver};
+use crate::engine::bytecode::compile;
+use crate::engine::import_resolver::{ExternVal, Import, ImportResolver, Imports};
 use crate::engine::memory::MemoryInstance;
+use crate::engine::segment::{DataInstance, ElemInstance};
 use crate::engine::store::Store;
 use crate::engine::*;
 use crate::engine::module::Functions;
-use crate::value::Value;
+use crate::value::{Ref, Value};
 use wasm_parser::core::*;
 use wasm_parser::Module;
 
 use crate::engine::module::ModuleInstance;
 use crate::engine::table::TableInstance;
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use validation::const_expr::{eval_const_expr, BinOp, ConstContext};
 
-pub fn allocate(
+pub(crate) fn allocate(
     m: &Module,
     mod_instance: &mut ModuleInstance,
     functions: &Functions,
     store: &mut Store,
-    imports: &[Import],
+    imports: ImportResolver,
+    module_addr: &ModuleAddr,
 ) -> Result<()> {
     debug!("allocate");
 
     // Step 1
     let imports_entries = get_extern_values_in_imports(m);
 
-    let imports = create_import_resolver(&imports_entries, imports)?;
-
     // Step 2a and 6
-    allocate_functions(m, mod_instance, functions, store).context("Allocating function instances failed")?;
-    //TODO host functions
+    allocate_functions(m, mod_instance, functions, store, &imports_entries, &imports, module_addr)
+        .context("Allocating function instances failed")?;
 
     // Step 3a and 7
     allocate_tables(m, mod_instance, store, &imports_entries, &imports)
         .context("Allocating table instances failed")?;
 
     // Step 4a and 8
-    allocate_memories(m, mod_instance, store)?;
+    allocate_memories(m, mod_instance, store, &imports)
+        .context("Allocating memory instances failed")?;
 
     // Step 5a and 9
     allocate_globals(m, mod_instance, store, &imports)
         .context("Allocating global instances failed")?;
 
-    // ... Step 13
+    // The tags of the exception handling proposal
+    allocate_tags(m, mod_instance, store, &imports_entries, &imports)
+        .context("Allocating tag instances failed")?;
+
+    // Step 10a and 13
+    allocate_elements(m, mod_instance, store)
+        .context("Allocating element instances failed")?;
+
+    // Step 11a and 13
+    allocate_data(m, mod_instance, store)
+        .context("Allocating data instances failed")?;
 
     // Step 14.
 
@@ -62,25 +75,39 @@ fn get_extern_values_in_imports(m: &Module) -> Vec<&ImportEntry> {
         .collect()
 }
 
-fn create_import_resolver(_entries: &[&ImportEntry], imports: &[Import]) -> Result<ImportResolver> {
+pub(crate) fn create_import_resolver(imports: Imports) -> Result<ImportResolver> {
     debug!("match imports");
     let mut resolver = ImportResolver::new();
 
-    for entry in imports.iter() {
+    for entry in imports.into_iter() {
         //TODO add more types
         match entry {
+            Import::Function(module, name, ty, func) => {
+                debug!("=> Injecting function import");
+
+                resolver
+                    .inject_function(module.clone(), name.clone(), ty, func)
+                    .with_context(|| format!("Injecting function failed {} {}", module, name))?;
+            }
             Import::Global(module, name, instance) => {
                 debug!("=> Injecting global import");
 
                 resolver
-                    .inject_global(module.clone(), name.clone(), instance)
+                    .inject_global(module.clone(), name.clone(), &instance)
                     .with_context(|| format!("Injecting global failed {} {}", module, name))?;
             }
+            Import::Memory(module, name, instance) => {
+                debug!("=> Injecting memory import");
+
+                resolver
+                    .inject_memory(module.clone(), name.clone(), &instance)
+                    .with_context(|| format!("Injecting memory failed {} {}", module, name))?;
+            }
             Import::Table(module, name, instance) => {
                 debug!("=> Injecting table import");
 
                 resolver
-                    .inject_table(module.clone(), name.clone(), instance)
+                    .inject_table(module.clone(), name.clone(), &instance)
                     .with_context(|| format!("Injecting table failed {} {}", module, name))?;
             }
         }
@@ -94,12 +121,21 @@ fn allocate_functions(
     mod_instance: &mut ModuleInstance,
     functions: &Functions,
     store: &mut Store,
+    imports: &[&ImportEntry],
+    import_resolver: &ImportResolver,
+    module_addr: &ModuleAddr,
 ) -> Result<()> {
     debug!("allocate function");
 
     // Gets all functions and imports
-    let ty = validation::extract::get_funcs(&m);
-    let num_imports = validation::extract::get_imports(&m).len();
+    let ty = validation::extract::get_funcs(m);
+
+    // Only the imported functions are in the function index space
+    let imported_functions: Vec<_> = imports
+        .iter()
+        .filter(|entry| matches!(entry.desc, ImportDesc::Function { .. }))
+        .collect();
+    let num_imports = imported_functions.len();
 
     debug!("functions extracted {:#?}", ty);
 
@@ -115,6 +151,25 @@ fn allocate_functions(
             }
         };
 
+        let host = match imported_functions.get(code_index) {
+            Some(entry) => {
+                // The function is an export of another instance in the store
+                if let Some(ExternVal::Function(addr)) =
+                    import_resolver.resolve_extern(&entry.module_name, &entry.name)
+                {
+                    mod_instance.store_func_addr(*addr)?;
+                    continue;
+                }
+
+                if !import_resolver.contains_function(&entry.module_name, &entry.name) {
+                    bail!("unlinkable: unknown import {} {}", entry.module_name, entry.name);
+                }
+
+                Some(import_resolver.resolve_function(&entry.module_name, &entry.name, fn_sig)?)
+            }
+            None => None,
+        };
+
         let code_index = code_index as isize - num_imports as isize;
 
         let code = {
@@ -134,23 +189,9 @@ fn allocate_functions(
                 let mut returns_const: Vec<_> = Vec::new();
 
                 for param_ty in fn_sig.param_types.iter() {
-                    params.push(match param_ty {
-                        ValueType::I32 => LocalEntry {
-                            count: 1,
-                            ty: ValueType::I32,
-                        },
-                        ValueType::I64 => LocalEntry {
-                            count: 1,
-                            ty: ValueType::I64,
-                        },
-                        ValueType::F32 => LocalEntry {
-                            count: 1,
-                            ty: ValueType::F32,
-                        },
-                        ValueType::F64 => LocalEntry {
-                            count: 1,
-                            ty: ValueType::F64,
-                        },
+                    params.push(LocalEntry {
+                        count: 1,
+                        ty: *param_ty,
                     });
                 }
 
@@ -160,6 +201,8 @@ fn allocate_functions(
                         ValueType::I64 => Instruction::OP_I64_CONST(0),
                         ValueType::F32 => Instruction::OP_F32_CONST(0.0),
                         ValueType::F64 => Instruction::OP_F64_CONST(0.0),
+                        ValueType::V128 => Instruction::OP_V128_CONST(0),
+                        ValueType::FuncRef | ValueType::ExternRef => Instruction::OP_REF_NULL(*ret_ty),
                     });
                 }
 
@@ -172,7 +215,10 @@ fn allocate_functions(
             }
         };
 
-        store.allocate_func_instance(fn_sig.clone(), fcode);
+        let bytecode = compile(&fcode, fn_sig, mod_instance)
+            .with_context(|| format!("Compiling function {} failed", code_index))?;
+
+        store.allocate_func_instance(fn_sig.clone(), fcode, bytecode, host, module_addr.clone());
 
         let addr = FuncAddr::new(store.count_functions() - 1);
         mod_instance.store_func_addr(addr)?;
@@ -190,15 +236,44 @@ fn allocate_tables(
 ) -> Result<()> {
     debug!("allocate tables");
 
-    // Gets all tables and imports
-    //let ty = validation::extract::get_tables(&m);
-    let ty = validation::extract::get_defined_tables(&m);
+    // Imported tables come first in the index space
+    for entry in imports {
+        if let ImportDesc::Table { ty } = &entry.desc {
+            if let Some(ExternVal::Table(addr)) =
+                import_resolver.resolve_extern(&entry.module_name, &entry.name)
+            {
+                mod_instance.store_table_addr(addr.clone())?;
+                continue;
+            }
+
+            let instance = import_resolver.resolve_table(&entry.module_name, &entry.name)?;
+            debug!("table {:#?}", instance);
+
+            if instance.ty != ty.element_type {
+                return Err(anyhow!(
+                    "Table {} {} has the type {:?}, but {:?} is expected",
+                    entry.module_name,
+                    entry.name,
+                    instance.ty,
+                    ty.element_type
+                ));
+            }
+
+            let addr = TableAddr::new(store.tables.len());
+            mod_instance.store_table_addr(addr)?;
+            store.tables.push(instance);
+        }
+    }
+
+    // Gets all defined tables
+    let ty = validation::extract::get_defined_tables(m);
 
     for t in ty.iter() {
         debug!("table {:#?}", t);
         let instance = match t.limits {
-            Limits::Zero(n) => TableInstance::new(n, None),
-            Limits::One(n, m) => TableInstance::new(n, Some(m)),
+            // The limits of tables are 32 bits wide
+            Limits::Zero(n) => TableInstance::new(t.element_type, n as u32, None),
+            Limits::One(n, m) | Limits::Shared(n, m) => TableInstance::new(t.element_type, n as u32, Some(m as u32)),
         };
 
         let addr = TableAddr::new(store.tables.len());
@@ -206,43 +281,53 @@ fn allocate_tables(
         store.tables.push(instance);
     }
 
-    for entry in imports {
-        if matches!(entry.desc, ImportDesc::Table { .. }) {
-            let instance = import_resolver.resolve_table(&entry.module_name, &entry.name)?;
-            debug!("table {:#?}", instance);
-
-            let addr = TableAddr::new(store.tables.len());
-            mod_instance.store_table_addr(addr)?;
-            store.tables.push(instance.clone());
-        }
-    }
-
     debug!("Tables in store {:#?}", store.tables);
 
     Ok(())
 }
 
-fn allocate_memories(m: &Module, mod_instance: &mut ModuleInstance, store: &mut Store) -> Result<()> {
+fn allocate_memories(
+    m: &Module,
+    mod_instance: &mut ModuleInstance,
+    store: &mut Store,
+    import_resolver: &ImportResolver,
+) -> Result<()> {
     debug!("allocate memories");
-    // Gets all memories and imports
-    let ty = validation::extract::get_mems(&m);
+
+    // Imported memories come first in the index space
+    for entry in validation::extract::get_imported_mems(m) {
+        if let ImportDesc::Memory { ty } = &entry.desc {
+            if let Some(ExternVal::Memory(addr)) =
+                import_resolver.resolve_extern(&entry.module_name, &entry.name)
+            {
+                mod_instance.store_memory_addr(addr.clone())?;
+                continue;
+            }
+
+            let instance = import_resolver.resolve_memory(&entry.module_name, &entry.name, ty)?;
+
+            let addr = MemoryAddr::new(store.memory.len());
+            mod_instance.store_memory_addr(addr)?;
+            store.memory.push(instance);
+        }
+    }
+
+    // Gets all defined memories
+    let ty = validation::extract::get_defined_mems(m);
 
     for mem_type in ty.iter() {
         debug!("mem_type {:#?}", mem_type);
-        let instance = match mem_type.limits {
-            Limits::Zero(n) => MemoryInstance {
-                data: vec![0u8; (n * 1024 * 64) as usize],
-                max: None,
-            },
-            Limits::One(n, m) => MemoryInstance {
-                data: vec![0u8; (n * 1024 * 64) as usize],
-                max: Some(m),
-            },
-        };
+        let mut instance = match mem_type.limits {
+            Limits::Zero(n) => MemoryInstance::new(n, None),
+            Limits::One(n, m) => MemoryInstance::new(n, Some(m)),
+            Limits::Shared(n, m) => MemoryInstance::new_shared(n, m),
+        }
+        .context("Creating the memory instance failed")?;
+        instance.index_type = mem_type.index_type;
 
         let addr = MemoryAddr::new(store.memory.len());
         mod_instance.store_memory_addr(addr)?;
-        store.memory.push(instance);
+        store.memory.push(instance.shared());
     }
 
     debug!("Memories in store {:#?}", store.memory);
@@ -257,20 +342,36 @@ fn allocate_globals(
 ) -> Result<()> {
     debug!("allocate globals");
     // Gets all globals and imports
-    let defined_globals = validation::extract::get_defined_globals(&m);
-    let imported_globals = validation::extract::get_imported_globals(&m);
+    let defined_globals = validation::extract::get_defined_globals(m);
+    let imported_globals = validation::extract::get_imported_globals(m);
 
     debug!("defined globals {:?}", defined_globals);
     debug!("imported globals {:?}", imported_globals);
     debug!("imports {:?}", imports);
 
+    // Imported globals come first in the index space
+    for gl in imported_globals.iter() {
+        debug!("global {:#?}", gl);
+
+        if let Some(ExternVal::Global(addr)) = imports.resolve_extern(&gl.module_name, &gl.name) {
+            mod_instance.store_global_addr(addr.clone())?;
+            continue;
+        }
+
+        let addr = GlobalAddr::new(store.globals.len());
+        mod_instance.store_global_addr(addr)?;
+        store
+            .globals
+            .push(imports.resolve_global(&gl.module_name, &gl.name)?);
+    }
+
     for gl in defined_globals.iter() {
         debug!("global {:#?}", gl);
 
         //TODO move to `allocate_global` in store
         let instance = Variable {
             mutable: matches!(gl.ty.mu, Mu::Var),
-            val: get_expr_const_ty_global(&gl.init, &mod_instance, store)?,
+            val: get_expr_const_ty_global(&gl.init, mod_instance, store)?,
         };
 
         let addr = GlobalAddr::new(store.globals.len());
@@ -278,17 +379,78 @@ fn allocate_globals(
         store.globals.push(instance);
     }
 
-    for gl in imported_globals.iter() {
-        debug!("global {:#?}", gl);
+    debug!("Globals in store {:#?}", store.globals);
 
-        let addr = GlobalAddr::new(store.globals.len());
-        mod_instance.store_global_addr(addr)?;
-        store
-            .globals
-            .push(imports.resolve_global(&gl.module_name, &gl.name)?);
+    Ok(())
+}
+
+fn allocate_tags(
+    m: &Module,
+    mod_instance: &mut ModuleInstance,
+    store: &mut Store,
+    imports: &[&ImportEntry],
+    import_resolver: &ImportResolver,
+) -> Result<()> {
+    debug!("allocate tags");
+
+    // Imported tags come first in the index space.
+    // They can only be exported by another instance in the store.
+    for entry in imports {
+        if let ImportDesc::Tag { .. } = &entry.desc {
+            match import_resolver.resolve_extern(&entry.module_name, &entry.name) {
+                Some(ExternVal::Tag(addr)) => mod_instance.store_tag_addr(*addr)?,
+                _ => return Err(anyhow!("Cannot find tag for {} {}", entry.module_name, entry.name)),
+            }
+        }
     }
 
-    debug!("Globals in store {:#?}", store.globals);
+    for ty in validation::extract::get_defined_tags(m) {
+        let fn_sig = mod_instance
+            .lookup_func_types(&ty)
+            .ok_or_else(|| anyhow!("{} function type is not defined", ty))?;
+
+        let addr = TagAddr::new(store.tags.len());
+        store.tags.push(TagInstance { ty: fn_sig.clone() });
+        mod_instance.store_tag_addr(addr)?;
+    }
+
+    debug!("Tags in store {:#?}", store.tags);
+
+    Ok(())
+}
+
+fn allocate_elements(m: &Module, mod_instance: &mut ModuleInstance, store: &mut Store) -> Result<()> {
+    debug!("allocate elements");
+
+    for e in validation::extract::get_elements(m) {
+        let elem = e
+            .init
+            .iter()
+            .map(|func_idx| match func_idx {
+                Some(idx) => mod_instance
+                    .lookup_function_addr(idx)
+                    .map(|addr| Some(Ref::Func(*addr)))
+                    .ok_or_else(|| anyhow!("No function with funcindex {}", idx)),
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let addr = ElemAddr::new(store.elems.len());
+        mod_instance.store_elem_addr(addr)?;
+        store.elems.push(ElemInstance::new(elem));
+    }
+
+    Ok(())
+}
+
+fn allocate_data(m: &Module, mod_instance: &mut ModuleInstance, store: &mut Store) -> Result<()> {
+    debug!("allocate data");
+
+    for d in validation::extract::get_data(m) {
+        let addr = DataAddr::new(store.datas.len());
+        mod_instance.store_data_addr(addr)?;
+        store.datas.push(DataInstance::new(d.init.clone()));
+    }
 
     Ok(())
 }
@@ -297,7 +459,7 @@ fn allocate_exports(m: &Module, mod_instance: &mut ModuleInstance, _store: &mut
     debug!("allocate exports");
 
     // Gets all exports
-    let ty = validation::extract::get_exports(&m);
+    let ty = validation::extract::get_exports(m);
 
     for export in ty.into_iter() {
         debug!("Export {:?}", export);
@@ -308,37 +470,74 @@ fn allocate_exports(m: &Module, mod_instance: &mut ModuleInstance, _store: &mut
     Ok(())
 }
 
-pub(crate) fn get_expr_const_ty_global(
-    init: &[InstructionWrapper],
-    mod_instance: &ModuleInstance,
-    store: &mut Store,
-) -> Result<Value> {
-    use wasm_parser::core::Instruction::*;
+/// Evaluates constant expressions with the globals and functions of the module
+struct ConstValues<'a> {
+    mod_instance: &'a ModuleInstance,
+    store: &'a Store,
+}
 
-    if init.is_empty() {
-        error!("No expr to evaluate");
-        return Err(anyhow!("No expr to evaluate"));
+impl ConstContext for ConstValues<'_> {
+    type Value = Value;
+
+    fn i32(&self, v: i32) -> Result<Value> {
+        Ok(Value::I32(v))
     }
 
-    match init
-        .get(0)
-        .context("Cannot access instruction")?
-        .get_instruction()
-    {
-        OP_I32_CONST(v) => Ok(Value::I32(*v)),
-        OP_I64_CONST(v) => Ok(Value::I64(*v)),
-        OP_F32_CONST(v) => Ok(Value::F32(*v)),
-        OP_F64_CONST(v) => Ok(Value::F64(*v)),
-        OP_GLOBAL_GET(idx) => {
-            let addr = mod_instance.lookup_global_addr(idx)
-                .context("Cannot find global addr by index")?;
-            let global_instance = store.get_global_instance(addr)?;
-
-            Ok(global_instance.val)
-        }
-        _ => {
-            error!("Wrong expression");
-            Err(anyhow!("Wrong expression"))
+    fn i64(&self, v: i64) -> Result<Value> {
+        Ok(Value::I64(v))
+    }
+
+    fn f32(&self, v: f32) -> Result<Value> {
+        Ok(Value::F32(v))
+    }
+
+    fn f64(&self, v: f64) -> Result<Value> {
+        Ok(Value::F64(v))
+    }
+
+    fn v128(&self, v: u128) -> Result<Value> {
+        Ok(Value::V128(v))
+    }
+
+    fn ref_null(&self, ty: ValueType) -> Result<Value> {
+        Ok(Value::null(ty))
+    }
+
+    fn ref_func(&self, idx: FuncIdx) -> Result<Value> {
+        let addr = self.mod_instance.lookup_function_addr(&idx)
+            .context("Cannot find function addr by index")?;
+
+        Ok(Value::FuncRef(Some(*addr)))
+    }
+
+    fn global_get(&self, idx: GlobalIdx) -> Result<Value> {
+        let addr = self.mod_instance.lookup_global_addr(&idx)
+            .context("Cannot find global addr by index")?;
+
+        Ok(self.store.get_global_instance(addr)?.val)
+    }
+
+    fn binary(&self, op: BinOp, lhs: Value, rhs: Value) -> Result<Value> {
+        use Value::*;
+
+        // The arithmetic wraps like the instructions
+        match (op, lhs, rhs) {
+            (BinOp::I32Add, I32(a), I32(b)) => Ok(I32(a.wrapping_add(b))),
+            (BinOp::I32Sub, I32(a), I32(b)) => Ok(I32(a.wrapping_sub(b))),
+            (BinOp::I32Mul, I32(a), I32(b)) => Ok(I32(a.wrapping_mul(b))),
+            (BinOp::I64Add, I64(a), I64(b)) => Ok(I64(a.wrapping_add(b))),
+            (BinOp::I64Sub, I64(a), I64(b)) => Ok(I64(a.wrapping_sub(b))),
+            (BinOp::I64Mul, I64(a), I64(b)) => Ok(I64(a.wrapping_mul(b))),
+            (op, lhs, rhs) => Err(anyhow!("{:?} cannot be applied to {:?} and {:?}", op, lhs, rhs)),
         }
     }
 }
+
+/// Evaluates the constant expression `init`
+pub(crate) fn get_expr_const_ty_global(
+    init: &[InstructionWrapper],
+    mod_instance: &ModuleInstance,
+    store: &Store,
+) -> Result<Value> {
+    eval_const_expr(&ConstValues { mod_instance, store }, init)
+}
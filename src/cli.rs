@@ -2,8 +2,9 @@ use regex::Regex;
 use regex::RegexSet;
 use crate::value::Value;
 use crate::value::Value::*;
+use anyhow::{bail, Context, Result};
 
-pub fn parse_args(args: Vec<String>) -> Vec<Value> {
+pub fn parse_args(args: Vec<String>) -> Result<Vec<Value>> {
     let matchers = &[
         r"I32\((-?[0-9]+)\)",
         r"I64\((-?[0-9]+)\)",
@@ -21,19 +22,25 @@ pub fn parse_args(args: Vec<String>) -> Vec<Value> {
         .map(|a| {
             let matches = set.matches(a);
             debug!("matches: {:?}", matches);
-            if matches.matched(0) {
+            let value = if matches.matched(0) {
                 let re = Regex::new(matchers[0]).unwrap();
                 let caps = re.captures(a).unwrap();
                 match caps[1].parse::<i32>() {
                     Ok(x) => I32(x),
-                    _ => I32(caps[1].parse::<u32>().unwrap() as i32),
+                    _ => I32(caps[1]
+                        .parse::<u32>()
+                        .with_context(|| format!("Invalid I32 parameter {}", a))?
+                        as i32),
                 }
             } else if matches.matched(1) {
                 let re = Regex::new(matchers[1]).unwrap();
                 let caps = re.captures(a).unwrap();
                 match caps[1].parse::<i64>() {
                     Ok(x) => I64(x),
-                    _ => I64(caps[1].parse::<u64>().unwrap() as i64),
+                    _ => I64(caps[1]
+                        .parse::<u64>()
+                        .with_context(|| format!("Invalid I64 parameter {}", a))?
+                        as i64),
                 }
             } else if matches.matched(2) {
                 let re = Regex::new(matchers[2]).unwrap();
@@ -56,8 +63,10 @@ pub fn parse_args(args: Vec<String>) -> Vec<Value> {
             } else if matches.matched(9) {
                 F64(f64::NAN)
             } else {
-                panic!("Invalid parameter type specified {}", a);
-            }
+                bail!("Invalid parameter type specified {}", a);
+            };
+
+            Ok(value)
         })
         .collect()
 }
@@ -1,5 +1,4 @@
-use crate::engine::stack::StackContent;
-use crate::value::Value;
+use crate::engine::stack::{Frame, Label, Slot};
 use anyhow::Result;
 use log::debug;
 use std::fmt;
@@ -12,16 +11,23 @@ use std::sync::mpsc::{Receiver, Sender};
 /// `RelativeProgramCounter` does not. Therefore, we should avoid allocations.
 pub struct BorrowedProgramState<'a> {
     current_pc: usize,
-    stack: &'a [StackContent],
-    locals: &'a [Value]
+    stack: &'a [Slot],
+    labels: &'a [Label],
+    frames: &'a [Frame],
 }
 
 impl<'a> BorrowedProgramState<'a> {
-    pub fn new(current_pc: usize, stack: &'a [StackContent], locals: &'a [Value]) -> Self {
+    pub fn new(
+        current_pc: usize,
+        stack: &'a [Slot],
+        labels: &'a [Label],
+        frames: &'a [Frame],
+    ) -> Self {
         Self {
             current_pc,
             stack,
-            locals,
+            labels,
+            frames,
         }
     }
 }
@@ -31,40 +37,79 @@ impl<'a> From<BorrowedProgramState<'a>> for ProgramState {
         ProgramState {
             current_pc: state.current_pc,
             stack: state.stack.to_vec(),
-            locals: state.locals.to_vec()
+            labels: state.labels.to_vec(),
+            frames: state.frames.to_vec(),
         }
     }
 }
 
+/// The state of the engine before an instruction is executed.
+/// The values on the stack are untagged slots, whose
+/// types are only known by the instructions.
 #[derive(Debug, Clone)]
 pub struct ProgramState {
     current_pc: usize,
-    stack: Vec<StackContent>,
-    locals: Vec<Value>,
+    stack: Vec<Slot>,
+    labels: Vec<Label>,
+    frames: Vec<Frame>,
 }
 
 impl ProgramState {
-    pub fn new(current_pc: usize, stack: Vec<StackContent>, locals: Vec<Value>) -> Self {
+    pub fn new(current_pc: usize, stack: Vec<Slot>, labels: Vec<Label>, frames: Vec<Frame>) -> Self {
         Self {
             current_pc,
             stack,
-            locals,
+            labels,
+            frames,
         }
     }
 
     pub fn get_pc(&self) -> usize {
         self.current_pc
     }
+
+    /// The value stack
+    pub fn get_stack(&self) -> &[Slot] {
+        &self.stack
+    }
+
+    /// The control stack
+    pub fn get_labels(&self) -> &[Label] {
+        &self.labels
+    }
+
+    /// The call stack, the current function is the last frame
+    pub fn get_frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// The locals of the current function
+    pub fn get_locals(&self) -> &[Slot] {
+        self.frames
+            .last()
+            .and_then(|frame| self.stack.get(frame.locals.clone()))
+            .unwrap_or(&[])
+    }
 }
 
 impl fmt::Display for ProgramState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let elements: Vec<_> = self.stack.iter().map(|w| format!("{}", w)).collect();
+        let elements: Vec<_> = self.stack.iter().map(|w| format!("{:#x}", w)).collect();
+        let labels: Vec<_> = self.labels.iter().map(|l| l.get_height()).collect();
+        let frames: Vec<_> = self
+            .frames
+            .iter()
+            .map(|frame| format!("{:?} locals {:?}", frame.func_addr, frame.locals))
+            .collect();
 
         write!(
             f,
-            "Current pc {}\n Stack: \n{:#?}\n Locals: \n {:#?}",
-            self.current_pc, elements, self.locals
+            "Current pc {}\n Stack: \n{:#?}\n Labels: \n{:?}\n Frames: \n{:#?}\n Locals: \n {:#x?}",
+            self.current_pc,
+            elements,
+            labels,
+            frames,
+            self.get_locals()
         )
     }
 }
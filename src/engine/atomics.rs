@@ -0,0 +1,206 @@
+//! The atomic memory instructions of the threads proposal.
+//! Every access holds the lock of the memory, therefore the accesses
+//! are sequentially consistent between the engines on different threads.
+//! https://webassembly.github.io/threads/core/exec/instructions.html#atomic-memory-instructions
+
+use crate::engine::memory::SharedMemory;
+use crate::engine::Engine;
+use anyhow::{bail, Context, Result};
+use std::convert::TryFrom;
+use std::time::Duration;
+use wasm_parser::core::Instruction::{self, *};
+use wasm_parser::core::MemArg;
+
+/// The bits of the value, which are stored by an access of `size` bytes
+fn mask(size: usize) -> u64 {
+    if size == 8 {
+        u64::MAX
+    } else {
+        (1 << (size * 8)) - 1
+    }
+}
+
+impl Engine {
+    /// Get the memory instance of the current module
+    fn memory(&self) -> Result<&SharedMemory> {
+        let addr = self
+            .module_instance
+            .lookup_memory_addr(&0)
+            .context("No memory address found")?;
+
+        self.store.get_memory_instance(addr)
+    }
+
+    /// Pops the address and pushes the `size` bytes at it zero-extended
+    fn atomic_load(&mut self, arg: &MemArg, size: usize) -> Result<()> {
+        let base = self.store.stack.pop_u64()?;
+
+        let value = self.memory()?.read().atomic_load(base, arg.offset, size)?;
+
+        // The zero-extended value is the representation of the I32 or I64
+        self.store.stack.push(value);
+
+        Ok(())
+    }
+
+    /// Pops the value and the address and stores the lower `size` bytes of the value
+    fn atomic_store(&mut self, arg: &MemArg, size: usize) -> Result<()> {
+        let value = self.store.stack.pop_u64()?;
+        let base = self.store.stack.pop_u64()?;
+
+        self.memory()?.write().atomic_store(base, arg.offset, size, value)?;
+
+        Ok(())
+    }
+
+    /// Pops the operand and the address, stores `op(old, operand)`
+    /// and pushes the old value zero-extended
+    fn atomic_rmw(&mut self, arg: &MemArg, size: usize, op: impl Fn(u64, u64) -> u64) -> Result<()> {
+        let operand = self.store.stack.pop_u64()?;
+        let base = self.store.stack.pop_u64()?;
+
+        let old = {
+            let mut instance = self.memory()?.write();
+
+            let old = instance.atomic_load(base, arg.offset, size)?;
+            instance.atomic_store(base, arg.offset, size, op(old, operand))?;
+
+            old
+        };
+
+        self.store.stack.push(old);
+
+        Ok(())
+    }
+
+    /// Pops the replacement, the expected value and the address.
+    /// The replacement is only stored, when the old value equals the wrapped expected value.
+    fn atomic_cmpxchg(&mut self, arg: &MemArg, size: usize) -> Result<()> {
+        let replacement = self.store.stack.pop_u64()?;
+        let expected = self.store.stack.pop_u64()? & mask(size);
+        let base = self.store.stack.pop_u64()?;
+
+        let old = {
+            let mut instance = self.memory()?.write();
+
+            let old = instance.atomic_load(base, arg.offset, size)?;
+            if old == expected {
+                instance.atomic_store(base, arg.offset, size, replacement)?;
+            }
+
+            old
+        };
+
+        self.store.stack.push(old);
+
+        Ok(())
+    }
+
+    /// Pops the timeout in nanoseconds, the expected value and the address
+    /// and pushes the `WaitResult`. A negative timeout waits forever.
+    fn atomic_wait(&mut self, arg: &MemArg, size: usize) -> Result<()> {
+        let timeout = self.store.stack.pop_u64()? as i64;
+        let expected = self.store.stack.pop_u64()? & mask(size);
+        let base = self.store.stack.pop_u64()?;
+
+        let timeout = u64::try_from(timeout).ok().map(Duration::from_nanos);
+
+        debug!("Waiting at {} + {} for {:?}", base, arg.offset, timeout);
+        let result = self.memory()?.wait(base, arg.offset, size, expected, timeout)?;
+        debug!("Waiting finished with {:?}", result);
+
+        self.store.stack.push(u64::from(result as u32));
+
+        Ok(())
+    }
+
+    /// Pops the count and the address and pushes the number of woken waiters
+    fn atomic_notify(&mut self, arg: &MemArg) -> Result<()> {
+        let count = self.store.stack.pop_u32()?;
+        let base = self.store.stack.pop_u64()?;
+
+        let woken = self.memory()?.notify(base, arg.offset, count)?;
+        debug!("Notified {} waiters at {} + {}", woken, base, arg.offset);
+
+        self.store.stack.push(u64::from(woken));
+
+        Ok(())
+    }
+
+    /// Executes an atomic instruction
+    pub(super) fn execute_atomic(&mut self, instruction: &Instruction) -> Result<()> {
+        match instruction {
+            OP_MEMORY_ATOMIC_NOTIFY(arg) => self.atomic_notify(arg)?,
+            OP_MEMORY_ATOMIC_WAIT32(arg) => self.atomic_wait(arg, 4)?,
+            OP_MEMORY_ATOMIC_WAIT64(arg) => self.atomic_wait(arg, 8)?,
+            // Every access is sequentially consistent already
+            OP_ATOMIC_FENCE => {}
+
+            OP_I32_ATOMIC_LOAD(arg) => self.atomic_load(arg, 4)?,
+            OP_I64_ATOMIC_LOAD(arg) => self.atomic_load(arg, 8)?,
+            OP_I32_ATOMIC_LOAD8_U(arg) | OP_I64_ATOMIC_LOAD8_U(arg) => self.atomic_load(arg, 1)?,
+            OP_I32_ATOMIC_LOAD16_U(arg) | OP_I64_ATOMIC_LOAD16_U(arg) => self.atomic_load(arg, 2)?,
+            OP_I64_ATOMIC_LOAD32_U(arg) => self.atomic_load(arg, 4)?,
+
+            OP_I32_ATOMIC_STORE(arg) => self.atomic_store(arg, 4)?,
+            OP_I64_ATOMIC_STORE(arg) => self.atomic_store(arg, 8)?,
+            OP_I32_ATOMIC_STORE8(arg) | OP_I64_ATOMIC_STORE8(arg) => self.atomic_store(arg, 1)?,
+            OP_I32_ATOMIC_STORE16(arg) | OP_I64_ATOMIC_STORE16(arg) => self.atomic_store(arg, 2)?,
+            OP_I64_ATOMIC_STORE32(arg) => self.atomic_store(arg, 4)?,
+
+            OP_I32_ATOMIC_RMW_ADD(arg) => self.atomic_rmw(arg, 4, u64::wrapping_add)?,
+            OP_I64_ATOMIC_RMW_ADD(arg) => self.atomic_rmw(arg, 8, u64::wrapping_add)?,
+            OP_I32_ATOMIC_RMW8_ADD_U(arg) | OP_I64_ATOMIC_RMW8_ADD_U(arg) => {
+                self.atomic_rmw(arg, 1, u64::wrapping_add)?
+            }
+            OP_I32_ATOMIC_RMW16_ADD_U(arg) | OP_I64_ATOMIC_RMW16_ADD_U(arg) => {
+                self.atomic_rmw(arg, 2, u64::wrapping_add)?
+            }
+            OP_I64_ATOMIC_RMW32_ADD_U(arg) => self.atomic_rmw(arg, 4, u64::wrapping_add)?,
+
+            OP_I32_ATOMIC_RMW_SUB(arg) => self.atomic_rmw(arg, 4, u64::wrapping_sub)?,
+            OP_I64_ATOMIC_RMW_SUB(arg) => self.atomic_rmw(arg, 8, u64::wrapping_sub)?,
+            OP_I32_ATOMIC_RMW8_SUB_U(arg) | OP_I64_ATOMIC_RMW8_SUB_U(arg) => {
+                self.atomic_rmw(arg, 1, u64::wrapping_sub)?
+            }
+            OP_I32_ATOMIC_RMW16_SUB_U(arg) | OP_I64_ATOMIC_RMW16_SUB_U(arg) => {
+                self.atomic_rmw(arg, 2, u64::wrapping_sub)?
+            }
+            OP_I64_ATOMIC_RMW32_SUB_U(arg) => self.atomic_rmw(arg, 4, u64::wrapping_sub)?,
+
+            OP_I32_ATOMIC_RMW_AND(arg) => self.atomic_rmw(arg, 4, |a, b| a & b)?,
+            OP_I64_ATOMIC_RMW_AND(arg) => self.atomic_rmw(arg, 8, |a, b| a & b)?,
+            OP_I32_ATOMIC_RMW8_AND_U(arg) | OP_I64_ATOMIC_RMW8_AND_U(arg) => self.atomic_rmw(arg, 1, |a, b| a & b)?,
+            OP_I32_ATOMIC_RMW16_AND_U(arg) | OP_I64_ATOMIC_RMW16_AND_U(arg) => self.atomic_rmw(arg, 2, |a, b| a & b)?,
+            OP_I64_ATOMIC_RMW32_AND_U(arg) => self.atomic_rmw(arg, 4, |a, b| a & b)?,
+
+            OP_I32_ATOMIC_RMW_OR(arg) => self.atomic_rmw(arg, 4, |a, b| a | b)?,
+            OP_I64_ATOMIC_RMW_OR(arg) => self.atomic_rmw(arg, 8, |a, b| a | b)?,
+            OP_I32_ATOMIC_RMW8_OR_U(arg) | OP_I64_ATOMIC_RMW8_OR_U(arg) => self.atomic_rmw(arg, 1, |a, b| a | b)?,
+            OP_I32_ATOMIC_RMW16_OR_U(arg) | OP_I64_ATOMIC_RMW16_OR_U(arg) => self.atomic_rmw(arg, 2, |a, b| a | b)?,
+            OP_I64_ATOMIC_RMW32_OR_U(arg) => self.atomic_rmw(arg, 4, |a, b| a | b)?,
+
+            OP_I32_ATOMIC_RMW_XOR(arg) => self.atomic_rmw(arg, 4, |a, b| a ^ b)?,
+            OP_I64_ATOMIC_RMW_XOR(arg) => self.atomic_rmw(arg, 8, |a, b| a ^ b)?,
+            OP_I32_ATOMIC_RMW8_XOR_U(arg) | OP_I64_ATOMIC_RMW8_XOR_U(arg) => self.atomic_rmw(arg, 1, |a, b| a ^ b)?,
+            OP_I32_ATOMIC_RMW16_XOR_U(arg) | OP_I64_ATOMIC_RMW16_XOR_U(arg) => self.atomic_rmw(arg, 2, |a, b| a ^ b)?,
+            OP_I64_ATOMIC_RMW32_XOR_U(arg) => self.atomic_rmw(arg, 4, |a, b| a ^ b)?,
+
+            OP_I32_ATOMIC_RMW_XCHG(arg) => self.atomic_rmw(arg, 4, |_, b| b)?,
+            OP_I64_ATOMIC_RMW_XCHG(arg) => self.atomic_rmw(arg, 8, |_, b| b)?,
+            OP_I32_ATOMIC_RMW8_XCHG_U(arg) | OP_I64_ATOMIC_RMW8_XCHG_U(arg) => self.atomic_rmw(arg, 1, |_, b| b)?,
+            OP_I32_ATOMIC_RMW16_XCHG_U(arg) | OP_I64_ATOMIC_RMW16_XCHG_U(arg) => self.atomic_rmw(arg, 2, |_, b| b)?,
+            OP_I64_ATOMIC_RMW32_XCHG_U(arg) => self.atomic_rmw(arg, 4, |_, b| b)?,
+
+            OP_I32_ATOMIC_RMW_CMPXCHG(arg) => self.atomic_cmpxchg(arg, 4)?,
+            OP_I64_ATOMIC_RMW_CMPXCHG(arg) => self.atomic_cmpxchg(arg, 8)?,
+            OP_I32_ATOMIC_RMW8_CMPXCHG_U(arg) | OP_I64_ATOMIC_RMW8_CMPXCHG_U(arg) => self.atomic_cmpxchg(arg, 1)?,
+            OP_I32_ATOMIC_RMW16_CMPXCHG_U(arg) | OP_I64_ATOMIC_RMW16_CMPXCHG_U(arg) => self.atomic_cmpxchg(arg, 2)?,
+            OP_I64_ATOMIC_RMW32_CMPXCHG_U(arg) => self.atomic_cmpxchg(arg, 4)?,
+
+            _ => bail!("{} is not an atomic instruction", instruction),
+        }
+
+        Ok(())
+    }
+}
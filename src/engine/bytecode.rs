@@ -0,0 +1,678 @@
+use crate::engine::module::ModuleInstance;
+use crate::engine::stack::{count_slots, slots};
+use crate::engine::stack_effect::stack_effect;
+use anyhow::{anyhow, bail, Result};
+use std::fmt;
+use wasm_parser::core::*;
+use wasm_parser::core::Instruction::*;
+
+/// The target of a branch, which is resolved by the compiler.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Branch {
+    /// The index of the next instruction
+    pub pc: usize,
+    /// The number of labels, which are left
+    pub depth: u32,
+    /// The number of slots, which are kept on the stack
+    pub arity: u32,
+}
+
+/// A local of the function, which is resolved by the compiler
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Local {
+    /// The index of its first slot in the locals of the frame
+    pub offset: u32,
+    pub slots: u32,
+}
+
+/// The operation of a compiled instruction.
+/// The blocks of the function body are flattened, therefore
+/// every control instruction knows the `pc` of its target.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Op {
+    /// Executes the instruction of the source
+    Execute,
+    /// Enters a block, which takes `params` slots of the stack
+    Block { params: u32 },
+    Loop { params: u32 },
+    /// Enters a `try` block, whose exceptions are
+    /// checked against the `catches` in order
+    Try { params: u32, catches: Vec<CatchHandler> },
+    /// Enters a `try` block, whose exceptions are
+    /// delegated to the label `depth` outside of it
+    TryDelegate { params: u32, depth: u32 },
+    /// Continues at `else_pc`, when the condition is zero
+    If { params: u32, else_pc: usize },
+    /// Continues at `pc` (the end of the then-branch)
+    Jump(usize),
+    /// Leaves the innermost block
+    End,
+    Br(Branch),
+    BrIf(Branch),
+    BrTable(Vec<Branch>, Branch),
+    /// Pushes the activation of the callee on the call stack
+    Call(FuncIdx),
+    CallIndirect(TypeIdx, TableIdx),
+    /// Replaces the activation of the caller with the callee
+    ReturnCall(FuncIdx),
+    ReturnCallIndirect(TypeIdx, TableIdx),
+    /// Drops the operand, which occupies `slots`
+    Drop { slots: u32 },
+    /// Selects one of the operands, which occupy `slots`
+    Select { slots: u32 },
+    LocalGet(Local),
+    LocalSet(Local),
+    LocalTee(Local),
+    /// Throws an exception of the tag with the arguments on the stack
+    Throw(TagIdx),
+    /// Throws the exception again, which was caught by the handler `depth`
+    Rethrow(u32),
+    /// The end of the function
+    Return,
+}
+
+/// A handler of `Op::Try`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CatchHandler {
+    /// The tag of the caught exceptions or `None` for `catch_all`
+    pub tag: Option<TagIdx>,
+    /// The index of the handler's first instruction
+    pub pc: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CompiledInstruction {
+    /// The `instruction_id` and instruction of the function body.
+    /// Control instructions do not contain their blocks anymore.
+    /// It is `None` for instructions, which were added by the compiler.
+    pub source: Option<(usize, Instruction)>,
+    pub op: Op,
+}
+
+/// The flat representation of a `FunctionBody`.
+/// It is compiled once, when the function is allocated.
+#[derive(Clone, Default, PartialEq)]
+pub struct Bytecode {
+    pub(crate) instructions: Vec<CompiledInstruction>,
+}
+
+// The instructions are already in the `FunctionBody`
+impl fmt::Debug for Bytecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bytecode({} instructions)", self.instructions.len())
+    }
+}
+
+impl Bytecode {
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+}
+
+/// Lowers the function body into `Bytecode`.
+/// The types of the blocks are resolved with the `module`.
+pub(crate) fn compile(
+    body: &FunctionBody,
+    signature: &FunctionSignature,
+    module: &ModuleInstance,
+) -> Result<Bytecode> {
+    let mut compiler = Compiler {
+        module,
+        body,
+        signature,
+        instructions: Vec::new(),
+        labels: Vec::new(),
+        operands: Vec::new(),
+        unreachable: false,
+    };
+
+    // The function's body is the outermost block
+    compiler.enter_block(&[], &signature.return_types, None)?;
+    compiler.compile_block(&body.code)?;
+    compiler.end_block()?;
+    compiler.push(None, Op::Return);
+
+    Ok(Bytecode {
+        instructions: compiler.instructions,
+    })
+}
+
+/// Which branch of an instruction has to be patched
+#[derive(Debug)]
+enum Patch {
+    Branch,
+    Table(usize),
+    Default,
+}
+
+/// A label of a block, which is not completely compiled
+#[derive(Debug)]
+struct PendingLabel {
+    /// The number of slots, which are kept by branches
+    arity: u32,
+    /// Branches of loops jump to the start
+    start: Option<usize>,
+    /// Branches to the end of the block, which is unknown yet
+    patches: Vec<(usize, Patch)>,
+    /// The number of operands below the block
+    height: usize,
+    results: Vec<ValueType>,
+}
+
+struct Compiler<'a> {
+    module: &'a ModuleInstance,
+    body: &'a FunctionBody,
+    signature: &'a FunctionSignature,
+    instructions: Vec<CompiledInstruction>,
+    labels: Vec<PendingLabel>,
+    /// The slots of the operands on the stack.
+    /// The operands of `drop` and `select` are not typed,
+    /// therefore the compiler tracks them.
+    operands: Vec<u32>,
+    /// Set after an unconditional branch, where the operands are unknown
+    unreachable: bool,
+}
+
+impl<'a> Compiler<'a> {
+    fn push(&mut self, source: Option<(usize, Instruction)>, op: Op) -> usize {
+        self.instructions.push(CompiledInstruction { source, op });
+        self.instructions.len() - 1
+    }
+
+    fn compile_block(&mut self, code: &[InstructionWrapper]) -> Result<()> {
+        for wrapped_instruction in code {
+            let id = wrapped_instruction.get_id();
+
+            match wrapped_instruction.get_instruction() {
+                OP_BLOCK(ty, block) => {
+                    let (params, results) = self.get_block_type(ty)?;
+
+                    self.push(
+                        Some((id, OP_BLOCK(ty.clone(), block.without_instructions()))),
+                        Op::Block {
+                            params: count_slots(&params),
+                        },
+                    );
+                    self.enter_block(&params, &results, None)?;
+                    self.compile_block(block.get_instructions())?;
+                    self.end_block()?;
+                }
+                OP_LOOP(ty, block) => {
+                    let (params, results) = self.get_block_type(ty)?;
+
+                    let start = self.push(
+                        Some((id, OP_LOOP(ty.clone(), block.without_instructions()))),
+                        Op::Loop {
+                            params: count_slots(&params),
+                        },
+                    ) + 1;
+                    // Branching to a loop takes its parameters
+                    self.enter_block(&params, &results, Some(start))?;
+                    self.compile_block(block.get_instructions())?;
+                    self.end_block()?;
+                }
+                OP_IF(ty, block) => {
+                    let (params, results) = self.get_block_type(ty)?;
+
+                    let if_pc = self.push(
+                        Some((id, OP_IF(ty.clone(), block.without_instructions()))),
+                        Op::If {
+                            params: count_slots(&params),
+                            else_pc: 0,
+                        },
+                    );
+                    // The condition is below the parameters
+                    self.pop_operand()?;
+                    self.enter_block(&params, &results, None)?;
+                    self.compile_block(block.get_instructions())?;
+                    let end = self.end_block()?;
+
+                    self.set_else_pc(if_pc, end);
+                }
+                OP_IF_AND_ELSE(ty, block_1, block_2) => {
+                    let (params, results) = self.get_block_type(ty)?;
+
+                    let if_pc = self.push(
+                        Some((
+                            id,
+                            OP_IF_AND_ELSE(
+                                ty.clone(),
+                                block_1.without_instructions(),
+                                block_2.without_instructions(),
+                            ),
+                        )),
+                        Op::If {
+                            params: count_slots(&params),
+                            else_pc: 0,
+                        },
+                    );
+                    self.pop_operand()?;
+                    self.enter_block(&params, &results, None)?;
+                    self.compile_block(block_1.get_instructions())?;
+
+                    let jump_pc = self.push(None, Op::Jump(0));
+                    self.set_else_pc(if_pc, jump_pc + 1);
+
+                    self.restart_block(&params)?;
+                    self.compile_block(block_2.get_instructions())?;
+                    let end = self.end_block()?;
+
+                    self.instructions[jump_pc].op = Op::Jump(end);
+                }
+                OP_TRY(ty, block, catches) => {
+                    let (params, results) = self.get_block_type(ty)?;
+
+                    let sources = catches
+                        .iter()
+                        .map(|catch| match catch {
+                            Catch::Tag(tag_idx, handler) => Catch::Tag(*tag_idx, handler.without_instructions()),
+                            Catch::All(handler) => Catch::All(handler.without_instructions()),
+                        })
+                        .collect();
+
+                    let try_pc = self.push(
+                        Some((id, OP_TRY(ty.clone(), block.without_instructions(), sources))),
+                        Op::Try {
+                            params: count_slots(&params),
+                            catches: Vec::new(),
+                        },
+                    );
+                    self.enter_block(&params, &results, None)?;
+                    self.compile_block(block.get_instructions())?;
+
+                    // The body and every handler continue at the end
+                    let mut jumps = vec![self.push(None, Op::Jump(0))];
+                    let mut handlers = Vec::with_capacity(catches.len());
+
+                    for catch in catches {
+                        let (tag, handler) = match catch {
+                            Catch::Tag(tag_idx, handler) => (Some(*tag_idx), handler),
+                            Catch::All(handler) => (None, handler),
+                        };
+
+                        handlers.push(CatchHandler {
+                            tag,
+                            pc: self.instructions.len(),
+                        });
+
+                        // The handler of a tag starts with the payload
+                        let payload = match tag {
+                            Some(tag_idx) => self
+                                .module
+                                .lookup_tag_type(&tag_idx)
+                                .ok_or_else(|| anyhow!("Cannot find tag {}", tag_idx))?
+                                .param_types
+                                .clone(),
+                            None => Vec::new(),
+                        };
+                        self.restart_block(&payload)?;
+                        self.compile_block(handler.get_instructions())?;
+                        jumps.push(self.push(None, Op::Jump(0)));
+                    }
+
+                    let end = self.end_block()?;
+
+                    for pc in jumps {
+                        self.instructions[pc].op = Op::Jump(end);
+                    }
+
+                    if let Op::Try { catches, .. } = &mut self.instructions[try_pc].op {
+                        *catches = handlers;
+                    }
+                }
+                OP_TRY_DELEGATE(ty, block, depth) => {
+                    let (params, results) = self.get_block_type(ty)?;
+
+                    self.push(
+                        Some((id, OP_TRY_DELEGATE(ty.clone(), block.without_instructions(), *depth))),
+                        Op::TryDelegate {
+                            params: count_slots(&params),
+                            depth: *depth,
+                        },
+                    );
+                    self.enter_block(&params, &results, None)?;
+                    self.compile_block(block.get_instructions())?;
+                    self.end_block()?;
+                }
+                OP_THROW(tag_idx) => {
+                    self.push(Some((id, OP_THROW(*tag_idx))), Op::Throw(*tag_idx));
+                    self.set_unreachable();
+                }
+                OP_RETHROW(depth) => {
+                    self.push(Some((id, OP_RETHROW(*depth))), Op::Rethrow(*depth));
+                    self.set_unreachable();
+                }
+                OP_BR(label_idx) => {
+                    let (branch, patch) = self.branch(*label_idx)?;
+                    let pc = self.push(Some((id, OP_BR(*label_idx))), Op::Br(branch));
+                    self.add_patch(*label_idx, pc, patch, Patch::Branch);
+                    self.set_unreachable();
+                }
+                OP_BR_IF(label_idx) => {
+                    let (branch, patch) = self.branch(*label_idx)?;
+                    let pc = self.push(Some((id, OP_BR_IF(*label_idx))), Op::BrIf(branch));
+                    self.add_patch(*label_idx, pc, patch, Patch::Branch);
+                    self.pop_operand()?;
+                }
+                OP_BR_TABLE(table, default) => {
+                    let mut branches = Vec::with_capacity(table.len());
+                    let mut patches = Vec::new();
+
+                    for (i, label_idx) in table.iter().enumerate() {
+                        let (branch, patch) = self.branch(*label_idx)?;
+                        branches.push(branch);
+
+                        if patch {
+                            patches.push((*label_idx, Patch::Table(i)));
+                        }
+                    }
+
+                    let (default_branch, patch) = self.branch(*default)?;
+                    if patch {
+                        patches.push((*default, Patch::Default));
+                    }
+
+                    let pc = self.push(
+                        Some((id, OP_BR_TABLE(table.clone(), *default))),
+                        Op::BrTable(branches, default_branch),
+                    );
+
+                    for (label_idx, patch) in patches {
+                        self.add_patch(label_idx, pc, true, patch);
+                    }
+
+                    self.set_unreachable();
+                }
+                OP_RETURN => {
+                    // Branches to the function's block
+                    let label_idx = self.labels.len() as u32 - 1;
+
+                    let (branch, patch) = self.branch(label_idx)?;
+                    let pc = self.push(Some((id, OP_RETURN)), Op::Br(branch));
+                    self.add_patch(label_idx, pc, patch, Patch::Branch);
+                    self.set_unreachable();
+                }
+                OP_CALL(idx) => {
+                    self.push(Some((id, OP_CALL(*idx))), Op::Call(*idx));
+
+                    let ty = self
+                        .module
+                        .lookup_function_type(idx)
+                        .ok_or_else(|| anyhow!("Cannot find function {}", idx))?;
+                    self.call(ty)?;
+                }
+                OP_CALL_INDIRECT(type_idx, table_idx) => {
+                    self.push(
+                        Some((id, OP_CALL_INDIRECT(*type_idx, *table_idx))),
+                        Op::CallIndirect(*type_idx, *table_idx),
+                    );
+
+                    // The index into the table
+                    self.pop_operand()?;
+                    self.call(self.lookup_type(type_idx)?)?;
+                }
+                OP_RETURN_CALL(idx) => {
+                    self.push(Some((id, OP_RETURN_CALL(*idx))), Op::ReturnCall(*idx));
+                    self.set_unreachable();
+                }
+                OP_RETURN_CALL_INDIRECT(type_idx, table_idx) => {
+                    self.push(
+                        Some((id, OP_RETURN_CALL_INDIRECT(*type_idx, *table_idx))),
+                        Op::ReturnCallIndirect(*type_idx, *table_idx),
+                    );
+                    self.set_unreachable();
+                }
+                OP_DROP => {
+                    let slots = self.pop_operand()?;
+                    self.push(Some((id, OP_DROP)), Op::Drop { slots });
+                }
+                OP_SELECT | OP_SELECT_T(_) => {
+                    // The condition is on top of the operands
+                    self.pop_operand()?;
+                    let v2 = self.pop_operand()?;
+                    let slots = self.pop_operand()?.max(v2);
+                    self.operands.push(slots);
+
+                    self.push(
+                        Some((id, wrapped_instruction.get_instruction().clone())),
+                        Op::Select { slots },
+                    );
+                }
+                OP_LOCAL_GET(idx) => {
+                    let local = self.local(*idx)?;
+                    self.operands.push(local.slots);
+                    self.push(Some((id, OP_LOCAL_GET(*idx))), Op::LocalGet(local));
+                }
+                OP_LOCAL_SET(idx) => {
+                    let local = self.local(*idx)?;
+                    self.pop_operand()?;
+                    self.push(Some((id, OP_LOCAL_SET(*idx))), Op::LocalSet(local));
+                }
+                OP_LOCAL_TEE(idx) => {
+                    let local = self.local(*idx)?;
+                    self.push(Some((id, OP_LOCAL_TEE(*idx))), Op::LocalTee(local));
+                }
+                OP_GLOBAL_GET(idx) => {
+                    let ty = self
+                        .module
+                        .lookup_global_type(idx)
+                        .ok_or_else(|| anyhow!("Cannot find global {}", idx))?;
+                    self.operands.push(slots(ty));
+                    self.push(Some((id, OP_GLOBAL_GET(*idx))), Op::Execute);
+                }
+                OP_GLOBAL_SET(idx) => {
+                    self.pop_operand()?;
+                    self.push(Some((id, OP_GLOBAL_SET(*idx))), Op::Execute);
+                }
+                OP_UNREACHABLE => {
+                    self.push(Some((id, OP_UNREACHABLE)), Op::Execute);
+                    self.set_unreachable();
+                }
+                instruction => {
+                    let (pops, results) = stack_effect(instruction)
+                        .ok_or_else(|| anyhow!("The instruction {} is not compiled", instruction))?;
+
+                    for _ in 0..pops {
+                        self.pop_operand()?;
+                    }
+                    self.operands.extend_from_slice(results);
+
+                    self.push(Some((id, instruction.clone())), Op::Execute);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates the branch to the label `label_idx`.
+    /// Returns `true`, if the `pc` must be patched at the end of the block.
+    fn branch(&self, label_idx: LabelIdx) -> Result<(Branch, bool)> {
+        let label = self
+            .labels
+            .iter()
+            .rev()
+            .nth(label_idx as usize)
+            .ok_or_else(|| anyhow!("Unknown label {}", label_idx))?;
+
+        let branch = Branch {
+            pc: label.start.unwrap_or(0),
+            depth: label_idx,
+            arity: label.arity,
+        };
+
+        Ok((branch, label.start.is_none()))
+    }
+
+    fn add_patch(&mut self, label_idx: LabelIdx, pc: usize, required: bool, patch: Patch) {
+        if required {
+            let idx = self.labels.len() - 1 - label_idx as usize;
+            self.labels[idx].patches.push((pc, patch));
+        }
+    }
+
+    fn set_else_pc(&mut self, if_pc: usize, pc: usize) {
+        if let Op::If { else_pc, .. } = &mut self.instructions[if_pc].op {
+            *else_pc = pc;
+        }
+    }
+
+    /// Adds the end of the innermost block and patches the branches to it.
+    /// Returns the `pc` of the end.
+    fn end_block(&mut self) -> Result<usize> {
+        let label = self
+            .labels
+            .pop()
+            .ok_or_else(|| anyhow!("No block to end"))?;
+
+        let end = self.push(None, Op::End);
+
+        // The results replace the operands of the block
+        self.operands.truncate(label.height);
+        self.operands.extend(label.results.iter().map(|ty| slots(*ty)));
+        self.unreachable = false;
+
+        for (pc, patch) in label.patches {
+            let branch = match (&mut self.instructions[pc].op, patch) {
+                (Op::Br(branch), Patch::Branch) | (Op::BrIf(branch), Patch::Branch) => branch,
+                (Op::BrTable(table, _), Patch::Table(i)) => &mut table[i],
+                (Op::BrTable(_, default), Patch::Default) => default,
+                (op, patch) => bail!("Cannot patch {:?} with {:?}", op, patch),
+            };
+
+            branch.pc = end;
+        }
+
+        Ok(end)
+    }
+
+    /// Returns the types of the parameters and the results of the block
+    fn get_block_type(&self, ty: &BlockType) -> Result<(Vec<ValueType>, Vec<ValueType>)> {
+        let types = match ty {
+            BlockType::Empty => (Vec::new(), Vec::new()),
+            BlockType::ValueType(ty) => (Vec::new(), vec![*ty]),
+            BlockType::FuncTy(idx) => {
+                let ty = self.lookup_type(idx)?;
+
+                (ty.param_types.clone(), ty.return_types.clone())
+            }
+        };
+
+        Ok(types)
+    }
+
+    fn lookup_type(&self, idx: &TypeIdx) -> Result<&'a FunctionSignature> {
+        self.module
+            .lookup_func_types(idx)
+            .ok_or_else(|| anyhow!("Cannot find func type {}", idx))
+    }
+
+    /// Pushes the label of the block, which takes its parameters from the operands.
+    /// Branches to a loop at `start` take the parameters instead of the results.
+    fn enter_block(&mut self, params: &[ValueType], results: &[ValueType], start: Option<usize>) -> Result<()> {
+        for _ in params {
+            self.pop_operand()?;
+        }
+
+        let height = self.operands.len();
+        self.operands.extend(params.iter().map(|ty| slots(*ty)));
+
+        let arity = match start {
+            Some(_) => count_slots(params),
+            None => count_slots(results),
+        };
+
+        self.labels.push(PendingLabel {
+            arity,
+            start,
+            patches: Vec::new(),
+            height,
+            results: results.to_vec(),
+        });
+
+        Ok(())
+    }
+
+    /// Continues the innermost block with the operands `types`,
+    /// like its `else` branch or a handler of `try`.
+    fn restart_block(&mut self, types: &[ValueType]) -> Result<()> {
+        let height = self
+            .labels
+            .last()
+            .map(|label| label.height)
+            .ok_or_else(|| anyhow!("No block to restart"))?;
+
+        self.operands.truncate(height);
+        self.operands.extend(types.iter().map(|ty| slots(*ty)));
+        self.unreachable = false;
+
+        Ok(())
+    }
+
+    /// Pops the arguments of the call and pushes its results
+    fn call(&mut self, ty: &FunctionSignature) -> Result<()> {
+        for _ in ty.param_types.iter() {
+            self.pop_operand()?;
+        }
+        self.operands.extend(ty.return_types.iter().map(|ty| slots(*ty)));
+
+        Ok(())
+    }
+
+    /// Pops the operand on top and returns its number of slots.
+    /// The operands of unreachable code are unknown, but never executed.
+    fn pop_operand(&mut self) -> Result<u32> {
+        let height = self.labels.last().map_or(0, |label| label.height);
+
+        if self.operands.len() <= height {
+            if self.unreachable {
+                return Ok(1);
+            }
+
+            bail!("The operands of the block are empty");
+        }
+
+        self.operands
+            .pop()
+            .ok_or_else(|| anyhow!("The operands are empty"))
+    }
+
+    /// The operands of the innermost block are unknown after an unconditional branch
+    fn set_unreachable(&mut self) {
+        let height = self.labels.last().map_or(0, |label| label.height);
+
+        self.operands.truncate(height);
+        self.unreachable = true;
+    }
+
+    /// Resolves the local `idx`, whose slots follow the slots of the previous locals
+    fn local(&self, idx: LocalIdx) -> Result<Local> {
+        let params = self.signature.param_types.iter().map(|ty| (1, *ty));
+        let declared = self.body.locals.iter().map(|entry| (entry.count, entry.ty));
+
+        let mut offset = 0u32;
+        let mut remaining = idx;
+
+        for (count, ty) in params.chain(declared) {
+            let size = slots(ty);
+            let n = remaining.min(count);
+
+            offset = n
+                .checked_mul(size)
+                .and_then(|n| offset.checked_add(n))
+                .ok_or_else(|| anyhow!("Too many locals"))?;
+
+            if remaining < count {
+                return Ok(Local { offset, slots: size });
+            }
+
+            remaining -= count;
+        }
+
+        bail!("Unknown local {}", idx)
+    }
+}
@@ -0,0 +1,43 @@
+use crate::engine::fuel::{default_cost, FuelCost};
+use crate::engine::interrupt::InterruptHandle;
+
+/// The configuration of an engine, which is applied before the
+/// instantiation. Therefore, the start function is metered and
+/// can be interrupted like every invocation.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Execution is unlimited, when it is not set
+    pub(crate) fuel: Option<u64>,
+    pub(crate) fuel_cost: FuelCost,
+    pub(crate) interrupt: InterruptHandle,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            fuel: None,
+            fuel_cost: default_cost,
+            interrupt: InterruptHandle::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Set the fuel, which bounds the number of executed instructions
+    pub fn fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Set the cost of each opcode. It applies, when the fuel is set.
+    pub fn fuel_cost(mut self, cost: FuelCost) -> Self {
+        self.fuel_cost = cost;
+        self
+    }
+
+    /// Set the handle, which interrupts the engine from another thread
+    pub fn interrupt_handle(mut self, handle: InterruptHandle) -> Self {
+        self.interrupt = handle;
+        self
+    }
+}
@@ -0,0 +1,38 @@
+use crate::value::Value;
+use std::fmt;
+use wasm_parser::core::{FunctionSignature, TagAddr};
+
+/// The runtime representation of a tag.
+/// The parameters of the signature are the types of the payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagInstance {
+    pub ty: FunctionSignature,
+}
+
+/// An exception, which was thrown by `throw` or `rethrow`.
+/// When no handler catches it, it is returned as the error of the engine
+/// and can be recovered with `Exception::from_error`, even if context was added.
+/// Unlike traps, exceptions can be caught by the instance.
+/// https://github.com/WebAssembly/exception-handling
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exception {
+    /// The tag, which identifies the exception
+    pub tag: TagAddr,
+    /// The arguments of the `throw`
+    pub payload: Vec<Value>,
+}
+
+impl Exception {
+    /// Returns the exception, when the error was caused by an uncaught one
+    pub fn from_error(err: &anyhow::Error) -> Option<Exception> {
+        err.downcast_ref::<Exception>().cloned()
+    }
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "uncaught exception with tag {} and payload {:?}", self.tag.get(), self.payload)
+    }
+}
+
+impl std::error::Error for Exception {}
@@ -0,0 +1,135 @@
+use crate::engine::{Engine, Entry};
+use crate::value::Value;
+use anyhow::{bail, Result};
+use wasm_parser::core::{FuncAddr, ValueType};
+
+#[derive(Debug)]
+enum State {
+    /// The invocation can continue
+    Running,
+    /// The function returned these results
+    Returned(Vec<Value>),
+    /// The function trapped or threw an exception
+    Failed,
+}
+
+/// An invocation of a function, which is run step by step.
+/// It is driven by the calling thread, therefore several executions
+/// of different engines can be interleaved cooperatively.
+/// Only wasm instructions are counted, a call of a host function
+/// is part of the instruction, which calls it.
+/// When it is dropped before it finished, the invocation is aborted
+/// and the engine can be reused.
+pub struct Execution<'a> {
+    engine: &'a mut Engine,
+    entry: Entry,
+    return_types: Vec<ValueType>,
+    state: State,
+}
+
+impl<'a> Execution<'a> {
+    /// Pushes the frame of the function, but does not run it.
+    /// Host functions cannot be paused and are called immediately.
+    pub(crate) fn new(engine: &'a mut Engine, func_addr: FuncAddr, args: Vec<Value>) -> Result<Self> {
+        engine.check_parameters_of_function(&func_addr, &args)?;
+
+        let func_instance = engine.get_function_instance(&func_addr)?;
+        let return_types = func_instance.ty.return_types.clone();
+        let is_host = func_instance.host.is_some();
+
+        let mut execution = Execution {
+            entry: engine.entry(),
+            engine,
+            return_types,
+            state: State::Running,
+        };
+
+        if is_host {
+            let result = execution.engine.invoke_function(func_addr, args);
+            execution.finish(result)?;
+        } else if let Err(err) = execution.engine.push_wasm_call(func_addr, args) {
+            execution.finish(Err(err))?;
+        }
+
+        Ok(execution)
+    }
+
+    /// Executes the next instruction
+    pub fn step(&mut self) -> Result<()> {
+        self.run_for(1)
+    }
+
+    /// Executes at most `n` instructions.
+    /// It stops earlier, when the function returns.
+    pub fn run_for(&mut self, n: u64) -> Result<()> {
+        self.run(Some(n))
+    }
+
+    /// Runs the function until it returns and returns its results
+    pub fn resume(&mut self) -> Result<Vec<Value>> {
+        self.run(None)?;
+
+        match &self.state {
+            State::Returned(results) => Ok(results.clone()),
+            state => bail!("The execution has not returned, but is {:?}", state),
+        }
+    }
+
+    /// Checks if the function returned or failed
+    pub fn is_finished(&self) -> bool {
+        !matches!(self.state, State::Running)
+    }
+
+    /// The engine, which can be inspected while the execution is paused
+    pub fn engine(&self) -> &Engine {
+        self.engine
+    }
+
+    fn run(&mut self, budget: Option<u64>) -> Result<()> {
+        match self.state {
+            State::Running => {}
+            State::Returned(_) => return Ok(()),
+            State::Failed => bail!("The execution already failed"),
+        }
+
+        self.engine.budget = budget;
+        let result = self.engine.run_call_stack(self.entry.depth);
+        self.engine.budget = None;
+
+        if result.is_ok() && self.engine.call_stack.len() > self.entry.depth {
+            debug!("Execution paused");
+            return Ok(());
+        }
+
+        self.finish(result)
+    }
+
+    /// Collects the results and restores the engine
+    fn finish(&mut self, result: Result<()>) -> Result<()> {
+        let results = result.and_then(|_| self.engine.pop_results(self.entry.height, &self.return_types));
+        let left = self.engine.leave(&self.entry, true);
+
+        match results.and_then(|results| left.map(|_| results)) {
+            Ok(results) => {
+                self.state = State::Returned(results);
+                Ok(())
+            }
+            Err(err) => {
+                self.state = State::Failed;
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Drop for Execution<'_> {
+    fn drop(&mut self) {
+        if let State::Running = self.state {
+            debug!("Aborting the paused execution");
+
+            if let Err(err) = self.engine.leave(&self.entry, true) {
+                error!("Aborting the execution failed: {:?}", err);
+            }
+        }
+    }
+}
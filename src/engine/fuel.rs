@@ -0,0 +1,53 @@
+use crate::engine::Trap;
+use wasm_parser::core::Instruction;
+
+/// The cost of an instruction in units of fuel
+pub type FuelCost = fn(&Instruction) -> u64;
+
+/// Every instruction costs one unit of fuel
+pub fn default_cost(_: &Instruction) -> u64 {
+    1
+}
+
+/// The fuel bounds the work of the engine.
+/// Every executed instruction consumes fuel and the
+/// engine traps with `Trap::OutOfFuel`, when it is empty.
+#[derive(Debug, Clone)]
+pub struct Fuel {
+    remaining: u64,
+    cost: FuelCost,
+}
+
+impl Fuel {
+    pub fn new(remaining: u64) -> Self {
+        Self {
+            remaining,
+            cost: default_cost,
+        }
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    pub(crate) fn set_remaining(&mut self, remaining: u64) {
+        self.remaining = remaining;
+    }
+
+    pub(crate) fn set_cost(&mut self, cost: FuelCost) {
+        self.cost = cost;
+    }
+
+    /// Consumes the fuel for the `instruction`.
+    /// Nothing is consumed, when there is not enough fuel left.
+    pub(crate) fn consume(&mut self, instruction: &Instruction) -> Result<(), Trap> {
+        let cost = (self.cost)(instruction);
+
+        self.remaining = self
+            .remaining
+            .checked_sub(cost)
+            .ok_or(Trap::OutOfFuel)?;
+
+        Ok(())
+    }
+}
@@ -1,8 +1,140 @@
-use wasm_parser::core::{FunctionBody, FunctionSignature};
+use crate::engine::bytecode::Bytecode;
+use crate::engine::memory::SharedMemory;
+use crate::engine::module::ModuleInstance;
+use crate::value::Value;
+use anyhow::{anyhow, bail, Context, Result};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+use wasm_parser::core::{FunctionBody, FunctionSignature, ModuleAddr};
+
+/// A function which is provided by the host (e.g. `println` or WASI).
+/// It receives the `Caller` to access the instance and the arguments,
+/// which are already typechecked against the signature of the import.
+pub type HostFunction = Box<dyn FnMut(&mut Caller, &[Value]) -> Result<Vec<Value>>>;
 
 #[derive(Debug, Clone)]
 pub struct FuncInstance {
-    //FIXME Add HostFunc
     pub ty: FunctionSignature,
     pub code: FunctionBody,
+    /// The compiled `code`, which is executed
+    pub bytecode: Arc<Bytecode>,
+    /// The module instance, which defines the function
+    pub module: ModuleAddr,
+    /// Is set when the function is imported from the host.
+    /// `code` is only a stub in this case.
+    pub host: Option<HostFunc>,
+}
+
+impl FuncInstance {
+    pub fn is_host(&self) -> bool {
+        self.host.is_some()
+    }
+}
+
+/// Shared handle of a `HostFunction`.
+/// It is shared, because `FuncInstance` must be cloneable.
+#[derive(Clone)]
+pub struct HostFunc(Rc<RefCell<HostFunction>>);
+
+impl HostFunc {
+    pub fn new(func: HostFunction) -> Self {
+        Self(Rc::new(RefCell::new(func)))
+    }
+
+    pub(crate) fn call(&self, caller: &mut Caller, args: &[Value]) -> Result<Vec<Value>> {
+        let mut func = self
+            .0
+            .try_borrow_mut()
+            .map_err(|_| anyhow!("Host function is already running"))?;
+
+        (func)(caller, args)
+    }
+}
+
+// `dyn FnMut` does not implement `Debug`
+impl fmt::Debug for HostFunc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HostFunc")
+    }
+}
+
+/// The `Caller` is given to a `HostFunction` and
+/// grants access to the calling instance.
+pub struct Caller<'a> {
+    module_instance: &'a ModuleInstance,
+    memory: &'a [SharedMemory],
+}
+
+impl<'a> Caller<'a> {
+    pub(crate) fn new(module_instance: &'a ModuleInstance, memory: &'a [SharedMemory]) -> Self {
+        Self {
+            module_instance,
+            memory,
+        }
+    }
+
+    /// Get the memory instance of the calling instance
+    pub fn get_memory(&self) -> Result<SharedMemory> {
+        let addr = self
+            .module_instance
+            .lookup_memory_addr(&0)
+            .context("No memory address found")?;
+
+        self.memory
+            .get(addr.get())
+            .cloned()
+            .ok_or_else(|| anyhow!("Cannot find memory by addr {:?}", addr))
+    }
+
+    /// Check if the `len` bytes at `offset` are in the memory
+    pub fn check_memory(&self, offset: usize, len: usize) -> Result<()> {
+        let end = offset
+            .checked_add(len)
+            .context("Memory range overflows")?;
+
+        if end as u64 > self.get_memory()?.read().len() {
+            bail!("Memory range {:?} is out of bounds", offset..end);
+        }
+
+        Ok(())
+    }
+
+    /// Read `len` bytes of the memory at `offset`
+    pub fn read_memory(&self, offset: usize, len: usize) -> Result<Vec<u8>> {
+        let end = offset
+            .checked_add(len)
+            .context("Memory range overflows")?;
+
+        // The length is controlled by the guest, therefore
+        // it is checked before the buffer is allocated
+        self.check_memory(offset, len)?;
+
+        let memory = self.get_memory()?;
+        let instance = memory.read();
+
+        let mut bytes = vec![0; len];
+        instance
+            .read_bytes(offset as u64, &mut bytes)
+            .map_err(|_| anyhow!("Cannot read memory range {:?}", offset..end))?;
+
+        Ok(bytes)
+    }
+
+    /// Write `bytes` into the memory at `offset`
+    pub fn write_memory(&mut self, offset: usize, bytes: &[u8]) -> Result<()> {
+        let end = offset
+            .checked_add(bytes.len())
+            .context("Memory range overflows")?;
+
+        let memory = self.get_memory()?;
+        let mut instance = memory.write();
+
+        instance
+            .write_bytes(offset as u64, bytes)
+            .map_err(|_| anyhow!("Cannot write memory range {:?}", offset..end))?;
+
+        Ok(())
+    }
 }
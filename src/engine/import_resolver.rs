@@ -1,6 +1,10 @@
+use crate::engine::func::{HostFunc, HostFunction};
+use crate::engine::memory::SharedMemory;
 use crate::engine::{GlobalInstance, TableInstance};
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::fmt;
+use wasm_parser::core::{FuncAddr, FunctionSignature, GlobalAddr, Limits, MemoryAddr, MemoryType, TableAddr, TagAddr};
 //use wasm_parser::core::ImportEntry;
 
 pub type Imports = Vec<Import>;
@@ -9,17 +13,88 @@ type Module = String;
 type Name = String;
 
 //TODO add more types
-#[derive(Debug)]
 pub enum Import {
+    Function(Module, Name, FunctionSignature, HostFunction),
     Global(Module, Name, GlobalInstance),
+    Memory(Module, Name, SharedMemory),
     Table(Module, Name, TableInstance),
 }
 
+// `HostFunction` does not implement `Debug`
+impl fmt::Debug for Import {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Import::Function(module, name, ty, _) => f
+                .debug_tuple("Function")
+                .field(module)
+                .field(name)
+                .field(ty)
+                .finish(),
+            Import::Global(module, name, instance) => f
+                .debug_tuple("Global")
+                .field(module)
+                .field(name)
+                .field(instance)
+                .finish(),
+            Import::Memory(module, name, instance) => f
+                .debug_tuple("Memory")
+                .field(module)
+                .field(name)
+                .field(instance)
+                .finish(),
+            Import::Table(module, name, instance) => f
+                .debug_tuple("Table")
+                .field(module)
+                .field(name)
+                .field(instance)
+                .finish(),
+        }
+    }
+}
+
+/// The resolved imported function
+#[derive(Debug)]
+struct ImportedFunction {
+    ty: FunctionSignature,
+    func: HostFunc,
+}
+
+/// An export of another module instance in the same store,
+/// which satisfies an import. It is provided by the `Linker`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ExternVal {
+    Function(FuncAddr),
+    Table(TableAddr),
+    Memory(MemoryAddr),
+    Global(GlobalAddr),
+    Tag(TagAddr),
+}
+
+/// Checks if the `current` size and the `max` of an instance
+/// match the `expected` limits of an import.
+/// https://webassembly.github.io/spec/core/exec/modules.html#limits
+pub(crate) fn matches_limits(current: u64, max: Option<u64>, expected: &Limits) -> bool {
+    let (min_expected, max_expected) = match *expected {
+        Limits::Zero(n) => (n, None),
+        Limits::One(n, m) | Limits::Shared(n, m) => (n, Some(m)),
+    };
+
+    let matches_max = match (max, max_expected) {
+        (_, None) => true,
+        (Some(max), Some(max_expected)) => max <= max_expected,
+        (None, Some(_)) => false,
+    };
+
+    current >= min_expected && matches_max
+}
+
 /// Private lookup table for
 /// modules and names
 #[derive(Debug, Default)]
 struct LookupTable {
     modules: HashMap<(Module, Name), Import>,
+    functions: HashMap<(Module, Name), ImportedFunction>,
+    externs: HashMap<(Module, Name), ExternVal>,
 }
 
 impl LookupTable {
@@ -46,6 +121,51 @@ impl ImportResolver {
         }
     }
 
+    /// Get the export of another instance by module and name
+    pub fn resolve_extern(&self, module: &str, name: &str) -> Option<&ExternVal> {
+        self.imports
+            .externs
+            .get(&(module.to_string(), name.to_string()))
+    }
+
+    /// Checks whether a function was imported by module and name
+    pub fn contains_function(&self, module: &str, name: &str) -> bool {
+        self.imports
+            .functions
+            .contains_key(&(module.to_string(), name.to_string()))
+    }
+
+    /// Get the imported function by module and name
+    /// and check its signature against the expected `ty`.
+    pub fn resolve_function(
+        &self,
+        module: &str,
+        name: &str,
+        ty: &FunctionSignature,
+    ) -> Result<HostFunc> {
+        debug!("resolve function {} {}", module, name);
+
+        if let Some(function) = self
+            .imports
+            .functions
+            .get(&(module.to_string(), name.to_string()))
+        {
+            if function.ty != *ty {
+                return Err(anyhow!(
+                    "Imported function {} {} has signature {:?}, but {:?} was expected",
+                    module,
+                    name,
+                    function.ty,
+                    ty
+                ));
+            }
+
+            return Ok(function.func.clone());
+        }
+
+        Err(anyhow!("Cannot find function for {} {}", module, name))
+    }
+
     /// Get the imported global by module and name
     pub fn resolve_global(&self, module: &str, name: &str) -> Result<GlobalInstance> {
         debug!("resolve global {} {}", module, name);
@@ -57,6 +177,34 @@ impl ImportResolver {
         Err(anyhow!("Cannot find global for {} {}", module, name))
     }
 
+    /// Get the imported memory by module and name
+    /// and check its limits against the expected `ty`.
+    pub fn resolve_memory(&self, module: &str, name: &str, ty: &MemoryType) -> Result<SharedMemory> {
+        debug!("resolve memory {} {}", module, name);
+
+        if let Some(Import::Memory(_, _, instance)) = self.imports.lookup(module, name) {
+            let memory = instance.read();
+
+            if !memory.matches(ty) {
+                return Err(anyhow!(
+                    "Imported memory {} {} with {} pages (max {:?}, shared {}) does not match the limits {:?}",
+                    module,
+                    name,
+                    memory.pages(),
+                    memory.max,
+                    memory.shared,
+                    ty.limits
+                ));
+            }
+
+            drop(memory);
+
+            return Ok(instance.clone());
+        }
+
+        Err(anyhow!("Cannot find memory for {} {}", module, name))
+    }
+
     /// Get the imported table by module and name
     pub fn resolve_table(&self, module: &str, name: &str) -> Result<TableInstance> {
         debug!("resolve table {} {}", module, name);
@@ -68,6 +216,30 @@ impl ImportResolver {
         Err(anyhow!("Cannot find table for {} {}", module, name))
     }
 
+    pub fn inject_extern(&mut self, module: Module, name: Name, val: ExternVal) -> Result<()> {
+        self.imports.externs.insert((module, name), val);
+
+        Ok(())
+    }
+
+    pub fn inject_function(
+        &mut self,
+        module: Module,
+        name: Name,
+        ty: FunctionSignature,
+        func: HostFunction,
+    ) -> Result<()> {
+        self.imports.functions.insert(
+            (module, name),
+            ImportedFunction {
+                ty,
+                func: HostFunc::new(func),
+            },
+        );
+
+        Ok(())
+    }
+
     pub fn inject_global(
         &mut self,
         module: Module,
@@ -82,6 +254,20 @@ impl ImportResolver {
         Ok(())
     }
 
+    pub fn inject_memory(
+        &mut self,
+        module: Module,
+        name: Name,
+        instance: &SharedMemory,
+    ) -> Result<()> {
+        self.imports.modules.insert(
+            (module.clone(), name.clone()),
+            Import::Memory(module, name, instance.clone()),
+        );
+
+        Ok(())
+    }
+
     pub fn inject_table(
         &mut self,
         module: Module,
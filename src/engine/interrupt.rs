@@ -0,0 +1,29 @@
+use crate::engine::Trap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A handle to cancel the invocation of an engine from another thread.
+/// After `interrupt`, the engine traps with `Trap::Interrupted` at the
+/// next loop back-edge or call. The interrupt is consumed by the trap,
+/// therefore it stays pending, when no invocation is running.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// Requests the engine to trap
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+    }
+
+    /// Traps and consumes the interrupt, when one was requested
+    pub(crate) fn check(&self) -> Result<(), Trap> {
+        if self.interrupted.load(Ordering::Relaxed) && self.interrupted.swap(false, Ordering::SeqCst) {
+            debug!("Engine was interrupted");
+            return Err(Trap::Interrupted);
+        }
+
+        Ok(())
+    }
+}
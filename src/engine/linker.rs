@@ -0,0 +1,174 @@
+use crate::engine::import_resolver::{matches_limits, ExternVal, Imports};
+use crate::engine::Engine;
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use wasm_parser::core::{ExternalKindType, FunctionSignature, ImportDesc, ImportEntry, ModuleAddr, Mu, Section, ValueType};
+use wasm_parser::Module;
+
+/// The `Linker` connects module instances in the same store of an `Engine`.
+/// Instances are registered by name (like the `register` command
+/// of the spec tests) and the imports of a later instantiated module
+/// are resolved against their exports.
+#[derive(Debug, Default)]
+pub struct Linker {
+    instances: HashMap<String, ModuleAddr>,
+}
+
+impl Linker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the module instance `addr` under `name`.
+    /// A previous instance with the same name is replaced.
+    pub fn register(&mut self, name: impl Into<String>, addr: ModuleAddr) {
+        let name = name.into();
+        debug!("Registering {:?} as {}", addr, name);
+
+        self.instances.insert(name, addr);
+    }
+
+    /// Get the address of a registered module instance
+    pub fn get(&self, name: &str) -> Option<&ModuleAddr> {
+        self.instances.get(name)
+    }
+
+    /// Instantiates the `module` in the store of the `engine`.
+    /// Imports of registered modules are linked to their exports,
+    /// all other imports must be given by the host in `imports`.
+    pub fn instantiate(&self, engine: &mut Engine, module: &Module, imports: Imports) -> Result<ModuleAddr> {
+        let mut resolver = crate::allocation::create_import_resolver(imports)
+            .context("Resolving imports failed")?;
+
+        let entries = module
+            .sections
+            .iter()
+            .filter_map(|section| match section {
+                Section::Import(t) => Some(&t.entries),
+                _ => None,
+            })
+            .flatten();
+
+        for entry in entries {
+            if let Some(addr) = self.instances.get(&entry.module_name) {
+                let val = self.resolve(engine, module, addr, entry)?;
+
+                resolver.inject_extern(entry.module_name.clone(), entry.name.clone(), val)?;
+            }
+        }
+
+        engine.instantiate_module(module, resolver)
+    }
+
+    /// Looks up the export of the registered instance `addr`, which matches
+    /// the import `entry` of `module`, and checks its type.
+    fn resolve(
+        &self,
+        engine: &Engine,
+        module: &Module,
+        addr: &ModuleAddr,
+        entry: &ImportEntry,
+    ) -> Result<ExternVal> {
+        debug!("Linking {} {}", entry.module_name, entry.name);
+
+        let instance = engine.get_module_instance(addr)?;
+
+        let export = instance
+            .get_export_instance_by_name(entry.name.as_str())
+            .ok_or_else(|| anyhow!("unlinkable: unknown import {} {}", entry.module_name, entry.name))?;
+
+        let incompatible = || anyhow!("unlinkable: incompatible import type {} {}", entry.module_name, entry.name);
+
+        match (&entry.desc, export.value) {
+            (ImportDesc::Function { ty }, ExternalKindType::Function { ty: idx }) => {
+                let expected = module_func_types(module)
+                    .get(*ty as usize)
+                    .ok_or_else(|| anyhow!("{} function type is not defined", ty))?;
+
+                let func_addr = instance
+                    .lookup_function_addr(&idx)
+                    .context("Exported function not found")?;
+
+                if engine.store.get_func_instance(func_addr)?.ty != *expected {
+                    return Err(incompatible());
+                }
+
+                Ok(ExternVal::Function(*func_addr))
+            }
+            (ImportDesc::Table { ty }, ExternalKindType::Table { ty: idx }) => {
+                let table_addr = instance
+                    .lookup_table_addr(&idx)
+                    .context("Exported table not found")?;
+
+                let table = engine
+                    .store
+                    .tables
+                    .get(table_addr.get())
+                    .ok_or_else(|| anyhow!("Cannot find table by addr {:?}", table_addr))?;
+
+                if table.ty != ty.element_type
+                    || !matches_limits(table.elem.len() as u64, table.max.map(u64::from), &ty.limits)
+                {
+                    return Err(incompatible());
+                }
+
+                Ok(ExternVal::Table(table_addr.clone()))
+            }
+            (ImportDesc::Memory { ty }, ExternalKindType::Memory { ty: idx }) => {
+                let memory_addr = instance
+                    .lookup_memory_addr(&idx)
+                    .context("Exported memory not found")?;
+
+                let memory = engine.store.get_memory_instance(memory_addr)?.read();
+
+                if !memory.matches(ty) {
+                    return Err(incompatible());
+                }
+
+                Ok(ExternVal::Memory(memory_addr.clone()))
+            }
+            (ImportDesc::Global { ty }, ExternalKindType::Global { ty: idx }) => {
+                let global_addr = instance
+                    .lookup_global_addr(&idx)
+                    .context("Exported global not found")?;
+
+                let global = engine.store.get_global_instance(global_addr)?;
+
+                let mutable = matches!(ty.mu, Mu::Var);
+                if global.mutable != mutable || ValueType::from(global.val) != ty.value_type {
+                    return Err(incompatible());
+                }
+
+                Ok(ExternVal::Global(global_addr.clone()))
+            }
+            (ImportDesc::Tag { ty }, ExternalKindType::Tag { ty: idx }) => {
+                let expected = module_func_types(module)
+                    .get(*ty as usize)
+                    .ok_or_else(|| anyhow!("{} function type is not defined", ty))?;
+
+                let tag_addr = instance
+                    .lookup_tag_addr(&idx)
+                    .context("Exported tag not found")?;
+
+                if engine.store.get_tag_instance(tag_addr)?.ty != *expected {
+                    return Err(incompatible());
+                }
+
+                Ok(ExternVal::Tag(*tag_addr))
+            }
+            _ => Err(incompatible()),
+        }
+    }
+}
+
+/// Get the function types of the type section of the module
+fn module_func_types(module: &Module) -> &[FunctionSignature] {
+    module
+        .sections
+        .iter()
+        .find_map(|section| match section {
+            Section::Type(t) => Some(t.entries.as_slice()),
+            _ => None,
+        })
+        .unwrap_or(&[])
+}
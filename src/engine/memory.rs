@@ -1,56 +1,466 @@
-use crate::engine::Page;
+use crate::engine::import_resolver::matches_limits;
+use crate::engine::Trap;
+use crate::PAGE_SIZE;
+use anyhow::{Context, Result};
+use std::convert::TryFrom;
 use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+use wasm_parser::core::{IndexType, Limits, MemoryType};
 
+/// A handle of a memory instance, which is shared
+/// between the host and the instances.
+/// Writes of one side are visible to the others.
+/// The handle is `Send` and `Sync`, therefore engines
+/// on different threads can share the same memory.
 #[derive(Clone)]
-pub struct MemoryInstance {
-    pub data: Vec<u8>,
-    pub max: Option<u32>,
+pub struct SharedMemory(Arc<MemoryCell>);
+
+struct MemoryCell {
+    instance: RwLock<MemoryInstance>,
+    /// The threads, which are suspended by `memory.atomic.wait`
+    waiters: Mutex<Waiters>,
+    notified: Condvar,
 }
 
-// Overwritten debug implementation
-// Because `data` can have a lot of entries, which
-// can be a problem when printing
-impl fmt::Debug for MemoryInstance {
+#[derive(Default)]
+struct Waiters {
+    next_id: u64,
+    /// The effective address and the id of every waiter in FIFO order
+    queue: Vec<(u64, u64)>,
+}
+
+/// The result of `memory.atomic.wait`
+/// https://webassembly.github.io/threads/core/exec/instructions.html#exec-memory-atomic-wait
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitResult {
+    /// The thread was woken by `memory.atomic.notify`
+    Ok = 0,
+    /// The loaded value did not match the expected value
+    NotEqual = 1,
+    TimedOut = 2,
+}
+
+impl SharedMemory {
+    /// Locks the memory for reading
+    pub fn read(&self) -> RwLockReadGuard<'_, MemoryInstance> {
+        // A poisoned lock only means that another thread panicked,
+        // the memory itself is still consistent.
+        self.0.instance.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Locks the memory for writing
+    pub fn write(&self) -> RwLockWriteGuard<'_, MemoryInstance> {
+        self.0.instance.write().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Suspends the thread until it is notified at the effective address or the
+    /// `timeout` is over. The thread is not suspended, when the `size` bytes
+    /// at the address do not equal `expected`.
+    pub(crate) fn wait(
+        &self,
+        base: u64,
+        offset: u64,
+        size: usize,
+        expected: u64,
+        timeout: Option<Duration>,
+    ) -> Result<WaitResult, Trap> {
+        let ea = aligned_address(base, offset, size)?;
+
+        // The comparison happens under the lock of the waiters,
+        // therefore a notify cannot be missed.
+        let mut waiters = self.0.waiters.lock().unwrap_or_else(|err| err.into_inner());
+
+        {
+            let instance = self.read();
+            if !instance.shared {
+                return Err(Trap::ExpectedSharedMemory);
+            }
+
+            if instance.atomic_load(base, offset, size)? != expected {
+                return Ok(WaitResult::NotEqual);
+            }
+        }
+
+        let id = waiters.next_id;
+        waiters.next_id += 1;
+        waiters.queue.push((ea, id));
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            waiters = match deadline {
+                None => self.0.notified.wait(waiters).unwrap_or_else(|err| err.into_inner()),
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    self.0
+                        .notified
+                        .wait_timeout(waiters, timeout)
+                        .unwrap_or_else(|err| err.into_inner())
+                        .0
+                }
+            };
+
+            // The notifier removes the woken waiters from the queue
+            if !waiters.queue.iter().any(|(_, waiter)| *waiter == id) {
+                return Ok(WaitResult::Ok);
+            }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                waiters.queue.retain(|(_, waiter)| *waiter != id);
+                return Ok(WaitResult::TimedOut);
+            }
+        }
+    }
+
+    /// Wakes up to `count` threads, which wait at the effective address.
+    /// Returns the number of woken threads.
+    pub(crate) fn notify(&self, base: u64, offset: u64, count: u32) -> Result<u32, Trap> {
+        let ea = aligned_address(base, offset, 4)?;
+
+        {
+            let instance = self.read();
+            instance.check_range(ea, 4)?;
+
+            // Nobody can wait on an unshared memory
+            if !instance.shared {
+                return Ok(0);
+            }
+        }
+
+        let mut waiters = self.0.waiters.lock().unwrap_or_else(|err| err.into_inner());
+
+        let mut woken = 0;
+        waiters.queue.retain(|(addr, _)| {
+            if *addr == ea && woken < count {
+                woken += 1;
+                false
+            } else {
+                true
+            }
+        });
+
+        if woken > 0 {
+            self.0.notified.notify_all();
+        }
+
+        Ok(woken)
+    }
+}
+
+impl fmt::Debug for SharedMemory {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("MemoryInstance")
-            .field("data (only length)", &self.data.len())
-            .field("max", &self.max)
-            .finish()
+        self.read().fmt(f)
+    }
+}
+
+/// The effective address `base + offset` of an access.
+/// It overflows only for 64-bit memories, which is always out of bounds.
+/// https://webassembly.github.io/memory64/core/exec/instructions.html#memory-instructions
+fn effective_address(base: u64, offset: u64) -> Result<u64, Trap> {
+    base.checked_add(offset).ok_or(Trap::MemoryOutOfBounds)
+}
+
+/// The effective address of an atomic access, which must be aligned to its `size`
+fn aligned_address(base: u64, offset: u64, size: usize) -> Result<u64, Trap> {
+    let ea = effective_address(base, offset)?;
+
+    if !ea.is_multiple_of(size as u64) {
+        debug!("Atomic access at {} is not aligned to {} bytes", ea, size);
+        return Err(Trap::UnalignedAtomic);
     }
+
+    Ok(ea)
+}
+
+/// Reads the little endian bytes zero-extended
+pub(crate) fn load_le(bytes: &[u8]) -> u64 {
+    let mut b = [0; 8];
+    b[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(b)
 }
 
-/// Returns Err when paging failed
-/// Returns new length in pages
-/// https://webassembly.github.io/spec/core/exec/modules.html#growing-memories
-pub(crate) fn grow_memory(instance: &mut MemoryInstance, n: Page) -> Result<Page, ()> {
-    if n.is_zero() {
-        return Ok(Page::from_count(instance.data.len()));
+/// The part of an access of `len` bytes at `addr`, which is in one page.
+/// `range` is in the page and `buf` in the bytes of the access.
+struct Segment {
+    page: usize,
+    range: Range<usize>,
+    buf: Range<usize>,
+}
+
+/// Splits the access at the boundaries of the pages
+fn segments(addr: usize, len: usize) -> impl Iterator<Item = Segment> {
+    let mut done = 0;
+
+    std::iter::from_fn(move || {
+        if done == len {
+            return None;
+        }
+
+        let ea = addr + done;
+        let start = ea % PAGE_SIZE;
+        let n = (PAGE_SIZE - start).min(len - done);
+
+        let segment = Segment {
+            page: ea / PAGE_SIZE,
+            range: start..start + n,
+            buf: done..done + n,
+        };
+        done += n;
+
+        Some(segment)
+    })
+}
+
+/// The memory is a list of pages, which are allocated on their first write.
+/// Unwritten pages are zero, therefore large memories are neither
+/// allocated eagerly nor as one contiguous buffer.
+#[derive(Clone)]
+pub struct MemoryInstance {
+    pages: Vec<Option<Box<[u8]>>>,
+    pub max: Option<u64>,
+    /// Only shared memories can be waited on
+    pub shared: bool,
+    /// The type of the addresses
+    pub index_type: IndexType,
+}
+
+impl MemoryInstance {
+    /// Create a memory with `n` pages of zeros.
+    /// Fails, when the list of the pages cannot be allocated.
+    pub fn new(n: u64, max: Option<u64>) -> Result<Self> {
+        let len = usize::try_from(n)
+            .with_context(|| format!("{} pages exceed the address space", n))?;
+
+        // Only the list of the pages is allocated
+        let mut pages = Vec::new();
+        pages
+            .try_reserve_exact(len)
+            .with_context(|| format!("Cannot allocate the memory of {} pages", n))?;
+        pages.resize(len, None);
+
+        Ok(Self {
+            pages,
+            max,
+            shared: false,
+            index_type: IndexType::I32,
+        })
+    }
+
+    /// Create a `shared` memory with `n` pages of zeros.
+    /// Shared memories always have a maximum.
+    pub fn new_shared(n: u64, max: u64) -> Result<Self> {
+        Ok(Self {
+            shared: true,
+            ..Self::new(n, Some(max))?
+        })
     }
 
-    let len = n + Page::from_count(instance.data.len());
+    /// Create a memory of the memory64 proposal, which is addressed by `i64`
+    pub fn new_64(n: u64, max: Option<u64>) -> Result<Self> {
+        Ok(Self {
+            index_type: IndexType::I64,
+            ..Self::new(n, max)?
+        })
+    }
 
-    if len.pages() > usize::pow(2, 16) {
-        error!("Length exceeded. Too many memory pages");
-        return Err(());
+    /// Create a shared handle of the memory
+    pub fn shared(self) -> SharedMemory {
+        SharedMemory(Arc::new(MemoryCell {
+            instance: RwLock::new(self),
+            waiters: Mutex::new(Waiters::default()),
+            notified: Condvar::new(),
+        }))
     }
 
-    if let Some(max) = instance.max {
-        debug!("Checking limit len {:?} < max {}", len.pages(), max);
-        if len.pages() > max as usize {
-            error!("Memory growing failed. Limit exceded");
-            return Err(());
+    /// Checks if the memory can be imported as `ty`
+    pub(crate) fn matches(&self, ty: &MemoryType) -> bool {
+        self.shared == matches!(ty.limits, Limits::Shared(..))
+            && self.index_type == ty.index_type
+            && matches_limits(self.pages(), self.max, &ty.limits)
+    }
+
+    /// The current size in pages
+    pub fn pages(&self) -> u64 {
+        self.pages.len() as u64
+    }
+
+    /// The current size in bytes
+    pub fn len(&self) -> u64 {
+        self.pages() * PAGE_SIZE as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// The maximum of pages, which can be addressed by the index type
+    fn max_pages(&self) -> u64 {
+        match self.index_type {
+            IndexType::I32 => 1 << 16,
+            IndexType::I64 => 1 << 48,
+        }
+    }
+
+    /// Checks that the `n` bytes at `addr` are in the memory
+    fn check_range(&self, addr: u64, n: u64) -> Result<(), Trap> {
+        match addr.checked_add(n) {
+            Some(end) if end <= self.len() => Ok(()),
+            _ => {
+                debug!("Access of {} bytes at {} is out of bounds ({} bytes)", n, addr, self.len());
+                Err(Trap::MemoryOutOfBounds)
+            }
         }
     }
 
-    let new_length = Page::from_count(instance.data.len()) + n;
-    debug!("Resize by {} bytes", new_length.elements());
+    /// Copy the bytes at `addr` into `buf`
+    pub fn read_bytes(&self, addr: u64, buf: &mut [u8]) -> Result<(), Trap> {
+        self.check_range(addr, buf.len() as u64)?;
+
+        for segment in segments(addr as usize, buf.len()) {
+            match &self.pages[segment.page] {
+                Some(page) => buf[segment.buf].copy_from_slice(&page[segment.range]),
+                None => buf[segment.buf].fill(0),
+            }
+        }
+
+        Ok(())
+    }
 
-    // Create new vec and fill it with 0u8
-    let extension = vec![0u8; n.elements()];
+    /// Copy the `bytes` into the memory at `addr`
+    pub fn write_bytes(&mut self, addr: u64, bytes: &[u8]) -> Result<(), Trap> {
+        self.check_range(addr, bytes.len() as u64)?;
+
+        for segment in segments(addr as usize, bytes.len()) {
+            self.page_mut(segment.page)?[segment.range].copy_from_slice(&bytes[segment.buf]);
+        }
+
+        Ok(())
+    }
+
+    /// Get the page and allocate it, when it was not written yet.
+    /// Traps, when the page cannot be allocated.
+    fn page_mut(&mut self, page: usize) -> Result<&mut [u8], Trap> {
+        let slot = &mut self.pages[page];
+        let bytes = match slot.take() {
+            Some(bytes) => bytes,
+            None => zeroed_page()?,
+        };
+
+        Ok(slot.insert(bytes))
+    }
+
+    /// Load the bytes at the effective address `base + offset`
+    pub(crate) fn load(&self, base: u64, offset: u64, buf: &mut [u8]) -> Result<(), Trap> {
+        self.read_bytes(effective_address(base, offset)?, buf)
+    }
+
+    /// Store the bytes at the effective address `base + offset`
+    pub(crate) fn store(&mut self, base: u64, offset: u64, bytes: &[u8]) -> Result<(), Trap> {
+        self.write_bytes(effective_address(base, offset)?, bytes)
+    }
+
+    /// Like `load`, but the effective address must be aligned to `size`.
+    /// Returns the `size` bytes zero-extended.
+    pub(crate) fn atomic_load(&self, base: u64, offset: u64, size: usize) -> Result<u64, Trap> {
+        let mut bytes = [0; 8];
+        self.read_bytes(aligned_address(base, offset, size)?, &mut bytes[..size])?;
+
+        Ok(load_le(&bytes[..size]))
+    }
+
+    /// Like `store`, but the effective address must be aligned to `size`.
+    /// Stores the lower `size` bytes of the value.
+    pub(crate) fn atomic_store(&mut self, base: u64, offset: u64, size: usize, value: u64) -> Result<(), Trap> {
+        self.write_bytes(aligned_address(base, offset, size)?, &value.to_le_bytes()[..size])
+    }
 
-    // Append the new vec to the instance
-    instance.data.extend_from_slice(&extension);
+    /// Copy the `n` bytes at `src` to `dst`. The regions may overlap.
+    pub(crate) fn copy_within(&mut self, dst: u64, src: u64, n: u64) -> Result<(), Trap> {
+        self.check_range(src, n)?;
+        self.check_range(dst, n)?;
 
-    // Return pages
-    Ok(new_length)
+        let mut buf = vec![0u8; n.min(PAGE_SIZE as u64) as usize];
+        let chunks = (0..n.div_ceil(PAGE_SIZE as u64)).map(|i| i * PAGE_SIZE as u64);
+
+        // The chunks are copied in the order, which does
+        // not overwrite the source before it was read
+        let chunks: Box<dyn Iterator<Item = u64>> = if dst <= src {
+            Box::new(chunks)
+        } else {
+            Box::new(chunks.rev())
+        };
+
+        for done in chunks {
+            let len = (n - done).min(PAGE_SIZE as u64) as usize;
+
+            self.read_bytes(src + done, &mut buf[..len])?;
+            self.write_bytes(dst + done, &buf[..len])?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the `n` bytes at `dst` to `val`
+    pub(crate) fn fill(&mut self, dst: u64, val: u8, n: u64) -> Result<(), Trap> {
+        self.check_range(dst, n)?;
+
+        for segment in segments(dst as usize, n as usize) {
+            // Unwritten pages are already zero
+            if val == 0 && self.pages[segment.page].is_none() {
+                continue;
+            }
+
+            self.page_mut(segment.page)?[segment.range].fill(val);
+        }
+
+        Ok(())
+    }
+
+    /// Grows the memory by `n` pages of zeros.
+    /// Returns the previous size in pages or `None`, when the
+    /// maximum is exceeded or the pages cannot be allocated.
+    /// https://webassembly.github.io/spec/core/exec/modules.html#growing-memories
+    pub(crate) fn grow(&mut self, n: u64) -> Option<u64> {
+        let previous = self.pages();
+        let len = previous.checked_add(n)?;
+
+        if len > self.max_pages() || self.max.is_some_and(|max| len > max) {
+            debug!("Growing the memory to {} pages exceeds the limit", len);
+            return None;
+        }
+
+        // Only the list of the pages is allocated
+        self.pages.try_reserve(n as usize).ok()?;
+        self.pages.resize(len as usize, None);
+
+        Some(previous)
+    }
+}
+
+/// Allocate a page of zeros without aborting, when the allocation fails
+fn zeroed_page() -> Result<Box<[u8]>, Trap> {
+    let mut bytes = Vec::new();
+    bytes.try_reserve_exact(PAGE_SIZE).map_err(|_| {
+        debug!("Cannot allocate a page of the memory");
+        Trap::OutOfMemory
+    })?;
+    bytes.resize(PAGE_SIZE, 0);
+
+    Ok(bytes.into_boxed_slice())
+}
+
+// Overwritten debug implementation
+// Because `data` can have a lot of entries, which
+// can be a problem when printing
+impl fmt::Debug for MemoryInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryInstance")
+            .field("data (only length)", &self.len())
+            .field("max", &self.max)
+            .field("shared", &self.shared)
+            .field("index_type", &self.index_type)
+            .finish()
+    }
 }
@@ -1,30 +1,51 @@
 #![allow(dead_code)]
 #![allow(clippy::upper_case_acronyms)]
 
+mod atomics;
+pub mod bytecode;
+pub mod config;
+pub mod exception;
+pub mod execution;
 pub(crate) mod export;
+pub mod fuel;
 pub mod func;
 pub mod import_resolver;
-pub(crate) mod memory;
+pub mod interrupt;
+pub mod linker;
+pub mod memory;
 pub mod module;
 mod op;
 pub(crate) mod prelude;
+pub mod segment;
+mod simd;
 pub mod stack;
+mod stack_effect;
 pub mod store;
 pub(crate) mod table;
+pub mod trap;
+pub mod typed;
 
-use self::stack::StackContent;
-use self::stack::StackContent::*;
-use self::stack::{Frame, Label};
+use self::bytecode::Op;
+use self::config::Config;
+use self::stack::{count_slots, slots, Frame, Label, LabelKind};
 use self::store::Store;
 use crate::convert;
 pub use crate::debugger::BorrowedProgramState;
 pub use crate::debugger::{ProgramCounter, RelativeProgramCounter};
+use crate::engine::fuel::{Fuel, FuelCost};
 use crate::engine::func::FuncInstance;
-use crate::engine::import_resolver::Import;
+use crate::engine::func::{Caller, HostFunc};
+use crate::engine::import_resolver::{ImportResolver, Imports};
+use crate::engine::memory::SharedMemory;
 use crate::engine::module::ModuleInstance;
 use crate::engine::module::Functions;
 pub use crate::engine::store::GlobalInstance;
 pub use crate::engine::table::TableInstance;
+pub use crate::engine::trap::Trap;
+pub use crate::engine::exception::{Exception, TagInstance};
+pub use crate::engine::execution::Execution;
+pub use crate::engine::interrupt::InterruptHandle;
+pub use crate::engine::typed::{TypedFunc, WasmTy, WasmTyList};
 use crate::operations::*;
 pub use crate::page::Page;
 use crate::value::{Value, Value::*};
@@ -36,19 +57,36 @@ pub use wasm_parser::Module;
 
 #[derive(Debug)]
 pub struct Engine {
+    /// The instance of the module, which is currently executed
     pub module_instance: ModuleInstance,
     /// `started` declares if the engine was already run.
     pub started: bool,
     pub store: Store,
     debugger: Box<dyn ProgramCounter>,
+    /// All module instances, which share the store (see `Linker`).
+    /// The entry of the current module is moved to `module_instance`.
+    modules: Vec<ModuleInstance>,
+    module_addr: ModuleAddr,
+    /// Execution is unlimited, when it is not set
+    fuel: Option<Fuel>,
+    /// The frames of the wasm functions, which were called
+    call_stack: Vec<Frame>,
+    max_call_depth: usize,
+    max_stack_size: usize,
+    /// The number of instructions, which are executed before
+    /// an `Execution` pauses. Execution does not pause, when it is not set
+    budget: Option<u64>,
+    /// Shared with the handles of `interrupt_handle`
+    interrupt: InterruptHandle,
 }
 
-#[derive(Debug)]
-pub enum InstructionOutcome {
-    EXIT,
-    BRANCH(u32),
-    RETURN,
-}
+/// The default maximum of nested function calls.
+/// The frames are kept on the engine's call stack,
+/// therefore the host's stack does not limit it.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 100_000;
+
+/// The default maximum of entries on the stack
+pub const DEFAULT_MAX_STACK_SIZE: usize = 1024 * 1024;
 
 #[allow(dead_code)]
 pub(crate) fn empty_engine() -> Engine {
@@ -56,12 +94,44 @@ pub(crate) fn empty_engine() -> Engine {
 
     Engine {
         started: true,
-        store: Store::default_with_frame(),
+        store: Store::default(),
         module_instance: mi,
         debugger: Box::new(RelativeProgramCounter::default()),
+        modules: vec![ModuleInstance::default()],
+        module_addr: ModuleAddr::new(0),
+        fuel: None,
+        call_stack: Vec::new(),
+        max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+        max_stack_size: DEFAULT_MAX_STACK_SIZE,
+        budget: None,
+        interrupt: InterruptHandle::default(),
     }
 }
 
+/// Why the execution of a frame stopped
+enum Outcome {
+    /// The function is called with the arguments on top of the stack
+    Call(FuncAddr),
+    /// Like `Call`, but the callee replaces the frame
+    TailCall(FuncAddr),
+    /// The results of the function are on top of the stack
+    Return,
+    /// The exception is unwound to its handler
+    Throw(Exception),
+    /// The budget of instructions is used up.
+    /// The frame continues at its saved `pc`
+    Pause,
+}
+
+/// The heights of the stacks and the module, when a function was invoked
+#[derive(Debug, Clone)]
+struct Entry {
+    depth: usize,
+    height: usize,
+    labels: usize,
+    module_addr: ModuleAddr,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Variable {
     pub mutable: bool,
@@ -78,182 +148,124 @@ impl Variable {
     }
 }
 
+/// Pops the value of the type `$ty`
 #[macro_export]
 macro_rules! fetch_unop {
-    ($stack: expr) => {{
-        use crate::engine::stack::StackContent;
-        debug!("Popping {:?}", $stack.last());
-        let v1 = match $stack.pop().unwrap() {
-            StackContent::Value(v) => v,
-            x => panic!("Top of stack was not a value, but instead {:?}", x),
-        };
+    ($stack: expr, $ty: ident) => {{
+        let v1 = $stack.pop_value($crate::engine::ValueType::$ty)?;
+        debug!("Popped {:?}", v1);
         (v1)
     }};
 }
 
+/// Pops two values of the type `$ty`, the top of the stack is first
 #[macro_export]
 macro_rules! fetch_binop {
-    ($stack: expr) => {{
-        use crate::fetch_unop;
-
-        let v1 = fetch_unop!($stack);
-        let v2 = fetch_unop!($stack);
+    ($stack: expr, $ty: ident) => {{
+        let v1 = $crate::fetch_unop!($stack, $ty);
+        let v2 = $crate::fetch_unop!($stack, $ty);
 
         (v1, v2)
     }};
 }
 
-macro_rules! load_memory {
-    ($self:expr, $arg:expr, $size:expr, $ty:ty, $variant:expr) => {
-        let v1 = fetch_unop!($self.store.stack);
-
-        if let I32(v) = v1 {
-            let ea = (v + $arg.offset as i32) as usize;
+/// Pops the operand of the type `$ty` and pushes the result of `$e`
+macro_rules! unop {
+    ($self:expr, $ty:ident, |$v:ident| $e:expr) => {{
+        let $v = fetch_unop!($self.store.stack, $ty);
+        $self.store.stack.push_value($e);
+    }};
+}
 
-            let module = &$self.module_instance;
+/// Pops the operands of the type `$ty` and pushes the result of `$e`.
+/// `$v1` is the first operand and `$v2` the top of the stack.
+macro_rules! binop {
+    ($self:expr, $ty:ident, |$v1:ident, $v2:ident| $e:expr) => {{
+        let ($v2, $v1) = fetch_binop!($self.store.stack, $ty);
+        $self.store.stack.push_value($e);
+    }};
+}
 
-            let addr = module.lookup_memory_addr(&0)
-                .context("No memory address found")?;
+/// Get the memory instance of the current module
+macro_rules! memory_instance {
+    ($self:expr) => {{
+        let addr = $self
+            .module_instance
+            .lookup_memory_addr(&0)
+            .context("No memory address found")?;
 
-            let instance = &$self.store.memory[addr.get()];
+        $self
+            .store
+            .memory
+            .get(addr.get())
+            .with_context(|| format!("Cannot access memory addr {:?}", addr))?
+    }};
+}
 
-            debug!("instance {:?}", instance);
-            debug!("Range {:?}", ea..ea + $size);
-            debug!("part {:?}", &instance.data[ea..ea + $size]);
+macro_rules! load_memory {
+    ($self:expr, $arg:expr, $size:expr, $ty:ty, $variant:expr) => {
+        let base = $self.store.stack.pop_u64()?;
 
-            let mut b = vec![0; $size];
-            b.copy_from_slice(&instance.data[ea..ea + $size]);
-            assert!(b.len() == $size);
+        let mut b = [0; $size];
+        memory_instance!($self).read().load(base, $arg.offset, &mut b)?;
 
-            debug!("b {:?}", b);
+        debug!("b {:?}", b);
 
-            unsafe {
-                //Convert [u8] to [number]
-                let c = &*(b.as_slice() as *const [u8] as *const [$ty]);
-                debug!("c is {:?}", c);
+        //Convert [u8] to number
+        let c = <$ty>::from_le_bytes(b);
+        debug!("c is {:?}", c);
 
-                $self.store.stack.push(StackContent::Value($variant(c[0])));
-            }
-        } else {
-            panic!("Expected I32, found something else");
-        }
+        $self.store.stack.push_value($variant(c));
     };
 }
 
+/// Loads `$cast_ty` and extends it to `$variant`
 macro_rules! load_memory_sx {
-    ($self:expr, $arg:expr, $size:expr, $ty:ty, $variant:expr, $cast_ty:ty) => {
-        let v1 = fetch_unop!($self.store.stack);
-
-        if let I32(v) = v1 {
-            let ea = (v + $arg.offset as i32) as usize;
-
-            let module = &$self.module_instance;
-
-            let addr = module.lookup_memory_addr(&0).context("No memory address found")?;
+    ($self:expr, $arg:expr, $variant:expr, $cast_ty:ty) => {
+        let base = $self.store.stack.pop_u64()?;
 
-            let instance = &$self
-                .store
-                .memory
-                .get(addr.get())
-                .with_context(|| format!("Cannot access memory addr {:?}", addr))?;
+        const SIZE: usize = std::mem::size_of::<$cast_ty>();
 
-            debug!("instance {:?}", instance);
-            debug!("Range {:?}", ea..ea + $size);
-            debug!(
-                "part {:?}",
-                &instance
-                    .data
-                    .get(ea..ea + $size)
-                    .context("Cannot access range")
-            );
+        let mut b = [0; SIZE];
+        memory_instance!($self).read().load(base, $arg.offset, &mut b)?;
 
-            let mut b = vec![0; $size];
-            b.copy_from_slice(
-                &instance
-                    .data
-                    .get(ea..ea + $size)
-                    .context("Cannot access range")?,
-            );
-            assert!(b.len() == $size);
+        debug!("b {:?}", b);
 
-            debug!("b {:?}", b);
+        // Convert [u8] to number
+        let c = <$cast_ty>::from_le_bytes(b);
+        debug!("c is {:?}", c);
 
-            unsafe {
-                // Convert [u8] to [number]
-                let c = &*(b.as_slice() as *const [u8] as *const [$ty]);
-                debug!("c is {:?}", c);
-
-                // THIS IS DIFFERENT THAN `load_memory!`
-
-                let c2 = c[0] as $cast_ty;
-
-                $self
-                    .store
-                    .stack
-                    .push(StackContent::Value($variant(c2.into())));
-
-                // END
-            }
-        } else {
-            panic!("Expected I32, found something else");
-        }
+        $self.store.stack.push_value($variant(c.into()));
     };
 }
 
 macro_rules! store_memory {
     ($self:expr, $arg:expr, $size:expr, $ty:ty, $variant:ident) => {
-        let k = fetch_unop!($self.store.stack);
-        let v1 = fetch_unop!($self.store.stack);
+        let k = fetch_unop!($self.store.stack, $variant);
+        let base = $self.store.stack.pop_u64()?;
 
         if let $variant(t) = k {
-            if let I32(v) = v1 {
-                let ea = (v + $arg.offset as i32) as usize;
-
-                let module = &$self.module_instance;
-
-                let addr = module.lookup_memory_addr(&0).context("No memory address found")?;
-
-                let instance = &mut $self.store.memory[addr.get()];
-
-                let mut bytes = t.to_le_bytes();
-
-                instance.data[ea..ea + $size].swap_with_slice(&mut bytes);
-            } else {
-                panic!("Expected I32, found something else");
-            }
+            memory_instance!($self)
+                .write()
+                .store(base, $arg.offset, &t.to_le_bytes())?;
         } else {
-            panic!("Expected a different value on the stack");
+            bail!("Expected {}, found {:?}", stringify!($variant), k);
         }
     };
 }
 
+/// Stores the lower `$N` bits of the value
 macro_rules! store_memory_n {
-    ($self:expr, $arg:expr, $size:expr, $ty:ty, $variant:ident, $new_ty:ty, $N:expr) => {
-        let k = fetch_unop!($self.store.stack);
-        let v1 = fetch_unop!($self.store.stack);
+    ($self:expr, $arg:expr, $variant:ident, $N:expr) => {
+        let k = fetch_unop!($self.store.stack, $variant);
+        let base = $self.store.stack.pop_u64()?;
 
         if let $variant(t) = k {
-            if let I32(v) = v1 {
-                let ea = (v + $arg.offset as i32) as usize;
-
-                let module = &$self.module_instance;
-
-                let addr = module.lookup_memory_addr(&0).context("No memory address found")?;
-
-                let instance = &mut $self.store.memory[addr.get()];
-
-                if instance.data.len() < ea + ($N / 8) {
-                    panic!("Offset is corrupt");
-                }
-
-                let mut bytes = t.to_le_bytes();
-
-                instance.data[ea..ea + $size].swap_with_slice(&mut bytes[0..($N / 8)]);
-            } else {
-                panic!("Expected I32, found something else");
-            }
+            memory_instance!($self)
+                .write()
+                .store(base, $arg.offset, &t.to_le_bytes()[0..($N / 8)])?;
         } else {
-            panic!("Expected a different value on the stack");
+            bail!("Expected {}, found {:?}", stringify!($variant), k);
         }
     };
 }
@@ -264,41 +276,186 @@ impl Engine {
         functions: &Functions,
         module: &Module,
         debugger: Box<dyn ProgramCounter>,
-        imports: &[Import],
+        imports: Imports,
     ) -> Result<Engine> {
+        Self::with_config(mi, functions, module, debugger, imports, Config::default())
+    }
+
+    /// Like `new`, but the `config` applies to the start function as well
+    pub fn with_config(
+        mi: ModuleInstance,
+        functions: &Functions,
+        module: &Module,
+        debugger: Box<dyn ProgramCounter>,
+        imports: Imports,
+        config: Config,
+    ) -> Result<Engine> {
+        let fuel = config.fuel.map(|fuel| {
+            let mut fuel = Fuel::new(fuel);
+            fuel.set_cost(config.fuel_cost);
+            fuel
+        });
+
         let mut e = Engine {
             module_instance: mi,
             started: false,
             store: Store::default(),
             debugger,
+            modules: vec![ModuleInstance::default()],
+            module_addr: ModuleAddr::new(0),
+            fuel,
+            call_stack: Vec::new(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_stack_size: DEFAULT_MAX_STACK_SIZE,
+            budget: None,
+            interrupt: config.interrupt,
         };
 
-        e.allocate(module, functions, &imports)
+        let imports = crate::allocation::create_import_resolver(imports)
+            .context("Resolving imports failed")?;
+
+        e.allocate(module, functions, imports)
             .context("Allocation instance failed")?;
         e.instantiation(module).context("Instantiation failed")?;
 
         Ok(e)
     }
 
+    /// Instantiates another module in the store of the engine.
+    /// The current module does not change.
+    pub(crate) fn instantiate_module(&mut self, module: &Module, imports: ImportResolver) -> Result<ModuleAddr> {
+        let (mi, functions) = ModuleInstance::new(module);
+
+        let addr = ModuleAddr::new(self.modules.len());
+        self.modules.push(mi);
+
+        let previous = self.switch_module(&addr)?;
+
+        let result = self
+            .allocate(module, &functions, imports)
+            .context("Allocation instance failed")
+            .and_then(|_| self.instantiation(module).context("Instantiation failed"));
+
+        self.switch_module(&previous)?;
+
+        result.map(|_| addr)
+    }
+
+    /// Get the address of the current module instance
+    pub fn get_module_addr(&self) -> &ModuleAddr {
+        &self.module_addr
+    }
+
+    /// Select the module instance, whose exports are used
+    /// by `get` and the invoking of exported functions.
+    pub fn select_module(&mut self, addr: &ModuleAddr) -> Result<()> {
+        self.switch_module(addr)?;
+
+        Ok(())
+    }
+
+    /// Set the fuel, which bounds the number of executed instructions.
+    /// The start function is only metered with the fuel of `Config::fuel`.
+    pub fn set_fuel(&mut self, fuel: u64) {
+        match self.fuel {
+            Some(ref mut f) => f.set_remaining(fuel),
+            None => self.fuel = Some(Fuel::new(fuel)),
+        }
+    }
+
+    /// Returns a handle, which interrupts the engine from another thread.
+    /// The start function is only interrupted by the handle of `Config::interrupt_handle`.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Get the remaining fuel or `None`, when the execution is unlimited
+    pub fn remaining_fuel(&self) -> Option<u64> {
+        self.fuel.as_ref().map(|f| f.remaining())
+    }
+
+    /// Set the cost of each opcode. The fuel must be set before.
+    pub fn set_fuel_cost(&mut self, cost: FuelCost) -> Result<()> {
+        self.fuel
+            .as_mut()
+            .context("Fuel metering is not enabled")?
+            .set_cost(cost);
+
+        Ok(())
+    }
+
+    /// Set the maximum of nested function calls.
+    /// Exceeding it traps with `Trap::CallStackExhausted`.
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_call_depth = depth;
+    }
+
+    /// Set the maximum of entries on the stack.
+    /// It is checked on every call, exceeding it traps with `Trap::CallStackExhausted`.
+    pub fn set_max_stack_size(&mut self, size: usize) {
+        self.max_stack_size = size;
+    }
+
+    /// Get the module instance by address
+    pub fn get_module_instance(&self, addr: &ModuleAddr) -> Result<&ModuleInstance> {
+        if *addr == self.module_addr {
+            return Ok(&self.module_instance);
+        }
+
+        self.modules
+            .get(addr.get())
+            .ok_or_else(|| anyhow!("Cannot find module instance by addr {:?}", addr))
+    }
+
+    /// Moves the module instance of `addr` to `module_instance`
+    /// and returns the address of the previous one.
+    fn switch_module(&mut self, addr: &ModuleAddr) -> Result<ModuleAddr> {
+        let previous = self.module_addr.clone();
+
+        if *addr == previous {
+            return Ok(previous);
+        }
+
+        let next = std::mem::take(
+            self.modules
+                .get_mut(addr.get())
+                .ok_or_else(|| anyhow!("Cannot find module instance by addr {:?}", addr))?,
+        );
+
+        self.modules[previous.get()] = std::mem::replace(&mut self.module_instance, next);
+        self.module_addr = addr.clone();
+
+        debug!("Switched module from {:?} to {:?}", previous, addr);
+
+        Ok(previous)
+    }
+
     /// Initializes `n` pages in memory
     pub(crate) fn init_memory(&mut self, n: usize) -> Result<()> {
-        let res = self
-            .store
-            .init_memory(n)
-            .context("Trying to initialize an empty memory instance");
-
         // only one memory module is allowed
-        assert!(self.module_instance.get_mem_addrs().is_empty());
-        let addr = MemoryAddr::new(self.module_instance.get_mem_addrs().len());
-        self.module_instance.store_memory_addr(addr)?;
+        if !self.module_instance.get_mem_addrs().is_empty() {
+            bail!("A memory instance is already defined or imported");
+        }
 
-        res
+        self.store
+            .init_memory(n)
+            .context("Trying to initialize an empty memory instance")?;
+
+        let addr = MemoryAddr::new(self.store.memory.len() - 1);
+        self.module_instance.store_memory_addr(addr)
     }
 
-    fn allocate(&mut self, m: &Module, functions: &Functions, imports: &[Import]) -> Result<()> {
+    fn allocate(&mut self, m: &Module, functions: &Functions, imports: ImportResolver) -> Result<()> {
         info!("Allocation");
-        crate::allocation::allocate(m, &mut self.module_instance, functions, &mut self.store, imports)
-            .context("Allocation failed")?;
+        crate::allocation::allocate(
+            m,
+            &mut self.module_instance,
+            functions,
+            &mut self.store,
+            imports,
+            &self.module_addr,
+        )
+        .context("Allocation failed")?;
 
         Ok(())
     }
@@ -334,7 +491,7 @@ impl Engine {
 
                 Ok(self
                     .store
-                    .get_global_instance(&global_addr)
+                    .get_global_instance(global_addr)
                     .context("Global not found in the store")?
                     .val)
             }
@@ -342,11 +499,55 @@ impl Engine {
         }
     }
 
+    /// Get an exported memory.
+    /// The handle is shared, therefore writes of the host
+    /// are visible to the instance and vice versa.
+    pub fn get_memory(&self, name: &str) -> Result<SharedMemory> {
+        debug!("get memory for {:?}", name);
+
+        let export_instance = self.module_instance.get_export_instance_by_name(name)
+            .ok_or_else(|| anyhow!("Export instance was not found by name: {}", name))?;
+        debug!("Export {:#?}", export_instance);
+
+        match export_instance.value {
+            ExternalKindType::Memory { ty } => {
+                let memory_addr = self.module_instance.lookup_memory_addr(&ty)
+                    .context("Memory not found")?;
+
+                Ok(self
+                    .store
+                    .get_memory_instance(memory_addr)
+                    .context("Memory not found in the store")?
+                    .clone())
+            }
+            _ => Err(anyhow!("Exported memory not found")),
+        }
+    }
+
+    /// Get the address of an exported tag.
+    /// It identifies the tag of an uncaught `Exception`.
+    pub fn get_tag(&self, name: &str) -> Result<TagAddr> {
+        debug!("get tag for {:?}", name);
+
+        let export_instance = self.module_instance.get_export_instance_by_name(name)
+            .ok_or_else(|| anyhow!("Export instance was not found by name: {}", name))?;
+
+        match export_instance.value {
+            ExternalKindType::Tag { ty } => Ok(*self
+                .module_instance
+                .lookup_tag_addr(&ty)
+                .context("Tag not found")?),
+            _ => Err(anyhow!("Exported tag not found")),
+        }
+    }
+
     /// Adding new function to the engine
     /// It will allocate the function in store and add it to the module's code.
     pub(crate) fn add_function(&mut self, signature: FunctionSignature, body: FunctionBody) -> Result<()> {
         //self.module_instance.add_code(body.clone())?;
-        self.store.allocate_func_instance(signature, body);
+        let bytecode = bytecode::compile(&body, &signature, &self.module_instance)?;
+        self.store
+            .allocate_func_instance(signature, body, bytecode, None, self.module_addr.clone());
 
         Ok(())
     }
@@ -369,8 +570,18 @@ impl Engine {
 
     /// Take only exported functions into consideration
     /// `idx` is the id of the export instance, not the function
-    pub fn invoke_exported_function(&mut self, idx: u32, args: Vec<Value>) -> Result<()> {
+    /// Returns the results of the function.
+    pub fn invoke_exported_function(&mut self, idx: u32, args: Vec<Value>) -> Result<Vec<Value>> {
         debug!("invoke_exported_function {} with args {:?}", idx, args);
+
+        let func_addr = self.lookup_exported_function_addr(idx)?;
+
+        self.invoke_and_collect_results(func_addr, args)
+            .context("Invoking the function failed")
+    }
+
+    /// Returns the function's addr of the export instance `idx`
+    fn lookup_exported_function_addr(&self, idx: u32) -> Result<FuncAddr> {
         let k = {
             let x = &self.module_instance;
 
@@ -386,23 +597,19 @@ impl Engine {
         debug!("Exports {:#?}", k);
 
         match k {
-            ExternalKindType::Function { ty } => {
-                let func_addr = self.module_instance.lookup_function_addr(&ty)
-                    .ok_or_else(|| anyhow!("Cannot find function's addr"))?
-                    .clone();
-
-                self.invoke_function(func_addr, args)
-                    .context("Invoking the function failed")?;
-            }
-            _ => {
-                return Err(anyhow!("Exported function not found"));
-            }
+            ExternalKindType::Function { ty } => Ok(*self
+                .module_instance
+                .lookup_function_addr(&ty)
+                .ok_or_else(|| anyhow!("Cannot find function's addr"))?),
+            _ => Err(anyhow!("Exported function not found")),
         }
-
-        Ok(())
     }
 
-    pub fn invoke_exported_function_by_name(&mut self, name: &str, args: Vec<Value>) -> Result<()> {
+    pub fn invoke_exported_function_by_name(
+        &mut self,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<Vec<Value>> {
         debug!(
             "Invoking exporting function by name {} with args {:?}",
             name, args
@@ -417,61 +624,410 @@ impl Engine {
         debug!("The function addr is {:?}", idx);
 
         self.invoke_exported_function(idx as u32, args)
-            .context("Invoking the exported function failed")?;
+            .context("Invoking the exported function failed")
+    }
 
-        Ok(())
+    /// Starts the exported function `name`, but does not run it.
+    /// The returned `Execution` pauses, resumes and steps it.
+    pub fn start_invoke(&mut self, name: &str, args: Vec<Value>) -> Result<Execution<'_>> {
+        debug!("Starting exported function {} with args {:?}", name, args);
+
+        let idx = self
+            .module_instance
+            .position_export_instance_by_name(name)
+            .ok_or_else(|| anyhow!("Cannot find export instance by name: {}", name))?;
+
+        let func_addr = self.lookup_exported_function_addr(idx as u32)?;
+
+        Execution::new(self, func_addr, args)
+    }
+
+    /// Returns a handle to the exported function `name`, which
+    /// is called with native types instead of `Value`.
+    /// The signature is only checked once, when the handle is created.
+    pub fn get_typed_func<Params, Results>(&self, name: &str) -> Result<TypedFunc<Params, Results>>
+    where
+        Params: WasmTyList,
+        Results: WasmTyList,
+    {
+        let idx = self
+            .module_instance
+            .position_export_instance_by_name(name)
+            .ok_or_else(|| anyhow!("Cannot find export instance by name: {}", name))?;
+
+        let func_addr = self.lookup_exported_function_addr(idx as u32)?;
+
+        let expected = FunctionSignature {
+            param_types: Params::value_types(),
+            return_types: Results::value_types(),
+        };
+        let actual = &self.get_function_instance(&func_addr)?.ty;
+
+        if *actual != expected {
+            bail!(
+                "Function {} has the signature {:?}, but expected {:?}",
+                name,
+                actual,
+                expected
+            );
+        }
+
+        Ok(TypedFunc::new(func_addr))
+    }
+
+    /// Invokes the function and pops its results from the stack.
+    /// The stack has the same height as before the call, even
+    /// when the function traps. Therefore, the engine can be reused.
+    pub(crate) fn invoke_and_collect_results(
+        &mut self,
+        func_addr: FuncAddr,
+        args: Vec<Value>,
+    ) -> Result<Vec<Value>> {
+        let height = self.store.stack.len();
+        let return_types = self.get_function_instance(&func_addr)?.ty.return_types.clone();
+
+        let results = self
+            .invoke_function(func_addr, args)
+            .and_then(|_| self.pop_results(height, &return_types));
+
+        self.store.stack.truncate(height);
+
+        results
+    }
+
+    /// Pops the results of the function, which were pushed above `height`
+    fn pop_results(&mut self, height: usize, return_types: &[ValueType]) -> Result<Vec<Value>> {
+        let pushed = self.store.stack.len().saturating_sub(height);
+        let expected = count_slots(return_types) as usize;
+        if pushed != expected {
+            bail!(
+                "Function returned {} slots, but expected {}",
+                pushed,
+                expected
+            );
+        }
+
+        // The types are known by the signature
+        self.store.stack.pop_values(return_types)
     }
 
     pub(crate) fn invoke_function(&mut self, func_addr: FuncAddr, args: Vec<Value>) -> Result<()> {
         self.check_parameters_of_function(&func_addr, &args)
             .with_context(|| format!("Checking parameter for function {:?} failed", func_addr))?;
 
-        let count_return_types =
-            self.get_function_instance(&func_addr)?.ty.return_types.len() as u32;
+        if let Some(host) = self.store.get_func_instance(&func_addr)?.host.clone() {
+            return self
+                .invoke_host_function(&func_addr, &host, args)
+                .with_context(|| format!("Host function with addr {:?} failed", func_addr));
+        }
+
+        let entry = self.entry();
+
+        let result = self
+            .push_wasm_call(func_addr, args)
+            .and_then(|_| self.run_call_stack(entry.depth));
 
-        let typed_locals = &self.store.get_func_instance(&func_addr)?.code.locals;
+        // The stacks are left with the height of the entry,
+        // even if the function traps.
+        self.leave(&entry, result.is_err())?;
 
-        debug!("defined locals are {:#?}", typed_locals);
+        result
+    }
 
-        let mut locals = args;
+    /// The state of the engine before a function is invoked
+    fn entry(&self) -> Entry {
+        Entry {
+            depth: self.call_stack.len(),
+            height: self.store.stack.len(),
+            labels: self.store.labels.len(),
+            module_addr: self.module_addr.clone(),
+        }
+    }
+
+    /// Pushes the arguments and the frame of the wasm function
+    fn push_wasm_call(&mut self, func_addr: FuncAddr, args: Vec<Value>) -> Result<()> {
+        for arg in args {
+            self.store.stack.push_value(arg);
+        }
+
+        self.push_frame(func_addr)
+    }
+
+    /// Restores the state of `entry`. The results of the function
+    /// are only kept on the stack, when `truncate` is not set.
+    fn leave(&mut self, entry: &Entry, truncate: bool) -> Result<()> {
+        if truncate {
+            self.store.stack.truncate(entry.height);
+        }
+
+        self.call_stack.truncate(entry.depth);
+        self.store.labels.truncate(entry.labels);
+        self.switch_module(&entry.module_addr)?;
+
+        Ok(())
+    }
+
+    /// Pushes the frame of the wasm function on the call stack
+    /// and switches to the module instance, which defines it.
+    /// The arguments are already on top of the stack and become
+    /// the first locals of the frame.
+    pub(crate) fn push_frame(&mut self, func_addr: FuncAddr) -> Result<()> {
+        if self.call_stack.len() >= self.max_call_depth
+            || self.store.stack.len() >= self.max_stack_size
+        {
+            debug!(
+                "Call depth {} or stack size {} exceeded",
+                self.call_stack.len(),
+                self.store.stack.len()
+            );
+            bail!(Trap::CallStackExhausted);
+        }
+
+        let func_instance = self.store.get_func_instance(&func_addr)?;
+        let arity = count_slots(&func_instance.ty.return_types);
+        let params = count_slots(&func_instance.ty.param_types) as usize;
+        let module = func_instance.module.clone();
+        // Only the reference is cloned
+        let bytecode = func_instance.bytecode.clone();
 
         // All parameters are `locals`, but
         // we can additionally define more of them.
-        // This is done in the function definition of locals
-        // It is very important to use the correct type
-        debug!("Adding additional locals");
-        for i in 0..typed_locals.len() {
-            let entry = typed_locals.get(i).unwrap();
-
-            // There is a count property to define multiple at once
-            for _ in 0..entry.count {
-                match entry.ty {
-                    ValueType::I32 => locals.push(I32(0)),
-                    ValueType::I64 => locals.push(I64(0)),
-                    ValueType::F32 => locals.push(F32(0.0)),
-                    ValueType::F64 => locals.push(F64(0.0)),
+        // There is a count property to define multiple at once
+        let additional_locals: usize = func_instance
+            .code
+            .locals
+            .iter()
+            .map(|entry| entry.count as usize * slots(entry.ty) as usize)
+            .sum();
+
+        let start = self
+            .store
+            .stack
+            .len()
+            .checked_sub(params)
+            .ok_or_else(|| anyhow!("Not enough arguments on the stack for {:?}", func_addr))?;
+
+        // Zero is the default of every type
+        self.store.stack.extend_zeros(additional_locals);
+        let end = self.store.stack.len();
+
+        debug!("Locals for frame are {:?}", start..end);
+
+        self.switch_module(&module)?;
+
+        self.call_stack.push(Frame {
+            func_addr,
+            module,
+            pc: 0,
+            arity,
+            locals: start..end,
+            labels: self.store.labels.len(),
+            bytecode,
+        });
+
+        // The label of the function's block
+        self.store.labels.push(Label::new(end));
+
+        Ok(())
+    }
+
+    /// Runs the frames above `depth` of the call stack until they returned
+    /// or the budget of instructions is used up.
+    /// Calls of wasm functions push a new frame, which is run next.
+    fn run_call_stack(&mut self, depth: usize) -> Result<()> {
+        while self.call_stack.len() > depth {
+            let outcome = self.run_frame().with_context(|| {
+                format!(
+                    "Function with addr {:?} failed",
+                    self.call_stack.last().map(|frame| &frame.func_addr)
+                )
+            })?;
+
+            match outcome {
+                Outcome::Call(func_addr) => {
+                    self.interrupt.check()?;
+
+                    let func_addr_inner = func_addr.get();
+                    self.call_function(func_addr)
+                        .with_context(|| format!("Invoking function {:?} failed", func_addr_inner))?;
+                }
+                Outcome::TailCall(func_addr) => {
+                    self.interrupt.check()?;
+
+                    let func_addr_inner = func_addr.get();
+                    self.return_call(func_addr, depth)
+                        .with_context(|| format!("Tail calling function {:?} failed", func_addr_inner))?;
+                }
+                Outcome::Return => {
+                    self.return_from_frame(depth)?;
+                }
+                Outcome::Throw(exception) => {
+                    self.unwind(exception, depth)?;
+                }
+                Outcome::Pause => {
+                    debug!("Execution paused with {} frames", self.call_stack.len());
+                    return Ok(());
                 }
             }
         }
 
-        debug!("Locals for frame are {:?}", locals);
+        Ok(())
+    }
 
-        let mut frame = Frame {
-            arity: count_return_types,
-            locals,
-        };
+    /// Pops the frame of the returning function.
+    /// Its results replace the locals on the stack.
+    fn return_from_frame(&mut self, depth: usize) -> Result<()> {
+        let frame = self
+            .call_stack
+            .pop()
+            .ok_or_else(|| anyhow!("The call stack is empty"))?;
 
-        self.store.stack.push(StackContent::Frame(frame.clone()));
+        debug!("Function with addr {:?} returned", frame.func_addr);
 
-        self.store
+        let results = self
+            .store
             .stack
-            .push(StackContent::Label(Label::new(count_return_types, 0)));
+            .len()
+            .checked_sub(frame.arity as usize)
+            .filter(|results| *results >= frame.locals.start)
+            .ok_or_else(|| anyhow!("Not enough results on the stack"))?;
+
+        self.store.stack.remove_range(frame.locals.start..results);
+        self.store.labels.truncate(frame.labels);
+
+        self.switch_to_caller(depth)
+    }
 
-        trace!("stack before invoking {:#?}", self.store.stack);
+    /// Continues at the innermost handler, which catches the exception.
+    /// The frames above `depth` without a handler are popped.
+    /// Returns the exception as error, when none of them catches it.
+    fn unwind(&mut self, exception: Exception, depth: usize) -> Result<()> {
+        while self.call_stack.len() > depth {
+            if let Some((idx, pc, with_payload)) = self.find_handler(&exception)? {
+                debug!("Exception {:?} is caught by label {} at {}", exception, idx, pc);
+
+                self.store.labels.truncate(idx + 1);
+                let label = &mut self.store.labels[idx];
+                self.store.stack.truncate(label.get_height());
+
+                if with_payload {
+                    for value in exception.payload.iter() {
+                        self.store.stack.push_value(*value);
+                    }
+                }
+
+                // `rethrow` in the handler throws it again
+                label.set_kind(LabelKind::Catch(Box::new(exception)));
+
+                return self.save_pc(pc);
+            }
+
+            let frame = self
+                .call_stack
+                .pop()
+                .ok_or_else(|| anyhow!("The call stack is empty"))?;
 
-        debug!("Invoking function");
-        self.run_function(&mut frame, &func_addr)
-            .with_context(|| format!("Function with addr {:?} failed", func_addr))?;
+            debug!("Exception {:?} leaves function with addr {:?}", exception, frame.func_addr);
+
+            self.store.stack.truncate(frame.locals.start);
+            self.store.labels.truncate(frame.labels);
+
+            self.switch_to_caller(depth)?;
+        }
+
+        bail!(exception)
+    }
+
+    /// Searches the labels of the frame on top of the call stack for the handler of the exception.
+    /// Returns the index of the `try` label, the `pc` of the handler and
+    /// if the handler takes the payload (`catch_all` does not).
+    fn find_handler(&self, exception: &Exception) -> Result<Option<(usize, usize, bool)>> {
+        let frame = self
+            .call_stack
+            .last()
+            .ok_or_else(|| anyhow!("The call stack is empty"))?;
+
+        let mut next = self.store.labels.len().checked_sub(1);
+
+        while let Some(idx) = next.filter(|idx| *idx >= frame.labels) {
+            next = idx.checked_sub(1);
+
+            match self.store.labels[idx].get_kind() {
+                LabelKind::Try(try_pc) => {
+                    let catches = match frame.bytecode.instructions.get(*try_pc).map(|compiled| &compiled.op) {
+                        Some(Op::Try { catches, .. }) => catches,
+                        op => bail!("Expected a try at {}, but found {:?}", try_pc, op),
+                    };
+
+                    for catch in catches {
+                        match catch.tag {
+                            Some(tag_idx) => {
+                                let tag = self
+                                    .module_instance
+                                    .lookup_tag_addr(&tag_idx)
+                                    .ok_or_else(|| anyhow!("Cannot find tag's addr {}", tag_idx))?;
+
+                                if *tag == exception.tag {
+                                    return Ok(Some((idx, catch.pc, true)));
+                                }
+                            }
+                            None => return Ok(Some((idx, catch.pc, false))),
+                        }
+                    }
+                }
+                LabelKind::Delegate(depth) => {
+                    // The search continues at the target of `delegate`
+                    next = idx.checked_sub(*depth as usize + 1);
+                }
+                LabelKind::Block | LabelKind::Catch(_) => {}
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Switches to the module of the frame on top of the call stack,
+    /// unless the frames above `depth` are finished.
+    pub(crate) fn switch_to_caller(&mut self, depth: usize) -> Result<()> {
+        if self.call_stack.len() > depth {
+            let caller = self.call_stack.last().map(|frame| frame.module.clone());
+            if let Some(module) = caller {
+                self.switch_module(&module)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calls the host function and pushes its results on the stack.
+    /// The arguments are already checked by `invoke_function`.
+    fn invoke_host_function(
+        &mut self,
+        func_addr: &FuncAddr,
+        host: &HostFunc,
+        args: Vec<Value>,
+    ) -> Result<()> {
+        debug!("Invoking host function {:?} with {:?}", func_addr, args);
+
+        let results = {
+            let mut caller = Caller::new(&self.module_instance, &self.store.memory);
+            host.call(&mut caller, &args).context(Trap::HostError)?
+        };
+
+        let return_types = &self.store.get_func_instance(func_addr)?.ty.return_types;
+        let result_types: Vec<_> = results.iter().map(|w| ValueType::from(*w)).collect();
+
+        if *return_types != result_types {
+            bail!(
+                "Host function returned different results! {:?} != {:?}",
+                result_types,
+                return_types
+            );
+        }
+
+        for result in results {
+            self.store.stack.push_value(result);
+        }
 
         Ok(())
     }
@@ -491,30 +1047,20 @@ impl Engine {
             .iter();
 
         // Extract the ValueType
-        let argtypes = args.iter().map(|w| match *w {
-            Value::I32(_) => ValueType::I32,
-            Value::I64(_) => ValueType::I64,
-            Value::F32(_) => ValueType::F32,
-            Value::F64(_) => ValueType::F64,
-        });
+        let argtypes = args.iter().map(|w| ValueType::from(*w));
 
         let len_1 = fn_types.len();
         let len_2 = argtypes.len();
 
         // Check if `fn_types` and `argtypes` are elementwise equal
-        let is_same = fn_types.zip(argtypes).map(|(x, y)| *x == y).all(|w| w);
+        let is_same = fn_types.zip(argtypes).all(|(x, y)| *x == y);
 
         if !is_same || len_1 != len_2 {
             // Report error
 
             let argtypes2 = args
                 .iter()
-                .map(|w| match *w {
-                    Value::I32(_) => ValueType::I32,
-                    Value::I64(_) => ValueType::I64,
-                    Value::F32(_) => ValueType::F32,
-                    Value::F64(_) => ValueType::F64,
-                })
+                .map(|w| ValueType::from(*w))
                 .collect::<Vec<_>>();
 
             let fn_types2 = self
@@ -537,911 +1083,576 @@ impl Engine {
         Ok(())
     }
 
-    #[allow(clippy::cognitive_complexity)]
-    pub(crate) fn run_function(&mut self, fr: &mut Frame, func_addr: &FuncAddr) -> Result<()> {
-        debug!("Running function with addr {:?}", func_addr);
-
-        //FIXME this `.clone` is extremly expensive!!!
-        // But there is a problem
-        // Because, we iterate over the borrowed iterator,
-        // we cannot easily run the block
-        //let f = &self.module.code[idx as usize].clone();
-        let f = &self.store.get_func_instance(func_addr)?.code.clone();
-
-        //let f = &self.module.code[func_addr.get() as usize].clone();
-        //debug!("mod {:#?}", f);
-        //debug!("instance {:#?}", self.store.get_func_instance(func_addr)?.code.clone());
-
-        //let mut fr = self.get_frame()?;
-
-        debug!("frame {:#?}", fr);
+    /// Runs the frame on top of the call stack until it returns or calls another function.
+    /// The labels of the blocks only keep the height of the stack,
+    /// because the targets of the branches are already resolved.
+    fn run_frame(&mut self) -> Result<Outcome> {
+        let frame = self
+            .call_stack
+            .last()
+            .ok_or_else(|| anyhow!("The call stack is empty"))?;
+        let bytecode = frame.bytecode.clone();
+        let locals = frame.locals.clone();
+        let mut pc = frame.pc;
+
+        loop {
+            let compiled = bytecode
+                .instructions
+                .get(pc)
+                .ok_or_else(|| anyhow!("The program counter {} is out of the function", pc))?;
+            pc += 1;
+
+            if let Some((id, instruction)) = &compiled.source {
+                if let Some(ref mut budget) = self.budget {
+                    if *budget == 0 {
+                        self.save_pc(pc - 1)?;
+                        return Ok(Outcome::Pause);
+                    }
 
-        match self
-            .run_instructions(fr, &mut f.code.iter())
-            .with_context(|| format!("Running instructions for function addr {:?}", func_addr))?
-        {
-            InstructionOutcome::EXIT | InstructionOutcome::RETURN => {
-                self.exit_block()
-                    .context("Exiting block for end of function failed")?;
-            }
-            _ => {}
-        }
+                    *budget -= 1;
+                }
 
-        // implicit return
-        debug!("Implicit return (arity {:?})", fr.arity);
+                self.debugger
+                    .set_pc(BorrowedProgramState::new(
+                        *id,
+                        self.store.stack.as_slice(),
+                        &self.store.labels,
+                        &self.call_stack,
+                    ))
+                    .context("Setting program state failed")?;
 
-        debug!("Stack before function return {:#?}", self.store.stack);
+                debug!("Evaluating instruction {}", instruction);
 
-        let mut ret = Vec::new();
-        for _ in 0..fr.arity {
-            debug!("Popping {:?}", self.store.stack.last());
-            if let Some(val) = self.store.stack.pop() {
-                ret.push(val);
+                // The instruction is not executed, when the fuel is empty
+                if let Some(ref mut fuel) = self.fuel {
+                    fuel.consume(instruction)?;
+                }
             }
-        }
-
-        let mut ret = ret.into_iter().rev().collect();
-
-        if let Some(Frame(_)) = self.store.stack.pop() {
-            debug!("Popping frame");
-        } else {
-            bail!("Cannot remove frame");
-        }
-
-        self.store.stack.append(&mut ret);
 
-        debug!("Stack after function return {:#?}", self.store.stack);
-
-        Ok(())
-    }
-
-    #[allow(clippy::cognitive_complexity)]
-    fn run_instructions<'a>(
-        &mut self,
-        fr: &mut Frame,
-        instruction_wrapper: &'a mut impl std::iter::Iterator<Item=&'a InstructionWrapper>,
-    ) -> Result<InstructionOutcome> {
-        //let mut ip = 0;
-        for wrapped_instruction in instruction_wrapper {
-            self.debugger
-                .set_pc(BorrowedProgramState::new(
-                    wrapped_instruction.get_id(),
-                    &self.store.stack,
-                    &fr.locals,
-                ))
-                .context("Setting program state failed")?;
-
-            let instruction = wrapped_instruction.get_instruction();
-            debug!("Evaluating instruction {}", instruction);
-
-            match &instruction {
-                OP_LOCAL_GET(idx) => {
-                    self.local_get(idx, fr)
-                        .with_context(|| format!("OP_LOCAL_GET({})", idx))?;
-                }
-                OP_LOCAL_SET(idx) => {
-                    self.local_set(idx, fr)
-                        .with_context(|| format!("OP_LOCAL_SET({})", idx))?;
-                }
-                OP_LOCAL_TEE(idx) => {
-                    self.local_tee(idx, fr)
-                        .with_context(|| format!("OP_LOCAL_TEE({})", idx))?;
-                }
-                OP_GLOBAL_GET(idx) => {
-                    self.global_get(idx, fr)
-                        .with_context(|| format!("OP_GLOBAL_GET({})", idx))?;
-                }
-                OP_GLOBAL_SET(idx) => {
-                    self.global_set(idx, fr)
-                        .with_context(|| format!("OP_GLOBAL_SET({})", idx))?;
-                }
-                OP_I32_CONST(v) => {
-                    debug!("OP_I32_CONST: pushing {} to stack", v);
-                    self.store.stack.push(StackContent::Value(I32(*v)));
-                    debug!("stack {:#?}", self.store.stack);
-                }
-                OP_I64_CONST(v) => {
-                    debug!("OP_I64_CONST: pushing {} to stack", v);
-                    self.store.stack.push(StackContent::Value(I64(*v)))
-                }
-                OP_F32_CONST(v) => {
-                    debug!("OP_F32_CONST: pushing {} to stack", v);
-                    self.store.stack.push(StackContent::Value(F32(*v)))
-                }
-                OP_F64_CONST(v) => {
-                    debug!("OP_F64_CONST: pushing {} to stack", v);
-                    self.store.stack.push(StackContent::Value(F64(*v)))
-                }
-                OP_F32_COPYSIGN => {
-                    let (z1, z2) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(copysign(z1, z2)))
-                }
-                OP_F64_COPYSIGN => {
-                    let (z1, z2) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(copysign(z1, z2)))
-                }
-                OP_I32_ADD | OP_I64_ADD | OP_F32_ADD | OP_F64_ADD => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(v1 + v2))
-                }
-                OP_I32_SUB | OP_I64_SUB | OP_F32_SUB | OP_F64_SUB => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(v1 - v2))
-                }
-                OP_I32_MUL | OP_I64_MUL | OP_F32_MUL | OP_F64_MUL => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(v1 * v2))
-                }
-                OP_I32_DIV_S | OP_I64_DIV_S | OP_F32_DIV | OP_F64_DIV => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(v1 / v2))
-                }
-                OP_I32_DIV_U | OP_I64_DIV_U => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    match (v1, v2) {
-                        (I32(x1), I32(x2)) => self
-                            .store
-                            .stack
-                            .push(StackContent::Value(I32(((x1 as u32) / (x2 as u32)) as i32))),
-                        (I64(x1), I64(x2)) => self
-                            .store
-                            .stack
-                            .push(StackContent::Value(I64(((x1 as u64) / (x2 as u64)) as i64))),
-                        _ => return Err(anyhow!("Invalid types for DIV_U")),
+            match &compiled.op {
+                Op::Execute => {
+                    if let Some((_, instruction)) = &compiled.source {
+                        self.execute(instruction)?;
                     }
                 }
-                OP_I32_REM_S | OP_I64_REM_S => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(v1 % v2))
-                }
-                OP_I32_REM_U | OP_I64_REM_U => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    match (v1, v2) {
-                        (I32(x1), I32(x2)) => self
-                            .store
-                            .stack
-                            .push(StackContent::Value(I32(((x1 as u32) % (x2 as u32)) as i32))),
-                        (I64(x1), I64(x2)) => self
-                            .store
-                            .stack
-                            .push(StackContent::Value(I64(((x1 as u64) % (x2 as u64)) as i64))),
-                        _ => return Err(anyhow!("Invalid types for REM_U")),
+                Op::Drop { slots } => {
+                    debug!("OP_DROP");
+                    for _ in 0..*slots {
+                        self.store.stack.pop()?;
                     }
                 }
-                OP_I32_AND | OP_I64_AND => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(v1 & v2))
+                Op::Select { slots } => {
+                    self.select(*slots)?;
                 }
-                OP_I32_OR | OP_I64_OR => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(v1 | v2))
+                Op::LocalGet(local) => {
+                    self.local_get(local, &locals)
+                        .with_context(|| format!("OP_LOCAL_GET({:?})", local))?;
                 }
-                OP_I32_XOR | OP_I64_XOR => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(v1 ^ v2))
+                Op::LocalSet(local) => {
+                    self.local_set(local, &locals)
+                        .with_context(|| format!("OP_LOCAL_SET({:?})", local))?;
                 }
-                OP_I32_SHL | OP_I64_SHL => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(v1 << v2))
+                Op::LocalTee(local) => {
+                    self.local_tee(local, &locals)
+                        .with_context(|| format!("OP_LOCAL_TEE({:?})", local))?;
                 }
-                OP_I32_SHR_S | OP_I64_SHR_S => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(v1 >> v2))
+                Op::Block { params } | Op::Loop { params } => {
+                    self.store.labels.push(Label::new(self.store.stack.len() - *params as usize));
                 }
-                OP_I32_SHR_U | OP_I64_SHR_U => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    match (v1, v2) {
-                        (I32(x1), I32(x2)) => {
-                            let k = x2 as u32 % 32;
-                            self.store
-                                .stack
-                                .push(StackContent::Value(I32(((x1 as u32).checked_shr(k))
-                                    .unwrap_or(0)
-                                    as i32)));
-                        }
-                        (I64(x1), I64(x2)) => {
-                            let k = x2 as u64 % 64;
-                            self.store
-                                .stack
-                                .push(StackContent::Value(I64(
-                                    ((x1 as u64).checked_shr(k as u32)).unwrap_or(0) as i64,
-                                )));
-                        }
-                        _ => return Err(anyhow!("Invalid types for SHR_U")),
-                    }
-                }
-                OP_I32_ROTL | OP_I64_ROTL => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(rotate_left(v1, v2)))
-                }
-                OP_I32_ROTR | OP_I64_ROTR => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(rotate_right(v1, v2)))
+                Op::Try { params, .. } => {
+                    let height = self.store.stack.len() - *params as usize;
+                    self.store.labels.push(Label::with_kind(height, LabelKind::Try(pc - 1)));
                 }
-                OP_I32_CLZ | OP_I64_CLZ => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(leading_zeros(v1)))
+                Op::TryDelegate { params, depth } => {
+                    let height = self.store.stack.len() - *params as usize;
+                    self.store.labels.push(Label::with_kind(height, LabelKind::Delegate(*depth)));
                 }
-                OP_I32_CTZ | OP_I64_CTZ => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(trailing_zeros(v1)))
-                }
-                OP_I32_POPCNT | OP_I64_POPCNT => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(count_ones(v1)))
-                }
-                OP_I32_EQZ | OP_I64_EQZ => {
-                    let v1 = fetch_unop!(self.store.stack);
+                Op::If { params, else_pc } => {
+                    let c = fetch_unop!(self.store.stack, I32);
+                    self.store.labels.push(Label::new(self.store.stack.len() - *params as usize));
 
-                    self.store.stack.push(StackContent::Value(eqz(v1)))
-                }
-                OP_I32_EQ | OP_I64_EQ | OP_F32_EQ | OP_F64_EQ => {
-                    let (v1, v2) = fetch_binop!(self.store.stack);
-                    let res = v1 == v2;
-
-                    if res {
-                        self.store.stack.push(StackContent::Value(Value::I32(1)))
-                    } else {
-                        self.store.stack.push(StackContent::Value(Value::I32(0)))
+                    if c == I32(0) {
+                        debug!("C is zero, therefore continuing at {}", else_pc);
+                        pc = *else_pc;
                     }
                 }
-                OP_I32_NE | OP_I64_NE | OP_F32_NE | OP_F64_NE => {
-                    let (v1, v2) = fetch_binop!(self.store.stack);
-                    let res = v1 != v2;
-
-                    if res {
-                        self.store.stack.push(StackContent::Value(Value::I32(1)))
-                    } else {
-                        self.store.stack.push(StackContent::Value(Value::I32(0)))
-                    }
-                }
-                OP_I32_LT_S | OP_I64_LT_S | OP_F32_LT | OP_F64_LT => {
-                    // switch ordering because of stack layout
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(lt(v1, v2).convert(ValueType::I32)))
-                }
-                OP_I32_LT_U | OP_I64_LT_U => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    match (v1, v2) {
-                        (I32(x1), I32(x2)) => self
-                            .store
-                            .stack
-                            .push(StackContent::Value(I32(((x1 as u32) < (x2 as u32)) as i32))),
-                        (I64(x1), I64(x2)) => self
-                            .store
-                            .stack
-                            .push(StackContent::Value(I32(((x1 as u64) < (x2 as u64)) as i32))),
-                        _ => return Err(anyhow!("Invalid types for LT_U comparison")),
-                    }
-                }
-                OP_I32_GT_S | OP_I64_GT_S | OP_F32_GT | OP_F64_GT => {
-                    // switch ordering because of stack layout
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(gt(v1, v2).convert(ValueType::I32)))
-                }
-                OP_I32_GT_U | OP_I64_GT_U => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    match (v1, v2) {
-                        (I32(x1), I32(x2)) => self
-                            .store
-                            .stack
-                            .push(StackContent::Value(I32(((x1 as u32) > (x2 as u32)) as i32))),
-                        (I64(x1), I64(x2)) => self
-                            .store
-                            .stack
-                            .push(StackContent::Value(I32(((x1 as u64) > (x2 as u64)) as i32))),
-                        _ => return Err(anyhow!("Invalid types for GT_U comparison")),
-                    }
-                }
-                OP_I32_LE_S | OP_I64_LE_S | OP_F32_LE | OP_F64_LE => {
-                    // switch ordering because of stack layout
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(le(v1, v2).convert(ValueType::I32)))
+                Op::Jump(target) => {
+                    pc = *target;
                 }
-                OP_I32_LE_U | OP_I64_LE_U => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    match (v1, v2) {
-                        (I32(x1), I32(x2)) => {
-                            self.store.stack.push(StackContent::Value(I32(((x1 as u32)
-                                <= (x2 as u32))
-                                as i32)))
-                        }
-                        (I64(x1), I64(x2)) => {
-                            self.store.stack.push(StackContent::Value(I32(((x1 as u64)
-                                <= (x2 as u64))
-                                as i32)))
-                        }
-                        _ => return Err(anyhow!("Invalid types for LE_U comparison")),
-                    }
+                Op::End => {
+                    self.store.labels.pop();
                 }
-                OP_I32_GE_S | OP_I64_GE_S | OP_F32_GE | OP_F64_GE => {
-                    // switch ordering because of stack layout
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(ge(v1, v2).convert(ValueType::I32)))
+                Op::Br(branch) => {
+                    pc = self.br(branch, pc)?;
                 }
-                OP_I32_GE_U | OP_I64_GE_U => {
-                    let (v2, v1) = fetch_binop!(self.store.stack);
-                    match (v1, v2) {
-                        (I32(x1), I32(x2)) => {
-                            self.store.stack.push(StackContent::Value(I32(((x1 as u32)
-                                >= (x2 as u32))
-                                as i32)))
-                        }
-                        (I64(x1), I64(x2)) => {
-                            self.store.stack.push(StackContent::Value(I32(((x1 as u64)
-                                >= (x2 as u64))
-                                as i32)))
-                        }
-                        _ => return Err(anyhow!("Invalid types for GE_U comparison")),
+                Op::BrIf(branch) => {
+                    if fetch_unop!(self.store.stack, I32) != I32(0) {
+                        pc = self.br(branch, pc)?;
                     }
                 }
-                OP_F32_ABS | OP_F64_ABS => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(abs(v1)))
-                }
-                OP_F32_NEG | OP_F64_NEG => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(neg(v1)))
-                }
-                OP_F32_CEIL | OP_F64_CEIL => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(ceil(v1)))
-                }
-                OP_F32_FLOOR | OP_F64_FLOOR => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(floor(v1)))
-                }
-                OP_F32_TRUNC | OP_F64_TRUNC => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(trunc(v1)))
-                }
-                OP_I32_TRUNC_SAT_F32_S | OP_I32_TRUNC_SAT_F64_S => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(trunc_sat_i32_s(v1)))
-                }
-                OP_I64_TRUNC_SAT_F32_S | OP_I64_TRUNC_SAT_F64_S => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(trunc_sat_i64_s(v1)))
-                }
-                OP_I32_TRUNC_SAT_F32_U => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(trunc_sat_from_f32_to_i32_u(v1)))
-                }
-                OP_I32_TRUNC_SAT_F64_U => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(trunc_sat_from_f64_to_i32_u(v1)))
-                }
-                OP_I64_TRUNC_SAT_F32_U => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(trunc_sat_from_f32_to_i64_u(v1)))
-                }
-                OP_I64_TRUNC_SAT_F64_U => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store
-                        .stack
-                        .push(StackContent::Value(trunc_sat_from_f64_to_i64_u(v1)))
-                }
-                OP_F32_NEAREST | OP_F64_NEAREST => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(nearest(v1)))
-                }
-                OP_F32_SQRT | OP_F64_SQRT => {
-                    let v1 = fetch_unop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(sqrt(v1)))
-                }
-                OP_F32_MIN | OP_F64_MIN => {
-                    let (v1, v2) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(min(v1, v2)))
-                }
-                OP_F32_MAX | OP_F64_MAX => {
-                    let (v1, v2) = fetch_binop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(max(v1, v2)))
-                }
-                OP_I32_WRAP_I64 => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I64, I32, i32);
-                }
-                OP_I64_EXTEND_I32_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I32, I64, i64);
-                }
-                OP_I64_EXTEND_I32_U => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I32, I64, i64, u32);
-                }
-                OP_I64_TRUNC_F32_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, F32, I64, i64);
-                }
-                OP_I64_TRUNC_F64_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, F64, I64, i64);
-                }
-                OP_I64_TRUNC_F32_U => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, F32, I64, i64, u64);
-                }
-                OP_I64_TRUNC_F64_U => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, F64, I64, i64, u64);
-                }
-                OP_I32_TRUNC_F32_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, F32, I32, i32);
-                }
-                OP_I32_TRUNC_F64_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, F64, I32, i32);
-                }
-                OP_I32_TRUNC_F32_U => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, F32, I32, i32, u32);
-                }
-                OP_I32_TRUNC_F64_U => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, F64, I32, i32, u32);
-                }
-                OP_F32_DEMOTE_F64 => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, F64, F32, f32);
-                }
-                OP_F64_PROMOTE_F32 => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, F32, F64, f64);
-                }
-                OP_F32_CONVERT_I32_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I32, F32, f32);
-                }
-                OP_F64_CONVERT_I32_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I32, F64, f64);
-                }
-                OP_F32_CONVERT_I64_S => {
-                    // Convert I64_S to F32
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I64, F32, f32);
-                }
-                OP_F64_CONVERT_I64_S => {
-                    // Convert I64_S to F64
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I64, F64, f64);
-                }
-                OP_F32_CONVERT_I32_U => {
-                    // Convert I32_S to F32
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I32, F32, f32, u32);
-                }
-                OP_F64_CONVERT_I32_U => {
-                    // Convert I32_S to F64
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I32, F64, f64, u32);
-                }
-                OP_F32_CONVERT_I64_U => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I64, F32, f32, u64);
-                }
-                OP_F64_CONVERT_I64_U => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I64, F64, f64, u64);
-                }
-                OP_I32_EXTEND8_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I32, I32, i32, i8);
-                }
-                OP_I32_EXTEND16_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I32, I32, i32, i16);
-                }
-                OP_I64_EXTEND8_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I64, I64, i64, i8);
-                }
-                OP_I64_EXTEND16_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I64, I64, i64, i16);
-                }
-                OP_I64_EXTEND32_S => {
-                    let v = fetch_unop!(self.store.stack);
-                    convert!(self, v, I64, I64, i64, i32);
-                }
-                OP_I32_REINTERPRET_F32
-                | OP_I64_REINTERPRET_F64
-                | OP_F32_REINTERPRET_I32
-                | OP_F64_REINTERPRET_I64 => {
-                    let v = fetch_unop!(self.store.stack);
-                    self.store.stack.push(StackContent::Value(reinterpret(v)));
-                }
-                OP_DROP => {
-                    debug!("OP_DROP");
-                    let k = self.store.stack.pop();
-                    debug!("Dropping {:?}", k);
-                }
-                OP_SELECT => {
-                    self.select()?;
-                }
-                OP_I32_LOAD_8_u(arg) => {
-                    load_memory_sx!(self, arg, 4, i32, I32, u8);
-                }
-                OP_I32_LOAD_16_u(arg) => {
-                    load_memory_sx!(self, arg, 4, i32, I32, u16);
-                }
-                OP_I32_LOAD_8_s(arg) => {
-                    load_memory_sx!(self, arg, 4, i32, I32, i8);
-                }
-                OP_I32_LOAD_16_s(arg) => {
-                    load_memory_sx!(self, arg, 4, i32, I32, i16);
-                }
-                OP_I32_LOAD(arg) => {
-                    load_memory!(self, arg, 4, i32, I32);
-                }
-                OP_I64_LOAD(arg) => {
-                    load_memory!(self, arg, 8, i64, I64);
-                }
-                OP_I64_LOAD_8_u(arg) => {
-                    load_memory_sx!(self, arg, 8, i64, I64, u8);
-                }
-                OP_I64_LOAD_16_u(arg) => {
-                    load_memory_sx!(self, arg, 8, i64, I64, u16);
-                }
-                OP_I64_LOAD_32_u(arg) => {
-                    load_memory_sx!(self, arg, 8, i64, I64, u32);
-                }
-                OP_I64_LOAD_8_s(arg) => {
-                    load_memory_sx!(self, arg, 8, i64, I64, i8);
-                }
-                OP_I64_LOAD_16_s(arg) => {
-                    load_memory_sx!(self, arg, 8, i64, I64, i16);
-                }
-                OP_I64_LOAD_32_s(arg) => {
-                    load_memory_sx!(self, arg, 8, i64, I64, i32);
-                }
-                OP_F32_LOAD(arg) => {
-                    load_memory!(self, arg, 4, f32, F32);
-                }
-                OP_F64_LOAD(arg) => {
-                    load_memory!(self, arg, 8, f64, F64);
-                }
-                OP_I32_STORE(arg) => {
-                    store_memory!(self, arg, 4, i32, I32);
-                }
-                OP_I64_STORE(arg) => {
-                    store_memory!(self, arg, 8, i64, I64);
-                }
-                OP_F32_STORE(arg) => {
-                    store_memory!(self, arg, 4, f32, F32);
-                }
-                OP_F64_STORE(arg) => {
-                    store_memory!(self, arg, 8, f64, F64);
-                }
-                OP_I32_STORE_8(arg) => {
-                    store_memory_n!(self, arg, 1, i32, I32, i8, 8);
-                }
-                OP_I32_STORE_16(arg) => {
-                    store_memory_n!(self, arg, 2, i32, I32, i16, 16);
-                }
-                OP_I64_STORE_8(arg) => {
-                    store_memory_n!(self, arg, 1, i64, I64, i8, 8);
-                }
-                OP_I64_STORE_16(arg) => {
-                    store_memory_n!(self, arg, 2, i64, I64, i16, 16);
-                }
-                OP_I64_STORE_32(arg) => {
-                    store_memory_n!(self, arg, 4, i64, I64, i32, 32);
-                }
-                OP_MEMORY_SIZE => {
-                    self.memory_size().context("Memory size failed")?;
-                }
-                OP_MEMORY_GROW => {
-                    self.memory_grow().context("Memory grow failed")?;
-                }
-                OP_BLOCK(ty, block_instructions) => {
-                    let outcome = self
-                        .block(fr, ty, block_instructions)
-                        .with_context(|| format!("OP_BLOCK with ty {:?} failed", ty))?;
-
-                    match outcome {
-                        InstructionOutcome::BRANCH(0) => {
-                            debug!("Instruction outcome was 0.");
-                        }
-                        InstructionOutcome::BRANCH(x) => {
-                            debug!("Instruction outcome was {}.", x - 1);
-                            return Ok(InstructionOutcome::BRANCH(x - 1));
-                        }
-                        InstructionOutcome::RETURN => {
-                            debug!("Instruction outcome was return.");
-                            self.exit_block()?;
-                            return Ok(InstructionOutcome::RETURN);
-                        }
-                        InstructionOutcome::EXIT => {
-                            debug!("Instruction outcome was exit.");
-                            self.exit_block()?;
-                        }
-                    }
-                }
-                OP_LOOP(ty, block_instructions) => {
-                    debug!("OP_LOOP {:?}, {:?}", ty, block_instructions);
-
-                    let param_count = self.get_mut_param_count_block(&ty)?;
-                    loop {
-                        self.setup_stack_for_entering_block(ty, block_instructions, param_count)?;
-
-                        let outcome = self
-                            .run_instructions(fr, &mut block_instructions.iter())
-                            .with_context(|| {
-                                format!("OP_LOOP({:?}) `run_instructions` failed", ty)
-                            })?;
-
-                        match outcome {
-                            InstructionOutcome::BRANCH(0) => {
-                                continue;
-                            }
-                            InstructionOutcome::BRANCH(x) => {
-                                return Ok(InstructionOutcome::BRANCH(x - 1));
-                            }
-                            InstructionOutcome::RETURN => {
-                                self.exit_block()?;
-                                return Ok(InstructionOutcome::RETURN);
-                            }
-                            InstructionOutcome::EXIT => {
-                                self.exit_block()?;
-                                break;
-                            }
-                        }
-                    }
-                }
-                OP_IF(ty, block_instructions_branch) => {
-                    debug!("OP_IF {:?}", ty);
-                    let element = self.store.stack.pop();
-                    debug!("Popping value {:?}", element);
-
-                    if let Some(StackContent::Value(Value::I32(v))) = element {
-                        if v != 0 {
-                            debug!("C is not zero, therefore branching");
-
-                            let arity_return_count = self.get_return_count_block(&ty)?;
-                            self.setup_stack_for_entering_block(
-                                ty,
-                                block_instructions_branch,
-                                arity_return_count,
-                            )?;
-
-                            let outcome = self
-                                .run_instructions(fr, &mut block_instructions_branch.iter())
-                                .with_context(|| {
-                                    format!("OP_IF({:?}) `run_instructions` failed", ty)
-                                })?;
-
-                            match outcome {
-                                InstructionOutcome::BRANCH(0) => {}
-                                InstructionOutcome::BRANCH(x) => {
-                                    return Ok(InstructionOutcome::BRANCH(x - 1));
-                                }
-                                InstructionOutcome::RETURN => {
-                                    self.exit_block()?;
-                                    return Ok(InstructionOutcome::RETURN);
-                                }
-                                InstructionOutcome::EXIT => {
-                                    self.exit_block()?;
-                                }
-                            }
-                        } else {
-                            debug!("C is zero, therefore not branching");
-                        }
-                    } else {
-                        return Err(anyhow!("Value must be i32.const. Instead {:#?}", element));
-                    }
+                Op::BrTable(table, default) => {
+                    let ival = fetch_unop!(self.store.stack, I32);
+
+                    let branch = match ival {
+                        I32(index) => table.get(index as u32 as usize).unwrap_or(default),
+                        _ => bail!("invalid index type: {:?}", ival),
+                    };
+
+                    pc = self.br(branch, pc)?;
                 }
-                OP_IF_AND_ELSE(ty, block_instructions_branch_1, block_instructions_branch_2) => {
-                    debug!("OP_IF_AND_ELSE {:?}", ty);
-                    let element = self.store.stack.pop();
-                    debug!("Popping value {:?}", element);
-
-                    if let Some(StackContent::Value(Value::I32(v))) = element {
-                        if v != 0 {
-                            debug!("C is not zero, therefore branching (1)");
-
-                            let arity_return_count = self.get_return_count_block(&ty)?;
-                            self.setup_stack_for_entering_block(
-                                ty,
-                                block_instructions_branch_1,
-                                arity_return_count,
-                            )?;
-
-                            let outcome = self
-                                .run_instructions(fr, &mut block_instructions_branch_1.iter())
-                                .with_context(|| {
-                                    format!("OP_IF_AND_ELSE({:?}) `run_instructions` failed", ty)
-                                })?;
-
-                            match outcome {
-                                InstructionOutcome::BRANCH(0) => {}
-                                InstructionOutcome::BRANCH(x) => {
-                                    return Ok(InstructionOutcome::BRANCH(x - 1));
-                                }
-                                InstructionOutcome::RETURN => {
-                                    self.exit_block()?;
-                                    return Ok(InstructionOutcome::RETURN);
-                                }
-                                InstructionOutcome::EXIT => {
-                                    self.exit_block()?;
-                                }
-                            }
-                        } else {
-                            debug!("C is zero, therefore branching (2)");
-
-                            let arity_return_count = self.get_return_count_block(&ty)?;
-                            self.setup_stack_for_entering_block(
-                                ty,
-                                block_instructions_branch_2,
-                                arity_return_count,
-                            )?;
-
-                            let outcome = self
-                                .run_instructions(fr, &mut block_instructions_branch_2.iter())
-                                .with_context(|| {
-                                    format!("OP_IF_AND_ELSE({:?}) `run_instructions` failed", ty)
-                                })?;
-
-                            match outcome {
-                                InstructionOutcome::BRANCH(0) => {}
-                                InstructionOutcome::BRANCH(x) => {
-                                    return Ok(InstructionOutcome::BRANCH(x - 1));
-                                }
-                                InstructionOutcome::RETURN => {
-                                    self.exit_block()?;
-                                    return Ok(InstructionOutcome::RETURN);
-                                }
-                                InstructionOutcome::EXIT => {
-                                    self.exit_block()?;
-                                }
-                            }
-                        }
-                    } else {
-                        return Err(anyhow!("Value must be i32.const"));
-                    }
+                Op::Call(idx) => {
+                    let func_addr = *self
+                        .module_instance
+                        .lookup_function_addr(idx)
+                        .ok_or_else(|| anyhow!("Cannot find function's addr {}", idx))?;
+
+                    self.save_pc(pc)?;
+                    return Ok(Outcome::Call(func_addr));
                 }
-                OP_BR(label_idx) => {
-                    return self.br(fr, *label_idx);
+                Op::CallIndirect(type_idx, table_idx) => {
+                    let func_addr = self
+                        .resolve_indirect_call(type_idx, table_idx)
+                        .with_context(|| {
+                            format!(
+                                "OP_CALL_INDIRECT with type ({}) and table ({}) failed",
+                                type_idx, table_idx
+                            )
+                        })?;
+
+                    self.save_pc(pc)?;
+                    return Ok(Outcome::Call(func_addr));
                 }
-                OP_BR_IF(label_idx) => {
-                    debug!("OP_BR_IF {}", label_idx);
-                    let element = self.store.stack.pop();
-                    debug!("Popping value {:?}", element);
-
-                    if let Some(StackContent::Value(Value::I32(c))) = element {
-                        debug!("c is {}", c);
-                        if c != 0 {
-                            debug!("Branching to {}", label_idx);
-                            return self.br(fr, *label_idx);
-                        } else {
-                            debug!("Not Branching to {}", label_idx);
-                        }
-                    }
+                Op::ReturnCall(idx) => {
+                    let func_addr = *self
+                        .module_instance
+                        .lookup_function_addr(idx)
+                        .ok_or_else(|| anyhow!("Cannot find function's addr {}", idx))?;
+
+                    return Ok(Outcome::TailCall(func_addr));
                 }
-                OP_BR_TABLE(table, default) => {
-                    debug!("OP_BR_TABLE {:?}, {:?}", table, default);
-                    let ival = fetch_unop!(self.store.stack);
-
-                    if let I32(index) = ival {
-                        let label_idx = if (index as usize) < table.len() {
-                            table[index as usize]
-                        } else {
-                            debug!("Using default case");
-                            *default
-                        };
-
-                        return self.br(fr, label_idx);
-                    } else {
-                        return Err(anyhow!("invalid index type: {:?}", ival));
-                    }
+                Op::ReturnCallIndirect(type_idx, table_idx) => {
+                    let func_addr = self
+                        .resolve_indirect_call(type_idx, table_idx)
+                        .with_context(|| {
+                            format!(
+                                "OP_RETURN_CALL_INDIRECT with type ({}) and table ({}) failed",
+                                type_idx, table_idx
+                            )
+                        })?;
+
+                    return Ok(Outcome::TailCall(func_addr));
                 }
-                OP_CALL(function_module_addr) => {
-                    let func_addr = self.module_instance.lookup_function_addr(function_module_addr)
-                        .ok_or_else(|| anyhow!("Cannot find function's addr"))?.clone();
-                    let func_addr_inner = func_addr.get();
-                    self.call_function(func_addr).with_context(|| {
-                        format!(
-                            "OP_CALL for function {:?} and module addr ({}) failed",
-                            func_addr_inner, function_module_addr
-                        )
-                    })?;
+                Op::Throw(tag_idx) => {
+                    let exception = self.throw(tag_idx)?;
+
+                    self.save_pc(pc)?;
+                    return Ok(Outcome::Throw(exception));
                 }
-                OP_CALL_INDIRECT(function_module_addr) => {
-                    let func_addr = self.module_instance.lookup_function_addr(function_module_addr)
-                        .ok_or_else(|| anyhow!("Cannot find function's addr"))?.clone();
-                    let func_addr_inner = func_addr.get();
-                    self.call_indirect_function(func_addr).with_context(|| {
-                        format!(
-                            "OP_CALL_INDIRECT for function {:?} failed and module addr ({}) failed",
-                            func_addr_inner, function_module_addr
-                        )
-                    })?;
+                Op::Rethrow(depth) => {
+                    let exception = self.rethrow(*depth)?;
+
+                    self.save_pc(pc)?;
+                    return Ok(Outcome::Throw(exception));
                 }
-                OP_RETURN => {
-                    debug!("Return");
-                    return Ok(InstructionOutcome::RETURN);
+                Op::Return => {
+                    return Ok(Outcome::Return);
                 }
-                OP_NOP => {}
-                OP_UNREACHABLE => return Err(anyhow!("Reached unreachable => trap!")),
             }
 
             trace!("stack {:#?}", self.store.stack);
         }
-
-        Ok(InstructionOutcome::EXIT)
-    }
-
-    /// Get the frame at the top of the stack
-    fn get_mut_frame(&mut self) -> Result<&Frame> {
-        debug!("get_frame");
-        // -2 because label
-        match self.store.stack.get(self.store.stack.len() - 2) {
-            Some(Frame(fr)) => Ok(fr),
-            Some(x) => bail!("Expected frame but found {:?}", x),
-            None => bail!("Empty stack on function call"),
-        }
     }
 
-    fn exit_block(&mut self) -> Result<()> {
-        debug!("exit_block");
-
-        let mut val_m = Vec::new();
-
-        while let Some(Value(_v)) = self.store.stack.last() {
-            val_m.push(self.store.stack.pop().unwrap());
-        }
-
-        val_m.reverse();
-
-        debug!("values before applying block's arity {:?}", val_m);
+    /// The frame continues at `pc`, when the callee returned
+    fn save_pc(&mut self, pc: usize) -> Result<()> {
+        self.call_stack
+            .last_mut()
+            .ok_or_else(|| anyhow!("The call stack is empty"))?
+            .pc = pc;
 
-        if let Some(Label(lb)) = self.store.stack.pop() {
-            debug!("Label on the top of the stack {:?}", lb);
+        Ok(())
+    }
 
-            // If and If-Else labels have arity 0
-            // therefore, we keep all results
-            if lb.get_arity() != 0 {
-                val_m = val_m
-                    .into_iter()
-                    .rev()
-                    .take(lb.get_arity() as usize)
-                    .rev()
-                    .collect();
+    /// Executes an instruction without control flow
+    #[allow(clippy::cognitive_complexity)]
+    fn execute(&mut self, instruction: &Instruction) -> Result<()> {
+        match instruction {
+            OP_GLOBAL_GET(idx) => {
+                self.global_get(idx)
+                    .with_context(|| format!("OP_GLOBAL_GET({})", idx))?;
             }
-        } else {
-            return Err(anyhow!("Expected label, but it's not a label"));
+            OP_GLOBAL_SET(idx) => {
+                self.global_set(idx)
+                    .with_context(|| format!("OP_GLOBAL_SET({})", idx))?;
+            }
+            OP_I32_CONST(v) => {
+                debug!("OP_I32_CONST: pushing {} to stack", v);
+                self.store.stack.push_value(I32(*v))
+            }
+            OP_I64_CONST(v) => {
+                debug!("OP_I64_CONST: pushing {} to stack", v);
+                self.store.stack.push_value(I64(*v))
+            }
+            OP_F32_CONST(v) => {
+                debug!("OP_F32_CONST: pushing {} to stack", v);
+                self.store.stack.push_value(F32(*v))
+            }
+            OP_F64_CONST(v) => {
+                debug!("OP_F64_CONST: pushing {} to stack", v);
+                self.store.stack.push_value(F64(*v))
+            }
+            OP_F32_COPYSIGN => binop!(self, F32, |v1, v2| copysign(v2, v1)),
+            OP_F64_COPYSIGN => binop!(self, F64, |v1, v2| copysign(v2, v1)),
+            OP_I32_ADD => binop!(self, I32, |v1, v2| v1 + v2),
+            OP_I64_ADD => binop!(self, I64, |v1, v2| v1 + v2),
+            OP_F32_ADD => binop!(self, F32, |v1, v2| v1 + v2),
+            OP_F64_ADD => binop!(self, F64, |v1, v2| v1 + v2),
+            OP_I32_SUB => binop!(self, I32, |v1, v2| v1 - v2),
+            OP_I64_SUB => binop!(self, I64, |v1, v2| v1 - v2),
+            OP_F32_SUB => binop!(self, F32, |v1, v2| v1 - v2),
+            OP_F64_SUB => binop!(self, F64, |v1, v2| v1 - v2),
+            OP_I32_MUL => binop!(self, I32, |v1, v2| v1 * v2),
+            OP_I64_MUL => binop!(self, I64, |v1, v2| v1 * v2),
+            OP_F32_MUL => binop!(self, F32, |v1, v2| v1 * v2),
+            OP_F64_MUL => binop!(self, F64, |v1, v2| v1 * v2),
+            OP_F32_DIV => binop!(self, F32, |v1, v2| (v1 / v2)?),
+            OP_F64_DIV => binop!(self, F64, |v1, v2| (v1 / v2)?),
+            OP_I32_DIV_S => binop!(self, I32, |v1, v2| div_s(v1, v2)?),
+            OP_I64_DIV_S => binop!(self, I64, |v1, v2| div_s(v1, v2)?),
+            OP_I32_DIV_U => binop!(self, I32, |v1, v2| div_u(v1, v2)?),
+            OP_I64_DIV_U => binop!(self, I64, |v1, v2| div_u(v1, v2)?),
+            OP_I32_REM_S => binop!(self, I32, |v1, v2| rem_s(v1, v2)?),
+            OP_I64_REM_S => binop!(self, I64, |v1, v2| rem_s(v1, v2)?),
+            OP_I32_REM_U => binop!(self, I32, |v1, v2| rem_u(v1, v2)?),
+            OP_I64_REM_U => binop!(self, I64, |v1, v2| rem_u(v1, v2)?),
+            OP_I32_AND => binop!(self, I32, |v1, v2| v1 & v2),
+            OP_I64_AND => binop!(self, I64, |v1, v2| v1 & v2),
+            OP_I32_OR => binop!(self, I32, |v1, v2| v1 | v2),
+            OP_I64_OR => binop!(self, I64, |v1, v2| v1 | v2),
+            OP_I32_XOR => binop!(self, I32, |v1, v2| v1 ^ v2),
+            OP_I64_XOR => binop!(self, I64, |v1, v2| v1 ^ v2),
+            OP_I32_SHL => binop!(self, I32, |v1, v2| v1 << v2),
+            OP_I64_SHL => binop!(self, I64, |v1, v2| v1 << v2),
+            OP_I32_SHR_S => binop!(self, I32, |v1, v2| v1 >> v2),
+            OP_I64_SHR_S => binop!(self, I64, |v1, v2| v1 >> v2),
+            OP_I32_SHR_U => binop!(self, I32, |v1, v2| shr_u(v1, v2)),
+            OP_I64_SHR_U => binop!(self, I64, |v1, v2| shr_u(v1, v2)),
+            OP_I32_ROTL => binop!(self, I32, |v1, v2| rotate_left(v1, v2)),
+            OP_I64_ROTL => binop!(self, I64, |v1, v2| rotate_left(v1, v2)),
+            OP_I32_ROTR => binop!(self, I32, |v1, v2| rotate_right(v1, v2)),
+            OP_I64_ROTR => binop!(self, I64, |v1, v2| rotate_right(v1, v2)),
+            OP_I32_CLZ => unop!(self, I32, |v| leading_zeros(v)),
+            OP_I64_CLZ => unop!(self, I64, |v| leading_zeros(v)),
+            OP_I32_CTZ => unop!(self, I32, |v| trailing_zeros(v)),
+            OP_I64_CTZ => unop!(self, I64, |v| trailing_zeros(v)),
+            OP_I32_POPCNT => unop!(self, I32, |v| count_ones(v)),
+            OP_I64_POPCNT => unop!(self, I64, |v| count_ones(v)),
+            OP_I32_EQZ => unop!(self, I32, |v| eqz(v)),
+            OP_I64_EQZ => unop!(self, I64, |v| eqz(v)),
+            OP_I32_EQ => binop!(self, I32, |v1, v2| I32((v1 == v2) as i32)),
+            OP_I64_EQ => binop!(self, I64, |v1, v2| I32((v1 == v2) as i32)),
+            OP_F32_EQ => binop!(self, F32, |v1, v2| I32((v1 == v2) as i32)),
+            OP_F64_EQ => binop!(self, F64, |v1, v2| I32((v1 == v2) as i32)),
+            OP_I32_NE => binop!(self, I32, |v1, v2| I32((v1 != v2) as i32)),
+            OP_I64_NE => binop!(self, I64, |v1, v2| I32((v1 != v2) as i32)),
+            OP_F32_NE => binop!(self, F32, |v1, v2| I32((v1 != v2) as i32)),
+            OP_F64_NE => binop!(self, F64, |v1, v2| I32((v1 != v2) as i32)),
+            OP_I32_LT_S => binop!(self, I32, |v1, v2| lt(v1, v2).convert(ValueType::I32)),
+            OP_I64_LT_S => binop!(self, I64, |v1, v2| lt(v1, v2).convert(ValueType::I32)),
+            OP_F32_LT => binop!(self, F32, |v1, v2| lt(v1, v2).convert(ValueType::I32)),
+            OP_F64_LT => binop!(self, F64, |v1, v2| lt(v1, v2).convert(ValueType::I32)),
+            OP_I32_LT_U => binop!(self, I32, |v1, v2| lt_u(v1, v2)),
+            OP_I64_LT_U => binop!(self, I64, |v1, v2| lt_u(v1, v2)),
+            OP_I32_GT_S => binop!(self, I32, |v1, v2| gt(v1, v2).convert(ValueType::I32)),
+            OP_I64_GT_S => binop!(self, I64, |v1, v2| gt(v1, v2).convert(ValueType::I32)),
+            OP_F32_GT => binop!(self, F32, |v1, v2| gt(v1, v2).convert(ValueType::I32)),
+            OP_F64_GT => binop!(self, F64, |v1, v2| gt(v1, v2).convert(ValueType::I32)),
+            OP_I32_GT_U => binop!(self, I32, |v1, v2| gt_u(v1, v2)),
+            OP_I64_GT_U => binop!(self, I64, |v1, v2| gt_u(v1, v2)),
+            OP_I32_LE_S => binop!(self, I32, |v1, v2| le(v1, v2).convert(ValueType::I32)),
+            OP_I64_LE_S => binop!(self, I64, |v1, v2| le(v1, v2).convert(ValueType::I32)),
+            OP_F32_LE => binop!(self, F32, |v1, v2| le(v1, v2).convert(ValueType::I32)),
+            OP_F64_LE => binop!(self, F64, |v1, v2| le(v1, v2).convert(ValueType::I32)),
+            OP_I32_LE_U => binop!(self, I32, |v1, v2| le_u(v1, v2)),
+            OP_I64_LE_U => binop!(self, I64, |v1, v2| le_u(v1, v2)),
+            OP_I32_GE_S => binop!(self, I32, |v1, v2| ge(v1, v2).convert(ValueType::I32)),
+            OP_I64_GE_S => binop!(self, I64, |v1, v2| ge(v1, v2).convert(ValueType::I32)),
+            OP_F32_GE => binop!(self, F32, |v1, v2| ge(v1, v2).convert(ValueType::I32)),
+            OP_F64_GE => binop!(self, F64, |v1, v2| ge(v1, v2).convert(ValueType::I32)),
+            OP_I32_GE_U => binop!(self, I32, |v1, v2| ge_u(v1, v2)),
+            OP_I64_GE_U => binop!(self, I64, |v1, v2| ge_u(v1, v2)),
+            OP_F32_ABS => unop!(self, F32, |v| abs(v)),
+            OP_F64_ABS => unop!(self, F64, |v| abs(v)),
+            OP_F32_NEG => unop!(self, F32, |v| neg(v)),
+            OP_F64_NEG => unop!(self, F64, |v| neg(v)),
+            OP_F32_CEIL => unop!(self, F32, |v| ceil(v)),
+            OP_F64_CEIL => unop!(self, F64, |v| ceil(v)),
+            OP_F32_FLOOR => unop!(self, F32, |v| floor(v)),
+            OP_F64_FLOOR => unop!(self, F64, |v| floor(v)),
+            OP_F32_TRUNC => unop!(self, F32, |v| trunc(v)),
+            OP_F64_TRUNC => unop!(self, F64, |v| trunc(v)),
+            OP_F32_NEAREST => unop!(self, F32, |v| nearest(v)),
+            OP_F64_NEAREST => unop!(self, F64, |v| nearest(v)),
+            OP_F32_SQRT => unop!(self, F32, |v| sqrt(v)),
+            OP_F64_SQRT => unop!(self, F64, |v| sqrt(v)),
+            OP_I32_TRUNC_SAT_F32_S => unop!(self, F32, |v| trunc_sat_i32_s(v)),
+            OP_I32_TRUNC_SAT_F64_S => unop!(self, F64, |v| trunc_sat_i32_s(v)),
+            OP_I64_TRUNC_SAT_F32_S => unop!(self, F32, |v| trunc_sat_i64_s(v)),
+            OP_I64_TRUNC_SAT_F64_S => unop!(self, F64, |v| trunc_sat_i64_s(v)),
+            OP_I32_TRUNC_SAT_F32_U => unop!(self, F32, |v| trunc_sat_from_f32_to_i32_u(v)),
+            OP_I32_TRUNC_SAT_F64_U => unop!(self, F64, |v| trunc_sat_from_f64_to_i32_u(v)),
+            OP_I64_TRUNC_SAT_F32_U => unop!(self, F32, |v| trunc_sat_from_f32_to_i64_u(v)),
+            OP_I64_TRUNC_SAT_F64_U => unop!(self, F64, |v| trunc_sat_from_f64_to_i64_u(v)),
+            OP_F32_MIN => binop!(self, F32, |v1, v2| min(v2, v1)),
+            OP_F64_MIN => binop!(self, F64, |v1, v2| min(v2, v1)),
+            OP_F32_MAX => binop!(self, F32, |v1, v2| max(v2, v1)),
+            OP_F64_MAX => binop!(self, F64, |v1, v2| max(v2, v1)),
+            OP_I32_WRAP_I64 => convert!(self, I64, I32, i32),
+            OP_I64_EXTEND_I32_S => convert!(self, I32, I64, i64),
+            OP_I64_EXTEND_I32_U => convert!(self, I32, I64, i64, u32),
+            OP_I64_TRUNC_F32_S => unop!(self, F32, |v| trunc_i64_s(v)?),
+            OP_I64_TRUNC_F32_U => unop!(self, F32, |v| trunc_i64_u(v)?),
+            OP_I64_TRUNC_F64_S => unop!(self, F64, |v| trunc_i64_s(v)?),
+            OP_I64_TRUNC_F64_U => unop!(self, F64, |v| trunc_i64_u(v)?),
+            OP_I32_TRUNC_F32_S => unop!(self, F32, |v| trunc_i32_s(v)?),
+            OP_I32_TRUNC_F32_U => unop!(self, F32, |v| trunc_i32_u(v)?),
+            OP_I32_TRUNC_F64_S => unop!(self, F64, |v| trunc_i32_s(v)?),
+            OP_I32_TRUNC_F64_U => unop!(self, F64, |v| trunc_i32_u(v)?),
+            OP_F32_DEMOTE_F64 => convert!(self, F64, F32, f32),
+            OP_F64_PROMOTE_F32 => convert!(self, F32, F64, f64),
+            OP_F32_CONVERT_I32_S => convert!(self, I32, F32, f32),
+            OP_F64_CONVERT_I32_S => convert!(self, I32, F64, f64),
+            OP_F32_CONVERT_I64_S => convert!(self, I64, F32, f32),
+            OP_F64_CONVERT_I64_S => convert!(self, I64, F64, f64),
+            OP_F32_CONVERT_I32_U => convert!(self, I32, F32, f32, u32),
+            OP_F64_CONVERT_I32_U => convert!(self, I32, F64, f64, u32),
+            OP_F32_CONVERT_I64_U => convert!(self, I64, F32, f32, u64),
+            OP_F64_CONVERT_I64_U => convert!(self, I64, F64, f64, u64),
+            OP_I32_EXTEND8_S => convert!(self, I32, I32, i32, i8),
+            OP_I32_EXTEND16_S => convert!(self, I32, I32, i32, i16),
+            OP_I64_EXTEND8_S => convert!(self, I64, I64, i64, i8),
+            OP_I64_EXTEND16_S => convert!(self, I64, I64, i64, i16),
+            OP_I64_EXTEND32_S => convert!(self, I64, I64, i64, i32),
+            OP_I32_REINTERPRET_F32
+            | OP_I64_REINTERPRET_F64
+            | OP_F32_REINTERPRET_I32
+            | OP_F64_REINTERPRET_I64 => {
+                // The bits of the slot are only interpreted differently
+            }
+            OP_REF_NULL(_) => {
+                // The null reference is zero for every type
+                self.store.stack.push(0);
+            }
+            OP_REF_IS_NULL => {
+                let slot = self.store.stack.pop()?;
+                self.store.stack.push_value(I32((slot == 0) as i32));
+            }
+            OP_REF_FUNC(func_idx) => {
+                self.ref_func(func_idx)?;
+            }
+            OP_TABLE_GET(table_idx) => {
+                self.table_get(table_idx).context("Table get failed")?;
+            }
+            OP_TABLE_SET(table_idx) => {
+                self.table_set(table_idx).context("Table set failed")?;
+            }
+            OP_TABLE_SIZE(table_idx) => {
+                self.table_size(table_idx)?;
+            }
+            OP_TABLE_GROW(table_idx) => {
+                self.table_grow(table_idx).context("Table grow failed")?;
+            }
+            OP_TABLE_FILL(table_idx) => {
+                self.table_fill(table_idx).context("Table fill failed")?;
+            }
+            OP_I32_LOAD_8_u(arg) => {
+                load_memory_sx!(self, arg, I32, u8);
+            }
+            OP_I32_LOAD_16_u(arg) => {
+                load_memory_sx!(self, arg, I32, u16);
+            }
+            OP_I32_LOAD_8_s(arg) => {
+                load_memory_sx!(self, arg, I32, i8);
+            }
+            OP_I32_LOAD_16_s(arg) => {
+                load_memory_sx!(self, arg, I32, i16);
+            }
+            OP_I32_LOAD(arg) => {
+                load_memory!(self, arg, 4, i32, I32);
+            }
+            OP_I64_LOAD(arg) => {
+                load_memory!(self, arg, 8, i64, I64);
+            }
+            OP_I64_LOAD_8_u(arg) => {
+                load_memory_sx!(self, arg, I64, u8);
+            }
+            OP_I64_LOAD_16_u(arg) => {
+                load_memory_sx!(self, arg, I64, u16);
+            }
+            OP_I64_LOAD_32_u(arg) => {
+                load_memory_sx!(self, arg, I64, u32);
+            }
+            OP_I64_LOAD_8_s(arg) => {
+                load_memory_sx!(self, arg, I64, i8);
+            }
+            OP_I64_LOAD_16_s(arg) => {
+                load_memory_sx!(self, arg, I64, i16);
+            }
+            OP_I64_LOAD_32_s(arg) => {
+                load_memory_sx!(self, arg, I64, i32);
+            }
+            OP_F32_LOAD(arg) => {
+                load_memory!(self, arg, 4, f32, F32);
+            }
+            OP_F64_LOAD(arg) => {
+                load_memory!(self, arg, 8, f64, F64);
+            }
+            OP_I32_STORE(arg) => {
+                store_memory!(self, arg, 4, i32, I32);
+            }
+            OP_I64_STORE(arg) => {
+                store_memory!(self, arg, 8, i64, I64);
+            }
+            OP_F32_STORE(arg) => {
+                store_memory!(self, arg, 4, f32, F32);
+            }
+            OP_F64_STORE(arg) => {
+                store_memory!(self, arg, 8, f64, F64);
+            }
+            OP_I32_STORE_8(arg) => {
+                store_memory_n!(self, arg, I32, 8);
+            }
+            OP_I32_STORE_16(arg) => {
+                store_memory_n!(self, arg, I32, 16);
+            }
+            OP_I64_STORE_8(arg) => {
+                store_memory_n!(self, arg, I64, 8);
+            }
+            OP_I64_STORE_16(arg) => {
+                store_memory_n!(self, arg, I64, 16);
+            }
+            OP_I64_STORE_32(arg) => {
+                store_memory_n!(self, arg, I64, 32);
+            }
+            OP_MEMORY_SIZE => {
+                self.memory_size().context("Memory size failed")?;
+            }
+            OP_MEMORY_GROW => {
+                self.memory_grow().context("Memory grow failed")?;
+            }
+            OP_MEMORY_INIT(data_idx) => {
+                self.memory_init(data_idx).context("Memory init failed")?;
+            }
+            OP_DATA_DROP(data_idx) => {
+                self.data_drop(data_idx)?;
+            }
+            OP_MEMORY_COPY => {
+                self.memory_copy().context("Memory copy failed")?;
+            }
+            OP_MEMORY_FILL => {
+                self.memory_fill().context("Memory fill failed")?;
+            }
+            OP_TABLE_INIT(elem_idx, table_idx) => {
+                self.table_init(elem_idx, table_idx).context("Table init failed")?;
+            }
+            OP_ELEM_DROP(elem_idx) => {
+                self.elem_drop(elem_idx)?;
+            }
+            OP_TABLE_COPY(dst_idx, src_idx) => {
+                self.table_copy(dst_idx, src_idx).context("Table copy failed")?;
+            }
+            OP_BLOCK(..)
+            | OP_LOOP(..)
+            | OP_IF(..)
+            | OP_IF_AND_ELSE(..)
+            | OP_BR(_)
+            | OP_BR_IF(_)
+            | OP_BR_TABLE(..)
+            | OP_RETURN
+            | OP_CALL(_)
+            | OP_CALL_INDIRECT(..)
+            | OP_RETURN_CALL(_)
+            | OP_RETURN_CALL_INDIRECT(..)
+            | OP_DROP
+            | OP_SELECT
+            | OP_SELECT_T(_)
+            | OP_LOCAL_GET(_)
+            | OP_LOCAL_SET(_)
+            | OP_LOCAL_TEE(_) => {
+                bail!("The instruction {} must be compiled", instruction)
+            }
+            OP_NOP => {}
+            OP_UNREACHABLE => bail!(Trap::Unreachable),
+            OP_MEMORY_ATOMIC_NOTIFY(_)
+            | OP_MEMORY_ATOMIC_WAIT32(_)
+            | OP_MEMORY_ATOMIC_WAIT64(_)
+            | OP_ATOMIC_FENCE
+            | OP_I32_ATOMIC_LOAD(_)
+            | OP_I64_ATOMIC_LOAD(_)
+            | OP_I32_ATOMIC_LOAD8_U(_)
+            | OP_I32_ATOMIC_LOAD16_U(_)
+            | OP_I64_ATOMIC_LOAD8_U(_)
+            | OP_I64_ATOMIC_LOAD16_U(_)
+            | OP_I64_ATOMIC_LOAD32_U(_)
+            | OP_I32_ATOMIC_STORE(_)
+            | OP_I64_ATOMIC_STORE(_)
+            | OP_I32_ATOMIC_STORE8(_)
+            | OP_I32_ATOMIC_STORE16(_)
+            | OP_I64_ATOMIC_STORE8(_)
+            | OP_I64_ATOMIC_STORE16(_)
+            | OP_I64_ATOMIC_STORE32(_)
+            | OP_I32_ATOMIC_RMW_ADD(_)
+            | OP_I64_ATOMIC_RMW_ADD(_)
+            | OP_I32_ATOMIC_RMW8_ADD_U(_)
+            | OP_I32_ATOMIC_RMW16_ADD_U(_)
+            | OP_I64_ATOMIC_RMW8_ADD_U(_)
+            | OP_I64_ATOMIC_RMW16_ADD_U(_)
+            | OP_I64_ATOMIC_RMW32_ADD_U(_)
+            | OP_I32_ATOMIC_RMW_SUB(_)
+            | OP_I64_ATOMIC_RMW_SUB(_)
+            | OP_I32_ATOMIC_RMW8_SUB_U(_)
+            | OP_I32_ATOMIC_RMW16_SUB_U(_)
+            | OP_I64_ATOMIC_RMW8_SUB_U(_)
+            | OP_I64_ATOMIC_RMW16_SUB_U(_)
+            | OP_I64_ATOMIC_RMW32_SUB_U(_)
+            | OP_I32_ATOMIC_RMW_AND(_)
+            | OP_I64_ATOMIC_RMW_AND(_)
+            | OP_I32_ATOMIC_RMW8_AND_U(_)
+            | OP_I32_ATOMIC_RMW16_AND_U(_)
+            | OP_I64_ATOMIC_RMW8_AND_U(_)
+            | OP_I64_ATOMIC_RMW16_AND_U(_)
+            | OP_I64_ATOMIC_RMW32_AND_U(_)
+            | OP_I32_ATOMIC_RMW_OR(_)
+            | OP_I64_ATOMIC_RMW_OR(_)
+            | OP_I32_ATOMIC_RMW8_OR_U(_)
+            | OP_I32_ATOMIC_RMW16_OR_U(_)
+            | OP_I64_ATOMIC_RMW8_OR_U(_)
+            | OP_I64_ATOMIC_RMW16_OR_U(_)
+            | OP_I64_ATOMIC_RMW32_OR_U(_)
+            | OP_I32_ATOMIC_RMW_XOR(_)
+            | OP_I64_ATOMIC_RMW_XOR(_)
+            | OP_I32_ATOMIC_RMW8_XOR_U(_)
+            | OP_I32_ATOMIC_RMW16_XOR_U(_)
+            | OP_I64_ATOMIC_RMW8_XOR_U(_)
+            | OP_I64_ATOMIC_RMW16_XOR_U(_)
+            | OP_I64_ATOMIC_RMW32_XOR_U(_)
+            | OP_I32_ATOMIC_RMW_XCHG(_)
+            | OP_I64_ATOMIC_RMW_XCHG(_)
+            | OP_I32_ATOMIC_RMW8_XCHG_U(_)
+            | OP_I32_ATOMIC_RMW16_XCHG_U(_)
+            | OP_I64_ATOMIC_RMW8_XCHG_U(_)
+            | OP_I64_ATOMIC_RMW16_XCHG_U(_)
+            | OP_I64_ATOMIC_RMW32_XCHG_U(_)
+            | OP_I32_ATOMIC_RMW_CMPXCHG(_)
+            | OP_I64_ATOMIC_RMW_CMPXCHG(_)
+            | OP_I32_ATOMIC_RMW8_CMPXCHG_U(_)
+            | OP_I32_ATOMIC_RMW16_CMPXCHG_U(_)
+            | OP_I64_ATOMIC_RMW8_CMPXCHG_U(_)
+            | OP_I64_ATOMIC_RMW16_CMPXCHG_U(_)
+            | OP_I64_ATOMIC_RMW32_CMPXCHG_U(_) => self.execute_atomic(instruction)?,
+            _ => self.execute_vector(instruction)?,
         }
 
-        debug!("values after applying block's arity {:?}", val_m);
-        self.store.stack.append(&mut val_m);
-
         Ok(())
     }
 }
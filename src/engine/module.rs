@@ -18,6 +18,19 @@ pub struct ModuleInstance {
     mem_addrs: Vec<MemoryAddr>,
     /// Keeps the indexes of the global variables
     global_addrs: Vec<GlobalAddr>,
+    /// Keeps the indexes of the element segments
+    elem_addrs: Vec<ElemAddr>,
+    /// Keeps the indexes of the data segments
+    data_addrs: Vec<DataAddr>,
+    /// Keeps the indexes of the tags
+    tag_addrs: Vec<TagAddr>,
+    /// The types of the functions (including the imported ones).
+    /// The compiler needs them before the functions are allocated.
+    func_types: Vec<TypeIdx>,
+    /// The types of the globals (including the imported ones)
+    global_types: Vec<ValueType>,
+    /// The types of the tags (including the imported ones)
+    tag_types: Vec<TypeIdx>,
     exports: Vec<ExportInstance>,
 }
 
@@ -29,18 +42,12 @@ impl ModuleInstance {
 
         let mut functions = Vec::new();
 
-        let code_sections = m.sections.iter().filter(|x| match x {
-            Section::Code(..) => true,
-            _ => false
-        }).count();
+        let code_sections = m.sections.iter().filter(|x| matches!(x, Section::Code(..))).count();
 
-        let type_sections = m.sections.iter().filter(|x| match x {
-            Section::Type(..) => true,
-            _ => false
-        }).count();
+        let type_sections = m.sections.iter().filter(|x| matches!(x, Section::Type(..))).count();
 
-        assert!(!(code_sections > 1), "A module cannot have multiple code sections.");
-        assert!(!(type_sections > 1), "A module cannot have multiple type sections.");
+        assert!((code_sections <= 1), "A module cannot have multiple code sections.");
+        assert!((type_sections <= 1), "A module cannot have multiple type sections.");
 
         for section in m.sections.iter() {
             match section {
@@ -50,6 +57,26 @@ impl ModuleInstance {
                 Section::Type(TypeSection { entries: x }) => {
                     mi.fn_types = x.clone();
                 }
+                // The imports come first in the index spaces
+                Section::Import(ImportSection { entries: x }) => {
+                    for entry in x.iter() {
+                        match &entry.desc {
+                            ImportDesc::Function { ty } => mi.func_types.push(*ty),
+                            ImportDesc::Global { ty } => mi.global_types.push(ty.value_type),
+                            ImportDesc::Tag { ty } => mi.tag_types.push(*ty),
+                            ImportDesc::Table { .. } | ImportDesc::Memory { .. } => {}
+                        }
+                    }
+                }
+                Section::Function(FunctionSection { types: x }) => {
+                    mi.func_types.extend(x.iter().copied());
+                }
+                Section::Global(GlobalSection { globals: x }) => {
+                    mi.global_types.extend(x.iter().map(|global| global.ty.value_type));
+                }
+                Section::Tag(TagSection { types: x }) => {
+                    mi.tag_types.extend(x.iter().copied());
+                }
                 _ => {}
             }
         }
@@ -96,11 +123,48 @@ impl ModuleInstance {
             .get(*idx as usize)
     }
 
+    pub fn lookup_elem_addr(&self, idx: &ElemIdx) -> Option<&ElemAddr> {
+        self
+            .elem_addrs
+            .get(*idx as usize)
+    }
+
+    pub fn lookup_data_addr(&self, idx: &DataIdx) -> Option<&DataAddr> {
+        self
+            .data_addrs
+            .get(*idx as usize)
+    }
+
+    pub fn lookup_tag_addr(&self, idx: &TagIdx) -> Option<&TagAddr> {
+        self
+            .tag_addrs
+            .get(*idx as usize)
+    }
+
     /// Looking up the the func type with given index.
     pub fn lookup_func_types(&self, index: &u32) -> Option<&FunctionSignature> {
         self.fn_types.get(*index as usize)
     }
 
+    /// Looking up the type of the function with the given index
+    pub fn lookup_function_type(&self, idx: &FuncIdx) -> Option<&FunctionSignature> {
+        self.func_types
+            .get(*idx as usize)
+            .and_then(|ty| self.lookup_func_types(ty))
+    }
+
+    /// Looking up the value type of the global with the given index
+    pub fn lookup_global_type(&self, idx: &GlobalIdx) -> Option<ValueType> {
+        self.global_types.get(*idx as usize).copied()
+    }
+
+    /// Looking up the type of the tag with the given index
+    pub fn lookup_tag_type(&self, idx: &TagIdx) -> Option<&FunctionSignature> {
+        self.tag_types
+            .get(*idx as usize)
+            .and_then(|ty| self.lookup_func_types(ty))
+    }
+
     // /// Looking up the code with given index.
     /*pub fn lookup_code(&self, index: usize) -> Option<&FunctionBody> {
         self.code.get(index)
@@ -134,6 +198,27 @@ impl ModuleInstance {
         Ok(())
     }
 
+    /// Storing a new element addr.
+    pub fn store_elem_addr(&mut self, new_addr: ElemAddr) -> Result<()> {
+        self.elem_addrs.push(new_addr);
+
+        Ok(())
+    }
+
+    /// Storing a new data addr.
+    pub fn store_data_addr(&mut self, new_addr: DataAddr) -> Result<()> {
+        self.data_addrs.push(new_addr);
+
+        Ok(())
+    }
+
+    /// Storing a new tag addr.
+    pub fn store_tag_addr(&mut self, new_addr: TagAddr) -> Result<()> {
+        self.tag_addrs.push(new_addr);
+
+        Ok(())
+    }
+
     /// Storing a new export instance.
     pub fn store_export(&mut self, export: ExportInstance) -> Result<()> {
         self.exports.push(export);
@@ -1,57 +1,40 @@
-use crate::engine::stack::{Frame, StackContent};
+use crate::engine::bytecode::Branch;
 use crate::engine::Engine;
-use crate::engine::InstructionOutcome;
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, Result};
 
 impl Engine {
-    pub(crate) fn br(&mut self, _fr: &mut Frame, label_idx: u32) -> Result<InstructionOutcome> {
-        debug!("OP_BR {}", label_idx);
+    /// Branches from `pc` to the label `branch.depth` and returns the next `pc`.
+    /// The `arity` values on the top of the stack are kept and
+    /// all other values of the left blocks are dropped.
+    pub(crate) fn br(&mut self, branch: &Branch, pc: usize) -> Result<usize> {
+        debug!("OP_BR {:?}", branch);
+
+        // Loops are only repeated by branching backwards
+        if branch.pc < pc {
+            self.interrupt.check()?;
+        }
 
-        debug!("stack {:#?}", self.store.stack);
+        let labels = &mut self.store.labels;
+        let idx = labels
+            .len()
+            .checked_sub(branch.depth as usize + 1)
+            .ok_or_else(|| anyhow!("Label {} not found", branch.depth))?;
 
-        let labels: Vec<_> = self
+        let height = labels[idx].get_height();
+        let keep = self
             .store
             .stack
-            .iter()
-            .filter(|x| matches!(x, StackContent::Label(_)))
-            .collect();
-
-        debug!(
-            "Getting label at {}",
-            labels.len() as isize - 1 - label_idx as isize
-        );
-        let label = labels
-            .get(labels.len() - 1 - label_idx as usize)
-            .copied();
-
-        if let Some(StackContent::Label(lb)) = label {
-            let arity = lb.get_arity();
-            debug!("The arity of the label is {}", arity);
+            .len()
+            .checked_sub(branch.arity as usize)
+            .filter(|keep| *keep >= height)
+            .ok_or_else(|| anyhow!("Not enough values on the stack for branching"))?;
 
-            // Pop off the `arity` elements off the stack.
-            let mut return_elements = self
-                .store
-                .pop_off_stack(arity as usize)
-                .context("Failed when fetching return elements")?;
+        // Drop the values between the label and the results
+        self.store.stack.remove_range(height..keep);
 
-            for _ in 0..(label_idx + 1) {
-                while matches!(self.store.stack.last(), Some(StackContent::Value(_))) {
-                    debug!("Popping off junk");
-                    self.store.stack.pop();
-                }
-
-                if let Some(StackContent::Label(_)) = self.store.stack.pop() {
-                    debug!("Popping off label");
-                } else {
-                    bail!("Popping off label failed");
-                }
-            }
-
-            self.store.stack.append(&mut return_elements);
-        } else {
-            bail!("Label not found");
-        }
+        // The target's label is left by its `End` or kept for loops
+        labels.truncate(idx + 1);
 
-        Ok(InstructionOutcome::BRANCH(label_idx))
+        Ok(branch.pc)
     }
 }
@@ -1,18 +1,31 @@
-use crate::engine::Engine;
+use crate::engine::{Engine, Trap};
 use crate::fetch_unop;
+use crate::value::Ref;
 use crate::value::Value::I32;
 use anyhow::{anyhow, Context, Result, bail};
-use wasm_parser::core::FuncAddr;
+use wasm_parser::core::{FuncAddr, TableIdx, TypeIdx};
 
 impl Engine {
-    pub(crate) fn call_indirect_function(&mut self, function_addr: FuncAddr) -> Result<()> {
-        debug!("OP_CALL_INDIRECT {:?}", function_addr);
+    /// Pops the index of the table and returns the function,
+    /// which is called by `OP_CALL_INDIRECT`.
+    pub(crate) fn resolve_indirect_call(
+        &mut self,
+        type_idx: &TypeIdx,
+        table_idx: &TableIdx,
+    ) -> Result<FuncAddr> {
+        debug!("OP_CALL_INDIRECT {:?} {:?}", type_idx, table_idx);
+
+        let expected_ty = self
+            .module_instance
+            .lookup_func_types(type_idx)
+            .ok_or_else(|| anyhow!("{} function type is not defined", type_idx))?
+            .clone();
 
         debug!("before ta");
         let ta = self
             .module_instance
-            .lookup_table_addr(&0)
-            .ok_or_else(|| anyhow!("Cannot find first table addr"))?;
+            .lookup_table_addr(table_idx)
+            .ok_or_else(|| anyhow!("Cannot find table addr {}", table_idx))?;
 
         debug!("before tab");
 
@@ -24,48 +37,32 @@ impl Engine {
 
         debug!("before i");
 
-        let i = match fetch_unop!(self.store.stack) {
+        let i = match fetch_unop!(self.store.stack, I32) {
             I32(x) => x,
             x => bail!("invalid index type: {:?}", x),
         };
-        if (i as usize) >= tab.elem.len() {
-            bail!(
-                "Attempt to perform indirect call to index larger than the table"
-            );
-        }
 
         debug!("after i");
 
-        let indirected_func_addr = tab
-            .elem
-            .get(i as usize)
-            .ok_or_else(|| anyhow!("Cannot access elem at {:?}", i))?
-            .as_ref()
-            .ok_or_else(|| anyhow!("Accessed element is not defined"))?
-            .clone();
+        let indirected_func_addr = match tab.get(i as u32).map_err(|_| Trap::UndefinedElement)? {
+            Some(Ref::Func(addr)) => addr,
+            Some(reference) => bail!("Expected a function in the table, found {:?}", reference),
+            None => bail!(Trap::UninitializedElement),
+        };
 
         debug!("ii i");
 
-        let func_instance = &self.store.get_func_instance(&indirected_func_addr)?.ty;
+        let func_ty = &self.store.get_func_instance(&indirected_func_addr)?.ty;
 
-        let param_count = func_instance.param_types.len();
-
-        debug!(
-            "Indirecting to {:?} with params {}",
-            indirected_func_addr, param_count
-        );
-
-        let args = &self.extract_args_of_stack(param_count).with_context(|| {
-            format!(
-                "Cannot extract args out of stack for function {:?}",
-                indirected_func_addr
-            )
-        })?;
-
-        debug!("=> Invoking {:?} with {:?}", indirected_func_addr, args);
+        // The types are compared structurally, because the callee
+        // can be defined by another module
+        if *func_ty != expected_ty {
+            debug!("Expected {:?}, but the callee has {:?}", expected_ty, func_ty);
+            bail!(Trap::IndirectCallTypeMismatch);
+        }
 
-        self.invoke_function(indirected_func_addr, args.to_vec())?;
+        debug!("Indirecting to {:?}", indirected_func_addr);
 
-        Ok(())
+        Ok(indirected_func_addr)
     }
 }
@@ -0,0 +1,16 @@
+use crate::engine::Engine;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::DataIdx;
+
+impl Engine {
+    pub(crate) fn data_drop(&mut self, data_idx: &DataIdx) -> Result<()> {
+        debug!("OP_DATA_DROP {:?}", data_idx);
+
+        let data_addr = self
+            .module_instance
+            .lookup_data_addr(data_idx)
+            .ok_or_else(|| anyhow!("Data segment {} is not defined", data_idx))?;
+
+        self.store.data_drop(data_addr)
+    }
+}
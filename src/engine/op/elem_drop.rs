@@ -0,0 +1,16 @@
+use crate::engine::Engine;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::ElemIdx;
+
+impl Engine {
+    pub(crate) fn elem_drop(&mut self, elem_idx: &ElemIdx) -> Result<()> {
+        debug!("OP_ELEM_DROP {:?}", elem_idx);
+
+        let elem_addr = self
+            .module_instance
+            .lookup_elem_addr(elem_idx)
+            .ok_or_else(|| anyhow!("Element segment {} is not defined", elem_idx))?;
+
+        self.store.elem_drop(elem_addr)
+    }
+}
@@ -1,26 +1,27 @@
-use crate::engine::stack::{Frame, StackContent};
 use crate::engine::Engine;
-use anyhow::{anyhow, Result};
+use crate::value::Value;
+use anyhow::{anyhow, Context, Result};
 use wasm_parser::core::GlobalIdx;
 
 impl Engine {
-    pub(crate) fn global_set(&mut self, idx: &GlobalIdx, _fr: &mut Frame) -> Result<()> {
-        match self.store.stack.pop() {
-            Some(StackContent::Value(v)) => {
-                if !self.store.globals[*idx as usize].mutable {
-                    return Err(anyhow!("Attempting to modify a immutable global"));
-                }
-                self.store.globals[*idx as usize].val = v;
-                debug!("globals {:#?}", self.store.globals);
+    pub(crate) fn global_set(&mut self, idx: &GlobalIdx) -> Result<()> {
+        let global = self
+            .store
+            .globals
+            .get(*idx as usize)
+            .context("Cannot access global")?;
 
-                Ok(())
-            }
-            Some(x) => {
-                Err(anyhow!("Expected value but found {:?}", x))
-            }
-            None => {
-                Err(anyhow!("Empty stack during local.tee"))
-            }
+        if !global.mutable {
+            return Err(anyhow!("Attempting to modify a immutable global"));
         }
+
+        // The global keeps its type
+        let ty = global.val.into();
+        let v: Value = self.store.stack.pop_value(ty)?;
+        self.store.globals[*idx as usize].val = v;
+
+        debug!("globals {:#?}", self.store.globals);
+
+        Ok(())
     }
 }
@@ -1,26 +1,27 @@
-use crate::engine::stack::{Frame, StackContent};
+use crate::engine::bytecode::Local;
 use crate::engine::Engine;
 use anyhow::{bail, Result};
-use wasm_parser::core::LocalIdx;
+use std::ops::Range;
 
 impl Engine {
-    pub(crate) fn local_get(&mut self, idx: &LocalIdx, fr: &mut Frame) -> Result<()> {
-        debug!("LOCAL_GET at {} is {:?}", idx, fr.locals[*idx as usize]);
-        debug!("locals {:#?}", fr.locals);
+    pub(crate) fn local_get(&mut self, local: &Local, locals: &Range<usize>) -> Result<()> {
+        let start = locals.start + local.offset as usize;
+        let end = start + local.slots as usize;
 
-        if let Some(val) = fr.locals.get(*idx as usize) {
-            debug!("Pushing {:?} to the stack", val);
-            self.store.stack.push(StackContent::Value(*val));
-
-            debug!("stack {:#?}", self.store.stack);
-
-            Ok(())
-        } else {
+        if end > locals.end {
             bail!(
-                "Trying to access locals ({}), but out of bounds (length {})",
-                idx,
-                fr.locals.len()
+                "Trying to access locals ({:?}), but out of bounds (length {})",
+                local,
+                locals.len()
             )
         }
+
+        for idx in start..end {
+            let slot = self.store.stack.get(idx)?;
+            debug!("LOCAL_GET at {} is {:#x}", idx, slot);
+            self.store.stack.push(slot);
+        }
+
+        Ok(())
     }
 }
@@ -1,25 +1,27 @@
-use crate::engine::stack::{Frame, StackContent};
+use crate::engine::bytecode::Local;
 use crate::engine::Engine;
-use anyhow::{Result, bail};
-use wasm_parser::core::LocalIdx;
+use anyhow::{bail, Result};
+use std::ops::Range;
 
 impl Engine {
-    pub(crate) fn local_set(&mut self, idx: &LocalIdx, fr: &mut Frame) -> Result<()> {
-        debug!("OP_LOCAL_SET {:?}", idx);
-        debug!("locals {:#?}", fr.locals);
+    pub(crate) fn local_set(&mut self, local: &Local, locals: &Range<usize>) -> Result<()> {
+        debug!("OP_LOCAL_SET {:?}", local);
 
-        match self.store.stack.pop() {
-            Some(StackContent::Value(v)) => {
-                match fr.locals.get_mut(*idx as usize) {
-                    Some(k) => *k = v, //Exists replace
-                    None => {
-                        //Does not exists; push
-                        fr.locals.push(v)
-                    }
-                }
-            }
-            Some(x) => bail!("Expected value but found {:?}", x),
-            None => bail!("Empty stack during local.set"),
+        let start = locals.start + local.offset as usize;
+        let end = start + local.slots as usize;
+
+        if end > locals.end {
+            bail!(
+                "Trying to access locals ({:?}), but out of bounds (length {})",
+                local,
+                locals.len()
+            )
+        }
+
+        // The last slot of the value is on top
+        for idx in (start..end).rev() {
+            let slot = self.store.stack.pop()?;
+            self.store.stack.set(idx, slot)?;
         }
 
         Ok(())
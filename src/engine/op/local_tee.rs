@@ -1,29 +1,25 @@
-use crate::engine::stack::{Frame, StackContent};
+use crate::engine::bytecode::Local;
 use crate::engine::Engine;
-use anyhow::{bail, Result};
-use wasm_parser::core::LocalIdx;
+use anyhow::{anyhow, Result};
+use std::ops::Range;
 
 impl Engine {
-    pub(crate) fn local_tee(&mut self, idx: &LocalIdx, fr: &mut Frame) -> Result<()> {
-        debug!("OP_LOCAL_TEE {:?}", idx);
+    pub(crate) fn local_tee(&mut self, local: &Local, locals: &Range<usize>) -> Result<()> {
+        debug!("OP_LOCAL_TEE {:?}", local);
 
-        let value = match self.store.stack.pop() {
-            Some(StackContent::Value(v)) => v,
-            Some(_) => {
-                bail!("Unexpected stack element at local.tee");
-            }
-            None => {
-                bail!("Empty stack during local.tee");
-            }
-        };
+        let start = self
+            .store
+            .stack
+            .len()
+            .checked_sub(local.slots as usize)
+            .ok_or_else(|| anyhow!("Empty stack during local.tee"))?;
 
-        self.store.stack.push(StackContent::Value(value));
-        self.store.stack.push(StackContent::Value(value));
+        for idx in start..start + local.slots as usize {
+            let slot = self.store.stack.get(idx)?;
+            self.store.stack.push(slot);
+        }
 
-        self.local_set(idx, fr)?;
-
-        debug!("stack {:?}", self.store.stack);
-        debug!("locals {:#?}", fr.locals);
+        self.local_set(local, locals)?;
 
         Ok(())
     }
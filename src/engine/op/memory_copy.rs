@@ -0,0 +1,23 @@
+use crate::engine::Engine;
+use anyhow::{Context, Result};
+
+impl Engine {
+    /// Copies a region of the memory, the regions may overlap
+    pub(crate) fn memory_copy(&mut self) -> Result<()> {
+        debug!("OP_MEMORY_COPY");
+
+        let n = self.store.stack.pop_u64()?;
+        let s = self.store.stack.pop_u64()?;
+        let d = self.store.stack.pop_u64()?;
+
+        let addr = self
+            .module_instance
+            .lookup_memory_addr(&0)
+            .context("No memory address found")?;
+        let mut instance = self.store.memory[addr.get()].write();
+
+        instance.copy_within(d, s, n)?;
+
+        Ok(())
+    }
+}
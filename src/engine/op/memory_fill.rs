@@ -0,0 +1,23 @@
+use crate::engine::Engine;
+use anyhow::{Context, Result};
+
+impl Engine {
+    /// Sets a region of the memory to a byte
+    pub(crate) fn memory_fill(&mut self) -> Result<()> {
+        debug!("OP_MEMORY_FILL");
+
+        let n = self.store.stack.pop_u64()?;
+        let val = self.store.stack.pop_u32()?;
+        let d = self.store.stack.pop_u64()?;
+
+        let addr = self
+            .module_instance
+            .lookup_memory_addr(&0)
+            .context("No memory address found")?;
+        let mut instance = self.store.memory[addr.get()].write();
+
+        instance.fill(d, val as u8, n)?;
+
+        Ok(())
+    }
+}
@@ -1,38 +1,32 @@
 use crate::engine::Engine;
-use crate::engine::memory::grow_memory;
-use crate::value::Value::I32;
-use crate::engine::stack::StackContent;
-use crate::PAGE_SIZE;
-use anyhow::{anyhow, Result, Context};
-use crate::engine::Page;
+use anyhow::{Result, Context};
+use wasm_parser::core::IndexType;
 
 impl Engine {
+    /// Pops the number of pages and pushes the previous size
+    /// or -1 of the memory's index type, when growing failed
     pub(crate) fn memory_grow(&mut self) -> Result<()> {
         let module = &self.module_instance;
         let addr = module
             .lookup_memory_addr(&0)
             .context("No memory address found")?;
-        let instance = &mut self.store.memory[addr.get()];
-        let _sz = instance.data.len() / PAGE_SIZE;
+        let mut instance = self.store.memory[addr.get()].write();
 
-        if let Some(StackContent::Value(I32(n))) = self.store.stack.pop() {
-            if n < 0 {
-                return Err(anyhow!("Memory grow expected n > 0, got {}", n));
-            }
+        let n = self.store.stack.pop_u64()?;
 
-            match grow_memory(instance, Page::new(n as usize)) {
-                Err(()) => {
-                    error!("Memory growing failed because paging failed.");
-                    self.store.stack.push(StackContent::Value(I32(-1)));
-                }
-                Ok(_new_sz) => {
-                    //debug!("Old memory size {} pages", _new_sz);
-                    self.store.stack.push(StackContent::Value(I32(_sz as i32)));
+        let result = match instance.grow(n) {
+            Some(previous) => previous,
+            None => {
+                error!("Memory growing failed because paging failed.");
+
+                match instance.index_type {
+                    IndexType::I32 => u32::MAX as u64,
+                    IndexType::I64 => u64::MAX,
                 }
             }
-        } else {
-            return Err(anyhow!("Unexpected stack element. Expected I32"));
-        }
+        };
+
+        self.store.stack.push(result);
 
         Ok(())
     }
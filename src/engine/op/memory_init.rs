@@ -0,0 +1,25 @@
+use crate::engine::Engine;
+use anyhow::{anyhow, Context, Result};
+use wasm_parser::core::DataIdx;
+
+impl Engine {
+    /// Copies a region of the data segment into the memory
+    pub(crate) fn memory_init(&mut self, data_idx: &DataIdx) -> Result<()> {
+        debug!("OP_MEMORY_INIT {:?}", data_idx);
+
+        let n = self.store.stack.pop_u32()?;
+        let s = self.store.stack.pop_u32()?;
+        let d = self.store.stack.pop_u64()?;
+
+        let mem_addr = self
+            .module_instance
+            .lookup_memory_addr(&0)
+            .context("No memory address found")?;
+        let data_addr = self
+            .module_instance
+            .lookup_data_addr(data_idx)
+            .ok_or_else(|| anyhow!("Data segment {} is not defined", data_idx))?;
+
+        self.store.memory_init(mem_addr, data_addr, d, s, n)
+    }
+}
@@ -1,20 +1,19 @@
 use crate::engine::Engine;
-use crate::value::Value::I32;
 use anyhow::{Result, Context};
-use crate::PAGE_SIZE;
-use crate::engine::StackContent;
 
 impl Engine {
+    /// Pushes the size in pages as an address of the memory's index type
     pub(crate) fn memory_size(&mut self) -> Result<()> {
         let module = &self.module_instance;
         let addr = module
             .lookup_memory_addr(&0)
             .context("No memory address found")?;
-        let instance = &self.store.memory[addr.get()];
+        let instance = self.store.memory[addr.get()].read();
 
-        let sz = instance.data.len() / PAGE_SIZE;
+        let sz = instance.pages();
 
-        self.store.stack.push(StackContent::Value(I32(sz as i32)));
+        // The size of 32-bit memories fits into an I32
+        self.store.stack.push(sz);
 
         Ok(())
     }
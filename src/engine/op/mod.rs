@@ -4,9 +4,24 @@ mod local_tee;
 mod global_get;
 mod global_set;
 mod select;
+mod ref_func;
+mod table_get;
+mod table_set;
+mod table_size;
+mod table_grow;
+mod table_fill;
 mod memory_size;
 mod memory_grow;
-mod block;
+mod memory_init;
+mod memory_copy;
+mod memory_fill;
+mod data_drop;
+mod table_init;
+mod table_copy;
+mod elem_drop;
 mod call;
 mod call_indirect;
-mod br;
\ No newline at end of file
+mod return_call;
+mod br;
+mod throw;
+mod rethrow;
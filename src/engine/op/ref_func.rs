@@ -0,0 +1,19 @@
+use crate::engine::Engine;
+use crate::value::Value::FuncRef;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::FuncIdx;
+
+impl Engine {
+    pub(crate) fn ref_func(&mut self, func_idx: &FuncIdx) -> Result<()> {
+        debug!("OP_REF_FUNC {:?}", func_idx);
+
+        let addr = self
+            .module_instance
+            .lookup_function_addr(func_idx)
+            .ok_or_else(|| anyhow!("Cannot find function's addr {}", func_idx))?;
+
+        self.store.stack.push_value(FuncRef(Some(*addr)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+use crate::engine::exception::Exception;
+use crate::engine::stack::LabelKind;
+use crate::engine::Engine;
+use anyhow::{anyhow, bail, Result};
+
+impl Engine {
+    /// Returns the exception, which was caught by the handler of the label `depth`
+    pub(crate) fn rethrow(&self, depth: u32) -> Result<Exception> {
+        debug!("OP_RETHROW {}", depth);
+
+        let labels = &self.store.labels;
+        let label = labels
+            .len()
+            .checked_sub(depth as usize + 1)
+            .and_then(|idx| labels.get(idx))
+            .ok_or_else(|| anyhow!("Label {} not found", depth))?;
+
+        match label.get_kind() {
+            LabelKind::Catch(exception) => Ok(exception.as_ref().clone()),
+            kind => bail!("Label {} is not a catch handler, but {:?}", depth, kind),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::engine::stack::count_slots;
+use crate::engine::Engine;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::FuncAddr;
+
+impl Engine {
+    /// Calls the function in place of the frame on top of the call stack.
+    /// The arguments on top of the stack replace the locals of the frame,
+    /// therefore tail calls do not grow the call stack.
+    pub(crate) fn return_call(&mut self, func_addr: FuncAddr, depth: usize) -> Result<()> {
+        debug!("OP_RETURN_CALL {:?}", func_addr);
+
+        let func_instance = self.store.get_func_instance(&func_addr)?;
+        let params = count_slots(&func_instance.ty.param_types) as usize;
+        let is_host = func_instance.host.is_some();
+
+        let frame = self
+            .call_stack
+            .pop()
+            .ok_or_else(|| anyhow!("The call stack is empty"))?;
+
+        let args = self
+            .store
+            .stack
+            .len()
+            .checked_sub(params)
+            .filter(|args| *args >= frame.locals.start)
+            .ok_or_else(|| anyhow!("Not enough arguments on the stack for {:?}", func_addr))?;
+
+        self.store.stack.remove_range(frame.locals.start..args);
+        self.store.labels.truncate(frame.labels);
+
+        self.call_function(func_addr)?;
+
+        // The host function has already returned to the caller of the frame
+        if is_host {
+            self.switch_to_caller(depth)?;
+        }
+
+        Ok(())
+    }
+}
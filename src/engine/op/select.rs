@@ -1,24 +1,29 @@
 use crate::engine::Engine;
-use crate::fetch_binop;
-use crate::value::Value::I32;
 use anyhow::{anyhow, Result};
-use crate::engine::stack::StackContent;
 
 impl Engine {
-    pub(crate) fn select(&mut self) -> Result<()> {
+    /// Selects one of the operands, which occupy `slots` each
+    pub(crate) fn select(&mut self, slots: u32) -> Result<()> {
         debug!("OP_SELECT");
-        debug!("Popping {:?}", self.store.stack.last());
-        let c = match self.store.stack.pop() {
-            Some(StackContent::Value(I32(x))) => x,
-            _ => return Err(anyhow!("Expected I32 on top of stack")),
-        };
-        let (v1, v2) = fetch_binop!(self.store.stack);
+        let c = self.store.stack.pop_u32()?;
+
+        let slots = slots as usize;
+        let v2 = self
+            .store
+            .stack
+            .len()
+            .checked_sub(slots)
+            .ok_or_else(|| anyhow!("Not enough operands on the stack for select"))?;
+        let v1 = v2
+            .checked_sub(slots)
+            .ok_or_else(|| anyhow!("Not enough operands on the stack for select"))?;
+
         if c != 0 {
-            debug!("C is not 0 therefore, pushing {:?}", v2);
-            self.store.stack.push(StackContent::Value(v2))
+            debug!("C is not 0 therefore, keeping the first operand");
+            self.store.stack.truncate(v2);
         } else {
-            debug!("C is not 0 therefore, pushing {:?}", v1);
-            self.store.stack.push(StackContent::Value(v1))
+            debug!("C is 0 therefore, keeping the second operand");
+            self.store.stack.remove_range(v1..v2);
         }
 
         Ok(())
@@ -0,0 +1,25 @@
+use crate::engine::Engine;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::TableIdx;
+
+impl Engine {
+    /// Copies a region of the table `src_idx` into the table `dst_idx`
+    pub(crate) fn table_copy(&mut self, dst_idx: &TableIdx, src_idx: &TableIdx) -> Result<()> {
+        debug!("OP_TABLE_COPY {:?} {:?}", dst_idx, src_idx);
+
+        let n = self.store.stack.pop_u32()?;
+        let s = self.store.stack.pop_u32()?;
+        let d = self.store.stack.pop_u32()?;
+
+        let dst_addr = self
+            .module_instance
+            .lookup_table_addr(dst_idx)
+            .ok_or_else(|| anyhow!("Table {} is not defined", dst_idx))?;
+        let src_addr = self
+            .module_instance
+            .lookup_table_addr(src_idx)
+            .ok_or_else(|| anyhow!("Table {} is not defined", src_idx))?;
+
+        self.store.table_copy(dst_addr, src_addr, d, s, n)
+    }
+}
@@ -0,0 +1,26 @@
+use crate::engine::Engine;
+use crate::value::Value;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::TableIdx;
+
+impl Engine {
+    /// Sets a region of the table to a reference
+    pub(crate) fn table_fill(&mut self, table_idx: &TableIdx) -> Result<()> {
+        debug!("OP_TABLE_FILL {:?}", table_idx);
+
+        let n = self.store.stack.pop_u32()?;
+        let slot = self.store.stack.pop()?;
+        let i = self.store.stack.pop_u32()?;
+
+        let addr = self
+            .module_instance
+            .lookup_table_addr(table_idx)
+            .ok_or_else(|| anyhow!("Table {} is not defined", table_idx))?;
+        let table = self.store.get_table_instance_mut(addr)?;
+
+        let reference = Value::from_bits(table.ty, slot).to_ref();
+        table.fill(i, reference, n)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+use crate::engine::Engine;
+use crate::value::Value;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::TableIdx;
+
+impl Engine {
+    pub(crate) fn table_get(&mut self, table_idx: &TableIdx) -> Result<()> {
+        debug!("OP_TABLE_GET {:?}", table_idx);
+
+        let i = self.store.stack.pop_u32()?;
+
+        let addr = self
+            .module_instance
+            .lookup_table_addr(table_idx)
+            .ok_or_else(|| anyhow!("Table {} is not defined", table_idx))?;
+        let table = self.store.get_table_instance(addr)?;
+
+        let value = Value::from_ref(table.ty, table.get(i)?);
+        self.store.stack.push_value(value);
+
+        Ok(())
+    }
+}
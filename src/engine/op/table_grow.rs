@@ -0,0 +1,31 @@
+use crate::engine::Engine;
+use crate::value::Value;
+use crate::value::Value::I32;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::TableIdx;
+
+impl Engine {
+    /// Grows the table by `n` references and pushes the previous size,
+    /// or -1 when the table cannot grow.
+    pub(crate) fn table_grow(&mut self, table_idx: &TableIdx) -> Result<()> {
+        debug!("OP_TABLE_GROW {:?}", table_idx);
+
+        let n = self.store.stack.pop_u32()?;
+        let slot = self.store.stack.pop()?;
+
+        let addr = self
+            .module_instance
+            .lookup_table_addr(table_idx)
+            .ok_or_else(|| anyhow!("Table {} is not defined", table_idx))?;
+        let table = self.store.get_table_instance_mut(addr)?;
+
+        let reference = Value::from_bits(table.ty, slot).to_ref();
+
+        match table.grow(n, reference) {
+            Some(size) => self.store.stack.push_value(I32(size as i32)),
+            None => self.store.stack.push_value(I32(-1)),
+        }
+
+        Ok(())
+    }
+}
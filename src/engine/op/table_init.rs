@@ -0,0 +1,25 @@
+use crate::engine::Engine;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::{ElemIdx, TableIdx};
+
+impl Engine {
+    /// Copies a region of the element segment into the table
+    pub(crate) fn table_init(&mut self, elem_idx: &ElemIdx, table_idx: &TableIdx) -> Result<()> {
+        debug!("OP_TABLE_INIT {:?} {:?}", elem_idx, table_idx);
+
+        let n = self.store.stack.pop_u32()?;
+        let s = self.store.stack.pop_u32()?;
+        let d = self.store.stack.pop_u32()?;
+
+        let table_addr = self
+            .module_instance
+            .lookup_table_addr(table_idx)
+            .ok_or_else(|| anyhow!("Table {} is not defined", table_idx))?;
+        let elem_addr = self
+            .module_instance
+            .lookup_elem_addr(elem_idx)
+            .ok_or_else(|| anyhow!("Element segment {} is not defined", elem_idx))?;
+
+        self.store.table_init(table_addr, elem_addr, d, s, n)
+    }
+}
@@ -0,0 +1,24 @@
+use crate::engine::Engine;
+use crate::value::Value;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::TableIdx;
+
+impl Engine {
+    pub(crate) fn table_set(&mut self, table_idx: &TableIdx) -> Result<()> {
+        debug!("OP_TABLE_SET {:?}", table_idx);
+
+        let slot = self.store.stack.pop()?;
+        let i = self.store.stack.pop_u32()?;
+
+        let addr = self
+            .module_instance
+            .lookup_table_addr(table_idx)
+            .ok_or_else(|| anyhow!("Table {} is not defined", table_idx))?;
+        let table = self.store.get_table_instance_mut(addr)?;
+
+        let reference = Value::from_bits(table.ty, slot).to_ref();
+        table.set(i, reference)?;
+
+        Ok(())
+    }
+}
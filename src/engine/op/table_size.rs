@@ -0,0 +1,20 @@
+use crate::engine::Engine;
+use crate::value::Value::I32;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::TableIdx;
+
+impl Engine {
+    pub(crate) fn table_size(&mut self, table_idx: &TableIdx) -> Result<()> {
+        debug!("OP_TABLE_SIZE {:?}", table_idx);
+
+        let addr = self
+            .module_instance
+            .lookup_table_addr(table_idx)
+            .ok_or_else(|| anyhow!("Table {} is not defined", table_idx))?;
+        let size = self.store.get_table_instance(addr)?.elem.len();
+
+        self.store.stack.push_value(I32(size as i32));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+use crate::engine::exception::Exception;
+use crate::engine::Engine;
+use anyhow::{anyhow, Result};
+use wasm_parser::core::TagIdx;
+
+impl Engine {
+    /// Pops the arguments of the tag and creates the exception
+    pub(crate) fn throw(&mut self, tag_idx: &TagIdx) -> Result<Exception> {
+        debug!("OP_THROW {}", tag_idx);
+
+        let tag = *self
+            .module_instance
+            .lookup_tag_addr(tag_idx)
+            .ok_or_else(|| anyhow!("Cannot find tag's addr {}", tag_idx))?;
+
+        let param_types = self.store.get_tag_instance(&tag)?.ty.param_types.clone();
+        let payload = self.store.stack.pop_values(&param_types)?;
+
+        Ok(Exception { tag, payload })
+    }
+}
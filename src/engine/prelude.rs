@@ -1,15 +1 @@
-pub use crate::convert;
-pub use crate::debugger::ProgramState;
-pub use crate::debugger::{ProgramCounter, RelativeProgramCounter};
-pub use crate::store::Store;
-pub use crate::engine::stack::StackContent::*;
-pub use crate::engine::stack::{Label, Frame};
-pub use crate::operations::*;
-pub use crate::page::Page;
-pub use crate::value::{Arity, Value, Value::*};
-pub use crate::PAGE_SIZE;
-pub use anyhow::{anyhow, Context, Result};
-pub use std::fmt;
-pub use wasm_parser::core::Instruction::*;
-pub use wasm_parser::core::*;
-pub use wasm_parser::Module;
+pub use crate::value::Value;
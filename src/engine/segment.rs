@@ -0,0 +1,76 @@
+use crate::engine::Trap;
+use std::fmt;
+use std::ops::Range;
+use crate::value::Ref;
+
+/// The bytes of a data segment, which are copied by `memory.init`.
+/// The segment is empty after `data.drop`.
+/// https://webassembly.github.io/spec/core/exec/runtime.html#data-instances
+#[derive(Clone, Default)]
+pub struct DataInstance {
+    pub data: Vec<u8>,
+}
+
+impl DataInstance {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+
+    /// Get the `n` bytes at `src`
+    pub(crate) fn bytes(&self, src: u32, n: u32) -> Result<&[u8], Trap> {
+        let range = segment_range(src, n, self.data.len()).ok_or(Trap::MemoryOutOfBounds)?;
+
+        Ok(&self.data[range])
+    }
+
+    pub(crate) fn drop(&mut self) {
+        self.data = Vec::new();
+    }
+}
+
+// Like the memory, only the length of the data is printed
+impl fmt::Debug for DataInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataInstance")
+            .field("data (only length)", &self.data.len())
+            .finish()
+    }
+}
+
+/// The references of an element segment, which are copied by `table.init`.
+/// The segment is empty after `elem.drop`.
+/// https://webassembly.github.io/spec/core/exec/runtime.html#element-instances
+#[derive(Debug, Clone, Default)]
+pub struct ElemInstance {
+    pub elem: Vec<Option<Ref>>,
+}
+
+impl ElemInstance {
+    pub fn new(elem: Vec<Option<Ref>>) -> Self {
+        Self { elem }
+    }
+
+    /// Get the `n` references at `src`
+    pub(crate) fn get(&self, src: u32, n: u32) -> Result<&[Option<Ref>], Trap> {
+        let range = segment_range(src, n, self.elem.len()).ok_or(Trap::TableOutOfBounds)?;
+
+        Ok(&self.elem[range])
+    }
+
+    pub(crate) fn drop(&mut self) {
+        self.elem = Vec::new();
+    }
+}
+
+/// The range `start..start + n` when it is within `len`.
+/// The end is computed in u64, therefore it cannot wrap around.
+pub(crate) fn segment_range(start: u32, n: u32, len: usize) -> Option<Range<usize>> {
+    let end = start as u64 + n as u64;
+
+    if end > len as u64 {
+        debug!("Access {:?} is out of bounds ({} entries)", start as u64..end, len);
+        return None;
+    }
+
+    Some(start as usize..end as usize)
+}
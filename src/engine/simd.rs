@@ -0,0 +1,787 @@
+//! The fixed-width vector instructions.
+//! A `v128` is split into its lanes, which are computed one by one
+//! in plain scalar code. Therefore, no SIMD support of the host is required.
+
+use crate::engine::Engine;
+use crate::operations::{max, min, nearest};
+use crate::value::Value::{self, *};
+use anyhow::{bail, Context, Result};
+use std::convert::TryInto;
+use wasm_parser::core::Instruction::{self, *};
+use wasm_parser::core::{LaneIdx, MemArg, ValueType};
+
+/// A lane of a `v128`, which is stored in little endian
+trait Lane: Copy + Default {
+    const BYTES: usize;
+
+    fn read(bytes: &[u8]) -> Self;
+
+    fn write(self, bytes: &mut [u8]);
+
+    /// The result of a comparison, which has all bits set for `true`
+    fn mask(b: bool) -> Self {
+        Self::read(&[if b { 0xFF } else { 0x00 }; 8][..Self::BYTES])
+    }
+}
+
+macro_rules! impl_lane {
+    ($($ty:ty),*) => {
+        $(
+            impl Lane for $ty {
+                const BYTES: usize = std::mem::size_of::<$ty>();
+
+                fn read(bytes: &[u8]) -> Self {
+                    <$ty>::from_le_bytes(bytes.try_into().expect("Lane has the wrong size"))
+                }
+
+                fn write(self, bytes: &mut [u8]) {
+                    bytes.copy_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_lane!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+/// Splits the vector into `N` lanes of `T`
+fn lanes<T: Lane, const N: usize>(v: u128) -> [T; N] {
+    let bytes = v.to_le_bytes();
+    let mut lanes = [T::default(); N];
+
+    for (lane, chunk) in lanes.iter_mut().zip(bytes.chunks_exact(T::BYTES)) {
+        *lane = T::read(chunk);
+    }
+
+    lanes
+}
+
+/// Joins the lanes to a vector
+fn from_lanes<T: Lane, const N: usize>(lanes: [T; N]) -> u128 {
+    let mut bytes = [0; 16];
+
+    for (lane, chunk) in lanes.iter().zip(bytes.chunks_exact_mut(T::BYTES)) {
+        lane.write(chunk);
+    }
+
+    u128::from_le_bytes(bytes)
+}
+
+/// Creates a vector, whose lane `i` is `f(i)`
+fn from_fn<T: Lane, const N: usize>(f: impl FnMut(usize) -> T) -> u128 {
+    from_lanes::<T, N>(std::array::from_fn(f))
+}
+
+fn map<T: Lane, const N: usize>(v: u128, f: impl Fn(T) -> T) -> u128 {
+    from_lanes(lanes::<T, N>(v).map(f))
+}
+
+fn zip<T: Lane, const N: usize>(v1: u128, v2: u128, f: impl Fn(T, T) -> T) -> u128 {
+    let (a, b) = (lanes::<T, N>(v1), lanes::<T, N>(v2));
+    from_fn::<T, N>(|i| f(a[i], b[i]))
+}
+
+fn try_map<T: Lane, const N: usize>(v: u128, f: impl Fn(T) -> Result<T>) -> Result<u128> {
+    let mut lanes = lanes::<T, N>(v);
+    for lane in lanes.iter_mut() {
+        *lane = f(*lane)?;
+    }
+
+    Ok(from_lanes(lanes))
+}
+
+fn try_zip<T: Lane, const N: usize>(
+    v1: u128,
+    v2: u128,
+    f: impl Fn(T, T) -> Result<T>,
+) -> Result<u128> {
+    let (mut a, b) = (lanes::<T, N>(v1), lanes::<T, N>(v2));
+    for (lane, other) in a.iter_mut().zip(b.iter()) {
+        *lane = f(*lane, *other)?;
+    }
+
+    Ok(from_lanes(a))
+}
+
+fn compare<T: Lane, const N: usize>(v1: u128, v2: u128, f: impl Fn(T, T) -> bool) -> u128 {
+    zip::<T, N>(v1, v2, |a, b| T::mask(f(a, b)))
+}
+
+/// Sets the bit `i` of the result, when the lane `i` is negative
+fn bitmask<T: Lane + PartialOrd, const N: usize>(v: u128) -> i32 {
+    lanes::<T, N>(v)
+        .iter()
+        .enumerate()
+        .filter(|(_, lane)| **lane < T::default())
+        .fold(0, |mask, (i, _)| mask | (1 << i))
+}
+
+fn all_true<T: Lane + PartialEq, const N: usize>(v: u128) -> i32 {
+    lanes::<T, N>(v).iter().all(|lane| *lane != T::default()) as i32
+}
+
+fn lane<T: Lane, const N: usize>(v: u128, idx: LaneIdx) -> Result<T> {
+    lanes::<T, N>(v)
+        .get(idx as usize)
+        .copied()
+        .with_context(|| format!("Lane {} is out of bounds", idx))
+}
+
+fn replace_lane<T: Lane, const N: usize>(v: u128, idx: LaneIdx, val: T) -> Result<u128> {
+    let mut lanes = lanes::<T, N>(v);
+    *lanes
+        .get_mut(idx as usize)
+        .with_context(|| format!("Lane {} is out of bounds", idx))? = val;
+
+    Ok(from_lanes(lanes))
+}
+
+/// Applies a scalar float operation of `operations` on lanes
+fn f32_binop(f: fn(Value, Value) -> Value, a: f32, b: f32) -> Result<f32> {
+    match f(F32(a), F32(b)) {
+        F32(v) => Ok(v),
+        v => bail!("Expected F32, found {:?}", v),
+    }
+}
+
+fn f64_binop(f: fn(Value, Value) -> Value, a: f64, b: f64) -> Result<f64> {
+    match f(F64(a), F64(b)) {
+        F64(v) => Ok(v),
+        v => bail!("Expected F64, found {:?}", v),
+    }
+}
+
+fn f32_nearest(a: f32) -> Result<f32> {
+    match nearest(F32(a)) {
+        F32(v) => Ok(v),
+        v => bail!("Expected F32, found {:?}", v),
+    }
+}
+
+fn f64_nearest(a: f64) -> Result<f64> {
+    match nearest(F64(a)) {
+        F64(v) => Ok(v),
+        v => bail!("Expected F64, found {:?}", v),
+    }
+}
+
+/// Pops the vector and pushes the lane-wise result of `$e`
+macro_rules! v128_unop {
+    ($self:expr, $ty:ty, $n:expr, |$a:ident| $e:expr) => {{
+        let v = $self.store.stack.pop_v128()?;
+        $self
+            .store
+            .stack
+            .push_value(V128(map::<$ty, $n>(v, |$a: $ty| $e)));
+    }};
+}
+
+/// Like `v128_unop`, but `$e` is fallible
+macro_rules! v128_try_unop {
+    ($self:expr, $ty:ty, $n:expr, |$a:ident| $e:expr) => {{
+        let v = $self.store.stack.pop_v128()?;
+        let result = try_map::<$ty, $n>(v, |$a: $ty| $e)?;
+        $self.store.stack.push_value(V128(result));
+    }};
+}
+
+/// Pops two vectors and pushes the lane-wise result of `$e`.
+/// `$a` is the first operand and `$b` the top of the stack.
+macro_rules! v128_binop {
+    ($self:expr, $ty:ty, $n:expr, |$a:ident, $b:ident| $e:expr) => {{
+        let v2 = $self.store.stack.pop_v128()?;
+        let v1 = $self.store.stack.pop_v128()?;
+        $self
+            .store
+            .stack
+            .push_value(V128(zip::<$ty, $n>(v1, v2, |$a: $ty, $b: $ty| $e)));
+    }};
+}
+
+/// Like `v128_binop`, but `$e` is fallible
+macro_rules! v128_try_binop {
+    ($self:expr, $ty:ty, $n:expr, |$a:ident, $b:ident| $e:expr) => {{
+        let v2 = $self.store.stack.pop_v128()?;
+        let v1 = $self.store.stack.pop_v128()?;
+        let result = try_zip::<$ty, $n>(v1, v2, |$a: $ty, $b: $ty| $e)?;
+        $self.store.stack.push_value(V128(result));
+    }};
+}
+
+/// Like `v128_binop`, but the lanes of the result are masks
+macro_rules! v128_compare {
+    ($self:expr, $ty:ty, $n:expr, |$a:ident, $b:ident| $e:expr) => {{
+        let v2 = $self.store.stack.pop_v128()?;
+        let v1 = $self.store.stack.pop_v128()?;
+        $self
+            .store
+            .stack
+            .push_value(V128(compare::<$ty, $n>(v1, v2, |$a: $ty, $b: $ty| $e)));
+    }};
+}
+
+/// Pops the shift count and the vector.
+/// The count is taken modulo the lane width.
+macro_rules! v128_shift {
+    ($self:expr, $ty:ty, $n:expr, $f:ident) => {{
+        let s = $self.store.stack.pop_u32()?;
+        let v = $self.store.stack.pop_v128()?;
+        $self
+            .store
+            .stack
+            .push_value(V128(map::<$ty, $n>(v, |a: $ty| a.$f(s))));
+    }};
+}
+
+/// Pops the vector and pushes the lanes `$from..` of it converted by `$e`
+macro_rules! v128_convert {
+    ($self:expr, $src:ty, $src_n:expr => $dst:ty, $dst_n:expr, $from:expr, |$a:ident| $e:expr) => {{
+        let v = $self.store.stack.pop_v128()?;
+        let lanes = lanes::<$src, $src_n>(v);
+        $self.store.stack.push_value(V128(from_fn::<$dst, $dst_n>(|i| {
+            let $a = lanes[$from + i];
+            $e
+        })));
+    }};
+}
+
+/// Pops the vector and converts both lanes of it by `$e`.
+/// The upper lanes of the result are zero.
+macro_rules! v128_convert_zero {
+    ($self:expr, $src:ty => $dst:ty, |$a:ident| $e:expr) => {{
+        let v = $self.store.stack.pop_v128()?;
+        let lanes = lanes::<$src, 2>(v);
+        $self.store.stack.push_value(V128(from_fn::<$dst, 4>(|i| match lanes.get(i) {
+            Some(&$a) => $e,
+            None => <$dst>::default(),
+        })));
+    }};
+}
+
+/// Pops two vectors and pushes `$e` of the lanes `$from..` of both
+macro_rules! v128_ext_binop {
+    ($self:expr, $src:ty, $src_n:expr => $dst:ty, $dst_n:expr, $from:expr, |$a:ident, $b:ident| $e:expr) => {{
+        let v2 = $self.store.stack.pop_v128()?;
+        let v1 = $self.store.stack.pop_v128()?;
+        let (a, b) = (lanes::<$src, $src_n>(v1), lanes::<$src, $src_n>(v2));
+        $self.store.stack.push_value(V128(from_fn::<$dst, $dst_n>(|i| {
+            let ($a, $b) = (a[$from + i], b[$from + i]);
+            $e
+        })));
+    }};
+}
+
+/// Pops the vector and pushes the sum of adjacent lanes
+macro_rules! v128_extadd_pairwise {
+    ($self:expr, $src:ty, $src_n:expr => $dst:ty, $dst_n:expr) => {{
+        let v = $self.store.stack.pop_v128()?;
+        let a = lanes::<$src, $src_n>(v);
+        $self.store.stack.push_value(V128(from_fn::<$dst, $dst_n>(|i| {
+            (a[2 * i] as $dst).wrapping_add(a[2 * i + 1] as $dst)
+        })));
+    }};
+}
+
+/// Pops two vectors and narrows the lanes of both with saturation
+macro_rules! v128_narrow {
+    ($self:expr, $src:ty, $src_n:expr => $dst:ty, $dst_n:expr) => {{
+        let v2 = $self.store.stack.pop_v128()?;
+        let v1 = $self.store.stack.pop_v128()?;
+        let (a, b) = (lanes::<$src, $src_n>(v1), lanes::<$src, $src_n>(v2));
+        $self.store.stack.push_value(V128(from_fn::<$dst, $dst_n>(|i| {
+            let lane = if i < $src_n { a[i] } else { b[i - $src_n] };
+            lane.clamp(<$dst>::MIN as $src, <$dst>::MAX as $src) as $dst
+        })));
+    }};
+}
+
+/// Pops a scalar of `$scalar` and pushes it in every lane
+macro_rules! v128_splat {
+    ($self:expr, $scalar:ident, $ty:ty, $n:expr) => {{
+        let v = match $self.store.stack.pop_value(ValueType::$scalar)? {
+            $scalar(v) => v as $ty,
+            v => bail!("Expected {}, found {:?}", stringify!($scalar), v),
+        };
+        $self.store.stack.push_value(V128(from_fn::<$ty, $n>(|_| v)));
+    }};
+}
+
+macro_rules! v128_extract_lane {
+    ($self:expr, $ty:ty, $n:expr, $idx:expr, $variant:ident, $scalar:ty) => {{
+        let v = $self.store.stack.pop_v128()?;
+        let lane = lane::<$ty, $n>(v, *$idx)?;
+        $self.store.stack.push_value($variant(lane as $scalar));
+    }};
+}
+
+/// Pops a scalar of `$scalar` and replaces the lane of the vector below
+macro_rules! v128_replace_lane {
+    ($self:expr, $ty:ty, $n:expr, $idx:expr, $scalar:ident) => {{
+        let val = match $self.store.stack.pop_value(ValueType::$scalar)? {
+            $scalar(v) => v as $ty,
+            v => bail!("Expected {}, found {:?}", stringify!($scalar), v),
+        };
+        let v = $self.store.stack.pop_v128()?;
+        $self
+            .store
+            .stack
+            .push_value(V128(replace_lane::<$ty, $n>(v, *$idx, val)?));
+    }};
+}
+
+/// Loads `$n` lanes of `$src` and extends them to `$dst`
+macro_rules! v128_load_extend {
+    ($self:expr, $arg:expr, $src:ty => $dst:ty, $n:expr) => {{
+        let bytes = $self.load_bytes::<8>($arg)?;
+        let mut lanes = [0 as $src; $n];
+        for (lane, chunk) in lanes.iter_mut().zip(bytes.chunks_exact(8 / $n)) {
+            *lane = <$src>::read(chunk);
+        }
+        $self
+            .store
+            .stack
+            .push_value(V128(from_fn::<$dst, $n>(|i| lanes[i] as $dst)));
+    }};
+}
+
+/// Loads a `$ty` and pushes it in every lane
+macro_rules! v128_load_splat {
+    ($self:expr, $arg:expr, $ty:ty, $n:expr) => {{
+        const SIZE: usize = std::mem::size_of::<$ty>();
+        let v = <$ty>::read(&$self.load_bytes::<SIZE>($arg)?);
+        $self.store.stack.push_value(V128(from_fn::<$ty, $n>(|_| v)));
+    }};
+}
+
+/// Loads a `$ty` into the lane of the vector on the stack
+macro_rules! v128_load_lane {
+    ($self:expr, $arg:expr, $idx:expr, $ty:ty, $n:expr) => {{
+        const SIZE: usize = std::mem::size_of::<$ty>();
+        let v = $self.store.stack.pop_v128()?;
+        let val = <$ty>::read(&$self.load_bytes::<SIZE>($arg)?);
+        $self
+            .store
+            .stack
+            .push_value(V128(replace_lane::<$ty, $n>(v, *$idx, val)?));
+    }};
+}
+
+/// Stores the lane of the vector on the stack
+macro_rules! v128_store_lane {
+    ($self:expr, $arg:expr, $idx:expr, $ty:ty, $n:expr) => {{
+        let v = $self.store.stack.pop_v128()?;
+        let lane = lane::<$ty, $n>(v, *$idx)?;
+        $self.store_bytes($arg, &lane.to_le_bytes())?;
+    }};
+}
+
+impl Engine {
+    /// Pops the address and reads `N` bytes of the memory
+    fn load_bytes<const N: usize>(&mut self, arg: &MemArg) -> Result<[u8; N]> {
+        let base = self.store.stack.pop_u64()?;
+
+        let addr = self
+            .module_instance
+            .lookup_memory_addr(&0)
+            .context("No memory address found")?;
+        let instance = self.store.get_memory_instance(addr)?.read();
+
+        let mut bytes = [0; N];
+        instance.load(base, arg.offset, &mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Pops the address and writes the bytes to the memory
+    fn store_bytes(&mut self, arg: &MemArg, bytes: &[u8]) -> Result<()> {
+        let base = self.store.stack.pop_u64()?;
+
+        let addr = self
+            .module_instance
+            .lookup_memory_addr(&0)
+            .context("No memory address found")?;
+        let mut instance = self.store.get_memory_instance(addr)?.write();
+
+        instance.store(base, arg.offset, bytes)?;
+
+        Ok(())
+    }
+
+    /// Executes a vector instruction
+    #[allow(clippy::cognitive_complexity)]
+    pub(super) fn execute_vector(&mut self, instruction: &Instruction) -> Result<()> {
+        match instruction {
+            // Memory
+            OP_V128_LOAD(arg) => {
+                let bytes = self.load_bytes::<16>(arg)?;
+                self.store.stack.push_value(V128(u128::from_le_bytes(bytes)));
+            }
+            OP_V128_LOAD8X8_S(arg) => v128_load_extend!(self, arg, i8 => i16, 8),
+            OP_V128_LOAD8X8_U(arg) => v128_load_extend!(self, arg, u8 => u16, 8),
+            OP_V128_LOAD16X4_S(arg) => v128_load_extend!(self, arg, i16 => i32, 4),
+            OP_V128_LOAD16X4_U(arg) => v128_load_extend!(self, arg, u16 => u32, 4),
+            OP_V128_LOAD32X2_S(arg) => v128_load_extend!(self, arg, i32 => i64, 2),
+            OP_V128_LOAD32X2_U(arg) => v128_load_extend!(self, arg, u32 => u64, 2),
+            OP_V128_LOAD8_SPLAT(arg) => v128_load_splat!(self, arg, u8, 16),
+            OP_V128_LOAD16_SPLAT(arg) => v128_load_splat!(self, arg, u16, 8),
+            OP_V128_LOAD32_SPLAT(arg) => v128_load_splat!(self, arg, u32, 4),
+            OP_V128_LOAD64_SPLAT(arg) => v128_load_splat!(self, arg, u64, 2),
+            OP_V128_LOAD32_ZERO(arg) => {
+                let v = u32::from_le_bytes(self.load_bytes::<4>(arg)?);
+                self.store.stack.push_value(V128(v as u128));
+            }
+            OP_V128_LOAD64_ZERO(arg) => {
+                let v = u64::from_le_bytes(self.load_bytes::<8>(arg)?);
+                self.store.stack.push_value(V128(v as u128));
+            }
+            OP_V128_LOAD8_LANE(arg, idx) => v128_load_lane!(self, arg, idx, u8, 16),
+            OP_V128_LOAD16_LANE(arg, idx) => v128_load_lane!(self, arg, idx, u16, 8),
+            OP_V128_LOAD32_LANE(arg, idx) => v128_load_lane!(self, arg, idx, u32, 4),
+            OP_V128_LOAD64_LANE(arg, idx) => v128_load_lane!(self, arg, idx, u64, 2),
+            OP_V128_STORE(arg) => {
+                let v = self.store.stack.pop_v128()?;
+                self.store_bytes(arg, &v.to_le_bytes())?;
+            }
+            OP_V128_STORE8_LANE(arg, idx) => v128_store_lane!(self, arg, idx, u8, 16),
+            OP_V128_STORE16_LANE(arg, idx) => v128_store_lane!(self, arg, idx, u16, 8),
+            OP_V128_STORE32_LANE(arg, idx) => v128_store_lane!(self, arg, idx, u32, 4),
+            OP_V128_STORE64_LANE(arg, idx) => v128_store_lane!(self, arg, idx, u64, 2),
+
+            // Constant and lanes
+            OP_V128_CONST(v) => self.store.stack.push_value(V128(*v)),
+            OP_I8X16_SHUFFLE(idx) => {
+                let v2 = self.store.stack.pop_v128()?;
+                let v1 = self.store.stack.pop_v128()?;
+                let (a, b) = (lanes::<u8, 16>(v1), lanes::<u8, 16>(v2));
+
+                let mut lanes = [0; 16];
+                for (lane, idx) in lanes.iter_mut().zip(idx.iter()) {
+                    *lane = match *idx as usize {
+                        i @ 0..=15 => a[i],
+                        i @ 16..=31 => b[i - 16],
+                        i => bail!("Lane {} is out of bounds", i),
+                    };
+                }
+
+                self.store.stack.push_value(V128(from_lanes(lanes)));
+            }
+            OP_I8X16_SWIZZLE => {
+                let v2 = self.store.stack.pop_v128()?;
+                let v1 = self.store.stack.pop_v128()?;
+                let (a, s) = (lanes::<u8, 16>(v1), lanes::<u8, 16>(v2));
+                self.store.stack.push_value(V128(from_fn::<u8, 16>(|i| {
+                    a.get(s[i] as usize).copied().unwrap_or(0)
+                })));
+            }
+            OP_I8X16_SPLAT => v128_splat!(self, I32, u8, 16),
+            OP_I16X8_SPLAT => v128_splat!(self, I32, u16, 8),
+            OP_I32X4_SPLAT => v128_splat!(self, I32, i32, 4),
+            OP_I64X2_SPLAT => v128_splat!(self, I64, i64, 2),
+            OP_F32X4_SPLAT => v128_splat!(self, F32, f32, 4),
+            OP_F64X2_SPLAT => v128_splat!(self, F64, f64, 2),
+            OP_I8X16_EXTRACT_LANE_S(idx) => v128_extract_lane!(self, i8, 16, idx, I32, i32),
+            OP_I8X16_EXTRACT_LANE_U(idx) => v128_extract_lane!(self, u8, 16, idx, I32, i32),
+            OP_I16X8_EXTRACT_LANE_S(idx) => v128_extract_lane!(self, i16, 8, idx, I32, i32),
+            OP_I16X8_EXTRACT_LANE_U(idx) => v128_extract_lane!(self, u16, 8, idx, I32, i32),
+            OP_I32X4_EXTRACT_LANE(idx) => v128_extract_lane!(self, i32, 4, idx, I32, i32),
+            OP_I64X2_EXTRACT_LANE(idx) => v128_extract_lane!(self, i64, 2, idx, I64, i64),
+            OP_F32X4_EXTRACT_LANE(idx) => v128_extract_lane!(self, f32, 4, idx, F32, f32),
+            OP_F64X2_EXTRACT_LANE(idx) => v128_extract_lane!(self, f64, 2, idx, F64, f64),
+            OP_I8X16_REPLACE_LANE(idx) => v128_replace_lane!(self, u8, 16, idx, I32),
+            OP_I16X8_REPLACE_LANE(idx) => v128_replace_lane!(self, u16, 8, idx, I32),
+            OP_I32X4_REPLACE_LANE(idx) => v128_replace_lane!(self, i32, 4, idx, I32),
+            OP_I64X2_REPLACE_LANE(idx) => v128_replace_lane!(self, i64, 2, idx, I64),
+            OP_F32X4_REPLACE_LANE(idx) => v128_replace_lane!(self, f32, 4, idx, F32),
+            OP_F64X2_REPLACE_LANE(idx) => v128_replace_lane!(self, f64, 2, idx, F64),
+
+            // Comparison
+            OP_I8X16_EQ => v128_compare!(self, i8, 16, |a, b| a == b),
+            OP_I8X16_NE => v128_compare!(self, i8, 16, |a, b| a != b),
+            OP_I8X16_LT_S => v128_compare!(self, i8, 16, |a, b| a < b),
+            OP_I8X16_LT_U => v128_compare!(self, u8, 16, |a, b| a < b),
+            OP_I8X16_GT_S => v128_compare!(self, i8, 16, |a, b| a > b),
+            OP_I8X16_GT_U => v128_compare!(self, u8, 16, |a, b| a > b),
+            OP_I8X16_LE_S => v128_compare!(self, i8, 16, |a, b| a <= b),
+            OP_I8X16_LE_U => v128_compare!(self, u8, 16, |a, b| a <= b),
+            OP_I8X16_GE_S => v128_compare!(self, i8, 16, |a, b| a >= b),
+            OP_I8X16_GE_U => v128_compare!(self, u8, 16, |a, b| a >= b),
+            OP_I16X8_EQ => v128_compare!(self, i16, 8, |a, b| a == b),
+            OP_I16X8_NE => v128_compare!(self, i16, 8, |a, b| a != b),
+            OP_I16X8_LT_S => v128_compare!(self, i16, 8, |a, b| a < b),
+            OP_I16X8_LT_U => v128_compare!(self, u16, 8, |a, b| a < b),
+            OP_I16X8_GT_S => v128_compare!(self, i16, 8, |a, b| a > b),
+            OP_I16X8_GT_U => v128_compare!(self, u16, 8, |a, b| a > b),
+            OP_I16X8_LE_S => v128_compare!(self, i16, 8, |a, b| a <= b),
+            OP_I16X8_LE_U => v128_compare!(self, u16, 8, |a, b| a <= b),
+            OP_I16X8_GE_S => v128_compare!(self, i16, 8, |a, b| a >= b),
+            OP_I16X8_GE_U => v128_compare!(self, u16, 8, |a, b| a >= b),
+            OP_I32X4_EQ => v128_compare!(self, i32, 4, |a, b| a == b),
+            OP_I32X4_NE => v128_compare!(self, i32, 4, |a, b| a != b),
+            OP_I32X4_LT_S => v128_compare!(self, i32, 4, |a, b| a < b),
+            OP_I32X4_LT_U => v128_compare!(self, u32, 4, |a, b| a < b),
+            OP_I32X4_GT_S => v128_compare!(self, i32, 4, |a, b| a > b),
+            OP_I32X4_GT_U => v128_compare!(self, u32, 4, |a, b| a > b),
+            OP_I32X4_LE_S => v128_compare!(self, i32, 4, |a, b| a <= b),
+            OP_I32X4_LE_U => v128_compare!(self, u32, 4, |a, b| a <= b),
+            OP_I32X4_GE_S => v128_compare!(self, i32, 4, |a, b| a >= b),
+            OP_I32X4_GE_U => v128_compare!(self, u32, 4, |a, b| a >= b),
+            OP_I64X2_EQ => v128_compare!(self, i64, 2, |a, b| a == b),
+            OP_I64X2_NE => v128_compare!(self, i64, 2, |a, b| a != b),
+            OP_I64X2_LT_S => v128_compare!(self, i64, 2, |a, b| a < b),
+            OP_I64X2_GT_S => v128_compare!(self, i64, 2, |a, b| a > b),
+            OP_I64X2_LE_S => v128_compare!(self, i64, 2, |a, b| a <= b),
+            OP_I64X2_GE_S => v128_compare!(self, i64, 2, |a, b| a >= b),
+            OP_F32X4_EQ => v128_compare!(self, f32, 4, |a, b| a == b),
+            OP_F32X4_NE => v128_compare!(self, f32, 4, |a, b| a != b),
+            OP_F32X4_LT => v128_compare!(self, f32, 4, |a, b| a < b),
+            OP_F32X4_GT => v128_compare!(self, f32, 4, |a, b| a > b),
+            OP_F32X4_LE => v128_compare!(self, f32, 4, |a, b| a <= b),
+            OP_F32X4_GE => v128_compare!(self, f32, 4, |a, b| a >= b),
+            OP_F64X2_EQ => v128_compare!(self, f64, 2, |a, b| a == b),
+            OP_F64X2_NE => v128_compare!(self, f64, 2, |a, b| a != b),
+            OP_F64X2_LT => v128_compare!(self, f64, 2, |a, b| a < b),
+            OP_F64X2_GT => v128_compare!(self, f64, 2, |a, b| a > b),
+            OP_F64X2_LE => v128_compare!(self, f64, 2, |a, b| a <= b),
+            OP_F64X2_GE => v128_compare!(self, f64, 2, |a, b| a >= b),
+
+            // Bitwise
+            OP_V128_NOT => {
+                let v = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(V128(!v));
+            }
+            OP_V128_AND => v128_binop!(self, u64, 2, |a, b| a & b),
+            OP_V128_ANDNOT => v128_binop!(self, u64, 2, |a, b| a & !b),
+            OP_V128_OR => v128_binop!(self, u64, 2, |a, b| a | b),
+            OP_V128_XOR => v128_binop!(self, u64, 2, |a, b| a ^ b),
+            OP_V128_BITSELECT => {
+                let c = self.store.stack.pop_v128()?;
+                let v2 = self.store.stack.pop_v128()?;
+                let v1 = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(V128((v1 & c) | (v2 & !c)));
+            }
+            OP_V128_ANY_TRUE => {
+                let v = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(I32((v != 0) as i32));
+            }
+
+            // i8x16
+            OP_I8X16_ABS => v128_unop!(self, i8, 16, |a| a.wrapping_abs()),
+            OP_I8X16_NEG => v128_unop!(self, i8, 16, |a| a.wrapping_neg()),
+            OP_I8X16_POPCNT => v128_unop!(self, u8, 16, |a| a.count_ones() as u8),
+            OP_I8X16_ALL_TRUE => {
+                let v = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(I32(all_true::<i8, 16>(v)));
+            }
+            OP_I8X16_BITMASK => {
+                let v = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(I32(bitmask::<i8, 16>(v)));
+            }
+            OP_I8X16_NARROW_I16X8_S => v128_narrow!(self, i16, 8 => i8, 16),
+            OP_I8X16_NARROW_I16X8_U => v128_narrow!(self, i16, 8 => u8, 16),
+            OP_I8X16_SHL => v128_shift!(self, u8, 16, wrapping_shl),
+            OP_I8X16_SHR_S => v128_shift!(self, i8, 16, wrapping_shr),
+            OP_I8X16_SHR_U => v128_shift!(self, u8, 16, wrapping_shr),
+            OP_I8X16_ADD => v128_binop!(self, u8, 16, |a, b| a.wrapping_add(b)),
+            OP_I8X16_ADD_SAT_S => v128_binop!(self, i8, 16, |a, b| a.saturating_add(b)),
+            OP_I8X16_ADD_SAT_U => v128_binop!(self, u8, 16, |a, b| a.saturating_add(b)),
+            OP_I8X16_SUB => v128_binop!(self, u8, 16, |a, b| a.wrapping_sub(b)),
+            OP_I8X16_SUB_SAT_S => v128_binop!(self, i8, 16, |a, b| a.saturating_sub(b)),
+            OP_I8X16_SUB_SAT_U => v128_binop!(self, u8, 16, |a, b| a.saturating_sub(b)),
+            OP_I8X16_MIN_S => v128_binop!(self, i8, 16, |a, b| a.min(b)),
+            OP_I8X16_MIN_U => v128_binop!(self, u8, 16, |a, b| a.min(b)),
+            OP_I8X16_MAX_S => v128_binop!(self, i8, 16, |a, b| a.max(b)),
+            OP_I8X16_MAX_U => v128_binop!(self, u8, 16, |a, b| a.max(b)),
+            OP_I8X16_AVGR_U => {
+                v128_binop!(self, u8, 16, |a, b| (a as u16 + b as u16).div_ceil(2) as u8)
+            }
+
+            // i16x8
+            OP_I16X8_EXTADD_PAIRWISE_I8X16_S => v128_extadd_pairwise!(self, i8, 16 => i16, 8),
+            OP_I16X8_EXTADD_PAIRWISE_I8X16_U => v128_extadd_pairwise!(self, u8, 16 => u16, 8),
+            OP_I16X8_ABS => v128_unop!(self, i16, 8, |a| a.wrapping_abs()),
+            OP_I16X8_NEG => v128_unop!(self, i16, 8, |a| a.wrapping_neg()),
+            OP_I16X8_Q15MULR_SAT_S => v128_binop!(self, i16, 8, |a, b| {
+                ((a as i32 * b as i32 + 0x4000) >> 15).clamp(i16::MIN as i32, i16::MAX as i32)
+                    as i16
+            }),
+            OP_I16X8_ALL_TRUE => {
+                let v = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(I32(all_true::<i16, 8>(v)));
+            }
+            OP_I16X8_BITMASK => {
+                let v = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(I32(bitmask::<i16, 8>(v)));
+            }
+            OP_I16X8_NARROW_I32X4_S => v128_narrow!(self, i32, 4 => i16, 8),
+            OP_I16X8_NARROW_I32X4_U => v128_narrow!(self, i32, 4 => u16, 8),
+            OP_I16X8_EXTEND_LOW_I8X16_S => v128_convert!(self, i8, 16 => i16, 8, 0, |a| a as i16),
+            OP_I16X8_EXTEND_HIGH_I8X16_S => v128_convert!(self, i8, 16 => i16, 8, 8, |a| a as i16),
+            OP_I16X8_EXTEND_LOW_I8X16_U => v128_convert!(self, u8, 16 => u16, 8, 0, |a| a as u16),
+            OP_I16X8_EXTEND_HIGH_I8X16_U => v128_convert!(self, u8, 16 => u16, 8, 8, |a| a as u16),
+            OP_I16X8_SHL => v128_shift!(self, u16, 8, wrapping_shl),
+            OP_I16X8_SHR_S => v128_shift!(self, i16, 8, wrapping_shr),
+            OP_I16X8_SHR_U => v128_shift!(self, u16, 8, wrapping_shr),
+            OP_I16X8_ADD => v128_binop!(self, u16, 8, |a, b| a.wrapping_add(b)),
+            OP_I16X8_ADD_SAT_S => v128_binop!(self, i16, 8, |a, b| a.saturating_add(b)),
+            OP_I16X8_ADD_SAT_U => v128_binop!(self, u16, 8, |a, b| a.saturating_add(b)),
+            OP_I16X8_SUB => v128_binop!(self, u16, 8, |a, b| a.wrapping_sub(b)),
+            OP_I16X8_SUB_SAT_S => v128_binop!(self, i16, 8, |a, b| a.saturating_sub(b)),
+            OP_I16X8_SUB_SAT_U => v128_binop!(self, u16, 8, |a, b| a.saturating_sub(b)),
+            OP_I16X8_MUL => v128_binop!(self, u16, 8, |a, b| a.wrapping_mul(b)),
+            OP_I16X8_MIN_S => v128_binop!(self, i16, 8, |a, b| a.min(b)),
+            OP_I16X8_MIN_U => v128_binop!(self, u16, 8, |a, b| a.min(b)),
+            OP_I16X8_MAX_S => v128_binop!(self, i16, 8, |a, b| a.max(b)),
+            OP_I16X8_MAX_U => v128_binop!(self, u16, 8, |a, b| a.max(b)),
+            OP_I16X8_AVGR_U => {
+                v128_binop!(self, u16, 8, |a, b| (a as u32 + b as u32).div_ceil(2) as u16)
+            }
+            OP_I16X8_EXTMUL_LOW_I8X16_S => {
+                v128_ext_binop!(self, i8, 16 => i16, 8, 0, |a, b| a as i16 * b as i16)
+            }
+            OP_I16X8_EXTMUL_HIGH_I8X16_S => {
+                v128_ext_binop!(self, i8, 16 => i16, 8, 8, |a, b| a as i16 * b as i16)
+            }
+            OP_I16X8_EXTMUL_LOW_I8X16_U => {
+                v128_ext_binop!(self, u8, 16 => u16, 8, 0, |a, b| a as u16 * b as u16)
+            }
+            OP_I16X8_EXTMUL_HIGH_I8X16_U => {
+                v128_ext_binop!(self, u8, 16 => u16, 8, 8, |a, b| a as u16 * b as u16)
+            }
+
+            // i32x4
+            OP_I32X4_EXTADD_PAIRWISE_I16X8_S => v128_extadd_pairwise!(self, i16, 8 => i32, 4),
+            OP_I32X4_EXTADD_PAIRWISE_I16X8_U => v128_extadd_pairwise!(self, u16, 8 => u32, 4),
+            OP_I32X4_ABS => v128_unop!(self, i32, 4, |a| a.wrapping_abs()),
+            OP_I32X4_NEG => v128_unop!(self, i32, 4, |a| a.wrapping_neg()),
+            OP_I32X4_ALL_TRUE => {
+                let v = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(I32(all_true::<i32, 4>(v)));
+            }
+            OP_I32X4_BITMASK => {
+                let v = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(I32(bitmask::<i32, 4>(v)));
+            }
+            OP_I32X4_EXTEND_LOW_I16X8_S => v128_convert!(self, i16, 8 => i32, 4, 0, |a| a as i32),
+            OP_I32X4_EXTEND_HIGH_I16X8_S => v128_convert!(self, i16, 8 => i32, 4, 4, |a| a as i32),
+            OP_I32X4_EXTEND_LOW_I16X8_U => v128_convert!(self, u16, 8 => u32, 4, 0, |a| a as u32),
+            OP_I32X4_EXTEND_HIGH_I16X8_U => v128_convert!(self, u16, 8 => u32, 4, 4, |a| a as u32),
+            OP_I32X4_SHL => v128_shift!(self, u32, 4, wrapping_shl),
+            OP_I32X4_SHR_S => v128_shift!(self, i32, 4, wrapping_shr),
+            OP_I32X4_SHR_U => v128_shift!(self, u32, 4, wrapping_shr),
+            OP_I32X4_ADD => v128_binop!(self, u32, 4, |a, b| a.wrapping_add(b)),
+            OP_I32X4_SUB => v128_binop!(self, u32, 4, |a, b| a.wrapping_sub(b)),
+            OP_I32X4_MUL => v128_binop!(self, u32, 4, |a, b| a.wrapping_mul(b)),
+            OP_I32X4_MIN_S => v128_binop!(self, i32, 4, |a, b| a.min(b)),
+            OP_I32X4_MIN_U => v128_binop!(self, u32, 4, |a, b| a.min(b)),
+            OP_I32X4_MAX_S => v128_binop!(self, i32, 4, |a, b| a.max(b)),
+            OP_I32X4_MAX_U => v128_binop!(self, u32, 4, |a, b| a.max(b)),
+            OP_I32X4_DOT_I16X8_S => {
+                let v2 = self.store.stack.pop_v128()?;
+                let v1 = self.store.stack.pop_v128()?;
+                let (a, b) = (lanes::<i16, 8>(v1), lanes::<i16, 8>(v2));
+                self.store.stack.push_value(V128(from_fn::<i32, 4>(|i| {
+                    let low = a[2 * i] as i32 * b[2 * i] as i32;
+                    let high = a[2 * i + 1] as i32 * b[2 * i + 1] as i32;
+                    low.wrapping_add(high)
+                })));
+            }
+            OP_I32X4_EXTMUL_LOW_I16X8_S => {
+                v128_ext_binop!(self, i16, 8 => i32, 4, 0, |a, b| a as i32 * b as i32)
+            }
+            OP_I32X4_EXTMUL_HIGH_I16X8_S => {
+                v128_ext_binop!(self, i16, 8 => i32, 4, 4, |a, b| a as i32 * b as i32)
+            }
+            OP_I32X4_EXTMUL_LOW_I16X8_U => {
+                v128_ext_binop!(self, u16, 8 => u32, 4, 0, |a, b| a as u32 * b as u32)
+            }
+            OP_I32X4_EXTMUL_HIGH_I16X8_U => {
+                v128_ext_binop!(self, u16, 8 => u32, 4, 4, |a, b| a as u32 * b as u32)
+            }
+
+            // i64x2
+            OP_I64X2_ABS => v128_unop!(self, i64, 2, |a| a.wrapping_abs()),
+            OP_I64X2_NEG => v128_unop!(self, i64, 2, |a| a.wrapping_neg()),
+            OP_I64X2_ALL_TRUE => {
+                let v = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(I32(all_true::<i64, 2>(v)));
+            }
+            OP_I64X2_BITMASK => {
+                let v = self.store.stack.pop_v128()?;
+                self.store.stack.push_value(I32(bitmask::<i64, 2>(v)));
+            }
+            OP_I64X2_EXTEND_LOW_I32X4_S => v128_convert!(self, i32, 4 => i64, 2, 0, |a| a as i64),
+            OP_I64X2_EXTEND_HIGH_I32X4_S => v128_convert!(self, i32, 4 => i64, 2, 2, |a| a as i64),
+            OP_I64X2_EXTEND_LOW_I32X4_U => v128_convert!(self, u32, 4 => u64, 2, 0, |a| a as u64),
+            OP_I64X2_EXTEND_HIGH_I32X4_U => v128_convert!(self, u32, 4 => u64, 2, 2, |a| a as u64),
+            OP_I64X2_SHL => v128_shift!(self, u64, 2, wrapping_shl),
+            OP_I64X2_SHR_S => v128_shift!(self, i64, 2, wrapping_shr),
+            OP_I64X2_SHR_U => v128_shift!(self, u64, 2, wrapping_shr),
+            OP_I64X2_ADD => v128_binop!(self, u64, 2, |a, b| a.wrapping_add(b)),
+            OP_I64X2_SUB => v128_binop!(self, u64, 2, |a, b| a.wrapping_sub(b)),
+            OP_I64X2_MUL => v128_binop!(self, u64, 2, |a, b| a.wrapping_mul(b)),
+            OP_I64X2_EXTMUL_LOW_I32X4_S => {
+                v128_ext_binop!(self, i32, 4 => i64, 2, 0, |a, b| a as i64 * b as i64)
+            }
+            OP_I64X2_EXTMUL_HIGH_I32X4_S => {
+                v128_ext_binop!(self, i32, 4 => i64, 2, 2, |a, b| a as i64 * b as i64)
+            }
+            OP_I64X2_EXTMUL_LOW_I32X4_U => {
+                v128_ext_binop!(self, u32, 4 => u64, 2, 0, |a, b| a as u64 * b as u64)
+            }
+            OP_I64X2_EXTMUL_HIGH_I32X4_U => {
+                v128_ext_binop!(self, u32, 4 => u64, 2, 2, |a, b| a as u64 * b as u64)
+            }
+
+            // f32x4
+            OP_F32X4_CEIL => v128_unop!(self, f32, 4, |a| a.ceil()),
+            OP_F32X4_FLOOR => v128_unop!(self, f32, 4, |a| a.floor()),
+            OP_F32X4_TRUNC => v128_unop!(self, f32, 4, |a| a.trunc()),
+            OP_F32X4_NEAREST => v128_try_unop!(self, f32, 4, |a| f32_nearest(a)),
+            OP_F32X4_ABS => v128_unop!(self, f32, 4, |a| a.abs()),
+            OP_F32X4_NEG => v128_unop!(self, f32, 4, |a| -a),
+            OP_F32X4_SQRT => v128_unop!(self, f32, 4, |a| a.sqrt()),
+            OP_F32X4_ADD => v128_binop!(self, f32, 4, |a, b| a + b),
+            OP_F32X4_SUB => v128_binop!(self, f32, 4, |a, b| a - b),
+            OP_F32X4_MUL => v128_binop!(self, f32, 4, |a, b| a * b),
+            OP_F32X4_DIV => v128_binop!(self, f32, 4, |a, b| a / b),
+            OP_F32X4_MIN => v128_try_binop!(self, f32, 4, |a, b| f32_binop(min, a, b)),
+            OP_F32X4_MAX => v128_try_binop!(self, f32, 4, |a, b| f32_binop(max, a, b)),
+            OP_F32X4_PMIN => v128_binop!(self, f32, 4, |a, b| if b < a { b } else { a }),
+            OP_F32X4_PMAX => v128_binop!(self, f32, 4, |a, b| if a < b { b } else { a }),
+
+            // f64x2
+            OP_F64X2_CEIL => v128_unop!(self, f64, 2, |a| a.ceil()),
+            OP_F64X2_FLOOR => v128_unop!(self, f64, 2, |a| a.floor()),
+            OP_F64X2_TRUNC => v128_unop!(self, f64, 2, |a| a.trunc()),
+            OP_F64X2_NEAREST => v128_try_unop!(self, f64, 2, |a| f64_nearest(a)),
+            OP_F64X2_ABS => v128_unop!(self, f64, 2, |a| a.abs()),
+            OP_F64X2_NEG => v128_unop!(self, f64, 2, |a| -a),
+            OP_F64X2_SQRT => v128_unop!(self, f64, 2, |a| a.sqrt()),
+            OP_F64X2_ADD => v128_binop!(self, f64, 2, |a, b| a + b),
+            OP_F64X2_SUB => v128_binop!(self, f64, 2, |a, b| a - b),
+            OP_F64X2_MUL => v128_binop!(self, f64, 2, |a, b| a * b),
+            OP_F64X2_DIV => v128_binop!(self, f64, 2, |a, b| a / b),
+            OP_F64X2_MIN => v128_try_binop!(self, f64, 2, |a, b| f64_binop(min, a, b)),
+            OP_F64X2_MAX => v128_try_binop!(self, f64, 2, |a, b| f64_binop(max, a, b)),
+            OP_F64X2_PMIN => v128_binop!(self, f64, 2, |a, b| if b < a { b } else { a }),
+            OP_F64X2_PMAX => v128_binop!(self, f64, 2, |a, b| if a < b { b } else { a }),
+
+            // Conversion
+            // The casts of floats to integers saturate and map NaN to zero
+            OP_I32X4_TRUNC_SAT_F32X4_S => v128_convert!(self, f32, 4 => i32, 4, 0, |a| a as i32),
+            OP_I32X4_TRUNC_SAT_F32X4_U => v128_convert!(self, f32, 4 => u32, 4, 0, |a| a as u32),
+            OP_F32X4_CONVERT_I32X4_S => v128_convert!(self, i32, 4 => f32, 4, 0, |a| a as f32),
+            OP_F32X4_CONVERT_I32X4_U => v128_convert!(self, u32, 4 => f32, 4, 0, |a| a as f32),
+            OP_I32X4_TRUNC_SAT_F64X2_S_ZERO => v128_convert_zero!(self, f64 => i32, |a| a as i32),
+            OP_I32X4_TRUNC_SAT_F64X2_U_ZERO => v128_convert_zero!(self, f64 => u32, |a| a as u32),
+            OP_F32X4_DEMOTE_F64X2_ZERO => v128_convert_zero!(self, f64 => f32, |a| a as f32),
+            OP_F64X2_PROMOTE_LOW_F32X4 => v128_convert!(self, f32, 4 => f64, 2, 0, |a| a as f64),
+            OP_F64X2_CONVERT_LOW_I32X4_S => v128_convert!(self, i32, 4 => f64, 2, 0, |a| a as f64),
+            OP_F64X2_CONVERT_LOW_I32X4_U => v128_convert!(self, u32, 4 => f64, 2, 0, |a| a as f64),
+
+            _ => bail!("{} is not a vector instruction", instruction),
+        }
+
+        Ok(())
+    }
+}
@@ -1,69 +1,217 @@
+use crate::engine::bytecode::Bytecode;
+use crate::engine::exception::Exception;
 use crate::engine::prelude::*;
-use std::fmt;
+use anyhow::{anyhow, bail, Result};
+use std::ops::Range;
+use std::sync::Arc;
+use wasm_parser::core::{FuncAddr, ModuleAddr, ValueType};
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum StackContent {
-    Frame(Frame),
-    Label(Label),
-    Value(Value),
+/// An untagged 64-bit value on the stack.
+/// A `v128` occupies two slots, see `ValueStack::push_v128`.
+pub type Slot = u64;
+
+/// The number of slots, which a value of the type `ty` occupies
+pub fn slots(ty: ValueType) -> u32 {
+    match ty {
+        ValueType::V128 => 2,
+        _ => 1,
+    }
+}
+
+/// The number of slots of the values of the types
+pub fn count_slots(types: &[ValueType]) -> u32 {
+    types.iter().map(|ty| slots(*ty)).sum()
+}
+
+/// The stack of the values.
+/// The values are untagged slots, because
+/// their types are already known by validation.
+/// The compiler resolves the number of slots of
+/// the operands, which are not typed by their instruction.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValueStack {
+    slots: Vec<Slot>,
 }
 
-impl StackContent {
-    pub fn is_label(&self) -> bool {
-        matches!(self, StackContent::Label(_))
+impl ValueStack {
+    pub fn len(&self) -> usize {
+        self.slots.len()
     }
 
-    pub fn is_frame(&self) -> bool {
-        matches!(self, StackContent::Frame(_))
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    pub fn push(&mut self, slot: Slot) {
+        self.slots.push(slot);
+    }
+
+    pub fn pop(&mut self) -> Result<Slot> {
+        self.slots
+            .pop()
+            .ok_or_else(|| anyhow!("The stack is empty"))
+    }
+
+    /// Pops an I32 and interprets it as unsigned, like addresses and lengths
+    pub fn pop_u32(&mut self) -> Result<u32> {
+        Ok(self.pop()? as u32)
+    }
+
+    /// Pops an address or a length of a memory.
+    /// I32 are stored zero-extended, therefore it is
+    /// the same for the index types `I32` and `I64`.
+    pub fn pop_u64(&mut self) -> Result<u64> {
+        self.pop()
+    }
+
+    /// Pushes the `v128` as two slots, where the high half is on top
+    pub fn push_v128(&mut self, v: u128) {
+        self.slots.push(v as u64);
+        self.slots.push((v >> 64) as u64);
+    }
+
+    pub fn pop_v128(&mut self) -> Result<u128> {
+        let high = self.pop()?;
+        let low = self.pop()?;
+
+        Ok(u128::from(high) << 64 | u128::from(low))
+    }
+
+    pub fn push_value(&mut self, value: Value) {
+        match value {
+            Value::V128(v) => self.push_v128(v),
+            // Every other value occupies a single slot
+            _ => self.slots.extend(value.to_bits()),
+        }
+    }
+
+    /// Pops the value and interprets it as `ty`
+    pub fn pop_value(&mut self, ty: ValueType) -> Result<Value> {
+        match ty {
+            ValueType::V128 => Ok(Value::V128(self.pop_v128()?)),
+            _ => Ok(Value::from_bits(ty, self.pop()?)),
+        }
     }
-}
 
-impl fmt::Display for StackContent {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &*self {
-            StackContent::Label(label) => write!(f, "{:?}", label),
-            StackContent::Frame(frame) => write!(f, "{:?}", frame),
-            StackContent::Value(vl) => write!(f, "{:?}", vl),
+    /// Pops the values, where the last type is on the top of the stack
+    pub fn pop_values(&mut self, types: &[ValueType]) -> Result<Vec<Value>> {
+        let needed = count_slots(types) as usize;
+        if needed > self.len() {
+            bail!("Expected {} values, but the stack is too small", types.len());
         }
+
+        let mut values = types
+            .iter()
+            .rev()
+            .map(|ty| self.pop_value(*ty))
+            .collect::<Result<Vec<_>>>()?;
+        values.reverse();
+
+        Ok(values)
+    }
+
+    pub fn get(&self, idx: usize) -> Result<Slot> {
+        self.slots
+            .get(idx)
+            .copied()
+            .ok_or_else(|| anyhow!("Cannot access the stack at {}", idx))
+    }
+
+    pub fn set(&mut self, idx: usize, slot: Slot) -> Result<()> {
+        let entry = self
+            .slots
+            .get_mut(idx)
+            .ok_or_else(|| anyhow!("Cannot access the stack at {}", idx))?;
+        *entry = slot;
+
+        Ok(())
+    }
+
+    pub fn last(&self) -> Option<Slot> {
+        self.slots.last().copied()
+    }
+
+    /// Pushes `n` zeros, which are the default of every type
+    pub fn extend_zeros(&mut self, n: usize) {
+        self.slots.resize(self.slots.len() + n, 0);
+    }
+
+    /// Removes the slots in `range` and moves the slots above down
+    pub fn remove_range(&mut self, range: Range<usize>) {
+        self.slots.drain(range);
+    }
+
+    pub fn truncate(&mut self, height: usize) {
+        self.slots.truncate(height);
     }
 }
 
 /// A label is an object which
-/// we can jump to in webassembly
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// we can jump to in webassembly.
+/// The target and the arity of a branch are resolved
+/// by the compiler. Therefore, the label only keeps
+/// the height of the stack, when the block was entered.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Label {
-    arity: Arity,
-    /// the `id` of the block to which the
-    /// program counter has to jump for the
-    /// beginning.
-    start_block_id: usize,
+    height: usize,
+    kind: LabelKind,
+}
+
+/// The kind of the block, which is needed for unwinding exceptions
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelKind {
+    Block,
+    /// The body of a `try`, whose `Op::Try` is at `pc`
+    Try(usize),
+    /// The body of a `try`, which delegates exceptions to the label `depth`
+    Delegate(u32),
+    /// The handler of a `try`, which caught the exception
+    Catch(Box<Exception>),
 }
 
 impl Label {
     /// Create new label
-    pub fn new(arity: Arity, block_id: usize) -> Self {
-        Label { arity, start_block_id: block_id }
+    pub fn new(height: usize) -> Self {
+        Self::with_kind(height, LabelKind::Block)
+    }
+
+    pub fn with_kind(height: usize, kind: LabelKind) -> Self {
+        Label { height, kind }
+    }
+
+    /// Get the height of the stack below the block
+    pub fn get_height(&self) -> usize {
+        self.height
     }
 
-    /// Get the arity of the label
-    pub fn get_arity(&self) -> Arity {
-        self.arity
+    pub fn get_kind(&self) -> &LabelKind {
+        &self.kind
     }
 
-    /// Get the `id` of the start block
-    pub fn get_start_block(&self) -> usize {
-        self.start_block_id
+    pub(crate) fn set_kind(&mut self, kind: LabelKind) {
+        self.kind = kind;
     }
 }
 
-#[derive(Debug, Clone)]
+/// The activation of a function on the call stack.
+/// The locals are kept on the value stack and the labels
+/// on the control stack, therefore the frame only references them.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Frame {
+    pub func_addr: FuncAddr,
+    /// The module instance, which defines the function
+    pub module: ModuleAddr,
+    /// The index of the next instruction, when the function was left for a call
+    pub pc: usize,
+    /// The number of slots of the results
     pub arity: u32,
-    pub locals: Vec<Value>,
-}
-
-impl PartialEq for Frame {
-    fn eq(&self, other: &Self) -> bool {
-        self.arity == other.arity && self.locals == other.locals
-    }
+    /// The slots of the locals (including the parameters) on the value stack
+    pub locals: Range<usize>,
+    /// The height of the control stack below the function's labels
+    pub labels: usize,
+    pub(crate) bytecode: Arc<Bytecode>,
 }
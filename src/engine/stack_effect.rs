@@ -0,0 +1,527 @@
+use wasm_parser::core::Instruction;
+use wasm_parser::core::Instruction::*;
+
+/// The slots of the results of an instruction
+const NOTHING: &[u32] = &[];
+const SCALAR: &[u32] = &[1];
+const VECTOR: &[u32] = &[2];
+
+/// Returns the number of operands, which the instruction pops,
+/// and the slots of its results.
+/// It is `None` for the instructions, whose operands depend on
+/// the function, the module or the enclosing blocks.
+pub(crate) fn stack_effect(instruction: &Instruction) -> Option<(usize, &'static [u32])> {
+    let effect = match instruction {
+            OP_UNREACHABLE
+            | OP_BLOCK(..)
+            | OP_LOOP(..)
+            | OP_IF(..)
+            | OP_IF_AND_ELSE(..)
+            | OP_BR(_)
+            | OP_BR_IF(_)
+            | OP_BR_TABLE(..)
+            | OP_RETURN
+            | OP_CALL(_)
+            | OP_CALL_INDIRECT(..)
+            | OP_RETURN_CALL(_)
+            | OP_RETURN_CALL_INDIRECT(..)
+            | OP_TRY(..)
+            | OP_TRY_DELEGATE(..)
+            | OP_THROW(_)
+            | OP_RETHROW(_)
+            | OP_DROP
+            | OP_SELECT
+            | OP_SELECT_T(_)
+            | OP_LOCAL_GET(_)
+            | OP_LOCAL_SET(_)
+            | OP_LOCAL_TEE(_)
+            | OP_GLOBAL_GET(_)
+            | OP_GLOBAL_SET(_) => return None,
+            OP_NOP
+            | OP_DATA_DROP(_)
+            | OP_ELEM_DROP(_)
+            | OP_ATOMIC_FENCE => (0, NOTHING),
+            OP_REF_NULL(_)
+            | OP_REF_FUNC(_)
+            | OP_TABLE_SIZE(_)
+            | OP_MEMORY_SIZE
+            | OP_I32_CONST(_)
+            | OP_I64_CONST(_)
+            | OP_F32_CONST(_)
+            | OP_F64_CONST(_) => (0, SCALAR),
+            OP_V128_CONST(_) => (0, VECTOR),
+            OP_REF_IS_NULL
+            | OP_TABLE_GET(_)
+            | OP_I32_LOAD(_)
+            | OP_I64_LOAD(_)
+            | OP_F32_LOAD(_)
+            | OP_F64_LOAD(_)
+            | OP_I32_LOAD_8_s(_)
+            | OP_I32_LOAD_8_u(_)
+            | OP_I32_LOAD_16_s(_)
+            | OP_I32_LOAD_16_u(_)
+            | OP_I64_LOAD_8_s(_)
+            | OP_I64_LOAD_8_u(_)
+            | OP_I64_LOAD_16_s(_)
+            | OP_I64_LOAD_16_u(_)
+            | OP_I64_LOAD_32_u(_)
+            | OP_I64_LOAD_32_s(_)
+            | OP_MEMORY_GROW
+            | OP_I32_EQZ
+            | OP_I64_EQZ
+            | OP_I32_CLZ
+            | OP_I32_CTZ
+            | OP_I32_POPCNT
+            | OP_I64_CLZ
+            | OP_I64_CTZ
+            | OP_I64_POPCNT
+            | OP_F32_ABS
+            | OP_F32_NEG
+            | OP_F32_CEIL
+            | OP_F32_FLOOR
+            | OP_F32_TRUNC
+            | OP_F32_NEAREST
+            | OP_F32_SQRT
+            | OP_F64_ABS
+            | OP_F64_NEG
+            | OP_F64_CEIL
+            | OP_F64_FLOOR
+            | OP_F64_TRUNC
+            | OP_F64_NEAREST
+            | OP_F64_SQRT
+            | OP_I32_WRAP_I64
+            | OP_I32_TRUNC_F32_S
+            | OP_I32_TRUNC_F32_U
+            | OP_I32_TRUNC_F64_S
+            | OP_I32_TRUNC_F64_U
+            | OP_I64_EXTEND_I32_U
+            | OP_I64_EXTEND_I32_S
+            | OP_I64_TRUNC_F32_S
+            | OP_I64_TRUNC_F32_U
+            | OP_I64_TRUNC_F64_S
+            | OP_I64_TRUNC_F64_U
+            | OP_F32_CONVERT_I32_S
+            | OP_F32_CONVERT_I32_U
+            | OP_F32_CONVERT_I64_S
+            | OP_F32_CONVERT_I64_U
+            | OP_F32_DEMOTE_F64
+            | OP_F64_CONVERT_I32_S
+            | OP_F64_CONVERT_I32_U
+            | OP_F64_CONVERT_I64_S
+            | OP_F64_CONVERT_I64_U
+            | OP_F64_PROMOTE_F32
+            | OP_I32_REINTERPRET_F32
+            | OP_I64_REINTERPRET_F64
+            | OP_F32_REINTERPRET_I32
+            | OP_F64_REINTERPRET_I64
+            | OP_I32_EXTEND8_S
+            | OP_I32_EXTEND16_S
+            | OP_I64_EXTEND8_S
+            | OP_I64_EXTEND16_S
+            | OP_I64_EXTEND32_S
+            | OP_I32_TRUNC_SAT_F32_S
+            | OP_I32_TRUNC_SAT_F32_U
+            | OP_I32_TRUNC_SAT_F64_S
+            | OP_I32_TRUNC_SAT_F64_U
+            | OP_I64_TRUNC_SAT_F32_S
+            | OP_I64_TRUNC_SAT_F32_U
+            | OP_I64_TRUNC_SAT_F64_S
+            | OP_I64_TRUNC_SAT_F64_U
+            | OP_I8X16_EXTRACT_LANE_S(_)
+            | OP_I8X16_EXTRACT_LANE_U(_)
+            | OP_I16X8_EXTRACT_LANE_S(_)
+            | OP_I16X8_EXTRACT_LANE_U(_)
+            | OP_I32X4_EXTRACT_LANE(_)
+            | OP_I64X2_EXTRACT_LANE(_)
+            | OP_F32X4_EXTRACT_LANE(_)
+            | OP_F64X2_EXTRACT_LANE(_)
+            | OP_V128_ANY_TRUE
+            | OP_I8X16_ALL_TRUE
+            | OP_I8X16_BITMASK
+            | OP_I16X8_ALL_TRUE
+            | OP_I16X8_BITMASK
+            | OP_I32X4_ALL_TRUE
+            | OP_I32X4_BITMASK
+            | OP_I64X2_ALL_TRUE
+            | OP_I64X2_BITMASK
+            | OP_I32_ATOMIC_LOAD(_)
+            | OP_I64_ATOMIC_LOAD(_)
+            | OP_I32_ATOMIC_LOAD8_U(_)
+            | OP_I32_ATOMIC_LOAD16_U(_)
+            | OP_I64_ATOMIC_LOAD8_U(_)
+            | OP_I64_ATOMIC_LOAD16_U(_)
+            | OP_I64_ATOMIC_LOAD32_U(_) => (1, SCALAR),
+            OP_V128_LOAD(_)
+            | OP_V128_LOAD8X8_S(_)
+            | OP_V128_LOAD8X8_U(_)
+            | OP_V128_LOAD16X4_S(_)
+            | OP_V128_LOAD16X4_U(_)
+            | OP_V128_LOAD32X2_S(_)
+            | OP_V128_LOAD32X2_U(_)
+            | OP_V128_LOAD8_SPLAT(_)
+            | OP_V128_LOAD16_SPLAT(_)
+            | OP_V128_LOAD32_SPLAT(_)
+            | OP_V128_LOAD64_SPLAT(_)
+            | OP_I8X16_SPLAT
+            | OP_I16X8_SPLAT
+            | OP_I32X4_SPLAT
+            | OP_I64X2_SPLAT
+            | OP_F32X4_SPLAT
+            | OP_F64X2_SPLAT
+            | OP_V128_NOT
+            | OP_V128_LOAD32_ZERO(_)
+            | OP_V128_LOAD64_ZERO(_)
+            | OP_F32X4_DEMOTE_F64X2_ZERO
+            | OP_F64X2_PROMOTE_LOW_F32X4
+            | OP_I8X16_ABS
+            | OP_I8X16_NEG
+            | OP_I8X16_POPCNT
+            | OP_F32X4_CEIL
+            | OP_F32X4_FLOOR
+            | OP_F32X4_TRUNC
+            | OP_F32X4_NEAREST
+            | OP_F64X2_CEIL
+            | OP_F64X2_FLOOR
+            | OP_F64X2_TRUNC
+            | OP_I16X8_EXTADD_PAIRWISE_I8X16_S
+            | OP_I16X8_EXTADD_PAIRWISE_I8X16_U
+            | OP_I32X4_EXTADD_PAIRWISE_I16X8_S
+            | OP_I32X4_EXTADD_PAIRWISE_I16X8_U
+            | OP_I16X8_ABS
+            | OP_I16X8_NEG
+            | OP_I16X8_EXTEND_LOW_I8X16_S
+            | OP_I16X8_EXTEND_HIGH_I8X16_S
+            | OP_I16X8_EXTEND_LOW_I8X16_U
+            | OP_I16X8_EXTEND_HIGH_I8X16_U
+            | OP_F64X2_NEAREST
+            | OP_I32X4_ABS
+            | OP_I32X4_NEG
+            | OP_I32X4_EXTEND_LOW_I16X8_S
+            | OP_I32X4_EXTEND_HIGH_I16X8_S
+            | OP_I32X4_EXTEND_LOW_I16X8_U
+            | OP_I32X4_EXTEND_HIGH_I16X8_U
+            | OP_I64X2_ABS
+            | OP_I64X2_NEG
+            | OP_I64X2_EXTEND_LOW_I32X4_S
+            | OP_I64X2_EXTEND_HIGH_I32X4_S
+            | OP_I64X2_EXTEND_LOW_I32X4_U
+            | OP_I64X2_EXTEND_HIGH_I32X4_U
+            | OP_F32X4_ABS
+            | OP_F32X4_NEG
+            | OP_F32X4_SQRT
+            | OP_F64X2_ABS
+            | OP_F64X2_NEG
+            | OP_F64X2_SQRT
+            | OP_I32X4_TRUNC_SAT_F32X4_S
+            | OP_I32X4_TRUNC_SAT_F32X4_U
+            | OP_F32X4_CONVERT_I32X4_S
+            | OP_F32X4_CONVERT_I32X4_U
+            | OP_I32X4_TRUNC_SAT_F64X2_S_ZERO
+            | OP_I32X4_TRUNC_SAT_F64X2_U_ZERO
+            | OP_F64X2_CONVERT_LOW_I32X4_S
+            | OP_F64X2_CONVERT_LOW_I32X4_U => (1, VECTOR),
+            OP_TABLE_SET(_)
+            | OP_I32_STORE(_)
+            | OP_I64_STORE(_)
+            | OP_F32_STORE(_)
+            | OP_F64_STORE(_)
+            | OP_I32_STORE_8(_)
+            | OP_I32_STORE_16(_)
+            | OP_I64_STORE_8(_)
+            | OP_I64_STORE_16(_)
+            | OP_I64_STORE_32(_)
+            | OP_V128_STORE(_)
+            | OP_V128_STORE8_LANE(..)
+            | OP_V128_STORE16_LANE(..)
+            | OP_V128_STORE32_LANE(..)
+            | OP_V128_STORE64_LANE(..)
+            | OP_I32_ATOMIC_STORE(_)
+            | OP_I64_ATOMIC_STORE(_)
+            | OP_I32_ATOMIC_STORE8(_)
+            | OP_I32_ATOMIC_STORE16(_)
+            | OP_I64_ATOMIC_STORE8(_)
+            | OP_I64_ATOMIC_STORE16(_)
+            | OP_I64_ATOMIC_STORE32(_) => (2, NOTHING),
+            OP_TABLE_GROW(_)
+            | OP_I32_EQ
+            | OP_I32_NE
+            | OP_I32_LT_S
+            | OP_I32_LT_U
+            | OP_I32_GT_S
+            | OP_I32_GT_U
+            | OP_I32_LE_S
+            | OP_I32_LE_U
+            | OP_I32_GE_S
+            | OP_I32_GE_U
+            | OP_I64_EQ
+            | OP_I64_NE
+            | OP_I64_LT_S
+            | OP_I64_LT_U
+            | OP_I64_GT_S
+            | OP_I64_GT_U
+            | OP_I64_LE_S
+            | OP_I64_LE_U
+            | OP_I64_GE_S
+            | OP_I64_GE_U
+            | OP_F32_EQ
+            | OP_F32_NE
+            | OP_F32_LT
+            | OP_F32_GT
+            | OP_F32_LE
+            | OP_F32_GE
+            | OP_F64_EQ
+            | OP_F64_NE
+            | OP_F64_LT
+            | OP_F64_GT
+            | OP_F64_LE
+            | OP_F64_GE
+            | OP_I32_ADD
+            | OP_I32_SUB
+            | OP_I32_MUL
+            | OP_I32_DIV_S
+            | OP_I32_DIV_U
+            | OP_I32_REM_S
+            | OP_I32_REM_U
+            | OP_I32_AND
+            | OP_I32_OR
+            | OP_I32_XOR
+            | OP_I32_SHL
+            | OP_I32_SHR_S
+            | OP_I32_SHR_U
+            | OP_I32_ROTL
+            | OP_I32_ROTR
+            | OP_I64_ADD
+            | OP_I64_SUB
+            | OP_I64_MUL
+            | OP_I64_DIV_S
+            | OP_I64_DIV_U
+            | OP_I64_REM_S
+            | OP_I64_REM_U
+            | OP_I64_AND
+            | OP_I64_OR
+            | OP_I64_XOR
+            | OP_I64_SHL
+            | OP_I64_SHR_S
+            | OP_I64_SHR_U
+            | OP_I64_ROTL
+            | OP_I64_ROTR
+            | OP_F32_ADD
+            | OP_F32_SUB
+            | OP_F32_MUL
+            | OP_F32_DIV
+            | OP_F32_MIN
+            | OP_F32_MAX
+            | OP_F32_COPYSIGN
+            | OP_F64_ADD
+            | OP_F64_SUB
+            | OP_F64_MUL
+            | OP_F64_DIV
+            | OP_F64_MIN
+            | OP_F64_MAX
+            | OP_F64_COPYSIGN
+            | OP_MEMORY_ATOMIC_NOTIFY(_)
+            | OP_I32_ATOMIC_RMW_ADD(_)
+            | OP_I64_ATOMIC_RMW_ADD(_)
+            | OP_I32_ATOMIC_RMW8_ADD_U(_)
+            | OP_I32_ATOMIC_RMW16_ADD_U(_)
+            | OP_I64_ATOMIC_RMW8_ADD_U(_)
+            | OP_I64_ATOMIC_RMW16_ADD_U(_)
+            | OP_I64_ATOMIC_RMW32_ADD_U(_)
+            | OP_I32_ATOMIC_RMW_SUB(_)
+            | OP_I64_ATOMIC_RMW_SUB(_)
+            | OP_I32_ATOMIC_RMW8_SUB_U(_)
+            | OP_I32_ATOMIC_RMW16_SUB_U(_)
+            | OP_I64_ATOMIC_RMW8_SUB_U(_)
+            | OP_I64_ATOMIC_RMW16_SUB_U(_)
+            | OP_I64_ATOMIC_RMW32_SUB_U(_)
+            | OP_I32_ATOMIC_RMW_AND(_)
+            | OP_I64_ATOMIC_RMW_AND(_)
+            | OP_I32_ATOMIC_RMW8_AND_U(_)
+            | OP_I32_ATOMIC_RMW16_AND_U(_)
+            | OP_I64_ATOMIC_RMW8_AND_U(_)
+            | OP_I64_ATOMIC_RMW16_AND_U(_)
+            | OP_I64_ATOMIC_RMW32_AND_U(_)
+            | OP_I32_ATOMIC_RMW_OR(_)
+            | OP_I64_ATOMIC_RMW_OR(_)
+            | OP_I32_ATOMIC_RMW8_OR_U(_)
+            | OP_I32_ATOMIC_RMW16_OR_U(_)
+            | OP_I64_ATOMIC_RMW8_OR_U(_)
+            | OP_I64_ATOMIC_RMW16_OR_U(_)
+            | OP_I64_ATOMIC_RMW32_OR_U(_)
+            | OP_I32_ATOMIC_RMW_XOR(_)
+            | OP_I64_ATOMIC_RMW_XOR(_)
+            | OP_I32_ATOMIC_RMW8_XOR_U(_)
+            | OP_I32_ATOMIC_RMW16_XOR_U(_)
+            | OP_I64_ATOMIC_RMW8_XOR_U(_)
+            | OP_I64_ATOMIC_RMW16_XOR_U(_)
+            | OP_I64_ATOMIC_RMW32_XOR_U(_)
+            | OP_I32_ATOMIC_RMW_XCHG(_)
+            | OP_I64_ATOMIC_RMW_XCHG(_)
+            | OP_I32_ATOMIC_RMW8_XCHG_U(_)
+            | OP_I32_ATOMIC_RMW16_XCHG_U(_)
+            | OP_I64_ATOMIC_RMW8_XCHG_U(_)
+            | OP_I64_ATOMIC_RMW16_XCHG_U(_)
+            | OP_I64_ATOMIC_RMW32_XCHG_U(_) => (2, SCALAR),
+            OP_I8X16_SHUFFLE(_)
+            | OP_I8X16_SWIZZLE
+            | OP_I8X16_REPLACE_LANE(_)
+            | OP_I16X8_REPLACE_LANE(_)
+            | OP_I32X4_REPLACE_LANE(_)
+            | OP_I64X2_REPLACE_LANE(_)
+            | OP_F32X4_REPLACE_LANE(_)
+            | OP_F64X2_REPLACE_LANE(_)
+            | OP_I8X16_EQ
+            | OP_I8X16_NE
+            | OP_I8X16_LT_S
+            | OP_I8X16_LT_U
+            | OP_I8X16_GT_S
+            | OP_I8X16_GT_U
+            | OP_I8X16_LE_S
+            | OP_I8X16_LE_U
+            | OP_I8X16_GE_S
+            | OP_I8X16_GE_U
+            | OP_I16X8_EQ
+            | OP_I16X8_NE
+            | OP_I16X8_LT_S
+            | OP_I16X8_LT_U
+            | OP_I16X8_GT_S
+            | OP_I16X8_GT_U
+            | OP_I16X8_LE_S
+            | OP_I16X8_LE_U
+            | OP_I16X8_GE_S
+            | OP_I16X8_GE_U
+            | OP_I32X4_EQ
+            | OP_I32X4_NE
+            | OP_I32X4_LT_S
+            | OP_I32X4_LT_U
+            | OP_I32X4_GT_S
+            | OP_I32X4_GT_U
+            | OP_I32X4_LE_S
+            | OP_I32X4_LE_U
+            | OP_I32X4_GE_S
+            | OP_I32X4_GE_U
+            | OP_F32X4_EQ
+            | OP_F32X4_NE
+            | OP_F32X4_LT
+            | OP_F32X4_GT
+            | OP_F32X4_LE
+            | OP_F32X4_GE
+            | OP_F64X2_EQ
+            | OP_F64X2_NE
+            | OP_F64X2_LT
+            | OP_F64X2_GT
+            | OP_F64X2_LE
+            | OP_F64X2_GE
+            | OP_V128_AND
+            | OP_V128_ANDNOT
+            | OP_V128_OR
+            | OP_V128_XOR
+            | OP_V128_LOAD8_LANE(..)
+            | OP_V128_LOAD16_LANE(..)
+            | OP_V128_LOAD32_LANE(..)
+            | OP_V128_LOAD64_LANE(..)
+            | OP_I8X16_NARROW_I16X8_S
+            | OP_I8X16_NARROW_I16X8_U
+            | OP_I8X16_SHL
+            | OP_I8X16_SHR_S
+            | OP_I8X16_SHR_U
+            | OP_I8X16_ADD
+            | OP_I8X16_ADD_SAT_S
+            | OP_I8X16_ADD_SAT_U
+            | OP_I8X16_SUB
+            | OP_I8X16_SUB_SAT_S
+            | OP_I8X16_SUB_SAT_U
+            | OP_I8X16_MIN_S
+            | OP_I8X16_MIN_U
+            | OP_I8X16_MAX_S
+            | OP_I8X16_MAX_U
+            | OP_I8X16_AVGR_U
+            | OP_I16X8_Q15MULR_SAT_S
+            | OP_I16X8_NARROW_I32X4_S
+            | OP_I16X8_NARROW_I32X4_U
+            | OP_I16X8_SHL
+            | OP_I16X8_SHR_S
+            | OP_I16X8_SHR_U
+            | OP_I16X8_ADD
+            | OP_I16X8_ADD_SAT_S
+            | OP_I16X8_ADD_SAT_U
+            | OP_I16X8_SUB
+            | OP_I16X8_SUB_SAT_S
+            | OP_I16X8_SUB_SAT_U
+            | OP_I16X8_MUL
+            | OP_I16X8_MIN_S
+            | OP_I16X8_MIN_U
+            | OP_I16X8_MAX_S
+            | OP_I16X8_MAX_U
+            | OP_I16X8_AVGR_U
+            | OP_I16X8_EXTMUL_LOW_I8X16_S
+            | OP_I16X8_EXTMUL_HIGH_I8X16_S
+            | OP_I16X8_EXTMUL_LOW_I8X16_U
+            | OP_I16X8_EXTMUL_HIGH_I8X16_U
+            | OP_I32X4_SHL
+            | OP_I32X4_SHR_S
+            | OP_I32X4_SHR_U
+            | OP_I32X4_ADD
+            | OP_I32X4_SUB
+            | OP_I32X4_MUL
+            | OP_I32X4_MIN_S
+            | OP_I32X4_MIN_U
+            | OP_I32X4_MAX_S
+            | OP_I32X4_MAX_U
+            | OP_I32X4_DOT_I16X8_S
+            | OP_I32X4_EXTMUL_LOW_I16X8_S
+            | OP_I32X4_EXTMUL_HIGH_I16X8_S
+            | OP_I32X4_EXTMUL_LOW_I16X8_U
+            | OP_I32X4_EXTMUL_HIGH_I16X8_U
+            | OP_I64X2_SHL
+            | OP_I64X2_SHR_S
+            | OP_I64X2_SHR_U
+            | OP_I64X2_ADD
+            | OP_I64X2_SUB
+            | OP_I64X2_MUL
+            | OP_I64X2_EQ
+            | OP_I64X2_NE
+            | OP_I64X2_LT_S
+            | OP_I64X2_GT_S
+            | OP_I64X2_LE_S
+            | OP_I64X2_GE_S
+            | OP_I64X2_EXTMUL_LOW_I32X4_S
+            | OP_I64X2_EXTMUL_HIGH_I32X4_S
+            | OP_I64X2_EXTMUL_LOW_I32X4_U
+            | OP_I64X2_EXTMUL_HIGH_I32X4_U
+            | OP_F32X4_ADD
+            | OP_F32X4_SUB
+            | OP_F32X4_MUL
+            | OP_F32X4_DIV
+            | OP_F32X4_MIN
+            | OP_F32X4_MAX
+            | OP_F32X4_PMIN
+            | OP_F32X4_PMAX
+            | OP_F64X2_ADD
+            | OP_F64X2_SUB
+            | OP_F64X2_MUL
+            | OP_F64X2_DIV
+            | OP_F64X2_MIN
+            | OP_F64X2_MAX
+            | OP_F64X2_PMIN
+            | OP_F64X2_PMAX => (2, VECTOR),
+            OP_TABLE_FILL(_)
+            | OP_MEMORY_INIT(_)
+            | OP_MEMORY_COPY
+            | OP_MEMORY_FILL
+            | OP_TABLE_INIT(..)
+            | OP_TABLE_COPY(..) => (3, NOTHING),
+            OP_MEMORY_ATOMIC_WAIT32(_)
+            | OP_MEMORY_ATOMIC_WAIT64(_)
+            | OP_I32_ATOMIC_RMW_CMPXCHG(_)
+            | OP_I64_ATOMIC_RMW_CMPXCHG(_)
+            | OP_I32_ATOMIC_RMW8_CMPXCHG_U(_)
+            | OP_I32_ATOMIC_RMW16_CMPXCHG_U(_)
+            | OP_I64_ATOMIC_RMW8_CMPXCHG_U(_)
+            | OP_I64_ATOMIC_RMW16_CMPXCHG_U(_)
+            | OP_I64_ATOMIC_RMW32_CMPXCHG_U(_) => (3, SCALAR),
+            OP_V128_BITSELECT => (3, VECTOR),
+    };
+
+    Some(effect)
+}
@@ -1,13 +1,18 @@
-use crate::engine::memory::MemoryInstance;
-use crate::engine::stack::StackContent;
-
-use crate::engine::stack::Frame;
+use crate::engine::bytecode::Bytecode;
+use crate::engine::exception::TagInstance;
+use crate::engine::memory::{MemoryInstance, SharedMemory};
+use crate::engine::stack::{Label, ValueStack};
 use crate::engine::Variable;
+use crate::engine::func::HostFunc;
+use crate::engine::segment::{DataInstance, ElemInstance};
 use crate::engine::{FuncInstance, TableInstance};
-use wasm_parser::core::{FuncAddr, GlobalAddr, FunctionBody, FunctionSignature};
+use wasm_parser::core::{
+    DataAddr, ElemAddr, FuncAddr, GlobalAddr, FunctionBody, FunctionSignature, MemoryAddr,
+    ModuleAddr, TableAddr, TagAddr,
+};
 
-use crate::PAGE_SIZE;
-use anyhow::{anyhow, Result, bail};
+use std::sync::Arc;
+use anyhow::{anyhow, Result};
 
 pub type GlobalInstance = Variable;
 
@@ -15,37 +20,22 @@ pub type GlobalInstance = Variable;
 pub struct Store {
     pub funcs: Vec<FuncInstance>,
     pub tables: Vec<TableInstance>,
-    pub memory: Vec<MemoryInstance>,
-    pub stack: Vec<StackContent>,
+    pub memory: Vec<SharedMemory>,
+    pub stack: ValueStack,
+    /// The control stack with the labels of the entered blocks
+    pub labels: Vec<Label>,
     pub globals: Vec<GlobalInstance>,
+    pub datas: Vec<DataInstance>,
+    pub elems: Vec<ElemInstance>,
+    pub tags: Vec<TagInstance>,
 }
 
 impl Store {
-    /// Create an empty store with a frame
-    pub(crate) fn default_with_frame() -> Self {
-        let mut store = Store::default();
-
-        store.stack.push(StackContent::Frame(Frame {
-            arity: 0,
-            locals: Vec::new(),
-        }));
-
-        store
-    }
-
-    /// Initializes `n` pages in memory with [0; n * PAGE_SIZE]
+    /// Allocates a new memory instance with `n` pages of [0; n * PAGE_SIZE]
     pub(crate) fn init_memory(&mut self, n: usize) -> Result<()> {
-        if self.memory.last().is_none() {
-            let instance = MemoryInstance {
-                max: None,
-                data: vec![0u8; n * PAGE_SIZE],
-            };
-
-            self.memory.push(instance);
-            Ok(())
-        } else {
-            Err(anyhow!("A memory instance is already defined"))
-        }
+        self.memory.push(MemoryInstance::new(n as u64, None)?.shared());
+
+        Ok(())
     }
 
     /// Get the function instance by address
@@ -57,6 +47,30 @@ impl Store {
             .ok_or_else(|| anyhow!("Cannot find function by addr {:?}", func_addr))
     }
 
+    /// Get the memory instance by address
+    pub fn get_memory_instance(&self, memory_addr: &MemoryAddr) -> Result<&SharedMemory> {
+        debug!("Get memory's instance by addr {:?}", memory_addr);
+
+        self.memory
+            .get(memory_addr.get())
+            .ok_or_else(|| anyhow!("Cannot find memory by addr {:?}", memory_addr))
+    }
+
+    /// Get the table instance by address
+    pub fn get_table_instance(&self, table_addr: &TableAddr) -> Result<&TableInstance> {
+        debug!("Get table's instance by addr {:?}", table_addr);
+
+        self.tables
+            .get(table_addr.get())
+            .ok_or_else(|| anyhow!("Cannot find table by addr {:?}", table_addr))
+    }
+
+    pub(crate) fn get_table_instance_mut(&mut self, table_addr: &TableAddr) -> Result<&mut TableInstance> {
+        self.tables
+            .get_mut(table_addr.get())
+            .ok_or_else(|| anyhow!("Cannot find table by addr {:?}", table_addr))
+    }
+
     /// Get the global instance by address
     pub(crate) fn get_global_instance(&self, global_addr: &GlobalAddr) -> Result<&GlobalInstance> {
         debug!("Get global's instance by addr {:?}", global_addr);
@@ -66,6 +80,93 @@ impl Store {
             .ok_or_else(|| anyhow!("Cannot find global by addr {:?}", global_addr))
     }
 
+    /// Get the tag instance by address
+    pub(crate) fn get_tag_instance(&self, tag_addr: &TagAddr) -> Result<&TagInstance> {
+        self.tags
+            .get(tag_addr.get())
+            .ok_or_else(|| anyhow!("Cannot find tag by addr {:?}", tag_addr))
+    }
+
+    /// Copy `n` bytes of the data segment at `src` into the memory at `dst`
+    pub(crate) fn memory_init(
+        &mut self,
+        memory_addr: &MemoryAddr,
+        data_addr: &DataAddr,
+        dst: u64,
+        src: u32,
+        n: u32,
+    ) -> Result<()> {
+        let data = self
+            .datas
+            .get(data_addr.get())
+            .ok_or_else(|| anyhow!("Cannot find data by addr {:?}", data_addr))?
+            .bytes(src, n)?;
+
+        self.get_memory_instance(memory_addr)?
+            .write()
+            .write_bytes(dst, data)?;
+
+        Ok(())
+    }
+
+    /// Copy `n` references of the element segment at `src` into the table at `dst`
+    pub(crate) fn table_init(
+        &mut self,
+        table_addr: &TableAddr,
+        elem_addr: &ElemAddr,
+        dst: u32,
+        src: u32,
+        n: u32,
+    ) -> Result<()> {
+        let elems = self
+            .elems
+            .get(elem_addr.get())
+            .ok_or_else(|| anyhow!("Cannot find element by addr {:?}", elem_addr))?
+            .get(src, n)?;
+
+        self.tables
+            .get_mut(table_addr.get())
+            .ok_or_else(|| anyhow!("Cannot find table by addr {:?}", table_addr))?
+            .set_range(dst, elems)?;
+
+        Ok(())
+    }
+
+    /// Copy `n` references of the table `src_addr` at `src` into the table `dst_addr` at `dst`.
+    /// The tables can be the same and the regions may overlap.
+    pub(crate) fn table_copy(
+        &mut self,
+        dst_addr: &TableAddr,
+        src_addr: &TableAddr,
+        dst: u32,
+        src: u32,
+        n: u32,
+    ) -> Result<()> {
+        let elems = self.get_table_instance(src_addr)?.get_range(src, n)?.to_vec();
+
+        self.get_table_instance_mut(dst_addr)?.set_range(dst, &elems)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn data_drop(&mut self, data_addr: &DataAddr) -> Result<()> {
+        self.datas
+            .get_mut(data_addr.get())
+            .ok_or_else(|| anyhow!("Cannot find data by addr {:?}", data_addr))?
+            .drop();
+
+        Ok(())
+    }
+
+    pub(crate) fn elem_drop(&mut self, elem_addr: &ElemAddr) -> Result<()> {
+        self.elems
+            .get_mut(elem_addr.get())
+            .ok_or_else(|| anyhow!("Cannot find element by addr {:?}", elem_addr))?
+            .drop();
+
+        Ok(())
+    }
+
     pub(crate) fn get_func_instances(&self) -> &[FuncInstance] {
         debug!("Getting func instances");
 
@@ -76,12 +177,18 @@ impl Store {
         &mut self,
         signature: FunctionSignature,
         code: FunctionBody,
+        bytecode: Bytecode,
+        host: Option<HostFunc>,
+        module: ModuleAddr,
     ) {
         debug!("Allocation function {:?}", signature);
 
         let instance = FuncInstance {
             ty: signature,
             code,
+            bytecode: Arc::new(bytecode),
+            module,
+            host,
         };
 
         self.funcs.push(instance);
@@ -90,23 +197,4 @@ impl Store {
     pub(crate) fn count_functions(&self) -> usize {
         self.funcs.len()
     }
-
-    /// Pop off `n` elements of the stack and return it.
-    pub fn pop_off_stack(&mut self, mut n: usize) -> Result<Vec<StackContent>> {
-        debug!("pop_off_stack; stack {}; n {}", self.stack.len(), n);
-        let mut result = Vec::with_capacity(n);
-
-        while n > 0 {
-            if let Some(val) = self.stack.pop() {
-                debug!("Popping off {:?}", val);
-                result.push(val);
-                n -= 1;
-            }
-            else {
-                bail!("The stack was unexpectedly empty while popping {} elements of the stack", n);
-            }
-        }
-
-        Ok(result.into_iter().rev().collect())
-    }
-}
\ No newline at end of file
+}
@@ -1,16 +1,87 @@
-use wasm_parser::core::FuncAddr;
+use crate::engine::segment::segment_range;
+use crate::engine::Trap;
+use crate::value::Ref;
+use wasm_parser::core::ValueType;
+
+/// The maximum number of elements, when the table has no limit
+const MAX_TABLE_SIZE: u64 = u32::MAX as u64;
 
 #[derive(Debug, Clone)]
 pub struct TableInstance {
-    pub elem: Vec<Option<FuncAddr>>,
+    /// The type of the references (`funcref` or `externref`)
+    pub ty: ValueType,
+    pub elem: Vec<Option<Ref>>,
     pub max: Option<u32>,
 }
 
 impl TableInstance {
-    pub fn new(n: u32, max: Option<u32>) -> Self {
+    /// Create a table of `ty` with `n` null references
+    pub fn new(ty: ValueType, n: u32, max: Option<u32>) -> Self {
         Self {
+            ty,
             elem: vec![None; n as usize],
             max,
         }
     }
+
+    /// Get the reference at `idx`
+    pub(crate) fn get(&self, idx: u32) -> Result<Option<Ref>, Trap> {
+        self.elem
+            .get(idx as usize)
+            .copied()
+            .ok_or(Trap::TableOutOfBounds)
+    }
+
+    /// Set the reference at `idx`
+    pub(crate) fn set(&mut self, idx: u32, reference: Option<Ref>) -> Result<(), Trap> {
+        let entry = self.elem.get_mut(idx as usize).ok_or(Trap::TableOutOfBounds)?;
+        *entry = reference;
+
+        Ok(())
+    }
+
+    /// Get the `n` references at `src`
+    pub(crate) fn get_range(&self, src: u32, n: u32) -> Result<&[Option<Ref>], Trap> {
+        let range = segment_range(src, n, self.elem.len()).ok_or(Trap::TableOutOfBounds)?;
+
+        Ok(&self.elem[range])
+    }
+
+    /// Overwrite the references at `dst` with `elems`.
+    /// Nothing is written, when they do not fit into the table.
+    pub(crate) fn set_range(&mut self, dst: u32, elems: &[Option<Ref>]) -> Result<(), Trap> {
+        let range =
+            segment_range(dst, elems.len() as u32, self.elem.len()).ok_or(Trap::TableOutOfBounds)?;
+
+        self.elem[range].copy_from_slice(elems);
+
+        Ok(())
+    }
+
+    /// Set the `n` references at `dst` to `reference`
+    pub(crate) fn fill(&mut self, dst: u32, reference: Option<Ref>, n: u32) -> Result<(), Trap> {
+        let range = segment_range(dst, n, self.elem.len()).ok_or(Trap::TableOutOfBounds)?;
+
+        self.elem[range].fill(reference);
+
+        Ok(())
+    }
+
+    /// Appends `n` references and returns the previous size.
+    /// Returns `None`, when the table would exceed its limit.
+    /// https://webassembly.github.io/spec/core/exec/modules.html#growing-tables
+    pub(crate) fn grow(&mut self, n: u32, reference: Option<Ref>) -> Option<u32> {
+        let size = self.elem.len() as u64;
+        let len = size + n as u64;
+        let max = self.max.map_or(MAX_TABLE_SIZE, |max| max as u64);
+
+        if len > max {
+            debug!("Table growing failed, {} exceeds the limit {}", len, max);
+            return None;
+        }
+
+        self.elem.resize(len as usize, reference);
+
+        Some(size as u32)
+    }
 }
@@ -0,0 +1,76 @@
+use std::fmt;
+
+/// A trap aborts the execution of the instance.
+/// It is returned as the error of the engine and can be
+/// recovered with `Trap::from_error`, even if context was added.
+/// https://webassembly.github.io/spec/core/intro/overview.html#trap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trap {
+    /// `unreachable` was executed
+    Unreachable,
+    /// A load or store is outside of the memory
+    MemoryOutOfBounds,
+    /// Integer division or remainder by zero
+    IntegerDivideByZero,
+    /// The result of an integer operation is not representable
+    IntegerOverflow,
+    /// Truncating NaN to an integer
+    InvalidConversionToInteger,
+    /// The signature of `call_indirect` does not match the called function
+    IndirectCallTypeMismatch,
+    /// The index of `call_indirect` is outside of the table
+    UndefinedElement,
+    /// A bulk operation accesses a table or element segment out of its bounds
+    TableOutOfBounds,
+    /// The index of `call_indirect` points to an empty slot of the table
+    UninitializedElement,
+    /// The call stack reached its limit
+    CallStackExhausted,
+    /// A host function returned an error
+    HostError,
+    /// The fuel of the engine is consumed
+    OutOfFuel,
+    /// The effective address of an atomic access is not aligned to its size
+    UnalignedAtomic,
+    /// `memory.atomic.wait` was executed on an unshared memory
+    ExpectedSharedMemory,
+    /// The engine was interrupted by its `InterruptHandle`
+    Interrupted,
+    /// A page of the memory cannot be allocated
+    OutOfMemory,
+}
+
+impl Trap {
+    /// Returns the trap, when the error was caused by one
+    pub fn from_error(err: &anyhow::Error) -> Option<Trap> {
+        err.downcast_ref::<Trap>().copied()
+    }
+}
+
+// The messages are the same as in the spec tests
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Trap::Unreachable => "unreachable",
+            Trap::MemoryOutOfBounds => "out of bounds memory access",
+            Trap::IntegerDivideByZero => "integer divide by zero",
+            Trap::IntegerOverflow => "integer overflow",
+            Trap::InvalidConversionToInteger => "invalid conversion to integer",
+            Trap::IndirectCallTypeMismatch => "indirect call type mismatch",
+            Trap::UndefinedElement => "undefined element",
+            Trap::TableOutOfBounds => "out of bounds table access",
+            Trap::UninitializedElement => "uninitialized element",
+            Trap::CallStackExhausted => "call stack exhausted",
+            Trap::HostError => "host error",
+            Trap::OutOfFuel => "out of fuel",
+            Trap::UnalignedAtomic => "unaligned atomic",
+            Trap::ExpectedSharedMemory => "expected shared memory",
+            Trap::Interrupted => "interrupted",
+            Trap::OutOfMemory => "out of memory",
+        };
+
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for Trap {}
@@ -0,0 +1,142 @@
+use crate::engine::Engine;
+use crate::value::Value;
+use anyhow::{anyhow, Result};
+use std::marker::PhantomData;
+use wasm_parser::core::{ExternAddr, FuncAddr, ValueType};
+
+/// A native type, which corresponds to a `ValueType`
+pub trait WasmTy: Sized {
+    fn value_type() -> ValueType;
+    fn into_value(self) -> Value;
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+macro_rules! impl_wasm_ty {
+    ($ty:ty, $variant:ident) => {
+        impl WasmTy for $ty {
+            fn value_type() -> ValueType {
+                ValueType::$variant
+            }
+
+            fn into_value(self) -> Value {
+                Value::$variant(self)
+            }
+
+            fn from_value(value: Value) -> Option<Self> {
+                match value {
+                    Value::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_wasm_ty!(i32, I32);
+impl_wasm_ty!(i64, I64);
+impl_wasm_ty!(f32, F32);
+impl_wasm_ty!(f64, F64);
+impl_wasm_ty!(u128, V128);
+impl_wasm_ty!(Option<FuncAddr>, FuncRef);
+impl_wasm_ty!(Option<ExternAddr>, ExternRef);
+
+/// The parameters or results of a function.
+/// It is implemented for every `WasmTy` and tuples of them.
+pub trait WasmTyList: Sized {
+    fn value_types() -> Vec<ValueType>;
+    fn into_values(self) -> Vec<Value>;
+    fn from_values(values: Vec<Value>) -> Option<Self>;
+}
+
+impl<T: WasmTy> WasmTyList for T {
+    fn value_types() -> Vec<ValueType> {
+        vec![T::value_type()]
+    }
+
+    fn into_values(self) -> Vec<Value> {
+        vec![self.into_value()]
+    }
+
+    fn from_values(values: Vec<Value>) -> Option<Self> {
+        match values.as_slice() {
+            [value] => T::from_value(*value),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_wasm_ty_list {
+    ($($name:ident),*) => {
+        impl<$($name: WasmTy),*> WasmTyList for ($($name,)*) {
+            fn value_types() -> Vec<ValueType> {
+                vec![$($name::value_type()),*]
+            }
+
+            #[allow(non_snake_case)]
+            fn into_values(self) -> Vec<Value> {
+                let ($($name,)*) = self;
+                vec![$($name.into_value()),*]
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn from_values(values: Vec<Value>) -> Option<Self> {
+                if values.len() != Self::value_types().len() {
+                    return None;
+                }
+
+                let mut values = values.into_iter();
+                Some(($($name::from_value(values.next()?)?,)*))
+            }
+        }
+    };
+}
+
+impl_wasm_ty_list!();
+impl_wasm_ty_list!(A);
+impl_wasm_ty_list!(A, B);
+impl_wasm_ty_list!(A, B, C);
+impl_wasm_ty_list!(A, B, C, D);
+impl_wasm_ty_list!(A, B, C, D, E);
+impl_wasm_ty_list!(A, B, C, D, E, F);
+impl_wasm_ty_list!(A, B, C, D, E, F, G);
+impl_wasm_ty_list!(A, B, C, D, E, F, G, H);
+
+/// A handle to an exported function with a checked signature.
+/// It is created by `Engine::get_typed_func`.
+#[derive(Debug)]
+pub struct TypedFunc<Params, Results> {
+    func_addr: FuncAddr,
+    ty: PhantomData<fn(Params) -> Results>,
+}
+
+// Deriving would require `Params: Clone` and `Results: Clone`
+impl<Params, Results> Clone for TypedFunc<Params, Results> {
+    fn clone(&self) -> Self {
+        Self {
+            func_addr: self.func_addr,
+            ty: PhantomData,
+        }
+    }
+}
+
+impl<Params, Results> TypedFunc<Params, Results>
+where
+    Params: WasmTyList,
+    Results: WasmTyList,
+{
+    pub(crate) fn new(func_addr: FuncAddr) -> Self {
+        Self {
+            func_addr,
+            ty: PhantomData,
+        }
+    }
+
+    /// Invokes the function on the `engine`, which created the handle
+    pub fn call(&self, engine: &mut Engine, params: Params) -> Result<Results> {
+        let results =
+            engine.invoke_and_collect_results(self.func_addr, params.into_values())?;
+
+        Results::from_values(results)
+            .ok_or_else(|| anyhow!("The results do not match the type of the function"))
+    }
+}
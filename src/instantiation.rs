@@ -1,10 +1,8 @@
 use crate::engine::module::ModuleInstance;
-use crate::engine::stack::Frame;
-use crate::engine::stack::StackContent;
 use crate::engine::store::Store;
 use crate::value::Value;
 use anyhow::{anyhow, Context, Result};
-use wasm_parser::core::FuncAddr;
+use wasm_parser::core::{DataMode, ElementMode, FuncAddr, InstructionWrapper};
 use wasm_parser::Module;
 
 type StartFunctionAddr = FuncAddr;
@@ -21,40 +19,14 @@ pub fn instantiation(
 
     // ... skip to Step 7 TODO
 
-    // Step 7
-
-    let frame = Frame {
-        locals: Vec::new(),
-        arity: 0,
-        //module_instance: Rc::downgrade(&mod_instance),
-    };
-
-    // Step 8
-
-    store.stack.push(StackContent::Frame(frame));
+    // Steps 7, 8, 11 and 12 push an auxiliary frame, which is not needed,
+    // because the offsets are evaluated without the call stack
 
     // Step 9 and Step 13
-    if let Err(err) = instantiate_elements(m, mod_instance, store) {
-        return Err(anyhow!("{}", err));
-    }
+    instantiate_elements(m, mod_instance, store)?;
 
     // Step 10 and Step 14
-    if let Err(err) = instantiate_data(m, mod_instance, store) {
-        return Err(anyhow!("{}", err));
-    }
-
-    // Step 11 and 12
-    if let Some(StackContent::Frame(f)) = store.stack.pop() {
-        let frame = Frame {
-            locals: Vec::new(),
-            arity: 0,
-            //module_instance: Rc::downgrade(&mod_instance),
-        };
-
-        assert_eq!(frame, f);
-    } else {
-        return Err(anyhow!("No frame on the stack"));
-    }
+    instantiate_data(m, mod_instance, store)?;
 
     // Step 15
 
@@ -63,6 +35,8 @@ pub fn instantiation(
     Ok(start_func)
 }
 
+/// The active element segments are copied into the tables like `table.init`.
+/// Afterwards, the active and declarative segments are dropped.
 fn instantiate_elements(
     m: &Module,
     mod_instance: &ModuleInstance,
@@ -70,58 +44,34 @@ fn instantiate_elements(
 ) -> Result<()> {
     debug!("instantiate elements");
 
-    let ty = validation::extract::get_elements(&m);
+    let ty = validation::extract::get_elements(m);
 
     info!("Module has {} elements defined", ty.len());
 
-    for e in ty.iter() {
-        debug!("Instantiate element {:?}", e.offset);
-        let eoval = crate::allocation::get_expr_const_ty_global(&e.offset, mod_instance, store)
-            .map_err(|_| anyhow!("Fetching const expr failed"))?;
-
-        let table_index = e.table as i32;
-        debug!("=> element's table_index {}", table_index);
-
-        if let Value::I32(eo) = eoval {
-            debug!("Assertion correct: Element's offset is I32({})", eo);
-
-            let borrow = &mod_instance;
-
-            let table_addr = borrow
-                .lookup_table_addr(&(table_index as u32))
-                .ok_or_else(|| anyhow!("Table index {} does not exists", table_index))?;
-
-            let table_inst = store
-                .tables
-                .get_mut(table_addr.get())
-                .ok_or_else(|| anyhow!("Table addr {:?} does not exists", table_addr))?;
-
-            let eend = table_index + e.init.len() as i32;
-
-            if eend > table_inst.elem.len() as i32 {
-                return Err(anyhow!("end is larger than table_inst.elem"));
+    for (i, e) in ty.iter().enumerate() {
+        let elem_addr = mod_instance
+            .lookup_elem_addr(&(i as u32))
+            .ok_or_else(|| anyhow!("Element index {} does not exists", i))?;
+
+        match &e.mode {
+            ElementMode::Active { table, offset } => {
+                debug!("Instantiate element {:?}", offset);
+                let eo = get_offset(offset, mod_instance, store)?;
+
+                let table_addr = mod_instance
+                    .lookup_table_addr(table)
+                    .ok_or_else(|| anyhow!("Table index {} does not exists", table))?;
+
+                // Step 13
+                store
+                    .table_init(table_addr, elem_addr, eo, 0, e.init.len() as u32)
+                    .context("Element segment does not fit into the table")?;
+                store.elem_drop(elem_addr)?;
             }
-
-            // Step 13
-
-            for (j, funcindex) in e.init.iter().enumerate() {
-                use std::mem::replace;
-
-                debug!("Updating function's addr in table");
-
-                let funcaddr = borrow
-                    .lookup_function_addr(funcindex)
-                    .ok_or_else(|| anyhow!("No function with funcindex"))?;
-
-                debug!("=> Updating for function {:?}", funcaddr);
-
-                let _ = replace(
-                    &mut table_inst.elem[eo as usize + j],
-                    Some(funcaddr.clone()),
-                );
+            ElementMode::Declarative => {
+                store.elem_drop(elem_addr)?;
             }
-        } else {
-            panic!("Assertion failed. Element's offset is not I32");
+            ElementMode::Passive => {}
         }
     }
 
@@ -130,51 +80,60 @@ fn instantiate_elements(
     Ok(())
 }
 
+/// The active data segments are copied into the memory like `memory.init`
+/// and dropped afterwards.
 fn instantiate_data(m: &Module, mod_instance: &ModuleInstance, store: &mut Store) -> Result<()> {
-    debug!("instantiate elements");
+    debug!("instantiate data");
 
-    let ty = validation::extract::get_data(&m);
+    let ty = validation::extract::get_data(m);
 
-    for data in ty.iter() {
-        debug!("data offset {:?}", data.offset);
+    for (i, data) in ty.iter().enumerate() {
+        if let DataMode::Active { memory, offset } = &data.mode {
+            debug!("data offset {:?}", offset);
+            let d = get_data_offset(offset, mod_instance, store)?;
 
-        let doval = crate::allocation::get_expr_const_ty_global(&data.offset, mod_instance, store)
-            .map_err(|_| anyhow!("Fetching const expr failed"))?;
+            let data_addr = mod_instance
+                .lookup_data_addr(&(i as u32))
+                .ok_or_else(|| anyhow!("Data index {} does not exists", i))?;
 
-        if let Value::I32(mem_idx) = doval {
-            debug!("Memory index is {}", mem_idx);
-
-            //mem_idx = do_i
-            let borrow = &mod_instance;
-            let mem_addr = borrow
-                .lookup_memory_addr(&0)
+            let mem_addr = mod_instance
+                .lookup_memory_addr(memory)
                 .ok_or_else(|| anyhow!("Memory index does not exists"))?;
 
             debug!("Memory addr is {:?}", mem_addr);
 
-            let mem_inst = store
-                .memory
-                .get_mut(mem_addr.get())
-                .ok_or_else(|| anyhow!("Memory addr does not exists"))?;
-
-            let dend = mem_idx + data.init.len() as i32;
-
-            if dend > mem_inst.data.len() as i32 {
-                return Err(anyhow!("dend is larger than mem_inst.data"));
-            }
-
             // Step 14
-
-            use std::mem::replace;
-            for (j, b) in data.init.iter().enumerate() {
-                let _ = replace(&mut mem_inst.data[mem_idx as usize + j], *b);
-            }
+            store
+                .memory_init(mem_addr, data_addr, d, 0, data.init.len() as u32)
+                .context("Data segment does not fit into the memory")?;
+            store.data_drop(data_addr)?;
         }
     }
 
     Ok(())
 }
 
+/// Evaluates the offset of an active segment
+fn get_offset(offset: &[InstructionWrapper], mod_instance: &ModuleInstance, store: &mut Store) -> Result<u32> {
+    match crate::allocation::get_expr_const_ty_global(offset, mod_instance, store)
+        .context("Evaluating the segment's offset failed")?
+    {
+        Value::I32(v) => Ok(v as u32),
+        v => Err(anyhow!("Segment's offset is not I32, but {:?}", v)),
+    }
+}
+
+/// The offset of a data segment is an I64 for 64-bit memories
+fn get_data_offset(offset: &[InstructionWrapper], mod_instance: &ModuleInstance, store: &mut Store) -> Result<u64> {
+    match crate::allocation::get_expr_const_ty_global(offset, mod_instance, store)
+        .context("Evaluating the segment's offset failed")?
+    {
+        Value::I32(v) => Ok(v as u32 as u64),
+        Value::I64(v) => Ok(v as u64),
+        v => Err(anyhow!("Segment's offset is not I32 or I64, but {:?}", v)),
+    }
+}
+
 fn instantiate_start(
     m: &Module,
     mod_instance: &ModuleInstance,
@@ -192,10 +151,10 @@ fn instantiate_start(
 
         // Check if the functions really exists
         store
-            .get_func_instance(&func_addr)
+            .get_func_instance(func_addr)
             .context("Checking if start function exists failed")?;
 
-        return Ok(Some(func_addr.clone()));
+        return Ok(Some(*func_addr));
     } else {
         debug!("No start section");
     }
@@ -10,7 +10,9 @@ pub mod instantiation;
 mod operations;
 mod page;
 pub mod value;
+pub mod wasi;
 
+pub use engine::Trap;
 pub use validation::validate;
 pub use wasm_parser::parse;
 pub use wasm_parser::read_wasm;
@@ -20,8 +22,6 @@ pub const PAGE_SIZE: usize = 65536;
 #[cfg(test)]
 mod tests;
 
-use engine::*;
-
 #[allow(unused_macros)]
 #[macro_export]
 macro_rules! construct_engine {
@@ -31,7 +31,7 @@ macro_rules! construct_engine {
         use wasm_parser::core::*;
         use wasm_parser::tests::UnannotatedCodeBlock;
 
-        let mut e = crate::empty_engine();
+        let mut e = $crate::empty_engine();
 
         let body = FunctionBody {
             locals: vec![],
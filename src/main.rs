@@ -7,10 +7,14 @@ extern crate regex;
 use docopt::Docopt;
 use funky::cli::parse_args;
 use funky::debugger::RelativeProgramCounter;
+use funky::engine::config::Config;
 use funky::engine::module::ModuleInstance;
 use funky::engine::Engine;
 use funky::engine::import_resolver::Imports;
+use funky::wasi::{self, WasiCtx};
 use serde::Deserialize;
+use std::cell::RefCell;
+use std::rc::Rc;
 use validation::validate;
 use wasm_parser::{parse, read_wasm};
 
@@ -18,22 +22,32 @@ const USAGE: &str = "
 Funky - a WebAssembly Interpreter
 
 Usage:
-  ./funky <input> <function> [<args>...] [--stage0 | --stage1] [--spec] [--debugger]
+  ./funky <input> <function> [<args>...] [--stage0 | --stage1] [--spec] [--debugger] [--fuel=<fuel>]
+  ./funky --wasi [--dir=<dir>...] [--env=<env>...] [--fuel=<fuel>] <input> [--] [<args>...]
   ./funky (-h | --help)
   ./funky --version
 
 Options:
-  -h --help     Show this screen.
-  --version     Show version.
-  --stage0      Stop at Parser.
-  --stage1      Stop at Validation.
-  --spec        Format output to be compliant for spec tests";
+  -h --help      Show this screen.
+  --version      Show version.
+  --stage0       Stop at Parser.
+  --stage1       Stop at Validation.
+  --spec         Format output to be compliant for spec tests
+  --wasi         Run `_start` with WASI. The <args> are passed to the program.
+  --dir=<dir>    Preopen the directory `host:guest` for WASI.
+  --env=<env>    Set the environment variable `KEY=VALUE` for WASI.
+  --fuel=<fuel>  Trap after executing instructions worth <fuel> units.";
 
 #[derive(Debug, Deserialize)]
+#[allow(dead_code)]
 struct Args {
     flag_stage0: bool,
     flag_stage1: bool,
     flag_spec: bool,
+    flag_wasi: bool,
+    flag_dir: Vec<String>,
+    flag_env: Vec<String>,
+    flag_fuel: Option<u64>,
     arg_input: String,
     arg_function: String,
     arg_args: Vec<String>,
@@ -46,7 +60,7 @@ fn main() {
         .and_then(|d| d.deserialize())
         .unwrap_or_else(|e| e.exit());
 
-    let reader = read_wasm!(args.arg_input);
+    let reader = read_wasm!(&args.arg_input);
 
     info!("Parsing wasm file");
 
@@ -69,18 +83,34 @@ fn main() {
     let (mi, functions) = ModuleInstance::new(&module);
     info!("Constructing engine");
 
-    let e = Engine::new(
+    let imports = if args.flag_wasi {
+        wasi::imports(&Rc::new(RefCell::new(create_wasi_ctx(&args))))
+    } else {
+        Imports::new()
+    };
+
+    let mut config = Config::default();
+    if let Some(fuel) = args.flag_fuel {
+        config = config.fuel(fuel);
+    }
+
+    let e = Engine::with_config(
         mi,
         &functions,
         &module,
         Box::new(RelativeProgramCounter::default()),
-        &Imports::new(),
+        imports,
+        config,
     );
     debug!("engine {:#?}", e);
 
     debug!("Instantiation engine");
 
     if let Err(err) = e {
+        if let Some(code) = wasi::exit_code(&err) {
+            std::process::exit(code);
+        }
+
         eprintln!("ERROR: {}", err);
         err.chain()
             .skip(1)
@@ -90,18 +120,72 @@ fn main() {
 
     let mut engine = e.unwrap();
 
-    let inv_args = parse_args(args.arg_args);
+    if args.flag_wasi {
+        if let Err(err) = engine.invoke_exported_function_by_name("_start", vec![]) {
+            if let Some(code) = wasi::exit_code(&err) {
+                std::process::exit(code);
+            }
 
-    if let Err(err) = engine.invoke_exported_function_by_name(&args.arg_function, inv_args) {
-        eprintln!("ERROR: {}", err);
-        err.chain()
-            .skip(1)
-            .for_each(|cause| eprintln!("because: {}", cause));
-        std::process::exit(1);
+            eprintln!("ERROR: {}", err);
+            err.chain()
+                .skip(1)
+                .for_each(|cause| eprintln!("because: {}", cause));
+            std::process::exit(1);
+        }
+
+        return;
     }
 
-    if engine.store.stack.last().is_some() {
-        let value = engine.store.stack.pop();
-        println!("{:?}", value);
+    let inv_args = match parse_args(args.arg_args) {
+        Ok(inv_args) => inv_args,
+        Err(err) => {
+            eprintln!("ERROR: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    match engine.invoke_exported_function_by_name(&args.arg_function, inv_args) {
+        Ok(results) => {
+            for value in results {
+                println!("{:?}", value);
+            }
+        }
+        Err(err) => {
+            eprintln!("ERROR: {}", err);
+            err.chain()
+                .skip(1)
+                .for_each(|cause| eprintln!("because: {}", cause));
+            std::process::exit(1);
+        }
     }
 }
+
+/// Create the WASI context of the command line arguments
+fn create_wasi_ctx(args: &Args) -> WasiCtx {
+    // The first argument is the program's name
+    let wasi_args = std::iter::once(args.arg_input.clone())
+        .chain(args.arg_args.iter().cloned())
+        .collect();
+
+    let env = args
+        .flag_env
+        .iter()
+        .map(|w| match w.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (w.clone(), String::new()),
+        })
+        .collect();
+
+    let mut ctx = WasiCtx::new(wasi_args, env);
+
+    for dir in args.flag_dir.iter() {
+        let (host, guest) = dir.split_once(':').unwrap_or((dir, dir));
+
+        if let Err(err) = ctx.preopen_dir(host, guest) {
+            eprintln!("ERROR: {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    ctx
+}
@@ -1,5 +1,7 @@
+use crate::engine::Trap;
 use crate::value::Value;
 use crate::value::Value::*;
+use anyhow::{bail, Result};
 
 macro_rules! impl_two_op_integer {
     ($f:ident) => {
@@ -27,6 +29,19 @@ macro_rules! impl_two_op_all_numbers {
     };
 }
 
+/// Compares the integers as unsigned
+macro_rules! impl_compare_u {
+    ($f:ident, $k:expr) => {
+        pub fn $f(left: Value, right: Value) -> Value {
+            match (left, right) {
+                (I32(v1), I32(v2)) => I32($k(v1 as u32 as u64, v2 as u32 as u64) as i32),
+                (I64(v1), I64(v2)) => I32($k(v1 as u64, v2 as u64) as i32),
+                _ => panic!("Type mismatch during {}", stringify!($f)),
+            }
+        }
+    };
+}
+
 macro_rules! impl_one_op_integer {
     ($f:ident) => {
         pub fn $f(left: Value) -> Value {
@@ -142,6 +157,32 @@ macro_rules! impl_trunc_sat_s {
     };
 }
 
+macro_rules! impl_trunc {
+    (@to $target:ident @as $cast:ident, $ret:ident, $fn:ident) => {
+        /// Truncates the float to an integer.
+        /// Traps, when the value is NaN or out of range.
+        pub(crate) fn $fn(f: Value) -> Result<Value> {
+            let val = match f {
+                F32(v) => v as f64,
+                F64(v) => v,
+                x => bail!("Truncation only works on floats, found {:?}", x),
+            };
+
+            if val.is_nan() {
+                bail!(Trap::InvalidConversionToInteger);
+            }
+
+            // `MAX as f64 + 1.0` is exact, because it is a power of two
+            let val = val.trunc();
+            if val < $target::MIN as f64 || val >= $target::MAX as f64 + 1.0 {
+                bail!(Trap::IntegerOverflow);
+            }
+
+            Ok($ret(val as $target as $cast))
+        }
+    };
+}
+
 impl_two_op_integer!(rotate_left);
 impl_two_op_integer!(rotate_right);
 
@@ -154,6 +195,11 @@ impl_two_op_all_numbers!(gt, |left, right| left > right);
 impl_two_op_all_numbers!(le, |left, right| left <= right);
 impl_two_op_all_numbers!(ge, |left, right| left >= right);
 
+impl_compare_u!(lt_u, |left: u64, right: u64| left < right);
+impl_compare_u!(gt_u, |left: u64, right: u64| left > right);
+impl_compare_u!(le_u, |left: u64, right: u64| left <= right);
+impl_compare_u!(ge_u, |left: u64, right: u64| left >= right);
+
 impl_one_op_float!(abs);
 impl_one_op_float_closure!(neg, |w: f64| -w);
 impl_one_op_float!(ceil);
@@ -163,6 +209,11 @@ impl_one_op_float!(floor);
 impl_one_op_float!(sqrt);
 impl_one_op_float!(trunc);
 
+impl_trunc!(@to i32 @as i32, I32, trunc_i32_s);
+impl_trunc!(@to u32 @as i32, I32, trunc_i32_u);
+impl_trunc!(@to i64 @as i64, I64, trunc_i64_s);
+impl_trunc!(@to u64 @as i64, I64, trunc_i64_u);
+
 impl_trunc_sat_s!(@from i32 @to i32, I32, trunc_sat_i32_s);
 impl_trunc_sat_s!(@from i64 @to i64, I64, trunc_sat_i64_s);
 impl_trunc_sat_u!(@from F32 @to u32 @but i32, I32, trunc_sat_from_f32_to_i32_u);
@@ -183,6 +234,50 @@ pub fn eqz(left: Value) -> Value {
     }
 }
 
+/// Signed integer division.
+/// Traps on division by zero and on `MIN / -1`.
+pub fn div_s(left: Value, right: Value) -> Result<Value> {
+    match (left, right) {
+        (I32(_), I32(0)) | (I64(_), I64(0)) => bail!(Trap::IntegerDivideByZero),
+        (I32(v1), I32(v2)) => Ok(I32(v1.checked_div(v2).ok_or(Trap::IntegerOverflow)?)),
+        (I64(v1), I64(v2)) => Ok(I64(v1.checked_div(v2).ok_or(Trap::IntegerOverflow)?)),
+        _ => bail!("Type mismatch during div_s"),
+    }
+}
+
+/// Unsigned integer division.
+/// Traps on division by zero.
+pub fn div_u(left: Value, right: Value) -> Result<Value> {
+    match (left, right) {
+        (I32(_), I32(0)) | (I64(_), I64(0)) => bail!(Trap::IntegerDivideByZero),
+        (I32(v1), I32(v2)) => Ok(I32(((v1 as u32) / (v2 as u32)) as i32)),
+        (I64(v1), I64(v2)) => Ok(I64(((v1 as u64) / (v2 as u64)) as i64)),
+        _ => bail!("Type mismatch during div_u"),
+    }
+}
+
+/// Signed integer remainder.
+/// Traps on division by zero, but `MIN % -1` is 0.
+pub fn rem_s(left: Value, right: Value) -> Result<Value> {
+    match (left, right) {
+        (I32(_), I32(0)) | (I64(_), I64(0)) => bail!(Trap::IntegerDivideByZero),
+        (I32(v1), I32(v2)) => Ok(I32(v1.wrapping_rem(v2))),
+        (I64(v1), I64(v2)) => Ok(I64(v1.wrapping_rem(v2))),
+        _ => bail!("Type mismatch during rem_s"),
+    }
+}
+
+/// Unsigned integer remainder.
+/// Traps on division by zero.
+pub fn rem_u(left: Value, right: Value) -> Result<Value> {
+    match (left, right) {
+        (I32(_), I32(0)) | (I64(_), I64(0)) => bail!(Trap::IntegerDivideByZero),
+        (I32(v1), I32(v2)) => Ok(I32(((v1 as u32) % (v2 as u32)) as i32)),
+        (I64(v1), I64(v2)) => Ok(I64(((v1 as u64) % (v2 as u64)) as i64)),
+        _ => bail!("Type mismatch during rem_u"),
+    }
+}
+
 pub fn nearest(v: Value) -> Value {
     use std::ops::Rem;
 
@@ -221,29 +316,29 @@ pub fn nearest(v: Value) -> Value {
     }
 }
 
-pub fn reinterpret(v: Value) -> Value {
-    match v {
-        I32(k) => F32(f32::from_bits(k as u32)),
-        I64(k) => F64(f64::from_bits(k as u64)),
-        F32(k) => I32(i32::from_le(k.to_bits() as i32)),
-        F64(k) => I64(i64::from_le(k.to_bits() as i64)),
+/// Logical shift right, the shift count is taken modulo the bit width
+pub fn shr_u(left: Value, right: Value) -> Value {
+    match (left, right) {
+        (I32(v1), I32(v2)) => I32(((v1 as u32) >> (v2 as u32 % 32)) as i32),
+        (I64(v1), I64(v2)) => I64(((v1 as u64) >> (v2 as u64 % 64)) as i64),
+        _ => panic!("Type mismatch during shr_u"),
     }
 }
 
 #[macro_export]
 macro_rules! convert {
-    ($self:expr, $val:ident, $from_ctr:ident, $to_ctr:ident, $to:ident) => {
-        match $val {
-            $from_ctr(i) => $self.store.stack.push(StackContent::Value($to_ctr(i as $to))),
+    ($self:expr, $from_ctr:ident, $to_ctr:ident, $to:ident) => {
+        match fetch_unop!($self.store.stack, $from_ctr) {
+            $from_ctr(i) => $self.store.stack.push_value($to_ctr(i as $to)),
             x => return Err(anyhow!("Expected $from_ctr on stack but found {:?}", x)),
         }
     };
-    ($self:expr, $val:ident, $from_ctr:ident, $to_ctr:ident, $to:ident, $intermediate:ident) => {
-        match $val {
+    ($self:expr, $from_ctr:ident, $to_ctr:ident, $to:ident, $intermediate:ident) => {
+        match fetch_unop!($self.store.stack, $from_ctr) {
             $from_ctr(i) => $self
                 .store
                 .stack
-                .push(StackContent::Value($to_ctr(i as $intermediate as $to))),
+                .push_value($to_ctr(i as $intermediate as $to)),
             x => return Err(anyhow!("Expected $from_ctr on stack but found {:?}", x)),
         }
     };
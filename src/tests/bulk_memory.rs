@@ -0,0 +1,201 @@
+use super::{instantiate, invoke, try_instantiate};
+use crate::engine::*;
+use crate::value::Value::*;
+
+fn memory(engine: &Engine, range: std::ops::Range<usize>) -> Vec<u8> {
+    let mut buf = vec![0; range.len()];
+    engine.store.memory[0].read().read_bytes(range.start as u64, &mut buf).unwrap();
+    buf
+}
+
+const DATA: &str = "(module
+    (memory 1)
+    (data $passive \"\\01\\02\\03\\04\")
+    (data (i32.const 8) \"\\aa\\bb\")
+    (func (export \"init\") (param i32 i32 i32)
+      (memory.init $passive (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"drop\")
+      (data.drop $passive))
+    (func (export \"init_active\") (param i32)
+      (memory.init 1 (i32.const 0) (i32.const 0) (local.get 0)))
+    (func (export \"copy\") (param i32 i32 i32)
+      (memory.copy (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"fill\") (param i32 i32 i32)
+      (memory.fill (local.get 0) (local.get 1) (local.get 2)))
+    )";
+
+#[test]
+fn test_memory_init() {
+    let mut engine = instantiate(DATA);
+
+    assert_eq!(vec![0xaa, 0xbb], memory(&engine, 8..10));
+
+    invoke(&mut engine, "init", vec![I32(16), I32(1), I32(3)]).unwrap();
+
+    assert_eq!(vec![0, 2, 3, 4, 0], memory(&engine, 15..20));
+}
+
+#[test]
+fn test_memory_init_out_of_bounds() {
+    let mut engine = instantiate(DATA);
+
+    let err = invoke(&mut engine, "init", vec![I32(0), I32(2), I32(3)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+
+    let err = invoke(&mut engine, "init", vec![I32(65535), I32(0), I32(2)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+
+    // Nothing was written
+    assert_eq!(vec![0, 0, 0], memory(&engine, 0..3));
+    assert_eq!(0, memory(&engine, 65535..65536)[0]);
+}
+
+#[test]
+fn test_data_drop() {
+    let mut engine = instantiate(DATA);
+
+    invoke(&mut engine, "drop", vec![]).unwrap();
+    // Dropping twice is allowed
+    invoke(&mut engine, "drop", vec![]).unwrap();
+
+    // An empty region of a dropped segment can be copied
+    invoke(&mut engine, "init", vec![I32(0), I32(0), I32(0)]).unwrap();
+
+    let err = invoke(&mut engine, "init", vec![I32(0), I32(0), I32(1)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+}
+
+#[test]
+fn test_active_data_is_dropped() {
+    let mut engine = instantiate(DATA);
+
+    invoke(&mut engine, "init_active", vec![I32(0)]).unwrap();
+
+    let err = invoke(&mut engine, "init_active", vec![I32(1)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+}
+
+#[test]
+fn test_memory_copy_overlapping() {
+    let mut engine = instantiate(DATA);
+
+    invoke(&mut engine, "init", vec![I32(0), I32(0), I32(4)]).unwrap();
+
+    invoke(&mut engine, "copy", vec![I32(1), I32(0), I32(4)]).unwrap();
+    assert_eq!(vec![1, 1, 2, 3, 4], memory(&engine, 0..5));
+
+    invoke(&mut engine, "copy", vec![I32(0), I32(1), I32(4)]).unwrap();
+    assert_eq!(vec![1, 2, 3, 4, 4], memory(&engine, 0..5));
+}
+
+#[test]
+fn test_memory_copy_out_of_bounds() {
+    let mut engine = instantiate(DATA);
+
+    let err = invoke(&mut engine, "copy", vec![I32(65535), I32(8), I32(2)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+
+    let err = invoke(&mut engine, "copy", vec![I32(0), I32(-1), I32(2)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+
+    // The length can be zero at the end of the memory
+    invoke(&mut engine, "copy", vec![I32(65536), I32(65536), I32(0)]).unwrap();
+}
+
+#[test]
+fn test_memory_fill() {
+    let mut engine = instantiate(DATA);
+
+    invoke(&mut engine, "fill", vec![I32(1), I32(0x1ff), I32(3)]).unwrap();
+
+    assert_eq!(vec![0, 0xff, 0xff, 0xff, 0], memory(&engine, 0..5));
+
+    let err = invoke(&mut engine, "fill", vec![I32(65530), I32(1), I32(7)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+    assert_eq!(vec![0; 6], memory(&engine, 65530..65536));
+}
+
+#[test]
+fn test_active_data_out_of_bounds() {
+    let err = try_instantiate(
+        "(module
+    (memory 1)
+    (data (i32.const 65535) \"\\01\\02\"))",
+    )
+    .unwrap_err();
+
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+}
+
+const TABLE: &str = "(module
+    (type $ret (func (result i32)))
+    (table 4 funcref)
+    (elem (i32.const 0) $one)
+    (elem $passive funcref (ref.func $two) (ref.null func) (ref.func $three))
+    (func $one (result i32) (i32.const 1))
+    (func $two (result i32) (i32.const 2))
+    (func $three (result i32) (i32.const 3))
+    (func (export \"call\") (param i32) (result i32)
+      (call_indirect (type $ret) (local.get 0)))
+    (func (export \"init\") (param i32 i32 i32)
+      (table.init $passive (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"drop\")
+      (elem.drop $passive))
+    (func (export \"copy\") (param i32 i32 i32)
+      (table.copy (local.get 0) (local.get 1) (local.get 2)))
+    )";
+
+#[test]
+fn test_table_init() {
+    let mut engine = instantiate(TABLE);
+
+    invoke(&mut engine, "init", vec![I32(1), I32(0), I32(3)]).unwrap();
+
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "call", vec![I32(0)]).unwrap());
+    assert_eq!(vec![I32(2)], invoke(&mut engine, "call", vec![I32(1)]).unwrap());
+    assert_eq!(vec![I32(3)], invoke(&mut engine, "call", vec![I32(3)]).unwrap());
+
+    let err = invoke(&mut engine, "call", vec![I32(2)]).unwrap_err();
+    assert_eq!(Some(Trap::UninitializedElement), Trap::from_error(&err));
+}
+
+#[test]
+fn test_table_init_out_of_bounds() {
+    let mut engine = instantiate(TABLE);
+
+    let err = invoke(&mut engine, "init", vec![I32(2), I32(0), I32(3)]).unwrap_err();
+    assert_eq!(Some(Trap::TableOutOfBounds), Trap::from_error(&err));
+
+    let err = invoke(&mut engine, "init", vec![I32(0), I32(1), I32(3)]).unwrap_err();
+    assert_eq!(Some(Trap::TableOutOfBounds), Trap::from_error(&err));
+
+    // Nothing was written
+    assert!(engine.store.tables[0].elem[1..].iter().all(|w| w.is_none()));
+}
+
+#[test]
+fn test_elem_drop() {
+    let mut engine = instantiate(TABLE);
+
+    invoke(&mut engine, "drop", vec![]).unwrap();
+    invoke(&mut engine, "init", vec![I32(0), I32(0), I32(0)]).unwrap();
+
+    let err = invoke(&mut engine, "init", vec![I32(0), I32(0), I32(1)]).unwrap_err();
+    assert_eq!(Some(Trap::TableOutOfBounds), Trap::from_error(&err));
+}
+
+#[test]
+fn test_table_copy() {
+    let mut engine = instantiate(TABLE);
+
+    invoke(&mut engine, "copy", vec![I32(1), I32(0), I32(3)]).unwrap();
+
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "call", vec![I32(0)]).unwrap());
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "call", vec![I32(1)]).unwrap());
+
+    let err = invoke(&mut engine, "call", vec![I32(2)]).unwrap_err();
+    assert_eq!(Some(Trap::UninitializedElement), Trap::from_error(&err));
+
+    let err = invoke(&mut engine, "copy", vec![I32(2), I32(0), I32(3)]).unwrap_err();
+    assert_eq!(Some(Trap::TableOutOfBounds), Trap::from_error(&err));
+}
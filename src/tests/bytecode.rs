@@ -0,0 +1,129 @@
+use super::instantiate;
+use crate::engine::bytecode::Op;
+use crate::value::Value::*;
+
+#[test]
+fn test_compile_branch_targets() {
+    let engine = instantiate(
+        "(module
+    (func (export \"main\") (param i32) (result i32)
+      (block (result i32)
+        (loop
+          (br_if 1 (i32.const 1) (local.get 0))
+          (br 0))
+        (i32.const 2))
+    ))",
+    );
+
+    let ops: Vec<_> = engine.store.funcs[0]
+        .bytecode
+        .instructions
+        .iter()
+        .map(|w| w.op.clone())
+        .collect();
+
+    // block, loop, const, get, br_if, br, end, const, end, end, return
+    assert_eq!(11, ops.len());
+    assert_eq!(Op::Block { params: 0 }, ops[0]);
+    assert_eq!(Op::Loop { params: 0 }, ops[1]);
+
+    match (&ops[4], &ops[5]) {
+        (Op::BrIf(out), Op::Br(back)) => {
+            // Leaves the block at its end
+            assert_eq!((8, 1, 1), (out.pc, out.depth, out.arity));
+            // Continues after the start of the loop
+            assert_eq!((2, 0, 0), (back.pc, back.depth, back.arity));
+        }
+        x => panic!("Unexpected ops {:?}", x),
+    }
+
+    assert_eq!(Op::End, ops[6]);
+    assert_eq!(Op::Return, ops[10]);
+}
+
+#[test]
+fn test_branch_drops_values_of_blocks() {
+    let mut engine = instantiate(
+        "(module
+    (func (export \"main\") (param i32) (result i32)
+      (i32.const 100)
+      (block (result i32)
+        (i32.const 1)
+        (block
+          (i32.const 2)
+          (i32.const 3)
+          (br_if 1 (i32.const 42) (local.get 0))
+          (drop)
+          (drop)
+          (drop))
+        (drop)
+        (i32.const 7))
+      (i32.add)
+    ))",
+    );
+
+    assert_eq!(
+        vec![I32(142)],
+        engine
+            .invoke_exported_function_by_name("main", vec![I32(1)])
+            .unwrap()
+    );
+    assert_eq!(
+        vec![I32(107)],
+        engine
+            .invoke_exported_function_by_name("main", vec![I32(0)])
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_return_from_nested_loop() {
+    let mut engine = instantiate(
+        "(module
+    (func (export \"main\") (param $n i32) (result i32)
+      (local $i i32)
+      (i32.const 99)
+      (loop
+        (block
+          (local.set $i (i32.add (local.get $i) (i32.const 1)))
+          (br_if 1 (i32.lt_u (local.get $i) (local.get $n)))
+          (return (i32.mul (local.get $i) (i32.const 2)))))
+      (unreachable)
+    ))",
+    );
+
+    assert_eq!(
+        vec![I32(20)],
+        engine
+            .invoke_exported_function_by_name("main", vec![I32(10)])
+            .unwrap()
+    );
+    assert!(engine.store.stack.is_empty());
+}
+
+#[test]
+fn test_if_else_branches() {
+    let mut engine = instantiate(
+        "(module
+    (func (export \"main\") (param i32) (result i32)
+      (if (result i32) (local.get 0)
+        (then (br 0 (i32.const 1)))
+        (else (i32.const 2)))
+      (if (i32.eqz (local.get 0))
+        (then (return (i32.const 3))))
+    ))",
+    );
+
+    assert_eq!(
+        vec![I32(1)],
+        engine
+            .invoke_exported_function_by_name("main", vec![I32(1)])
+            .unwrap()
+    );
+    assert_eq!(
+        vec![I32(3)],
+        engine
+            .invoke_exported_function_by_name("main", vec![I32(0)])
+            .unwrap()
+    );
+}
@@ -0,0 +1,62 @@
+use super::try_instantiate_with_imports;
+use crate::engine::import_resolver::{Import, Imports};
+use crate::engine::*;
+use crate::value::Value::*;
+
+fn import_base(base: i32) -> Imports {
+    vec![Import::Global(
+        "env".to_string(),
+        "base".to_string(),
+        Variable {
+            mutable: false,
+            val: I32(base),
+        },
+    )]
+}
+
+const INPUT: &str = "(module
+    (import \"env\" \"base\" (global $base i32))
+    (global $size i32 (i32.mul (i32.const 4) (i32.const 3)))
+    (global $end i32 (i32.add (global.get $base) (global.get $size)))
+    (global $wrapped i64 (i64.sub (i64.const -9223372036854775808) (i64.const 1)))
+    (memory 1)
+    (table 8 funcref)
+    (data (offset (i32.add (global.get $base) (i32.const 16))) \"\\2a\")
+    (elem (offset (i32.sub (global.get $base) (i32.const 1))) $load)
+    (func $load (export \"load\") (param i32) (result i32)
+      (i32.load8_u (local.get 0)))
+    (func (export \"end\") (result i32)
+      (global.get $end))
+    (func (export \"wrapped\") (result i64)
+      (global.get $wrapped))
+    (func (export \"call\") (param i32 i32) (result i32)
+      (call_indirect (param i32) (result i32) (local.get 1) (local.get 0)))
+    )";
+
+#[test]
+fn test_extended_const_globals() {
+    let mut engine = try_instantiate_with_imports(INPUT, import_base(5)).unwrap();
+
+    assert_eq!(vec![I32(17)], engine.invoke_exported_function_by_name("end", vec![]).unwrap());
+    assert_eq!(
+        vec![I64(i64::MAX)],
+        engine.invoke_exported_function_by_name("wrapped", vec![]).unwrap()
+    );
+}
+
+#[test]
+fn test_extended_const_offsets() {
+    let mut engine = try_instantiate_with_imports(INPUT, import_base(5)).unwrap();
+
+    assert_eq!(vec![I32(42)], engine.invoke_exported_function_by_name("load", vec![I32(21)]).unwrap());
+    assert_eq!(
+        vec![I32(42)],
+        engine.invoke_exported_function_by_name("call", vec![I32(4), I32(21)]).unwrap()
+    );
+}
+
+#[test]
+fn test_extended_const_offset_out_of_bounds() {
+    // The element segment starts before the table
+    assert!(try_instantiate_with_imports(INPUT, import_base(0)).is_err());
+}
@@ -0,0 +1,237 @@
+use super::{instantiate, instantiate_with_imports, invoke, parse_wat, try_instantiate};
+use crate::engine::import_resolver::Import;
+use crate::engine::linker::Linker;
+use crate::engine::*;
+use crate::value::Value::*;
+
+const INPUT: &str = "(module
+    (tag $e0 (export \"e0\"))
+    (tag $e1 (export \"e1\") (param i32 i64))
+    (func $throw_if (param i32)
+      (if (local.get 0) (then (throw $e0))))
+    (func (export \"catch\") (param i32) (result i32)
+      (try (result i32)
+        (do (call $throw_if (local.get 0)) (i32.const 1))
+        (catch $e0 (i32.const 2))))
+    (func (export \"payload\") (param i32) (result i64)
+      (local i64)
+      (try (result i64)
+        (do (throw $e1 (local.get 0) (i64.const 10)))
+        (catch $e1
+          (local.set 1)
+          (i64.add (i64.extend_i32_s) (local.get 1)))))
+    (func (export \"catch_all\") (param i32) (result i32)
+      (i32.const 100)
+      (try (param i32) (result i32)
+        (do
+          (drop)
+          (i32.const 7)
+          (if (i32.eq (local.get 0) (i32.const 1)) (then (throw $e0)))
+          (if (i32.eq (local.get 0) (i32.const 2)) (then (throw $e1 (i32.const 0) (i64.const 0)))))
+        (catch $e0 (i32.const 3))
+        (catch_all (i32.const 4))))
+    (func (export \"uncaught\") (param i32)
+      (try
+        (do (throw $e1 (local.get 0) (i64.const -1)))
+        (catch $e0)))
+    (func (export \"rethrow\") (result i32)
+      (try (result i32)
+        (do
+          (try
+            (do (throw $e0))
+            (catch_all (rethrow 0)))
+          (i32.const 0))
+        (catch $e0 (i32.const 1))))
+    (func (export \"rethrow_outer\") (result i32)
+      (try (result i32)
+        (do
+          (try
+            (do (throw $e0))
+            (catch $e0
+              (try
+                (do (throw $e1 (i32.const 1) (i64.const 2)))
+                (catch $e1 (drop) (drop) (rethrow 1)))))
+          (i32.const 0))
+        (catch $e0 (i32.const 1))
+        (catch $e1 (drop) (drop) (i32.const 2))))
+    (func (export \"delegate\") (result i32)
+      (try $outer (result i32)
+        (do
+          (try (result i32)
+            (do
+              (try (result i32)
+                (do (throw $e0))
+                (delegate $outer)))
+            (catch $e0 (i32.const 1))))
+        (catch $e0 (i32.const 2))))
+    (func (export \"delegate_to_caller\")
+      (try
+        (do
+          (try
+            (do (throw $e0))
+            (delegate 1)))
+        (catch_all)))
+    (func $deep (param i32)
+      (if (local.get 0)
+        (then (call $deep (i32.sub (local.get 0) (i32.const 1))))
+        (else (throw $e0))))
+    (func (export \"across_frames\") (param i32) (result i32)
+      (try (result i32)
+        (do (call $deep (local.get 0)) (i32.const 0))
+        (catch $e0 (i32.const 1))))
+    (func (export \"trap\") (result i32)
+      (try (result i32)
+        (do (unreachable))
+        (catch_all (i32.const 1))))
+    (func (export \"br\") (result i32)
+      (try (result i32)
+        (do (br 0 (i32.const 5)))
+        (catch_all (i32.const 1))))
+    )";
+
+#[test]
+fn test_catch() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "catch", vec![I32(0)]).unwrap());
+    assert_eq!(vec![I32(2)], invoke(&mut engine, "catch", vec![I32(1)]).unwrap());
+}
+
+#[test]
+fn test_catch_payload() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I64(7)], invoke(&mut engine, "payload", vec![I32(-3)]).unwrap());
+}
+
+#[test]
+fn test_catch_all() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I32(7)], invoke(&mut engine, "catch_all", vec![I32(0)]).unwrap());
+    assert_eq!(vec![I32(3)], invoke(&mut engine, "catch_all", vec![I32(1)]).unwrap());
+    assert_eq!(vec![I32(4)], invoke(&mut engine, "catch_all", vec![I32(2)]).unwrap());
+}
+
+#[test]
+fn test_uncaught_exception() {
+    let mut engine = instantiate(INPUT);
+
+    let err = invoke(&mut engine, "uncaught", vec![I32(42)]).unwrap_err();
+    let exception = Exception::from_error(&err).unwrap();
+
+    assert_eq!(engine.get_tag("e1").unwrap(), exception.tag);
+    assert_eq!(vec![I32(42), I64(-1)], exception.payload);
+    assert_eq!(None, Trap::from_error(&err));
+
+    // The engine can be reused
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "catch", vec![I32(0)]).unwrap());
+}
+
+#[test]
+fn test_rethrow() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "rethrow", vec![]).unwrap());
+    // The outer handler rethrows its exception, not the inner one
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "rethrow_outer", vec![]).unwrap());
+}
+
+#[test]
+fn test_delegate() {
+    let mut engine = instantiate(INPUT);
+
+    // The handlers between the `delegate` and its label are skipped
+    assert_eq!(vec![I32(2)], invoke(&mut engine, "delegate", vec![]).unwrap());
+
+    let err = invoke(&mut engine, "delegate_to_caller", vec![]).unwrap_err();
+    assert_eq!(engine.get_tag("e0").unwrap(), Exception::from_error(&err).unwrap().tag);
+}
+
+#[test]
+fn test_exception_across_frames() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "across_frames", vec![I32(100)]).unwrap());
+}
+
+#[test]
+fn test_traps_are_not_caught() {
+    let mut engine = instantiate(INPUT);
+
+    let err = invoke(&mut engine, "trap", vec![]).unwrap_err();
+    assert_eq!(Some(Trap::Unreachable), Trap::from_error(&err));
+}
+
+#[test]
+fn test_branch_out_of_try() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I32(5)], invoke(&mut engine, "br", vec![]).unwrap());
+}
+
+#[test]
+fn test_throw_after_host_call() {
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "id".to_string(),
+        FunctionSignature {
+            param_types: vec![ValueType::I32],
+            return_types: vec![ValueType::I32],
+        },
+        Box::new(|_, args| Ok(args.to_vec())),
+    )];
+
+    let mut engine = instantiate_with_imports(
+        "(module
+    (import \"env\" \"id\" (func $id (param i32) (result i32)))
+    (tag $e (param i32))
+    (func $throw (param i32) (result i32)
+      (throw $e (call $id (local.get 0))))
+    (func (export \"main\") (param i32) (result i32)
+      (try (result i32)
+        (do (call $throw (local.get 0)))
+        (catch $e (i32.add (i32.const 1)))))
+    )",
+        imports,
+    );
+
+    assert_eq!(vec![I32(6)], invoke(&mut engine, "main", vec![I32(5)]).unwrap());
+}
+
+#[test]
+fn test_imported_tag() {
+    let mut engine = instantiate(INPUT);
+    let mut linker = Linker::new();
+    linker.register("exceptions", engine.get_module_addr().clone());
+
+    let module = parse_wat(
+        "(module
+    (import \"exceptions\" \"e1\" (tag $e (param i32 i64)))
+    (func (export \"throw\")
+      (throw $e (i32.const 1) (i64.const 2)))
+    )",
+    );
+    let addr = linker.instantiate(&mut engine, &module, vec![]).unwrap();
+
+    let tag = engine.get_tag("e1").unwrap();
+    engine.select_module(&addr).unwrap();
+
+    let err = invoke(&mut engine, "throw", vec![]).unwrap_err();
+    assert_eq!(
+        Exception {
+            tag,
+            payload: vec![I32(1), I64(2)],
+        },
+        Exception::from_error(&err).unwrap()
+    );
+
+    // The types of the tags must match
+    let module = parse_wat("(module (import \"exceptions\" \"e1\" (tag (param i32))))");
+    assert!(linker.instantiate(&mut engine, &module, vec![]).is_err());
+}
+
+#[test]
+fn test_unresolved_tag_import() {
+    assert!(try_instantiate("(module (import \"env\" \"e\" (tag)))").is_err());
+}
@@ -0,0 +1,159 @@
+use super::{instantiate, instantiate_with_imports};
+use crate::engine::import_resolver::Import;
+use crate::engine::*;
+use crate::value::Value;
+use crate::value::Value::*;
+
+const INPUT: &str = "(module
+    (global $counter (export \"counter\") (mut i32) (i32.const 0))
+    (func $add (param i32 i32) (result i32)
+      (i32.add (local.get 0) (local.get 1)))
+    (func (export \"add\") (param i32 i32) (result i32)
+      (call $add (local.get 0) (local.get 1)))
+    (func (export \"count\") (param i32) (result i32)
+      (global.set $counter (i32.const 0))
+      (block
+        (loop
+          (br_if 1 (i32.ge_s (global.get $counter) (local.get 0)))
+          (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+          (br 0)))
+      (global.get $counter))
+    (func (export \"trap\") (result i32)
+      (i32.div_s (i32.const 1) (i32.const 0)))
+    )";
+
+fn counter(engine: &Engine) -> Value {
+    engine.store.globals[0].val
+}
+
+#[test]
+fn test_step() {
+    let mut engine = instantiate(INPUT);
+    let mut execution = engine.start_invoke("add", vec![I32(1), I32(2)]).unwrap();
+
+    let mut steps = 0;
+    while !execution.is_finished() {
+        execution.step().unwrap();
+        steps += 1;
+    }
+
+    // local.get, local.get and call in the caller and
+    // local.get, local.get and i32.add in the callee
+    assert_eq!(6, steps);
+    assert_eq!(vec![I32(3)], execution.resume().unwrap());
+}
+
+#[test]
+fn test_run_for() {
+    let mut engine = instantiate(INPUT);
+    let mut execution = engine.start_invoke("count", vec![I32(100)]).unwrap();
+
+    execution.run_for(0).unwrap();
+    assert_eq!(I32(0), counter(execution.engine()));
+
+    execution.run_for(50).unwrap();
+    assert!(!execution.is_finished());
+
+    // The state can be inspected while it is paused
+    let paused = match counter(execution.engine()) {
+        I32(v) => v,
+        v => panic!("Unexpected counter {:?}", v),
+    };
+    assert!(paused > 0 && paused < 100);
+
+    assert_eq!(vec![I32(100)], execution.resume().unwrap());
+    assert!(execution.is_finished());
+    drop(execution);
+
+    assert!(engine.store.stack.is_empty());
+    assert!(engine.store.labels.is_empty());
+}
+
+#[test]
+fn test_interleave_executions() {
+    let mut first = instantiate(INPUT);
+    let mut second = instantiate(INPUT);
+
+    let mut executions = [
+        first.start_invoke("count", vec![I32(10)]).unwrap(),
+        second.start_invoke("count", vec![I32(1000)]).unwrap(),
+    ];
+
+    // Round robin until all of them finished
+    while executions.iter().any(|execution| !execution.is_finished()) {
+        for execution in executions.iter_mut() {
+            execution.run_for(7).unwrap();
+        }
+    }
+
+    let results: Vec<_> = executions
+        .iter_mut()
+        .map(|execution| execution.resume().unwrap())
+        .collect();
+    assert_eq!(vec![vec![I32(10)], vec![I32(1000)]], results);
+}
+
+#[test]
+fn test_trap_finishes_execution() {
+    let mut engine = instantiate(INPUT);
+    let mut execution = engine.start_invoke("trap", vec![]).unwrap();
+
+    let err = execution.resume().unwrap_err();
+    assert_eq!(Some(Trap::IntegerDivideByZero), Trap::from_error(&err));
+    assert!(execution.is_finished());
+    assert!(execution.step().is_err());
+    drop(execution);
+
+    assert!(engine.store.stack.is_empty());
+    assert_eq!(vec![I32(5)], engine.invoke_exported_function_by_name("add", vec![I32(2), I32(3)]).unwrap());
+}
+
+#[test]
+fn test_drop_paused_execution() {
+    let mut engine = instantiate(INPUT);
+
+    let mut execution = engine.start_invoke("count", vec![I32(100)]).unwrap();
+    execution.run_for(20).unwrap();
+    drop(execution);
+
+    // The aborted invocation leaves nothing behind
+    assert!(engine.store.stack.is_empty());
+    assert!(engine.store.labels.is_empty());
+    assert_eq!(vec![I32(3)], engine.invoke_exported_function_by_name("count", vec![I32(3)]).unwrap());
+}
+
+#[test]
+fn test_start_invoke_checks_arguments() {
+    let mut engine = instantiate(INPUT);
+
+    assert!(engine.start_invoke("add", vec![I32(1)]).is_err());
+    assert!(engine.start_invoke("missing", vec![]).is_err());
+}
+
+#[test]
+fn test_host_function_finishes_immediately() {
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "double".to_string(),
+        FunctionSignature {
+            param_types: vec![ValueType::I32],
+            return_types: vec![ValueType::I32],
+        },
+        Box::new(|_, args| match args {
+            [I32(v)] => Ok(vec![I32(v * 2)]),
+            _ => Err(anyhow!("Unexpected arguments {:?}", args)),
+        }),
+    )];
+
+    let mut engine = instantiate_with_imports(
+        "(module
+    (import \"env\" \"double\" (func $double (param i32) (result i32)))
+    (export \"double\" (func $double))
+    )",
+        imports,
+    );
+
+    let mut execution = engine.start_invoke("double", vec![I32(21)]).unwrap();
+    assert!(execution.is_finished());
+    assert_eq!(vec![I32(42)], execution.resume().unwrap());
+}
@@ -0,0 +1,116 @@
+use super::{instantiate, try_instantiate_with_config};
+use crate::engine::config::Config;
+use crate::engine::*;
+use crate::value::Value::*;
+
+/// Sums up `0..n` in a loop
+const SUM: &str = "(module
+    (func (export \"main\") (param $n i32) (result i32)
+      (local $sum i32)
+      (block
+        (loop
+          (br_if 1 (i32.eqz (local.get $n)))
+          (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+          (local.set $sum (i32.add (local.get $sum) (local.get $n)))
+          (br 0)))
+      (local.get $sum)
+    ))";
+
+#[test]
+fn test_fuel_unlimited_by_default() {
+    let mut engine = instantiate(SUM);
+
+    assert_eq!(None, engine.remaining_fuel());
+
+    let results = engine.invoke_exported_function_by_name("main", vec![I32(100)]).unwrap();
+    assert_eq!(vec![I32(4950)], results);
+}
+
+#[test]
+fn test_fuel_consumed_per_instruction() {
+    let mut engine = instantiate("(module
+    (func (export \"main\") (result i32)
+      (i32.add (i32.const 1) (i32.const 2))
+    ))");
+
+    engine.set_fuel(10);
+    engine.invoke_exported_function_by_name("main", vec![]).unwrap();
+
+    assert_eq!(Some(7), engine.remaining_fuel());
+}
+
+#[test]
+fn test_out_of_fuel() {
+    let mut engine = instantiate(SUM);
+
+    engine.set_fuel(50);
+    let err = engine
+        .invoke_exported_function_by_name("main", vec![I32(100)])
+        .unwrap_err();
+
+    assert_eq!(Some(Trap::OutOfFuel), Trap::from_error(&err));
+    assert_eq!(Some(0), engine.remaining_fuel());
+
+    // With enough fuel, the loop finishes
+    let mut engine = instantiate(SUM);
+
+    engine.set_fuel(100_000);
+    let results = engine.invoke_exported_function_by_name("main", vec![I32(100)]).unwrap();
+
+    assert_eq!(vec![I32(4950)], results);
+}
+
+#[test]
+fn test_fuel_cost() {
+    let mut engine = instantiate("(module
+    (func (export \"main\") (result i32)
+      (i32.mul (i32.const 2) (i32.const 3))
+    ))");
+
+    assert!(engine.set_fuel_cost(|_| 1).is_err());
+
+    engine.set_fuel(100);
+    engine
+        .set_fuel_cost(|instruction| match instruction {
+            OP_I32_MUL => 10,
+            _ => 1,
+        })
+        .unwrap();
+
+    engine.invoke_exported_function_by_name("main", vec![]).unwrap();
+
+    assert_eq!(Some(88), engine.remaining_fuel());
+}
+
+#[test]
+fn test_start_function_out_of_fuel() {
+    // The start function never returns
+    let err = try_instantiate_with_config(
+        "(module
+    (func $start (loop (br 0)))
+    (start $start)
+    )",
+        Config::default().fuel(1000),
+    )
+    .unwrap_err();
+
+    assert_eq!(Some(Trap::OutOfFuel), Trap::from_error(&err));
+
+    // The start function is metered with the cost of the config
+    let engine = try_instantiate_with_config(
+        "(module
+    (global $g (mut i32) (i32.const 0))
+    (func $start (global.set $g (i32.mul (i32.const 2) (i32.const 3))))
+    (start $start)
+    )",
+        Config::default()
+            .fuel(100)
+            .fuel_cost(|instruction| match instruction {
+                OP_I32_MUL => 10,
+                _ => 1,
+            }),
+    )
+    .unwrap();
+
+    assert_eq!(Some(87), engine.remaining_fuel());
+}
@@ -0,0 +1,248 @@
+use super::{try_instantiate, try_instantiate_with_imports};
+use crate::engine::import_resolver::Import;
+use crate::engine::memory::MemoryInstance;
+use crate::engine::*;
+use crate::value::Value::*;
+
+fn signature(param_types: Vec<ValueType>, return_types: Vec<ValueType>) -> FunctionSignature {
+    FunctionSignature {
+        param_types,
+        return_types,
+    }
+}
+
+#[test]
+fn test_call_host_function() {
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "add".to_string(),
+        signature(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32]),
+        Box::new(|_, args| match args {
+            [I32(a), I32(b)] => Ok(vec![I32(a + b)]),
+            _ => Err(anyhow!("Unexpected arguments {:?}", args)),
+        }),
+    )];
+
+    let mut engine = try_instantiate_with_imports(
+        "(module
+    (import \"env\" \"add\" (func $add (param i32 i32) (result i32)))
+    (func (export \"main\") (param i32) (result i32)
+      (call $add (local.get 0) (i32.const 2))
+    ))",
+        imports,
+    )
+    .unwrap();
+
+    let results = engine
+        .invoke_exported_function_by_name("main", vec![I32(40)])
+        .unwrap();
+
+    assert_eq!(vec![I32(42)], results);
+}
+
+#[test]
+fn test_call_indirect_host_function() {
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "forty_two".to_string(),
+        signature(vec![], vec![ValueType::I64]),
+        Box::new(|_, _| Ok(vec![I64(42)])),
+    )];
+
+    let mut engine = try_instantiate_with_imports(
+        "(module
+    (import \"env\" \"forty_two\" (func $f (result i64)))
+    (type $t (func (result i64)))
+    (table 1 funcref)
+    (elem (i32.const 0) $f)
+    (func (export \"main\") (result i64)
+      (call_indirect (type $t) (i32.const 0))
+    ))",
+        imports,
+    )
+    .unwrap();
+
+    let results = engine.invoke_exported_function_by_name("main", vec![]).unwrap();
+
+    assert_eq!(vec![I64(42)], results);
+}
+
+#[test]
+fn test_host_function_accesses_memory() {
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "double".to_string(),
+        signature(vec![ValueType::I32], vec![]),
+        Box::new(|caller, args| {
+            let offset = match args {
+                [I32(offset)] => *offset as usize,
+                _ => bail!("Unexpected arguments {:?}", args),
+            };
+
+            let value = caller.read_memory(offset, 1)?[0];
+            caller.write_memory(offset, &[value * 2])?;
+
+            Ok(vec![])
+        }),
+    )];
+
+    let mut engine = try_instantiate_with_imports(
+        "(module
+    (import \"env\" \"double\" (func $double (param i32)))
+    (memory 1)
+    (data (i32.const 8) \"\\15\")
+    (func (export \"main\") (result i32)
+      (call $double (i32.const 8))
+      (i32.load8_u (i32.const 8))
+    ))",
+        imports,
+    )
+    .unwrap();
+
+    let results = engine.invoke_exported_function_by_name("main", vec![]).unwrap();
+
+    assert_eq!(vec![I32(42)], results);
+}
+
+#[test]
+fn test_host_function_signature_mismatch() {
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "add".to_string(),
+        signature(vec![ValueType::I64], vec![ValueType::I64]),
+        Box::new(|_, _| Ok(vec![I64(0)])),
+    )];
+
+    let engine = try_instantiate_with_imports(
+        "(module
+    (import \"env\" \"add\" (func $add (param i32 i32) (result i32)))
+    )",
+        imports,
+    );
+
+    assert!(engine.is_err());
+}
+
+#[test]
+fn test_missing_host_function() {
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "add".to_string(),
+        signature(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32]),
+        Box::new(|_, _| Ok(vec![I32(0)])),
+    )];
+
+    let err = try_instantiate_with_imports(
+        "(module
+    (import \"env\" \"add\" (func $add (param i32 i32) (result i32)))
+    (import \"env\" \"sub\" (func $sub (param i32 i32) (result i32)))
+    )",
+        imports,
+    )
+    .unwrap_err();
+
+    assert_eq!("unlinkable: unknown import env sub", err.root_cause().to_string());
+}
+
+#[test]
+fn test_host_function_wrong_results() {
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "get".to_string(),
+        signature(vec![], vec![ValueType::I32]),
+        Box::new(|_, _| Ok(vec![F64(1.0)])),
+    )];
+
+    let mut engine = try_instantiate_with_imports(
+        "(module
+    (import \"env\" \"get\" (func $get (result i32)))
+    (func (export \"main\") (result i32)
+      (call $get)
+    ))",
+        imports,
+    )
+    .unwrap();
+
+    assert!(engine.invoke_exported_function_by_name("main", vec![]).is_err());
+}
+
+#[test]
+fn test_import_memory() {
+    let memory = MemoryInstance::new(1, Some(2)).unwrap().shared();
+    let imports = vec![Import::Memory(
+        "env".to_string(),
+        "memory".to_string(),
+        memory.clone(),
+    )];
+
+    let mut engine = try_instantiate_with_imports(
+        "(module
+    (import \"env\" \"memory\" (memory 1 2))
+    (func (export \"main\") (param i32)
+      (i32.store8 (i32.const 4) (local.get 0))
+    ))",
+        imports,
+    )
+    .unwrap();
+
+    memory.write().write_bytes(5, &[1]).unwrap();
+
+    engine
+        .invoke_exported_function_by_name("main", vec![I32(42)])
+        .unwrap();
+
+    // The host sees the write of the instance and vice versa
+    let mut buf = [0; 2];
+    memory.read().read_bytes(4, &mut buf).unwrap();
+    assert_eq!([42, 1], buf);
+}
+
+#[test]
+fn test_import_memory_limits_mismatch() {
+    // Too small
+    let imports = vec![Import::Memory(
+        "env".to_string(),
+        "memory".to_string(),
+        MemoryInstance::new(1, None).unwrap().shared(),
+    )];
+
+    assert!(try_instantiate_with_imports("(module (import \"env\" \"memory\" (memory 2)))", imports).is_err());
+
+    // The maximum is larger than allowed
+    let imports = vec![Import::Memory(
+        "env".to_string(),
+        "memory".to_string(),
+        MemoryInstance::new(1, Some(4)).unwrap().shared(),
+    )];
+
+    assert!(try_instantiate_with_imports("(module (import \"env\" \"memory\" (memory 1 2)))", imports).is_err());
+
+    // No maximum, but one is required
+    let imports = vec![Import::Memory(
+        "env".to_string(),
+        "memory".to_string(),
+        MemoryInstance::new(1, None).unwrap().shared(),
+    )];
+
+    assert!(try_instantiate_with_imports("(module (import \"env\" \"memory\" (memory 1 2)))", imports).is_err());
+}
+
+#[test]
+fn test_exported_memory() {
+    let mut engine = try_instantiate(
+        "(module
+    (memory (export \"memory\") 1)
+    (func (export \"main\") (result i32)
+      (i32.load (i32.const 8))
+    ))",
+    )
+    .unwrap();
+
+    let memory = engine.get_memory("memory").unwrap();
+    memory.write().write_bytes(8, &42i32.to_le_bytes()).unwrap();
+
+    let results = engine.invoke_exported_function_by_name("main", vec![]).unwrap();
+
+    assert_eq!(vec![I32(42)], results);
+    assert!(engine.get_memory("main").is_err());
+}
@@ -0,0 +1,117 @@
+use super::{instantiate, try_instantiate_with_config};
+use crate::engine::config::Config;
+use crate::engine::*;
+use crate::value::Value::*;
+use std::thread;
+use std::time::Duration;
+
+const INPUT: &str = "(module
+    (global $counter (mut i32) (i32.const 0))
+    (func (export \"spin\")
+      (loop
+        (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+        (br 0)))
+    (func $recurse (export \"recurse\")
+      (return_call $recurse))
+    (func (export \"add\") (param i32 i32) (result i32)
+      (i32.add (local.get 0) (local.get 1)))
+    (func (export \"counter\") (result i32)
+      (global.get $counter))
+    )";
+
+#[test]
+fn test_interrupt_handle_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<InterruptHandle>();
+}
+
+#[test]
+fn test_interrupt_from_another_thread() {
+    let mut engine = instantiate(INPUT);
+    let handle = engine.interrupt_handle();
+
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+
+    let err = engine.invoke_exported_function_by_name("spin", vec![]).unwrap_err();
+    assert_eq!(Some(Trap::Interrupted), Trap::from_error(&err));
+    interrupter.join().unwrap();
+
+    // The engine is consistent and can be inspected or reused
+    assert!(engine.store.stack.is_empty());
+    assert!(engine.store.labels.is_empty());
+    match engine.invoke_exported_function_by_name("counter", vec![]).unwrap().as_slice() {
+        [I32(counter)] => assert!(*counter > 0),
+        results => panic!("Unexpected results {:?}", results),
+    }
+}
+
+#[test]
+fn test_interrupt_at_call() {
+    let mut engine = instantiate(INPUT);
+    engine.interrupt_handle().interrupt();
+
+    // A tail call without a loop never returns
+    let err = engine.invoke_exported_function_by_name("recurse", vec![]).unwrap_err();
+    assert_eq!(Some(Trap::Interrupted), Trap::from_error(&err));
+}
+
+#[test]
+fn test_interrupt_start_function() {
+    let input = "(module
+    (func $start (loop (br 0)))
+    (start $start)
+    )";
+
+    let handle = InterruptHandle::default();
+    let interrupter = {
+        let handle = handle.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.interrupt();
+        })
+    };
+
+    let config = Config::default().interrupt_handle(handle);
+    let err = try_instantiate_with_config(input, config).unwrap_err();
+    assert_eq!(Some(Trap::Interrupted), Trap::from_error(&err));
+    interrupter.join().unwrap();
+}
+
+#[test]
+fn test_interrupt_is_consumed() {
+    let mut engine = instantiate(INPUT);
+    engine.interrupt_handle().interrupt();
+
+    // Without a back-edge or call, the interrupt stays pending
+    assert_eq!(
+        vec![I32(3)],
+        engine.invoke_exported_function_by_name("add", vec![I32(1), I32(2)]).unwrap()
+    );
+
+    let err = engine.invoke_exported_function_by_name("spin", vec![]).unwrap_err();
+    assert_eq!(Some(Trap::Interrupted), Trap::from_error(&err));
+
+    // The trap consumed it
+    let mut execution = engine.start_invoke("spin", vec![]).unwrap();
+    execution.run_for(1000).unwrap();
+    assert!(!execution.is_finished());
+}
+
+#[test]
+fn test_interrupt_paused_execution() {
+    let mut engine = instantiate(INPUT);
+    let handle = engine.interrupt_handle();
+
+    let mut execution = engine.start_invoke("spin", vec![]).unwrap();
+    execution.run_for(100).unwrap();
+
+    handle.interrupt();
+
+    let err = execution.resume().unwrap_err();
+    assert_eq!(Some(Trap::Interrupted), Trap::from_error(&err));
+    assert!(execution.is_finished());
+}
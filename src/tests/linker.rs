@@ -0,0 +1,192 @@
+use super::{instantiate, parse_wat};
+use crate::engine::linker::Linker;
+use crate::engine::*;
+use crate::value::Value::*;
+
+/// Creates the engine with the instance of `input` registered as "lib"
+fn instantiate_lib(input: &str) -> (Engine, Linker) {
+    let engine = instantiate(input);
+
+    let mut linker = Linker::new();
+    linker.register("lib", engine.get_module_addr().clone());
+
+    (engine, linker)
+}
+
+#[test]
+fn test_link_function() {
+    let (mut engine, linker) = instantiate_lib(
+        "(module
+    (global $g i32 (i32.const 40))
+    (func (export \"add\") (param i32) (result i32)
+      (i32.add (local.get 0) (global.get $g))
+    ))",
+    );
+
+    let addr = linker
+        .instantiate(
+            &mut engine,
+            &parse_wat(
+                "(module
+    (import \"lib\" \"add\" (func $add (param i32) (result i32)))
+    (global $g i32 (i32.const 1000))
+    (func (export \"main\") (result i32)
+      (call $add (i32.const 2))
+    ))",
+            ),
+            vec![],
+        )
+        .unwrap();
+
+    engine.select_module(&addr).unwrap();
+    let results = engine.invoke_exported_function_by_name("main", vec![]).unwrap();
+
+    // The imported function uses the global of its own instance
+    assert_eq!(vec![I32(42)], results);
+    assert_eq!(&addr, engine.get_module_addr());
+}
+
+#[test]
+fn test_link_memory() {
+    let (mut engine, linker) = instantiate_lib(
+        "(module
+    (memory (export \"memory\") 1)
+    (func (export \"load\") (result i32)
+      (i32.load8_u (i32.const 4))
+    ))",
+    );
+    let lib = engine.get_module_addr().clone();
+
+    let addr = linker
+        .instantiate(
+            &mut engine,
+            &parse_wat(
+                "(module
+    (import \"lib\" \"memory\" (memory 1))
+    (func (export \"store\") (param i32)
+      (i32.store8 (i32.const 4) (local.get 0))
+    ))",
+            ),
+            vec![],
+        )
+        .unwrap();
+
+    engine.select_module(&addr).unwrap();
+    engine.invoke_exported_function_by_name("store", vec![I32(42)]).unwrap();
+
+    engine.select_module(&lib).unwrap();
+    let results = engine.invoke_exported_function_by_name("load", vec![]).unwrap();
+
+    assert_eq!(vec![I32(42)], results);
+}
+
+#[test]
+fn test_link_global() {
+    let (mut engine, linker) = instantiate_lib(
+        "(module
+    (global (export \"counter\") (mut i32) (i32.const 0))
+    )",
+    );
+    let lib = engine.get_module_addr().clone();
+
+    let addr = linker
+        .instantiate(
+            &mut engine,
+            &parse_wat(
+                "(module
+    (import \"lib\" \"counter\" (global $c (mut i32)))
+    (func (export \"inc\")
+      (global.set $c (i32.add (global.get $c) (i32.const 1)))
+    ))",
+            ),
+            vec![],
+        )
+        .unwrap();
+
+    engine.select_module(&addr).unwrap();
+    engine.invoke_exported_function_by_name("inc", vec![]).unwrap();
+    engine.invoke_exported_function_by_name("inc", vec![]).unwrap();
+
+    engine.select_module(&lib).unwrap();
+    assert_eq!(I32(2), engine.get("counter").unwrap());
+}
+
+#[test]
+fn test_link_table() {
+    let (mut engine, linker) = instantiate_lib(
+        "(module
+    (table (export \"table\") 2 funcref)
+    )",
+    );
+
+    linker
+        .instantiate(
+            &mut engine,
+            &parse_wat(
+                "(module
+    (import \"lib\" \"table\" (table 2 funcref))
+    (func $f)
+    (elem (i32.const 1) $f)
+    )",
+            ),
+            vec![],
+        )
+        .unwrap();
+
+    // The element segment was written into the shared table
+    assert_eq!(1, engine.store.tables.len());
+    assert!(engine.store.tables[0].elem[1].is_some());
+}
+
+#[test]
+fn test_link_unknown_import() {
+    let (mut engine, linker) = instantiate_lib("(module (func (export \"f\")))");
+
+    let err = linker
+        .instantiate(
+            &mut engine,
+            &parse_wat("(module (import \"lib\" \"g\" (func)))"),
+            vec![],
+        )
+        .unwrap_err();
+    assert!(err.to_string().starts_with("unlinkable: unknown import"));
+
+    let err = linker
+        .instantiate(
+            &mut engine,
+            &parse_wat("(module (import \"other\" \"f\" (func)))"),
+            vec![],
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().starts_with("unlinkable: unknown import"));
+}
+
+#[test]
+fn test_link_incompatible_import() {
+    let (mut engine, linker) = instantiate_lib(
+        "(module
+    (func (export \"f\") (param i32))
+    (global (export \"g\") i32 (i32.const 0))
+    (memory (export \"memory\") 1 2)
+    )",
+    );
+
+    for input in &[
+        "(module (import \"lib\" \"f\" (func (param i64))))",
+        "(module (import \"lib\" \"f\" (global i32)))",
+        "(module (import \"lib\" \"g\" (global (mut i32))))",
+        "(module (import \"lib\" \"g\" (global i64)))",
+        "(module (import \"lib\" \"memory\" (memory 2)))",
+        "(module (import \"lib\" \"memory\" (memory 1 1)))",
+    ] {
+        let err = linker
+            .instantiate(&mut engine, &parse_wat(input), vec![])
+            .unwrap_err();
+        assert!(
+            err.to_string().starts_with("unlinkable: incompatible import type"),
+            "{} failed with {}",
+            input,
+            err
+        );
+    }
+}
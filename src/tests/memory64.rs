@@ -0,0 +1,112 @@
+use super::{instantiate, invoke, try_instantiate};
+use crate::engine::*;
+use crate::value::Value::*;
+
+const INPUT: &str = "(module
+    (memory i64 1 5)
+    (data (i64.const 8) \"\\2a\")
+    (func (export \"load\") (param i64) (result i32)
+      (i32.load8_u (local.get 0)))
+    (func (export \"load_offset\") (param i64) (result i64)
+      (i64.load offset=4 (local.get 0)))
+    (func (export \"store\") (param i64 i64)
+      (i64.store (local.get 0) (local.get 1)))
+    (func (export \"size\") (result i64)
+      (memory.size))
+    (func (export \"grow\") (param i64) (result i64)
+      (memory.grow (local.get 0)))
+    (func (export \"fill\") (param i64 i32 i64)
+      (memory.fill (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"copy\") (param i64 i64 i64)
+      (memory.copy (local.get 0) (local.get 1) (local.get 2)))
+    )";
+
+#[test]
+fn test_data_segment_with_i64_offset() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I32(42)], invoke(&mut engine, "load", vec![I64(8)]).unwrap());
+    assert_eq!(vec![I32(0)], invoke(&mut engine, "load", vec![I64(9)]).unwrap());
+}
+
+#[test]
+fn test_load_and_store() {
+    let mut engine = instantiate(INPUT);
+
+    invoke(&mut engine, "store", vec![I64(100), I64(-2)]).unwrap();
+    assert_eq!(vec![I64(-2)], invoke(&mut engine, "load_offset", vec![I64(96)]).unwrap());
+    assert_eq!(vec![I32(0xfe)], invoke(&mut engine, "load", vec![I64(100)]).unwrap());
+}
+
+#[test]
+fn test_out_of_bounds() {
+    let mut engine = instantiate(INPUT);
+
+    let err = invoke(&mut engine, "load", vec![I64(65536)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+
+    // Addresses beyond 32 bits are not truncated
+    let err = invoke(&mut engine, "load", vec![I64(1 << 32)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+
+    // The effective address overflows
+    let err = invoke(&mut engine, "load_offset", vec![I64(-1)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+}
+
+#[test]
+fn test_size_and_grow() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I64(1)], invoke(&mut engine, "size", vec![]).unwrap());
+    assert_eq!(vec![I64(1)], invoke(&mut engine, "grow", vec![I64(2)]).unwrap());
+    assert_eq!(vec![I64(3)], invoke(&mut engine, "size", vec![]).unwrap());
+
+    // Growing beyond the maximum fails with -1
+    assert_eq!(vec![I64(-1)], invoke(&mut engine, "grow", vec![I64(3)]).unwrap());
+    assert_eq!(vec![I64(-1)], invoke(&mut engine, "grow", vec![I64(1 << 40)]).unwrap());
+    assert_eq!(vec![I64(3)], invoke(&mut engine, "size", vec![]).unwrap());
+}
+
+#[test]
+fn test_bulk_memory() {
+    let mut engine = instantiate(INPUT);
+
+    invoke(&mut engine, "fill", vec![I64(65530), I32(7), I64(6)]).unwrap();
+    invoke(&mut engine, "copy", vec![I64(0), I64(65534), I64(2)]).unwrap();
+    assert_eq!(vec![I32(7)], invoke(&mut engine, "load", vec![I64(1)]).unwrap());
+
+    let err = invoke(&mut engine, "fill", vec![I64(65530), I32(7), I64(7)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+}
+
+#[test]
+fn test_large_memory_is_allocated_lazily() {
+    // 8 GiB, which are only backed by memory when they are written
+    let mut engine = instantiate(
+        "(module
+    (memory i64 131072)
+    (func (export \"store\") (param i64 i32)
+      (i32.store (local.get 0) (local.get 1)))
+    (func (export \"load\") (param i64) (result i32)
+      (i32.load (local.get 0)))
+    )",
+    );
+
+    let address = (1 << 33) - 4;
+
+    assert_eq!(vec![I32(0)], invoke(&mut engine, "load", vec![I64(address)]).unwrap());
+    invoke(&mut engine, "store", vec![I64(address), I32(42)]).unwrap();
+    assert_eq!(vec![I32(42)], invoke(&mut engine, "load", vec![I64(address)]).unwrap());
+
+    let memory = engine.store.memory[0].read();
+    assert_eq!(1 << 33, memory.len());
+}
+
+#[test]
+fn test_memory_cannot_be_allocated() {
+    // Even the list of 2^48 pages does not fit into the host's memory
+    let err = try_instantiate("(module (memory i64 0x1000000000000))").unwrap_err();
+
+    assert!(format!("{:#}", err).contains("Cannot allocate the memory of 281474976710656 pages"));
+}
@@ -1,3 +1,84 @@
 //mod unit_tests;
+mod bulk_memory;
+mod bytecode;
+mod const_expr;
+mod exceptions;
+mod execution;
+mod fuel;
+mod imports;
+mod interrupt;
+mod linker;
+mod memory64;
+mod multi_value;
+mod reference_types;
+mod simd;
+mod stack;
+mod tail_calls;
+mod threads;
+mod trap;
+mod typed;
+mod wasi;
 mod wasm;
-mod wat;
\ No newline at end of file
+mod wat;
+
+use crate::debugger::RelativeProgramCounter;
+use crate::engine::config::Config;
+use crate::engine::import_resolver::Imports;
+use crate::engine::module::ModuleInstance;
+use crate::engine::*;
+use crate::value::Value;
+use validation::validate;
+use wasm_parser::{parse, Module};
+
+/// Converts `input` to a module and checks that it is valid
+fn parse_wat(input: &str) -> Module {
+    let file = ::wat::parse_str(input).expect("Converting wat to wasm failed");
+
+    let module = parse(file).expect("Parsing failed");
+    assert!(validate(&module).is_ok());
+
+    module
+}
+
+/// Instantiates `module` with the `imports` and the `config`
+fn try_instantiate_module(module: &Module, imports: Imports, config: Config) -> Result<Engine> {
+    let (instance, functions) = ModuleInstance::new(module);
+    Engine::with_config(
+        instance,
+        &functions,
+        module,
+        Box::new(RelativeProgramCounter::default()),
+        imports,
+        config,
+    )
+}
+
+fn try_instantiate_with_config(input: &str, config: Config) -> Result<Engine> {
+    try_instantiate_module(&parse_wat(input), vec![], config)
+}
+
+fn try_instantiate_with_imports(input: &str, imports: Imports) -> Result<Engine> {
+    try_instantiate_module(&parse_wat(input), imports, Config::default())
+}
+
+fn instantiate_with_imports(input: &str, imports: Imports) -> Engine {
+    try_instantiate_with_imports(input, imports).unwrap()
+}
+
+fn try_instantiate(input: &str) -> Result<Engine> {
+    try_instantiate_with_imports(input, vec![])
+}
+
+fn instantiate(input: &str) -> Engine {
+    try_instantiate(input).unwrap()
+}
+
+/// Invokes the export `name` and checks that the stacks are empty afterwards
+fn invoke(engine: &mut Engine, name: &str, args: Vec<Value>) -> Result<Vec<Value>> {
+    let results = engine.invoke_exported_function_by_name(name, args);
+
+    assert!(engine.store.stack.is_empty());
+    assert!(engine.store.labels.is_empty());
+
+    results
+}
@@ -0,0 +1,220 @@
+use super::{instantiate, instantiate_with_imports};
+use crate::engine::func::HostFunction;
+use crate::engine::import_resolver::Import;
+use crate::engine::*;
+use crate::value::Value;
+use crate::value::Value::*;
+
+/// Invokes `main` of `input` and checks that the stack is clean afterwards
+fn run(input: &str, args: Vec<Value>) -> Vec<Value> {
+    let mut engine = instantiate(input);
+
+    let results = engine
+        .invoke_exported_function_by_name("main", args)
+        .expect("Invoking main failed");
+
+    assert!(engine.store.stack.is_empty());
+    assert!(engine.store.labels.is_empty());
+
+    results
+}
+
+#[test]
+fn test_block_with_params_and_results() {
+    let results = run(
+        "(module
+    (func (export \"main\") (result i32 i64 i32)
+      (i32.const 1)
+      (i32.const 2)
+      (block (param i32 i32) (result i32 i64 i32)
+        (i32.add)
+        (i64.const 20)
+        (i32.const 30))
+    ))",
+        vec![],
+    );
+
+    assert_eq!(vec![I32(3), I64(20), I32(30)], results);
+}
+
+#[test]
+fn test_br_with_several_values() {
+    let results = run(
+        "(module
+    (func (export \"main\") (result i32 f64)
+      (block (result i32 f64)
+        (i32.const 99)
+        (block (result i32 f64)
+          (i64.const 7)
+          (i32.const 1)
+          (f64.const 2.5)
+          (br 1))
+        (drop)
+        (drop))
+    ))",
+        vec![],
+    );
+
+    assert_eq!(vec![I32(1), F64(2.5)], results);
+}
+
+#[test]
+fn test_loop_with_params() {
+    // Sums 1..=n, the sum and the counter are the parameters of the loop
+    let results = run(
+        "(module
+    (func (export \"main\") (param i32) (result i32 i32)
+      (i32.const 0)
+      (local.get 0)
+      (loop (param i32 i32) (result i32 i32)
+        (local.set 0)
+        (i32.add (local.get 0))
+        (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+        (local.get 0)
+        (br_if 0 (local.get 0)))
+    ))",
+        vec![I32(100)],
+    );
+
+    assert_eq!(vec![I32(5050), I32(0)], results);
+}
+
+#[test]
+fn test_if_with_params_and_results() {
+    let input = "(module
+    (func (export \"main\") (param i32) (result i32 i32)
+      (i32.const 6)
+      (i32.const 3)
+      (if (param i32 i32) (result i32 i32) (local.get 0)
+        (then (i32.add) (i32.const 1))
+        (else (i32.sub) (i32.const 0)))
+    ))";
+
+    assert_eq!(vec![I32(9), I32(1)], run(input, vec![I32(1)]));
+    assert_eq!(vec![I32(3), I32(0)], run(input, vec![I32(0)]));
+}
+
+#[test]
+fn test_br_table_with_several_values() {
+    let input = "(module
+    (func (export \"main\") (param i32) (result i32 i32)
+      (block (result i32 i32)
+        (block (result i32 i32)
+          (i32.const 1)
+          (i32.const 2)
+          (br_table 0 1 (local.get 0)))
+        (i32.add)
+        (i32.const 0))
+    ))";
+
+    assert_eq!(vec![I32(3), I32(0)], run(input, vec![I32(0)]));
+    assert_eq!(vec![I32(1), I32(2)], run(input, vec![I32(1)]));
+    assert_eq!(vec![I32(1), I32(2)], run(input, vec![I32(7)]));
+}
+
+#[test]
+fn test_call_returns_several_values() {
+    let results = run(
+        "(module
+    (func $swap (param i32 i64) (result i64 i32)
+      (local.get 1)
+      (local.get 0))
+    (func $divmod (param i32 i32) (result i32 i32)
+      (i32.div_u (local.get 0) (local.get 1))
+      (i32.rem_u (local.get 0) (local.get 1)))
+    (func (export \"main\") (result i64 i32 i32 i32)
+      (call $swap (i32.const 1) (i64.const 2))
+      (call $divmod (i32.const 17) (i32.const 5))
+    ))",
+        vec![],
+    );
+
+    assert_eq!(vec![I64(2), I32(1), I32(3), I32(2)], results);
+}
+
+#[test]
+fn test_call_indirect_returns_several_values() {
+    let results = run(
+        "(module
+    (type $pair (func (param i32) (result i32 i32)))
+    (table 1 funcref)
+    (elem (i32.const 0) $dup)
+    (func $dup (type $pair)
+      (local.get 0)
+      (local.get 0))
+    (func (export \"main\") (result i32 i32)
+      (call_indirect (type $pair) (i32.const 21) (i32.const 0))
+      (i32.add)
+      (i32.const 1)
+    ))",
+        vec![],
+    );
+
+    assert_eq!(vec![I32(42), I32(1)], results);
+}
+
+#[test]
+fn test_return_several_values_from_nested_blocks() {
+    let results = run(
+        "(module
+    (func (export \"main\") (result i32 i32)
+      (i32.const 0)
+      (block
+        (loop
+          (return (i32.const 1) (i32.const 2))))
+      (i32.const 3)
+    ))",
+        vec![],
+    );
+
+    assert_eq!(vec![I32(1), I32(2)], results);
+}
+
+#[test]
+fn test_host_function_returns_several_values() {
+    let split: HostFunction = Box::new(|_, args: &[Value]| match args {
+        [I64(v)] => Ok(vec![I32(*v as i32), I32((*v >> 32) as i32)]),
+        _ => Err(anyhow!("Expected an i64")),
+    });
+
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "split".to_string(),
+        FunctionSignature {
+            param_types: vec![ValueType::I64],
+            return_types: vec![ValueType::I32, ValueType::I32],
+        },
+        split,
+    )];
+
+    let mut engine = instantiate_with_imports(
+        "(module
+    (import \"env\" \"split\" (func $split (param i64) (result i32 i32)))
+    (func (export \"main\") (param i64) (result i32 i32)
+      (call $split (local.get 0))
+    ))",
+        imports,
+    );
+
+    let results = engine
+        .invoke_exported_function_by_name("main", vec![I64(0x0000_0002_0000_0001)])
+        .unwrap();
+
+    assert_eq!(vec![I32(1), I32(2)], results);
+}
+
+#[test]
+fn test_typed_func_with_several_results() {
+    let mut engine = instantiate(
+        "(module
+    (func (export \"main\") (param i32) (result i32 i64 f32)
+      (local.get 0)
+      (i64.extend_i32_s (local.get 0))
+      (f32.convert_i32_s (local.get 0))
+    ))",
+    );
+
+    let main = engine.get_typed_func::<i32, (i32, i64, f32)>("main").unwrap();
+
+    assert_eq!((-4, -4, -4.0), main.call(&mut engine, -4).unwrap());
+}
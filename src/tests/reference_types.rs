@@ -0,0 +1,164 @@
+use super::{instantiate, invoke};
+use crate::engine::*;
+use crate::value::Value;
+use crate::value::Value::*;
+
+fn externref(addr: usize) -> Value {
+    ExternRef(Some(ExternAddr::new(addr)))
+}
+
+const INPUT: &str = "(module
+    (type $ret (func (result i32)))
+    (table $funcs 2 funcref)
+    (table $externs 1 3 externref)
+    (elem (table $funcs) (i32.const 0) func $one $two)
+    (global $f funcref (ref.func $one))
+    (func $one (result i32) (i32.const 1))
+    (func $two (result i32) (i32.const 2))
+    (func (export \"identity\") (param externref) (result externref)
+      (local.get 0))
+    (func (export \"is_null\") (param externref) (result i32)
+      (ref.is_null (local.get 0)))
+    (func (export \"null\") (result funcref externref)
+      (ref.null func)
+      (ref.null extern))
+    (func (export \"func\") (result funcref)
+      (ref.func $two))
+    (func (export \"global\") (result funcref)
+      (global.get $f))
+    (func (export \"select\") (param externref externref i32) (result externref)
+      (select (result externref) (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"get\") (param i32) (result externref)
+      (table.get $externs (local.get 0)))
+    (func (export \"set\") (param i32 externref)
+      (table.set $externs (local.get 0) (local.get 1)))
+    (func (export \"size\") (result i32)
+      (table.size $externs))
+    (func (export \"grow\") (param externref i32) (result i32)
+      (table.grow $externs (local.get 0) (local.get 1)))
+    (func (export \"fill\") (param i32 externref i32)
+      (table.fill $externs (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"call\") (param i32) (result i32)
+      (call_indirect $funcs (type $ret) (local.get 0)))
+    (func (export \"swap\")
+      (table.set $funcs (i32.const 0) (table.get $funcs (i32.const 1))))
+    )";
+
+#[test]
+fn test_externref_roundtrip() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![externref(7)], invoke(&mut engine, "identity", vec![externref(7)]).unwrap());
+    assert_eq!(
+        vec![ExternRef(None)],
+        invoke(&mut engine, "identity", vec![ExternRef(None)]).unwrap()
+    );
+}
+
+#[test]
+fn test_ref_is_null() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I32(0)], invoke(&mut engine, "is_null", vec![externref(0)]).unwrap());
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "is_null", vec![ExternRef(None)]).unwrap());
+}
+
+#[test]
+fn test_ref_null() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(
+        vec![FuncRef(None), ExternRef(None)],
+        invoke(&mut engine, "null", vec![]).unwrap()
+    );
+}
+
+#[test]
+fn test_ref_func() {
+    let mut engine = instantiate(INPUT);
+
+    let two = engine.module_instance.lookup_function_addr(&1).copied();
+    let one = engine.module_instance.lookup_function_addr(&0).copied();
+
+    assert_eq!(vec![FuncRef(two)], invoke(&mut engine, "func", vec![]).unwrap());
+    assert_eq!(vec![FuncRef(one)], invoke(&mut engine, "global", vec![]).unwrap());
+}
+
+#[test]
+fn test_typed_select() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(
+        vec![externref(1)],
+        invoke(&mut engine, "select", vec![externref(1), externref(2), I32(1)]).unwrap()
+    );
+    assert_eq!(
+        vec![externref(2)],
+        invoke(&mut engine, "select", vec![externref(1), externref(2), I32(0)]).unwrap()
+    );
+}
+
+#[test]
+fn test_table_get_and_set() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![ExternRef(None)], invoke(&mut engine, "get", vec![I32(0)]).unwrap());
+
+    invoke(&mut engine, "set", vec![I32(0), externref(42)]).unwrap();
+    assert_eq!(vec![externref(42)], invoke(&mut engine, "get", vec![I32(0)]).unwrap());
+
+    let err = invoke(&mut engine, "get", vec![I32(1)]).unwrap_err();
+    assert_eq!(Some(Trap::TableOutOfBounds), Trap::from_error(&err));
+
+    let err = invoke(&mut engine, "set", vec![I32(1), externref(1)]).unwrap_err();
+    assert_eq!(Some(Trap::TableOutOfBounds), Trap::from_error(&err));
+}
+
+#[test]
+fn test_table_grow_and_fill() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "size", vec![]).unwrap());
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "grow", vec![externref(5), I32(2)]).unwrap());
+    assert_eq!(vec![I32(3)], invoke(&mut engine, "size", vec![]).unwrap());
+
+    // The maximum of the table is 3
+    assert_eq!(vec![I32(-1)], invoke(&mut engine, "grow", vec![ExternRef(None), I32(1)]).unwrap());
+    assert_eq!(vec![I32(3)], invoke(&mut engine, "grow", vec![ExternRef(None), I32(0)]).unwrap());
+
+    assert_eq!(vec![ExternRef(None)], invoke(&mut engine, "get", vec![I32(0)]).unwrap());
+    assert_eq!(vec![externref(5)], invoke(&mut engine, "get", vec![I32(2)]).unwrap());
+
+    invoke(&mut engine, "fill", vec![I32(0), externref(9), I32(2)]).unwrap();
+    assert_eq!(vec![externref(9)], invoke(&mut engine, "get", vec![I32(1)]).unwrap());
+    assert_eq!(vec![externref(5)], invoke(&mut engine, "get", vec![I32(2)]).unwrap());
+
+    let err = invoke(&mut engine, "fill", vec![I32(2), externref(1), I32(2)]).unwrap_err();
+    assert_eq!(Some(Trap::TableOutOfBounds), Trap::from_error(&err));
+}
+
+#[test]
+fn test_call_indirect_with_table_index() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "call", vec![I32(0)]).unwrap());
+    assert_eq!(vec![I32(2)], invoke(&mut engine, "call", vec![I32(1)]).unwrap());
+
+    invoke(&mut engine, "swap", vec![]).unwrap();
+    assert_eq!(vec![I32(2)], invoke(&mut engine, "call", vec![I32(0)]).unwrap());
+}
+
+#[test]
+fn test_typed_func_with_externref() {
+    let mut engine = instantiate(INPUT);
+
+    let identity = engine
+        .get_typed_func::<Option<ExternAddr>, Option<ExternAddr>>("identity")
+        .unwrap();
+
+    assert_eq!(
+        Some(ExternAddr::new(3)),
+        identity.call(&mut engine, Some(ExternAddr::new(3))).unwrap()
+    );
+    assert_eq!(None, identity.call(&mut engine, None).unwrap());
+}
@@ -0,0 +1,482 @@
+use super::{instantiate, invoke};
+use crate::engine::*;
+use crate::value::Value;
+use crate::value::Value::*;
+
+fn i8x16(lanes: [i8; 16]) -> Value {
+    V128(u128::from_le_bytes(lanes.map(|lane| lane as u8)))
+}
+
+fn i16x8(lanes: [i16; 8]) -> Value {
+    let mut bytes = [0; 16];
+    for (chunk, lane) in bytes.chunks_exact_mut(2).zip(lanes.iter()) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    V128(u128::from_le_bytes(bytes))
+}
+
+fn i32x4(lanes: [i32; 4]) -> Value {
+    let mut bytes = [0; 16];
+    for (chunk, lane) in bytes.chunks_exact_mut(4).zip(lanes.iter()) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    V128(u128::from_le_bytes(bytes))
+}
+
+fn f32x4(lanes: [f32; 4]) -> Value {
+    i32x4(lanes.map(|lane| lane.to_bits() as i32))
+}
+
+const INPUT: &str = "(module
+    (memory 1)
+    (data (i32.const 0) \"\\01\\02\\03\\04\\05\\06\\07\\08\\f9\\fa\\fb\\fc\\fd\\fe\\ff\\00\")
+    (global $g (mut v128) (v128.const i32x4 1 2 3 4))
+    (func (export \"identity\") (param v128) (result v128)
+      (local.get 0))
+    (func (export \"local\") (result v128)
+      (local v128)
+      (local.get 0))
+    (func (export \"global\") (param v128) (result v128)
+      (global.get $g)
+      (global.set $g (local.get 0)))
+    (func (export \"select\") (param v128 v128 i32) (result v128)
+      (select (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"drop\") (param v128) (result i32)
+      (drop (local.get 0))
+      (i32.const 1))
+    (func (export \"i32x4.add\") (param v128 v128) (result v128)
+      (i32x4.add (local.get 0) (local.get 1)))
+    (func (export \"i8x16.add_sat_s\") (param v128 v128) (result v128)
+      (i8x16.add_sat_s (local.get 0) (local.get 1)))
+    (func (export \"i8x16.sub_sat_u\") (param v128 v128) (result v128)
+      (i8x16.sub_sat_u (local.get 0) (local.get 1)))
+    (func (export \"i16x8.mul\") (param v128 v128) (result v128)
+      (i16x8.mul (local.get 0) (local.get 1)))
+    (func (export \"i32x4.shl\") (param v128 i32) (result v128)
+      (i32x4.shl (local.get 0) (local.get 1)))
+    (func (export \"i32x4.shr_s\") (param v128 i32) (result v128)
+      (i32x4.shr_s (local.get 0) (local.get 1)))
+    (func (export \"i32x4.lt_s\") (param v128 v128) (result v128)
+      (i32x4.lt_s (local.get 0) (local.get 1)))
+    (func (export \"i32x4.lt_u\") (param v128 v128) (result v128)
+      (i32x4.lt_u (local.get 0) (local.get 1)))
+    (func (export \"f32x4.gt\") (param v128 v128) (result v128)
+      (f32x4.gt (local.get 0) (local.get 1)))
+    (func (export \"v128.bitselect\") (param v128 v128 v128) (result v128)
+      (v128.bitselect (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"any_true\") (param v128) (result i32)
+      (v128.any_true (local.get 0)))
+    (func (export \"all_true\") (param v128) (result i32)
+      (i32x4.all_true (local.get 0)))
+    (func (export \"bitmask\") (param v128) (result i32)
+      (i8x16.bitmask (local.get 0)))
+    (func (export \"splat\") (param i32) (result v128)
+      (i16x8.splat (local.get 0)))
+    (func (export \"extract_s\") (param v128) (result i32)
+      (i8x16.extract_lane_s 15 (local.get 0)))
+    (func (export \"extract_u\") (param v128) (result i32)
+      (i8x16.extract_lane_u 15 (local.get 0)))
+    (func (export \"replace\") (param v128 f32) (result v128)
+      (f32x4.replace_lane 2 (local.get 0) (local.get 1)))
+    (func (export \"shuffle\") (param v128 v128) (result v128)
+      (i8x16.shuffle 16 0 17 1 18 2 19 3 20 4 21 5 22 6 23 7 (local.get 0) (local.get 1)))
+    (func (export \"swizzle\") (param v128 v128) (result v128)
+      (i8x16.swizzle (local.get 0) (local.get 1)))
+    (func (export \"narrow\") (param v128 v128) (result v128)
+      (i8x16.narrow_i16x8_s (local.get 0) (local.get 1)))
+    (func (export \"extend_high\") (param v128) (result v128)
+      (i16x8.extend_high_i8x16_u (local.get 0)))
+    (func (export \"dot\") (param v128 v128) (result v128)
+      (i32x4.dot_i16x8_s (local.get 0) (local.get 1)))
+    (func (export \"f32x4.min\") (param v128 v128) (result v128)
+      (f32x4.min (local.get 0) (local.get 1)))
+    (func (export \"f32x4.nearest\") (param v128) (result v128)
+      (f32x4.nearest (local.get 0)))
+    (func (export \"trunc_sat\") (param v128) (result v128)
+      (i32x4.trunc_sat_f32x4_s (local.get 0)))
+    (func (export \"convert\") (param v128) (result v128)
+      (f32x4.convert_i32x4_u (local.get 0)))
+    (func (export \"load\") (param i32) (result v128)
+      (v128.load (local.get 0)))
+    (func (export \"load8x8_s\") (param i32) (result v128)
+      (v128.load8x8_s (local.get 0)))
+    (func (export \"load32_splat\") (param i32) (result v128)
+      (v128.load32_splat (local.get 0)))
+    (func (export \"load64_zero\") (param i32) (result v128)
+      (v128.load64_zero (local.get 0)))
+    (func (export \"load16_lane\") (param i32 v128) (result v128)
+      (v128.load16_lane 7 (local.get 0) (local.get 1)))
+    (func (export \"store\") (param i32 v128)
+      (v128.store (local.get 0) (local.get 1)))
+    (func (export \"store32_lane\") (param i32 v128)
+      (v128.store32_lane 3 (local.get 0) (local.get 1)))
+    )";
+
+#[test]
+fn test_v128_roundtrip() {
+    let mut engine = instantiate(INPUT);
+
+    let v = i32x4([1, -2, 3, -4]);
+
+    assert_eq!(vec![v], invoke(&mut engine, "identity", vec![v]).unwrap());
+    assert_eq!(vec![V128(0)], invoke(&mut engine, "local", vec![]).unwrap());
+    assert_eq!(
+        vec![i32x4([1, 2, 3, 4])],
+        invoke(&mut engine, "global", vec![v]).unwrap()
+    );
+    assert_eq!(vec![v], invoke(&mut engine, "global", vec![V128(0)]).unwrap());
+}
+
+#[test]
+fn test_v128_select_and_drop() {
+    let mut engine = instantiate(INPUT);
+
+    let a = i32x4([1, 2, 3, 4]);
+    let b = i32x4([5, 6, 7, 8]);
+
+    assert_eq!(vec![a], invoke(&mut engine, "select", vec![a, b, I32(1)]).unwrap());
+    assert_eq!(vec![b], invoke(&mut engine, "select", vec![a, b, I32(0)]).unwrap());
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "drop", vec![a]).unwrap());
+}
+
+#[test]
+fn test_v128_occupies_two_slots() {
+    let mut engine = instantiate(
+        "(module
+        (func $swap (param i64 v128) (result v128 i64)
+          (local.get 1)
+          (local.get 0))
+        (func (export \"mixed\") (param i32 v128 i64) (result v128 i64 i32)
+          (local v128 i32)
+          (local.set 4 (i32.const 5))
+          (local.set 3 (block (result v128)
+            (br_if 0 (local.tee 3 (local.get 1)) (local.get 0))
+            (drop)
+            (select (v128.const i64x2 7 8) (local.get 3) (local.get 4))))
+          (call $swap (local.get 2) (local.get 3))
+          (select (local.get 4) (i32.const 0) (local.get 0)))
+        )",
+    );
+
+    let v = i32x4([1, -2, 3, -4]);
+
+    assert_eq!(
+        vec![v, I64(9), I32(5)],
+        invoke(&mut engine, "mixed", vec![I32(1), v, I64(9)]).unwrap()
+    );
+    assert_eq!(
+        vec![V128(8 << 64 | 7), I64(9), I32(0)],
+        invoke(&mut engine, "mixed", vec![I32(0), v, I64(9)]).unwrap()
+    );
+}
+
+#[test]
+fn test_integer_arithmetic() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(
+        vec![i32x4([i32::MIN, 0, 10, -1])],
+        invoke(
+            &mut engine,
+            "i32x4.add",
+            vec![i32x4([i32::MAX, -1, 5, -2]), i32x4([1, 1, 5, 1])]
+        )
+        .unwrap()
+    );
+
+    let mut a = [0; 16];
+    a[0] = 100;
+    a[1] = -100;
+    a[2] = 5;
+    let mut expected = [0; 16];
+    expected[0] = i8::MAX;
+    expected[1] = i8::MIN;
+    expected[2] = 10;
+    assert_eq!(
+        vec![i8x16(expected)],
+        invoke(&mut engine, "i8x16.add_sat_s", vec![i8x16(a), i8x16(a)]).unwrap()
+    );
+
+    let mut b = [0; 16];
+    b[0] = 1;
+    let mut expected = [0; 16];
+    expected[1] = 1;
+    assert_eq!(
+        vec![i8x16(expected)],
+        invoke(&mut engine, "i8x16.sub_sat_u", vec![i8x16(expected), i8x16(b)]).unwrap()
+    );
+
+    assert_eq!(
+        vec![i16x8([6, -6, 0, 0x8004u16 as i16, 0, 0, 0, 0])],
+        invoke(
+            &mut engine,
+            "i16x8.mul",
+            vec![
+                i16x8([2, -2, 0, 0x4002, 0, 0, 0, 0]),
+                i16x8([3, 3, 7, 2, 0, 0, 0, 0])
+            ]
+        )
+        .unwrap()
+    );
+
+    assert_eq!(
+        vec![i32x4([4, -4, 0, 4])],
+        invoke(&mut engine, "i32x4.shl", vec![i32x4([1, -1, 0, 1]), I32(34)]).unwrap()
+    );
+    assert_eq!(
+        vec![i32x4([-1, 1, 0, -4])],
+        invoke(&mut engine, "i32x4.shr_s", vec![i32x4([-4, 4, 0, -16]), I32(2)]).unwrap()
+    );
+}
+
+#[test]
+fn test_comparisons() {
+    let mut engine = instantiate(INPUT);
+
+    let a = i32x4([-1, 0, 1, 2]);
+    let b = i32x4([0, 0, 0, 3]);
+
+    assert_eq!(
+        vec![i32x4([-1, 0, 0, -1])],
+        invoke(&mut engine, "i32x4.lt_s", vec![a, b]).unwrap()
+    );
+    assert_eq!(
+        vec![i32x4([0, 0, 0, -1])],
+        invoke(&mut engine, "i32x4.lt_u", vec![a, b]).unwrap()
+    );
+    assert_eq!(
+        vec![i32x4([0, -1, 0, 0])],
+        invoke(
+            &mut engine,
+            "f32x4.gt",
+            vec![
+                f32x4([1.0, 2.0, f32::NAN, -0.0]),
+                f32x4([1.0, 1.5, 0.0, 0.0])
+            ]
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_bitwise_and_reductions() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(
+        vec![V128(0xFF00_FF00)],
+        invoke(
+            &mut engine,
+            "v128.bitselect",
+            vec![V128(u128::MAX), V128(0), V128(0xFF00_FF00)]
+        )
+        .unwrap()
+    );
+
+    assert_eq!(vec![I32(0)], invoke(&mut engine, "any_true", vec![V128(0)]).unwrap());
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "any_true", vec![V128(1 << 100)]).unwrap());
+    assert_eq!(
+        vec![I32(1)],
+        invoke(&mut engine, "all_true", vec![i32x4([1, -1, 2, 3])]).unwrap()
+    );
+    assert_eq!(
+        vec![I32(0)],
+        invoke(&mut engine, "all_true", vec![i32x4([1, -1, 0, 3])]).unwrap()
+    );
+
+    let mut lanes = [0; 16];
+    lanes[0] = -1;
+    lanes[15] = i8::MIN;
+    assert_eq!(
+        vec![I32(0x8001)],
+        invoke(&mut engine, "bitmask", vec![i8x16(lanes)]).unwrap()
+    );
+}
+
+#[test]
+fn test_lanes() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(
+        vec![i16x8([0x5678; 8])],
+        invoke(&mut engine, "splat", vec![I32(0x1234_5678)]).unwrap()
+    );
+
+    let mut lanes = [0; 16];
+    lanes[15] = -2;
+    assert_eq!(vec![I32(-2)], invoke(&mut engine, "extract_s", vec![i8x16(lanes)]).unwrap());
+    assert_eq!(vec![I32(254)], invoke(&mut engine, "extract_u", vec![i8x16(lanes)]).unwrap());
+
+    assert_eq!(
+        vec![f32x4([1.0, 2.0, 0.5, 4.0])],
+        invoke(
+            &mut engine,
+            "replace",
+            vec![f32x4([1.0, 2.0, 3.0, 4.0]), F32(0.5)]
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_shuffle_and_swizzle() {
+    let mut engine = instantiate(INPUT);
+
+    let a = i8x16([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+    let b = i8x16([
+        16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31,
+    ]);
+
+    assert_eq!(
+        vec![i8x16([
+            16, 0, 17, 1, 18, 2, 19, 3, 20, 4, 21, 5, 22, 6, 23, 7
+        ])],
+        invoke(&mut engine, "shuffle", vec![a, b]).unwrap()
+    );
+
+    // Indices out of range select zero
+    let s = i8x16([15, 0, 16, -1, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+    assert_eq!(
+        vec![i8x16([15, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12])],
+        invoke(&mut engine, "swizzle", vec![a, s]).unwrap()
+    );
+}
+
+#[test]
+fn test_conversions() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(
+        vec![i8x16([
+            127, -128, 5, 0, 0, 0, 0, 0, -1, 0, 0, 0, 0, 0, 0, 0
+        ])],
+        invoke(
+            &mut engine,
+            "narrow",
+            vec![
+                i16x8([300, -300, 5, 0, 0, 0, 0, 0]),
+                i16x8([-1, 0, 0, 0, 0, 0, 0, 0])
+            ]
+        )
+        .unwrap()
+    );
+
+    let mut lanes = [0; 16];
+    lanes[8] = -1;
+    lanes[15] = 7;
+    assert_eq!(
+        vec![i16x8([255, 0, 0, 0, 0, 0, 0, 7])],
+        invoke(&mut engine, "extend_high", vec![i8x16(lanes)]).unwrap()
+    );
+
+    assert_eq!(
+        vec![i32x4([5, 0, 0, -2])],
+        invoke(
+            &mut engine,
+            "dot",
+            vec![
+                i16x8([1, 2, 0, 0, 0, 0, -1, 1]),
+                i16x8([1, 2, 0, 0, 0, 0, 1, -1])
+            ]
+        )
+        .unwrap()
+    );
+
+    assert_eq!(
+        vec![i32x4([i32::MAX, i32::MIN, 0, -1])],
+        invoke(
+            &mut engine,
+            "trunc_sat",
+            vec![f32x4([1e10, f32::NEG_INFINITY, f32::NAN, -1.5])]
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        vec![f32x4([4294967296.0, 1.0, 0.0, 2.0])],
+        invoke(&mut engine, "convert", vec![i32x4([-1, 1, 0, 2])]).unwrap()
+    );
+}
+
+#[test]
+fn test_float_arithmetic() {
+    let mut engine = instantiate(INPUT);
+
+    let result = invoke(
+        &mut engine,
+        "f32x4.min",
+        vec![
+            f32x4([1.0, f32::NAN, -3.0, 0.5]),
+            f32x4([2.0, 1.0, 3.0, -0.5]),
+        ],
+    )
+    .unwrap();
+
+    match result.as_slice() {
+        [V128(v)] => {
+            let lanes: Vec<f32> = v
+                .to_le_bytes()
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect();
+
+            assert_eq!(1.0, lanes[0]);
+            assert!(lanes[1].is_nan());
+            assert_eq!(-3.0, lanes[2]);
+            assert_eq!(-0.5, lanes[3]);
+        }
+        x => panic!("Expected a vector, found {:?}", x),
+    }
+
+    assert_eq!(
+        vec![f32x4([2.0, 2.0, -4.0, 0.0])],
+        invoke(&mut engine, "f32x4.nearest", vec![f32x4([2.5, 1.5, -3.7, 0.2])]).unwrap()
+    );
+}
+
+#[test]
+fn test_memory() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(
+        vec![i8x16([
+            1, 2, 3, 4, 5, 6, 7, 8, -7, -6, -5, -4, -3, -2, -1, 0
+        ])],
+        invoke(&mut engine, "load", vec![I32(0)]).unwrap()
+    );
+    assert_eq!(
+        vec![i16x8([-7, -6, -5, -4, -3, -2, -1, 0])],
+        invoke(&mut engine, "load8x8_s", vec![I32(8)]).unwrap()
+    );
+    assert_eq!(
+        vec![i32x4([0x0403_0201; 4])],
+        invoke(&mut engine, "load32_splat", vec![I32(0)]).unwrap()
+    );
+    assert_eq!(
+        vec![V128(0x0807_0605_0403_0201)],
+        invoke(&mut engine, "load64_zero", vec![I32(0)]).unwrap()
+    );
+    assert_eq!(
+        vec![i16x8([0, 0, 0, 0, 0, 0, 0, 0x0201])],
+        invoke(&mut engine, "load16_lane", vec![I32(0), V128(0)]).unwrap()
+    );
+
+    let v = i32x4([10, 20, 30, 40]);
+    invoke(&mut engine, "store", vec![I32(100), v]).unwrap();
+    assert_eq!(vec![v], invoke(&mut engine, "load", vec![I32(100)]).unwrap());
+
+    invoke(&mut engine, "store32_lane", vec![I32(0), v]).unwrap();
+    assert_eq!(
+        vec![i32x4([40; 4])],
+        invoke(&mut engine, "load32_splat", vec![I32(0)]).unwrap()
+    );
+}
+
+#[test]
+fn test_memory_out_of_bounds() {
+    let mut engine = instantiate(INPUT);
+
+    let err = invoke(&mut engine, "load", vec![I32(65536 - 15)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+
+    let err = invoke(&mut engine, "store", vec![I32(65536 - 8), V128(0)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+}
@@ -0,0 +1,100 @@
+use super::parse_wat;
+use crate::debugger::{BorrowedProgramState, ProgramCounter, ProgramState};
+use crate::engine::module::ModuleInstance;
+use crate::engine::stack::ValueStack;
+use crate::engine::*;
+use crate::value::Value::*;
+use std::sync::{Arc, Mutex};
+
+/// Records the state before every instruction
+#[derive(Debug, Default, Clone)]
+struct RecordingProgramCounter(Arc<Mutex<Vec<ProgramState>>>);
+
+impl ProgramCounter for RecordingProgramCounter {
+    fn set_pc(&mut self, state: BorrowedProgramState<'_>) -> Result<()> {
+        self.0.lock().unwrap().push(state.into());
+
+        Ok(())
+    }
+}
+
+fn instantiate(input: &str, debugger: Box<dyn ProgramCounter>) -> Engine {
+    let module = parse_wat(input);
+
+    let (instance, functions) = ModuleInstance::new(&module);
+    Engine::new(instance, &functions, &module, debugger, vec![]).unwrap()
+}
+
+#[test]
+fn test_value_stack_untagged_slots() {
+    let mut stack = ValueStack::default();
+
+    let values = vec![I32(-1), I64(i64::MIN), F32(-0.0), V128(u128::MAX - 1), F64(1.5)];
+    for value in values.iter() {
+        stack.push_value(*value);
+    }
+
+    // The upper bits of 32-bit values are zero
+    assert_eq!(0xffff_ffff, stack.get(0).unwrap());
+    assert_eq!(u64::from((-0.0f32).to_bits()), stack.get(2).unwrap());
+
+    // The high half of a `v128` is above its low half
+    assert_eq!(6, stack.len());
+    assert_eq!(u64::MAX - 1, stack.get(3).unwrap());
+    assert_eq!(u64::MAX, stack.get(4).unwrap());
+
+    let types = [ValueType::I32, ValueType::I64, ValueType::F32, ValueType::V128, ValueType::F64];
+    assert_eq!(values, stack.pop_values(&types).unwrap());
+    assert!(stack.is_empty());
+    assert!(stack.pop().is_err());
+}
+
+#[test]
+fn test_value_stack_keeps_nan_bits() {
+    let mut stack = ValueStack::default();
+    let nan = f32::from_bits(0x7fa0_0001);
+
+    stack.push_value(F32(nan));
+
+    match stack.pop_value(ValueType::F32).unwrap() {
+        F32(v) => assert_eq!(0x7fa0_0001, v.to_bits()),
+        x => panic!("Expected F32, but got {:?}", x),
+    }
+}
+
+#[test]
+fn test_program_state_exposes_stacks() {
+    let recorder = RecordingProgramCounter::default();
+    let mut engine = instantiate(
+        "(module
+    (func $add (param i32 i32) (result i32)
+      (local i64)
+      (block (result i32)
+        (i32.add (local.get 0) (local.get 1))))
+    (func (export \"main\") (result i32)
+      (call $add (i32.const 2) (i32.const 3))
+    ))",
+        Box::new(recorder.clone()),
+    );
+
+    let results = engine.invoke_exported_function_by_name("main", vec![]).unwrap();
+    assert_eq!(vec![I32(5)], results);
+
+    let states = recorder.0.lock().unwrap();
+
+    // The state before `i32.add` in the block of `$add`
+    let state = states
+        .iter()
+        .find(|state| state.get_frames().len() == 2 && state.get_stack().len() == 5)
+        .expect("No state inside of the callee");
+
+    // The arguments are the locals of the callee without copying
+    assert_eq!(&[2, 3, 0], state.get_locals());
+    assert_eq!(&[2, 3, 0, 2, 3], state.get_stack());
+    // The labels of `main`, `$add` and the block
+    assert_eq!(3, state.get_labels().len());
+    assert_eq!(3, state.get_labels()[2].get_height());
+
+    assert!(engine.store.stack.is_empty());
+    assert!(engine.store.labels.is_empty());
+}
@@ -0,0 +1,146 @@
+use super::{instantiate, instantiate_with_imports, invoke};
+use crate::engine::import_resolver::Import;
+use crate::engine::*;
+use crate::value::Value::*;
+
+const INPUT: &str = "(module
+    (type $i64_i64 (func (param i64 i64) (result i64)))
+    (table 2 funcref)
+    (elem (i32.const 0) $sum_indirect $even)
+    (func $sum (export \"sum\") (param i64 i64) (result i64)
+      (if (result i64) (i64.eqz (local.get 0))
+        (then (local.get 1))
+        (else
+          (return_call $sum
+            (i64.sub (local.get 0) (i64.const 1))
+            (i64.add (local.get 0) (local.get 1))))))
+    (func $sum_call (export \"sum_call\") (param i64 i64) (result i64)
+      (if (result i64) (i64.eqz (local.get 0))
+        (then (local.get 1))
+        (else
+          (call $sum_call
+            (i64.sub (local.get 0) (i64.const 1))
+            (i64.add (local.get 0) (local.get 1))))))
+    (func $sum_indirect (export \"sum_indirect\") (param i64 i64) (result i64)
+      (if (result i64) (i64.eqz (local.get 0))
+        (then (local.get 1))
+        (else
+          (return_call_indirect (type $i64_i64)
+            (i64.sub (local.get 0) (i64.const 1))
+            (i64.add (local.get 0) (local.get 1))
+            (i32.const 0)))))
+    (func $even (export \"even\") (param i64) (result i32)
+      (if (result i32) (i64.eqz (local.get 0))
+        (then (i32.const 1))
+        (else (return_call $odd (i64.sub (local.get 0) (i64.const 1))))))
+    (func $odd (export \"odd\") (param i64) (result i32)
+      (if (result i32) (i64.eqz (local.get 0))
+        (then (i32.const 0))
+        (else (return_call $even (i64.sub (local.get 0) (i64.const 1))))))
+    (func (export \"nested\") (param i32) (result i32)
+      (local i64 i64)
+      (i32.const 100)
+      (block (result i32)
+        (loop (result i32)
+          (return_call $id (local.get 0)))))
+    (func $id (param i32) (result i32)
+      (local.get 0))
+    (func (export \"wrong_type\") (result i64)
+      (return_call_indirect (type $i64_i64) (i64.const 0) (i64.const 0) (i32.const 1)))
+    (func (export \"trap\") (param i32) (result i32)
+      (return_call $div (i32.const 1) (local.get 0)))
+    (func $div (param i32 i32) (result i32)
+      (i32.div_u (local.get 0) (local.get 1)))
+    )";
+
+#[test]
+fn test_return_call() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I64(55)], invoke(&mut engine, "sum", vec![I64(10), I64(0)]).unwrap());
+}
+
+#[test]
+fn test_return_call_does_not_grow_the_call_stack() {
+    let mut engine = instantiate(INPUT);
+    engine.set_max_call_depth(100);
+
+    assert_eq!(
+        vec![I64(500_000_500_000)],
+        invoke(&mut engine, "sum", vec![I64(1_000_000), I64(0)]).unwrap()
+    );
+
+    // The same recursion without tail calls exhausts the call stack
+    let err = invoke(&mut engine, "sum_call", vec![I64(1_000), I64(0)]).unwrap_err();
+    assert_eq!(Some(Trap::CallStackExhausted), Trap::from_error(&err));
+}
+
+#[test]
+fn test_return_call_indirect() {
+    let mut engine = instantiate(INPUT);
+    engine.set_max_call_depth(100);
+
+    assert_eq!(
+        vec![I64(5_000_050_000)],
+        invoke(&mut engine, "sum_indirect", vec![I64(100_000), I64(0)]).unwrap()
+    );
+
+    let err = invoke(&mut engine, "wrong_type", vec![]).unwrap_err();
+    assert_eq!(Some(Trap::IndirectCallTypeMismatch), Trap::from_error(&err));
+}
+
+#[test]
+fn test_mutual_recursion() {
+    let mut engine = instantiate(INPUT);
+    engine.set_max_call_depth(100);
+
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "even", vec![I64(100_000)]).unwrap());
+    assert_eq!(vec![I32(0)], invoke(&mut engine, "even", vec![I64(100_001)]).unwrap());
+}
+
+#[test]
+fn test_return_call_leaves_blocks() {
+    let mut engine = instantiate(INPUT);
+
+    // The operands and labels of the caller are discarded
+    assert_eq!(vec![I32(7)], invoke(&mut engine, "nested", vec![I32(7)]).unwrap());
+}
+
+#[test]
+fn test_trap_in_tail_callee() {
+    let mut engine = instantiate(INPUT);
+
+    assert_eq!(vec![I32(1)], invoke(&mut engine, "trap", vec![I32(1)]).unwrap());
+
+    let err = invoke(&mut engine, "trap", vec![I32(0)]).unwrap_err();
+    assert_eq!(Some(Trap::IntegerDivideByZero), Trap::from_error(&err));
+}
+
+#[test]
+fn test_return_call_host_function() {
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "double".to_string(),
+        FunctionSignature {
+            param_types: vec![ValueType::I32],
+            return_types: vec![ValueType::I32],
+        },
+        Box::new(|_, args| match args {
+            [I32(a)] => Ok(vec![I32(a * 2)]),
+            _ => Err(anyhow!("Unexpected arguments {:?}", args)),
+        }),
+    )];
+
+    let mut engine = instantiate_with_imports(
+        "(module
+    (import \"env\" \"double\" (func $double (param i32) (result i32)))
+    (func $tail (param i32) (result i32)
+      (return_call $double (i32.add (local.get 0) (i32.const 1))))
+    (func (export \"main\") (param i32) (result i32)
+      (i32.add (call $tail (local.get 0)) (i32.const 1)))
+    )",
+        imports,
+    );
+
+    assert_eq!(vec![I32(9)], invoke(&mut engine, "main", vec![I32(3)]).unwrap());
+}
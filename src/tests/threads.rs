@@ -0,0 +1,262 @@
+use super::{try_instantiate, try_instantiate_with_imports};
+use crate::engine::import_resolver::{Import, Imports};
+use crate::engine::memory::{MemoryInstance, SharedMemory};
+use crate::engine::*;
+use crate::value::Value::*;
+use std::thread;
+
+fn import_memory(memory: &SharedMemory) -> Imports {
+    vec![Import::Memory(
+        "env".to_string(),
+        "memory".to_string(),
+        memory.clone(),
+    )]
+}
+
+fn bytes(memory: &SharedMemory, n: usize) -> Vec<u8> {
+    let mut buf = vec![0; n];
+    memory.read().read_bytes(0, &mut buf).unwrap();
+    buf
+}
+
+const INPUT: &str = "(module
+    (import \"env\" \"memory\" (memory 1 1 shared))
+    (func (export \"load\") (param i32) (result i32)
+      (i32.atomic.load (local.get 0)))
+    (func (export \"load16_u\") (param i32) (result i64)
+      (i64.atomic.load16_u (local.get 0)))
+    (func (export \"store\") (param i32 i32)
+      (i32.atomic.store (local.get 0) (local.get 1)))
+    (func (export \"store8\") (param i32 i64)
+      (i64.atomic.store8 (local.get 0) (local.get 1)))
+    (func (export \"add\") (param i32 i32) (result i32)
+      (i32.atomic.rmw.add (local.get 0) (local.get 1)))
+    (func (export \"sub8\") (param i32 i32) (result i32)
+      (i32.atomic.rmw8.sub_u (local.get 0) (local.get 1)))
+    (func (export \"xchg\") (param i32 i64) (result i64)
+      (i64.atomic.rmw.xchg (local.get 0) (local.get 1)))
+    (func (export \"cmpxchg\") (param i32 i32 i32) (result i32)
+      (i32.atomic.rmw.cmpxchg (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"cmpxchg16\") (param i32 i64 i64) (result i64)
+      (i64.atomic.rmw16.cmpxchg_u (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"wait\") (param i32 i32 i64) (result i32)
+      (memory.atomic.wait32 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export \"notify\") (param i32 i32) (result i32)
+      (memory.atomic.notify (local.get 0) (local.get 1)))
+    (func (export \"increment\") (param i32)
+      (loop $continue
+        (drop (i32.atomic.rmw.add (i32.const 0) (i32.const 1)))
+        (atomic.fence)
+        (br_if $continue (local.tee 0 (i32.sub (local.get 0) (i32.const 1))))))
+    )";
+
+#[test]
+fn test_shared_memory_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<SharedMemory>();
+}
+
+#[test]
+fn test_atomic_load_and_store() {
+    let memory = MemoryInstance::new_shared(1, 1).unwrap().shared();
+    let mut engine = try_instantiate_with_imports(INPUT, import_memory(&memory)).unwrap();
+
+    engine.invoke_exported_function_by_name("store", vec![I32(0), I32(-2)]).unwrap();
+    engine.invoke_exported_function_by_name("store8", vec![I32(5), I64(0x1ff)]).unwrap();
+
+    assert_eq!(&[0xfe, 0xff, 0xff, 0xff, 0, 0xff, 0], &bytes(&memory, 7)[..]);
+
+    let results = engine.invoke_exported_function_by_name("load", vec![I32(0)]).unwrap();
+    assert_eq!(vec![I32(-2)], results);
+
+    // Narrow loads are zero-extended
+    let results = engine.invoke_exported_function_by_name("load16_u", vec![I32(4)]).unwrap();
+    assert_eq!(vec![I64(0xff00)], results);
+}
+
+#[test]
+fn test_atomic_rmw() {
+    let memory = MemoryInstance::new_shared(1, 1).unwrap().shared();
+    let mut engine = try_instantiate_with_imports(INPUT, import_memory(&memory)).unwrap();
+
+    // The old value is returned
+    let results = engine.invoke_exported_function_by_name("add", vec![I32(0), I32(40)]).unwrap();
+    assert_eq!(vec![I32(0)], results);
+    let results = engine.invoke_exported_function_by_name("add", vec![I32(0), I32(2)]).unwrap();
+    assert_eq!(vec![I32(40)], results);
+    assert_eq!(&42u32.to_le_bytes(), &bytes(&memory, 4)[..]);
+
+    // Only the lowest byte wraps around
+    let results = engine.invoke_exported_function_by_name("sub8", vec![I32(1), I32(1)]).unwrap();
+    assert_eq!(vec![I32(0)], results);
+    assert_eq!(&[42, 0xff, 0, 0], &bytes(&memory, 4)[..]);
+
+    let results = engine.invoke_exported_function_by_name("xchg", vec![I32(8), I64(-1)]).unwrap();
+    assert_eq!(vec![I64(0)], results);
+    let results = engine.invoke_exported_function_by_name("xchg", vec![I32(8), I64(7)]).unwrap();
+    assert_eq!(vec![I64(-1)], results);
+}
+
+#[test]
+fn test_atomic_cmpxchg() {
+    let memory = MemoryInstance::new_shared(1, 1).unwrap().shared();
+    let mut engine = try_instantiate_with_imports(INPUT, import_memory(&memory)).unwrap();
+
+    // Not replaced, because the expected value does not match
+    let results = engine
+        .invoke_exported_function_by_name("cmpxchg", vec![I32(0), I32(1), I32(5)])
+        .unwrap();
+    assert_eq!(vec![I32(0)], results);
+    assert_eq!(&[0, 0, 0, 0], &bytes(&memory, 4)[..]);
+
+    let results = engine
+        .invoke_exported_function_by_name("cmpxchg", vec![I32(0), I32(0), I32(5)])
+        .unwrap();
+    assert_eq!(vec![I32(0)], results);
+    assert_eq!(&[5, 0, 0, 0], &bytes(&memory, 4)[..]);
+
+    // The expected value is wrapped to 16 bits
+    let results = engine
+        .invoke_exported_function_by_name("cmpxchg16", vec![I32(0), I64(0x10005), I64(0x1234)])
+        .unwrap();
+    assert_eq!(vec![I64(5)], results);
+    assert_eq!(&[0x34, 0x12, 0, 0], &bytes(&memory, 4)[..]);
+}
+
+#[test]
+fn test_unaligned_atomic() {
+    let memory = MemoryInstance::new_shared(1, 1).unwrap().shared();
+    let mut engine = try_instantiate_with_imports(INPUT, import_memory(&memory)).unwrap();
+
+    let err = engine.invoke_exported_function_by_name("load", vec![I32(2)]).unwrap_err();
+    assert_eq!(Some(Trap::UnalignedAtomic), Trap::from_error(&err));
+
+    let err = engine
+        .invoke_exported_function_by_name("add", vec![I32(1), I32(1)])
+        .unwrap_err();
+    assert_eq!(Some(Trap::UnalignedAtomic), Trap::from_error(&err));
+
+    let err = engine
+        .invoke_exported_function_by_name("notify", vec![I32(6), I32(1)])
+        .unwrap_err();
+    assert_eq!(Some(Trap::UnalignedAtomic), Trap::from_error(&err));
+
+    // Byte accesses are always aligned
+    assert!(engine.invoke_exported_function_by_name("sub8", vec![I32(3), I32(1)]).is_ok());
+
+    let err = engine.invoke_exported_function_by_name("load", vec![I32(65536)]).unwrap_err();
+    assert_eq!(Some(Trap::MemoryOutOfBounds), Trap::from_error(&err));
+}
+
+#[test]
+fn test_wait_without_notify() {
+    let memory = MemoryInstance::new_shared(1, 1).unwrap().shared();
+    let mut engine = try_instantiate_with_imports(INPUT, import_memory(&memory)).unwrap();
+
+    // The value does not match
+    let results = engine
+        .invoke_exported_function_by_name("wait", vec![I32(0), I32(1), I64(-1)])
+        .unwrap();
+    assert_eq!(vec![I32(1)], results);
+
+    // 1ms
+    let results = engine
+        .invoke_exported_function_by_name("wait", vec![I32(0), I32(0), I64(1_000_000)])
+        .unwrap();
+    assert_eq!(vec![I32(2)], results);
+
+    // Nobody is waiting
+    let results = engine.invoke_exported_function_by_name("notify", vec![I32(0), I32(1)]).unwrap();
+    assert_eq!(vec![I32(0)], results);
+}
+
+#[test]
+fn test_wait_on_unshared_memory() {
+    let mut engine = try_instantiate(
+        "(module
+    (memory 1)
+    (func (export \"wait\") (result i32)
+      (memory.atomic.wait32 (i32.const 0) (i32.const 0) (i64.const -1)))
+    (func (export \"notify\") (result i32)
+      (memory.atomic.notify (i32.const 0) (i32.const 1)))
+    )",
+    )
+    .unwrap();
+
+    let err = engine.invoke_exported_function_by_name("wait", vec![]).unwrap_err();
+    assert_eq!(Some(Trap::ExpectedSharedMemory), Trap::from_error(&err));
+
+    let results = engine.invoke_exported_function_by_name("notify", vec![]).unwrap();
+    assert_eq!(vec![I32(0)], results);
+}
+
+#[test]
+fn test_import_shared_memory_mismatch() {
+    let memory = MemoryInstance::new(1, Some(1)).unwrap().shared();
+    assert!(try_instantiate_with_imports(INPUT, import_memory(&memory)).is_err());
+
+    let memory = MemoryInstance::new_shared(1, 1).unwrap().shared();
+    assert!(try_instantiate_with_imports("(module (import \"env\" \"memory\" (memory 1 1)))", import_memory(&memory)).is_err());
+}
+
+#[test]
+fn test_engines_on_several_threads() {
+    const THREADS: usize = 4;
+    const ITERATIONS: i32 = 1_000;
+
+    let memory = MemoryInstance::new_shared(1, 1).unwrap().shared();
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let memory = memory.clone();
+
+            thread::spawn(move || {
+                let mut engine = try_instantiate_with_imports(INPUT, import_memory(&memory)).unwrap();
+
+                engine
+                    .invoke_exported_function_by_name("increment", vec![I32(ITERATIONS)])
+                    .unwrap();
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let expected = THREADS as u32 * ITERATIONS as u32;
+    assert_eq!(&expected.to_le_bytes(), &bytes(&memory, 4)[..]);
+}
+
+#[test]
+fn test_wait_and_notify() {
+    let memory = MemoryInstance::new_shared(1, 1).unwrap().shared();
+
+    let waiter = {
+        let memory = memory.clone();
+
+        thread::spawn(move || {
+            let mut engine = try_instantiate_with_imports(INPUT, import_memory(&memory)).unwrap();
+
+            engine
+                .invoke_exported_function_by_name("wait", vec![I32(8), I32(0), I64(-1)])
+                .unwrap()
+        })
+    };
+
+    let mut engine = try_instantiate_with_imports(INPUT, import_memory(&memory)).unwrap();
+
+    // Retry until the other thread is suspended
+    loop {
+        let results = engine.invoke_exported_function_by_name("notify", vec![I32(8), I32(1)]).unwrap();
+
+        if results == vec![I32(1)] {
+            break;
+        }
+
+        thread::yield_now();
+    }
+
+    assert_eq!(vec![I32(0)], waiter.join().unwrap());
+}
@@ -0,0 +1,376 @@
+use super::{instantiate, instantiate_with_imports};
+use crate::engine::import_resolver::{Import, Imports};
+use crate::engine::*;
+use crate::value::Value;
+use crate::value::Value::*;
+
+/// Invokes `main` of `input` and returns the trap
+fn run(input: &str, args: Vec<Value>, imports: Imports) -> Option<Trap> {
+    let mut engine = instantiate_with_imports(input, imports);
+
+    let err = engine
+        .invoke_exported_function_by_name("main", args)
+        .expect_err("Expected a trap");
+
+    Trap::from_error(&err)
+}
+
+/// Invokes `main` of a module with the body `instr` and the memory `(memory 1)`
+fn trap(params: &str, results: &str, instr: &str, args: Vec<Value>) -> Option<Trap> {
+    let input = format!(
+        "(module
+    (memory 1)
+    (func (export \"main\") (param {}) (result {})
+      {}
+    ))",
+        params, results, instr
+    );
+
+    run(&input, args, vec![])
+}
+
+#[test]
+fn test_trap_unreachable() {
+    assert_eq!(Some(Trap::Unreachable), trap("", "", "(unreachable)", vec![]));
+}
+
+#[test]
+fn test_trap_integer_divide_by_zero() {
+    for op in &["i32.div_s", "i32.div_u", "i32.rem_s", "i32.rem_u"] {
+        let instr = format!("({} (local.get 0) (local.get 1))", op);
+
+        assert_eq!(
+            Some(Trap::IntegerDivideByZero),
+            trap("i32 i32", "i32", &instr, vec![I32(1), I32(0)]),
+            "{}",
+            op
+        );
+    }
+
+    for op in &["i64.div_s", "i64.div_u", "i64.rem_s", "i64.rem_u"] {
+        let instr = format!("({} (local.get 0) (local.get 1))", op);
+
+        assert_eq!(
+            Some(Trap::IntegerDivideByZero),
+            trap("i64 i64", "i64", &instr, vec![I64(1), I64(0)]),
+            "{}",
+            op
+        );
+    }
+}
+
+#[test]
+fn test_value_divide_by_zero() {
+    for result in &[I32(1) / I32(0), I64(1) / I64(0), I32(1) % I32(0), I64(1) % I64(0)] {
+        let err = result.as_ref().unwrap_err();
+        assert_eq!(Some(Trap::IntegerDivideByZero), Trap::from_error(err));
+    }
+
+    assert_eq!(I32(3), (I32(7) / I32(2)).unwrap());
+    assert_eq!(I64(1), (I64(7) % I64(2)).unwrap());
+}
+
+#[test]
+fn test_trap_integer_overflow() {
+    assert_eq!(
+        Some(Trap::IntegerOverflow),
+        trap(
+            "i32 i32",
+            "i32",
+            "(i32.div_s (local.get 0) (local.get 1))",
+            vec![I32(i32::MIN), I32(-1)]
+        )
+    );
+    assert_eq!(
+        Some(Trap::IntegerOverflow),
+        trap(
+            "i64 i64",
+            "i64",
+            "(i64.div_s (local.get 0) (local.get 1))",
+            vec![I64(i64::MIN), I64(-1)]
+        )
+    );
+    assert_eq!(
+        Some(Trap::IntegerOverflow),
+        trap("f32", "i32", "(i32.trunc_f32_s (local.get 0))", vec![F32(2147483648.0)])
+    );
+    assert_eq!(
+        Some(Trap::IntegerOverflow),
+        trap("f64", "i32", "(i32.trunc_f64_u (local.get 0))", vec![F64(-1.0)])
+    );
+    assert_eq!(
+        Some(Trap::IntegerOverflow),
+        trap("f64", "i64", "(i64.trunc_f64_s (local.get 0))", vec![F64(9223372036854775808.0)])
+    );
+}
+
+#[test]
+fn test_trap_invalid_conversion_to_integer() {
+    assert_eq!(
+        Some(Trap::InvalidConversionToInteger),
+        trap("f32", "i32", "(i32.trunc_f32_s (local.get 0))", vec![F32(f32::NAN)])
+    );
+    assert_eq!(
+        Some(Trap::InvalidConversionToInteger),
+        trap("f64", "i64", "(i64.trunc_f64_u (local.get 0))", vec![F64(f64::NAN)])
+    );
+}
+
+#[test]
+fn test_trap_memory_out_of_bounds() {
+    assert_eq!(
+        Some(Trap::MemoryOutOfBounds),
+        trap("", "i32", "(i32.load (i32.const 65534))", vec![])
+    );
+    assert_eq!(
+        Some(Trap::MemoryOutOfBounds),
+        trap("", "i64", "(i64.load8_u (i32.const 65536))", vec![])
+    );
+    assert_eq!(
+        Some(Trap::MemoryOutOfBounds),
+        trap("", "", "(i64.store (i32.const 65530) (i64.const 1))", vec![])
+    );
+    assert_eq!(
+        Some(Trap::MemoryOutOfBounds),
+        trap("", "", "(i32.store16 (i32.const 65535) (i32.const 1))", vec![])
+    );
+}
+
+#[test]
+fn test_trap_memory_effective_address_wraps() {
+    // `base + offset` is larger than `u32::MAX`
+    assert_eq!(
+        Some(Trap::MemoryOutOfBounds),
+        trap("", "i32", "(i32.load offset=4294967295 (i32.const 1))", vec![])
+    );
+    assert_eq!(
+        Some(Trap::MemoryOutOfBounds),
+        trap("", "i32", "(i32.load8_s offset=1 (i32.const -1))", vec![])
+    );
+    assert_eq!(
+        Some(Trap::MemoryOutOfBounds),
+        trap("", "", "(f64.store offset=4294967295 (i32.const -1) (f64.const 1))", vec![])
+    );
+}
+
+#[test]
+fn test_memory_last_bytes_in_bounds() {
+    let mut engine = instantiate(
+        "(module
+    (memory 1)
+    (func (export \"main\") (result i32)
+      (i64.store32 offset=65532 (i32.const 0) (i64.const 1))
+      (i32.store8 offset=65534 (i32.const 1) (i32.const 255))
+      (i32.add
+        (i32.load8_u (i32.const 65535))
+        (i32.load16_s offset=65532 (i32.const 0)))
+    ))",
+    );
+
+    let results = engine.invoke_exported_function_by_name("main", vec![]).unwrap();
+
+    assert_eq!(vec![I32(256)], results);
+}
+
+#[test]
+fn test_trap_indirect_call_elements() {
+    let input = "(module
+    (type $t (func))
+    (table 2 funcref)
+    (func $f)
+    (elem (i32.const 0) $f)
+    (func (export \"main\") (param i32)
+      (call_indirect (type $t) (local.get 0))
+    ))";
+
+    assert_eq!(Some(Trap::UninitializedElement), run(input, vec![I32(1)], vec![]));
+    assert_eq!(Some(Trap::UndefinedElement), run(input, vec![I32(2)], vec![]));
+    assert_eq!(Some(Trap::UndefinedElement), run(input, vec![I32(-1)], vec![]));
+}
+
+#[test]
+fn test_trap_indirect_call_type_mismatch() {
+    let input = "(module
+    (type $i32 (func (result i32)))
+    (type $i64 (func (result i64)))
+    (table 2 funcref)
+    (func $f (result i64) (i64.const 1))
+    (func $g (result i32) (i32.const 2))
+    (elem (i32.const 0) $f $g)
+    (func (export \"main\") (param i32) (result i32)
+      (call_indirect (type $i32) (local.get 0))
+    ))";
+
+    assert_eq!(Some(Trap::IndirectCallTypeMismatch), run(input, vec![I32(0)], vec![]));
+
+    // The type immediate is not a function index
+    let mut engine = instantiate(input);
+    let results = engine.invoke_exported_function_by_name("main", vec![I32(1)]).unwrap();
+
+    assert_eq!(vec![I32(2)], results);
+}
+
+#[test]
+fn test_indirect_call_structural_type() {
+    // `$a` and `$b` are different type indices, but the same signature
+    let mut engine = instantiate(
+        "(module
+    (type $a (func (param i32) (result i32)))
+    (type $b (func (param i32) (result i32)))
+    (table 1 funcref)
+    (func $f (type $a) (i32.add (local.get 0) (i32.const 1)))
+    (elem (i32.const 0) $f)
+    (func (export \"main\") (param i32) (result i32)
+      (call_indirect (type $b) (local.get 0) (i32.const 0))
+    ))",
+    );
+
+    let results = engine.invoke_exported_function_by_name("main", vec![I32(41)]).unwrap();
+
+    assert_eq!(vec![I32(42)], results);
+}
+
+#[test]
+fn test_trap_call_stack_exhausted() {
+    let input = "(module
+    (func $f (export \"main\") (param i32) (result i32)
+      (block (result i32)
+        (call $f (i32.add (local.get 0) (i32.const 1))))
+    ))";
+
+    assert_eq!(Some(Trap::CallStackExhausted), run(input, vec![I32(0)], vec![]));
+
+    let mut engine = instantiate(input);
+    engine.set_max_call_depth(10);
+
+    let err = engine
+        .invoke_exported_function_by_name("main", vec![I32(0)])
+        .unwrap_err();
+    assert_eq!(Some(Trap::CallStackExhausted), Trap::from_error(&err));
+}
+
+#[test]
+fn test_default_call_depth() {
+    let mut engine = instantiate(
+        "(module
+    (global $depth (export \"depth\") (mut i32) (i32.const 0))
+    (func $f (export \"main\")
+      (global.set $depth (i32.add (global.get $depth) (i32.const 1)))
+      (call $f)
+    ))",
+    );
+
+    // The default limit is reached on the default stack of the test's thread
+    let err = engine.invoke_exported_function_by_name("main", vec![]).unwrap_err();
+    assert_eq!(Some(Trap::CallStackExhausted), Trap::from_error(&err));
+    assert_eq!(I32(DEFAULT_MAX_CALL_DEPTH as i32), engine.store.globals[0].val);
+}
+
+#[test]
+fn test_deep_recursion() {
+    let mut engine = instantiate(
+        "(module
+    (func $count (export \"main\") (param i32) (result i32)
+      (if (result i32) (i32.eqz (local.get 0))
+        (then (i32.const 0))
+        (else
+          (i32.add
+            (i32.const 1)
+            (call $count (i32.sub (local.get 0) (i32.const 1))))))
+    ))",
+    );
+
+    // The calls do not recurse on the host's stack
+    let results = engine
+        .invoke_exported_function_by_name("main", vec![I32(50_000)])
+        .unwrap();
+
+    assert_eq!(vec![I32(50_000)], results);
+    assert!(engine.store.stack.is_empty());
+}
+
+#[test]
+fn test_trap_stack_size_exhausted() {
+    let mut engine = instantiate(
+        "(module
+    (func $f (export \"main\") (param i32) (result i32)
+      (i32.const 1)
+      (i32.const 2)
+      (i32.const 3)
+      (drop (call $f (local.get 0)))
+      (drop)
+      (drop)
+    ))",
+    );
+    engine.set_max_stack_size(100);
+
+    let err = engine
+        .invoke_exported_function_by_name("main", vec![I32(0)])
+        .unwrap_err();
+    assert_eq!(Some(Trap::CallStackExhausted), Trap::from_error(&err));
+}
+
+#[test]
+fn test_trap_host_error() {
+    let imports = vec![Import::Function(
+        "env".to_string(),
+        "fail".to_string(),
+        FunctionSignature {
+            param_types: vec![],
+            return_types: vec![],
+        },
+        Box::new(|_, _| Err(anyhow!("Host failed"))),
+    )];
+
+    let input = "(module
+    (import \"env\" \"fail\" (func $fail))
+    (func (export \"main\")
+      (call $fail)
+    ))";
+
+    assert_eq!(Some(Trap::HostError), run(input, vec![], imports));
+}
+
+#[test]
+fn test_engine_reusable_after_trap() {
+    let mut engine = instantiate(
+        "(module
+    (func (export \"main\") (param i32) (result i32)
+      (i32.const 1)
+      (block (result i32)
+        (i32.div_u (i32.const 10) (local.get 0)))
+      (i32.add)
+    ))",
+    );
+
+    for _ in 0..3 {
+        let err = engine
+            .invoke_exported_function_by_name("main", vec![I32(0)])
+            .unwrap_err();
+        assert_eq!(Some(Trap::IntegerDivideByZero), Trap::from_error(&err));
+        assert!(engine.store.stack.is_empty());
+
+        let results = engine
+            .invoke_exported_function_by_name("main", vec![I32(2)])
+            .unwrap();
+        assert_eq!(vec![I32(6)], results);
+        assert!(engine.store.stack.is_empty());
+    }
+}
+
+#[test]
+fn test_invoke_returns_all_results() {
+    let mut engine = instantiate(
+        "(module
+    (func (export \"main\") (result i32 i64 f32)
+      (i32.const 1)
+      (i64.const 2)
+      (f32.const 3)
+    ))",
+    );
+
+    let results = engine.invoke_exported_function_by_name("main", vec![]).unwrap();
+
+    assert_eq!(vec![I32(1), I64(2), F32(3.0)], results);
+    assert!(engine.store.stack.is_empty());
+}
@@ -0,0 +1,61 @@
+use super::instantiate;
+use crate::engine::*;
+
+const INPUT: &str = "(module
+    (func (export \"add\") (param i32 i32) (result i32)
+      (i32.add (local.get 0) (local.get 1)))
+    (func (export \"swap\") (param f32 i64) (result i64 f32)
+      (local.get 1)
+      (local.get 0))
+    (func (export \"nop\"))
+    (func (export \"div\") (param i64) (result i64)
+      (i64.div_s (i64.const 10) (local.get 0)))
+    )";
+
+#[test]
+fn test_typed_func_call() {
+    let mut engine = instantiate(INPUT);
+
+    let add = engine.get_typed_func::<(i32, i32), i32>("add").unwrap();
+    assert_eq!(42, add.call(&mut engine, (40, 2)).unwrap());
+    assert_eq!(i32::MIN, add.call(&mut engine, (i32::MAX, 1)).unwrap());
+
+    let swap = engine
+        .get_typed_func::<(f32, i64), (i64, f32)>("swap")
+        .unwrap();
+    assert_eq!((7, 1.5), swap.call(&mut engine, (1.5, 7)).unwrap());
+
+    let nop = engine.get_typed_func::<(), ()>("nop").unwrap();
+    nop.call(&mut engine, ()).unwrap();
+
+    // A single parameter does not need a tuple
+    let div = engine.get_typed_func::<i64, i64>("div").unwrap();
+    assert_eq!(5, div.call(&mut engine, 2).unwrap());
+}
+
+#[test]
+fn test_typed_func_signature_mismatch() {
+    let engine = instantiate(INPUT);
+
+    assert!(engine.get_typed_func::<(i32, i64), i32>("add").is_err());
+    assert!(engine.get_typed_func::<(i32, i32), i64>("add").is_err());
+    assert!(engine.get_typed_func::<(i32, i32), ()>("add").is_err());
+    assert!(engine
+        .get_typed_func::<(i32, i32, i32), i32>("add")
+        .is_err());
+    assert!(engine
+        .get_typed_func::<(f32, i64), (f32, i64)>("swap")
+        .is_err());
+    assert!(engine.get_typed_func::<(), ()>("unknown").is_err());
+}
+
+#[test]
+fn test_typed_func_trap() {
+    let mut engine = instantiate(INPUT);
+
+    let div = engine.get_typed_func::<i64, i64>("div").unwrap();
+
+    let err = div.call(&mut engine, 0).unwrap_err();
+    assert_eq!(Some(Trap::IntegerDivideByZero), Trap::from_error(&err));
+    assert_eq!(-10, div.call(&mut engine, -1).unwrap());
+}
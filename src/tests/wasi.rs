@@ -0,0 +1,342 @@
+use super::try_instantiate_module;
+use crate::engine::config::Config;
+use crate::engine::*;
+use crate::wasi::{self, WasiCtx};
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+use validation::validate;
+use wasm_parser::{parse, read_wasm};
+
+/// Captures the output of the guest
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+/// Runs `_start` of the module and returns the result and stdout
+fn run(file: Vec<u8>, mut ctx: WasiCtx) -> (Result<()>, String) {
+    let module = parse(file).expect("Parsing failed");
+    assert!(validate(&module).is_ok());
+
+    let stdout = SharedBuffer::default();
+    ctx.set_stdout(Box::new(stdout.clone()));
+
+    let imports = wasi::imports(&Rc::new(RefCell::new(ctx)));
+
+    let result = try_instantiate_module(&module, imports, Config::default())
+        .and_then(|mut engine| engine.invoke_exported_function_by_name("_start", vec![]))
+        .map(|_| ());
+
+    (result, stdout.contents())
+}
+
+fn run_wat(input: &str, ctx: WasiCtx) -> (Result<()>, String) {
+    run(
+        wat::parse_str(input).expect("Converting wat to wasm failed"),
+        ctx,
+    )
+}
+
+#[test]
+fn test_wasi_test_wasm() {
+    let (result, stdout) = run(
+        read_wasm!("tests/wasi_test.wasm"),
+        WasiCtx::new(vec!["wasi_test.wasm".to_string()], vec![]),
+    );
+
+    assert!(result.is_ok());
+    assert_eq!("2\n", stdout);
+}
+
+#[test]
+fn test_wasi_fd_write() {
+    let (result, stdout) = run_wat(
+        "(module
+    (import \"wasi_snapshot_preview1\" \"fd_write\"
+      (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (memory (export \"memory\") 1)
+    (data (i32.const 0) \"\\10\\00\\00\\00\\06\\00\\00\\00\")
+    (data (i32.const 16) \"Hello\\n\")
+    (func (export \"_start\")
+      (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 32)))
+    ))",
+        WasiCtx::new(vec![], vec![]),
+    );
+
+    assert!(result.is_ok());
+    assert_eq!("Hello\n", stdout);
+}
+
+#[test]
+fn test_wasi_args_and_environ() {
+    // Prints the first argument and the environment
+    let (result, stdout) = run_wat(
+        "(module
+    (import \"wasi_snapshot_preview1\" \"args_sizes_get\"
+      (func $args_sizes_get (param i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"args_get\"
+      (func $args_get (param i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"environ_sizes_get\"
+      (func $environ_sizes_get (param i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"environ_get\"
+      (func $environ_get (param i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"fd_write\"
+      (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (memory (export \"memory\") 1)
+    (func (export \"_start\")
+      ;; args at 100, environ at 200
+      (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))
+      (drop (call $args_get (i32.const 8) (i32.const 100)))
+      (drop (call $environ_sizes_get (i32.const 16) (i32.const 20)))
+      (drop (call $environ_get (i32.const 24) (i32.const 200)))
+      ;; ciovec of the whole args buffer
+      (i32.store (i32.const 32) (i32.const 100))
+      (i32.store (i32.const 36) (i32.load (i32.const 4)))
+      ;; ciovec of the whole environ buffer
+      (i32.store (i32.const 40) (i32.const 200))
+      (i32.store (i32.const 44) (i32.load (i32.const 20)))
+      (drop (call $fd_write (i32.const 1) (i32.const 32) (i32.const 2) (i32.const 48)))
+    ))",
+        WasiCtx::new(
+            vec!["prog".to_string(), "arg".to_string()],
+            vec![("KEY".to_string(), "VALUE".to_string())],
+        ),
+    );
+
+    assert!(result.is_ok());
+    assert_eq!("prog\0arg\0KEY=VALUE\0", stdout);
+}
+
+#[test]
+fn test_wasi_proc_exit() {
+    let (result, _) = run_wat(
+        "(module
+    (import \"wasi_snapshot_preview1\" \"proc_exit\" (func $proc_exit (param i32)))
+    (memory (export \"memory\") 1)
+    (func (export \"_start\")
+      (call $proc_exit (i32.const 3))
+      (unreachable)
+    ))",
+        WasiCtx::new(vec![], vec![]),
+    );
+
+    assert_eq!(Some(3), wasi::exit_code(&result.unwrap_err()));
+}
+
+#[test]
+fn test_wasi_buffer_out_of_bounds() {
+    // Returns the errno of `fd_read` as exit code
+    let (result, _) = run_wat(
+        "(module
+    (import \"wasi_snapshot_preview1\" \"random_get\"
+      (func $random_get (param i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"fd_read\"
+      (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"proc_exit\" (func $proc_exit (param i32)))
+    (memory (export \"memory\") 1)
+    (func (export \"_start\")
+      (if (i32.ne (call $random_get (i32.const 16) (i32.const 0x7fffffff)) (i32.const 21))
+        (then (unreachable)))
+      ;; iovec of 2 GiB at 16
+      (i32.store (i32.const 0) (i32.const 16))
+      (i32.store (i32.const 4) (i32.const 0x7fffffff))
+      (call $proc_exit (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+    ))",
+        WasiCtx::new(vec![], vec![]),
+    );
+
+    assert_eq!(
+        Some(wasi::errno::FAULT as i32),
+        wasi::exit_code(&result.unwrap_err())
+    );
+}
+
+#[test]
+fn test_wasi_iovs_len_overflow() {
+    // Returns the errno of `fd_write` as exit code
+    let (result, stdout) = run_wat(
+        "(module
+    (import \"wasi_snapshot_preview1\" \"fd_write\"
+      (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"proc_exit\" (func $proc_exit (param i32)))
+    (memory (export \"memory\") 1)
+    (data (i32.const 32) \"Hello\\n\")
+    ;; The lengths of the ciovecs add up to more than 4 GiB
+    (data (i32.const 0) \"\\20\\00\\00\\00\\06\\00\\00\\00\")
+    (data (i32.const 8) \"\\20\\00\\00\\00\\ff\\ff\\ff\\ff\")
+    (func (export \"_start\")
+      (call $proc_exit (call $fd_write (i32.const 1) (i32.const 0) (i32.const 2) (i32.const 16)))
+    ))",
+        WasiCtx::new(vec![], vec![]),
+    );
+
+    // Nothing is written
+    assert_eq!(
+        Some(wasi::errno::INVAL as i32),
+        wasi::exit_code(&result.unwrap_err())
+    );
+    assert_eq!("", stdout);
+}
+
+#[test]
+fn test_wasi_path_open() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("file.txt"), "content").unwrap();
+
+    let mut ctx = WasiCtx::new(vec![], vec![]);
+    ctx.preopen_dir(dir.path(), "/sandbox").unwrap();
+
+    // Opens `file.txt` in the preopened directory (fd 3) and prints it
+    let (result, stdout) = run_wat(
+        "(module
+    (import \"wasi_snapshot_preview1\" \"path_open\"
+      (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"fd_read\"
+      (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"fd_write\"
+      (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"fd_close\"
+      (func $fd_close (param i32) (result i32)))
+    (memory (export \"memory\") 1)
+    (data (i32.const 0) \"file.txt\")
+    (func (export \"_start\")
+      (if (call $path_open (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 8)
+            (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 16))
+        (then (unreachable)))
+      ;; iovec of 100 bytes at 100
+      (i32.store (i32.const 32) (i32.const 100))
+      (i32.store (i32.const 36) (i32.const 100))
+      (drop (call $fd_read (i32.load (i32.const 16)) (i32.const 32) (i32.const 1) (i32.const 40)))
+      (i32.store (i32.const 36) (i32.load (i32.const 40)))
+      (drop (call $fd_write (i32.const 1) (i32.const 32) (i32.const 1) (i32.const 44)))
+      (if (call $fd_close (i32.load (i32.const 16)))
+        (then (unreachable)))
+    ))",
+        ctx,
+    );
+
+    assert!(result.is_ok());
+    assert_eq!("content", stdout);
+}
+
+#[test]
+fn test_wasi_path_open_escape() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut ctx = WasiCtx::new(vec![], vec![]);
+    ctx.preopen_dir(dir.path(), "/sandbox").unwrap();
+
+    // Returns the errno as exit code
+    let (result, _) = run_wat(
+        "(module
+    (import \"wasi_snapshot_preview1\" \"path_open\"
+      (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"proc_exit\" (func $proc_exit (param i32)))
+    (memory (export \"memory\") 1)
+    (data (i32.const 0) \"../secret\")
+    (func (export \"_start\")
+      (call $proc_exit (call $path_open (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 9)
+            (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 16)))
+    ))",
+        ctx,
+    );
+
+    assert_eq!(
+        Some(wasi::errno::NOTCAPABLE as i32),
+        wasi::exit_code(&result.unwrap_err())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_wasi_path_open_symlink_escape() {
+    use std::os::unix::fs::symlink;
+
+    let outside = tempfile::tempdir().unwrap();
+    std::fs::write(outside.path().join("secret"), "secret").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("file.txt"), "content").unwrap();
+    symlink(outside.path().join("secret"), dir.path().join("secret")).unwrap();
+    symlink(outside.path(), dir.path().join("outside")).unwrap();
+    symlink(dir.path().join("file.txt"), dir.path().join("inside")).unwrap();
+
+    // Returns the errno of opening the path with the lookup flags as exit code
+    let open = |path: &str, lookupflags: i32| {
+        let mut ctx = WasiCtx::new(vec![], vec![]);
+        ctx.preopen_dir(dir.path(), "/sandbox").unwrap();
+
+        let (result, _) = run_wat(
+            &format!(
+                "(module
+    (import \"wasi_snapshot_preview1\" \"path_open\"
+      (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"proc_exit\" (func $proc_exit (param i32)))
+    (memory (export \"memory\") 1)
+    (data (i32.const 0) \"{}\")
+    (func (export \"_start\")
+      (call $proc_exit (call $path_open (i32.const 3) (i32.const {}) (i32.const 0) (i32.const {})
+            (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 64)))
+    ))",
+                path,
+                lookupflags,
+                path.len()
+            ),
+            ctx,
+        );
+
+        wasi::exit_code(&result.unwrap_err())
+    };
+
+    assert_eq!(Some(wasi::errno::NOTCAPABLE as i32), open("secret", 1));
+    assert_eq!(Some(wasi::errno::NOTCAPABLE as i32), open("outside/secret", 1));
+    assert_eq!(Some(wasi::errno::SUCCESS as i32), open("inside", 1));
+
+    // Without SYMLINK_FOLLOW the symlink itself is not opened
+    assert_eq!(Some(wasi::errno::LOOP as i32), open("inside", 0));
+    assert_eq!(Some(wasi::errno::SUCCESS as i32), open("file.txt", 0));
+}
+
+#[test]
+fn test_wasi_clock_time_get() {
+    // Returns the errno of `clock_time_get` of the clock as exit code
+    let clock = |id: i32| {
+        let (result, _) = run_wat(
+            &format!(
+                "(module
+    (import \"wasi_snapshot_preview1\" \"clock_time_get\"
+      (func $clock_time_get (param i32 i64 i32) (result i32)))
+    (import \"wasi_snapshot_preview1\" \"proc_exit\" (func $proc_exit (param i32)))
+    (memory (export \"memory\") 1)
+    (func (export \"_start\")
+      (call $proc_exit (call $clock_time_get (i32.const {}) (i64.const 1) (i32.const 0)))
+    ))",
+                id
+            ),
+            WasiCtx::new(vec![], vec![]),
+        );
+
+        wasi::exit_code(&result.unwrap_err())
+    };
+
+    assert_eq!(Some(wasi::errno::SUCCESS as i32), clock(0));
+    assert_eq!(Some(wasi::errno::SUCCESS as i32), clock(1));
+    assert_eq!(Some(wasi::errno::NOTSUP as i32), clock(2));
+    assert_eq!(Some(wasi::errno::NOTSUP as i32), clock(3));
+    assert_eq!(Some(wasi::errno::INVAL as i32), clock(4));
+}
@@ -2,7 +2,6 @@ use crate::debugger::RelativeProgramCounter;
 use crate::engine::export::ExportInstance;
 use crate::engine::module::ModuleInstance;
 use crate::engine::*;
-use crate::engine::stack::StackContent;
 use crate::value::Value::*;
 use crate::value::*;
 use crate::wrap_instructions;
@@ -24,7 +23,7 @@ macro_rules! test_file_engine {
             &functions,
             &module,
             Box::new(RelativeProgramCounter::default()),
-            &imports
+            imports
         ).unwrap();
 
         assert_snapshot!($fs_name, format!("{:#?}", engine));
@@ -45,21 +44,24 @@ macro_rules! test_run_engine {
             &functions,
             &module,
             Box::new(crate::debugger::RelativeProgramCounter::default()),
-            &imports,
+            imports,
         ).unwrap();
 
         assert_snapshot!($fs_name, format!("{:#?}", engine));
 
-        if let Err(err) = engine.invoke_exported_function($num_f, $init) {
-            error!("ERROR: {}", err);
-            err.chain()
-                .skip(1)
-                .for_each(|cause| error!("because: {}", cause));
+        let results = match engine.invoke_exported_function($num_f, $init) {
+            Ok(results) => results,
+            Err(err) => {
+                error!("ERROR: {}", err);
+                err.chain()
+                    .skip(1)
+                    .for_each(|cause| error!("because: {}", cause));
 
-            panic!("Test failed");
-        }
+                panic!("Test failed");
+            }
+        };
 
-        engine
+        (engine, results)
     }};
 }
 
@@ -77,7 +79,7 @@ macro_rules! test_get_exported_global {
             &functions,
             &module,
             Box::new(crate::debugger::RelativeProgramCounter::default()),
-            &imports
+            imports
         ).unwrap();
 
         assert_snapshot!($fs_name, format!("{:#?}", engine));
@@ -100,7 +102,7 @@ macro_rules! allocation {
             &functions,
             &module,
             Box::new(RelativeProgramCounter::default()),
-            &imports
+            imports
         );
 
         engine.unwrap()
@@ -141,7 +143,7 @@ fn test_allocation_funcs() {
     assert_eq!(1, mi.get_fn_types().len());
     assert_eq!(sig, mi.get_fn_types()[0]);
     assert_eq!(1, mi.get_func_addrs().len());
-    assert_eq!(0, mi.get_func_addrs().get(0).unwrap().get());
+    assert_eq!(0, mi.get_func_addrs().first().unwrap().get());
 
     // Store has an entry for func instance
 
@@ -154,8 +156,8 @@ fn test_allocation_funcs() {
 fn test_allocation_tables_zero() {
     let engine = allocation!(vec![Section::Table(TableSection {
         entries: vec![TableType {
-            element_type: 0x70,
-            limits: Limits::Zero(10)
+            element_type: ValueType::FuncRef,
+            limits: Limits::Zero(10),
         }]
     })]);
 
@@ -163,11 +165,11 @@ fn test_allocation_tables_zero() {
     // Store has a table instance
 
     assert_eq!(1, engine.module_instance.get_table_addrs().len());
-    assert_eq!(0, engine.module_instance.get_table_addrs().get(0).unwrap().get());
+    assert_eq!(0, engine.module_instance.get_table_addrs().first().unwrap().get());
 
     assert_eq!(1, engine.store.tables.len());
     assert_eq!(10, engine.store.tables[0].elem.len());
-    assert!(engine.store.tables[0].elem.iter().all(|w| w == &None));
+    assert!(engine.store.tables[0].elem.iter().all(|w| w.is_none()));
     assert_eq!(None, engine.store.tables[0].max);
 }
 
@@ -175,8 +177,8 @@ fn test_allocation_tables_zero() {
 fn test_allocation_tables_one() {
     let engine = allocation!(vec![Section::Table(TableSection {
         entries: vec![TableType {
-            element_type: 0x70,
-            limits: Limits::One(10, 20)
+            element_type: ValueType::FuncRef,
+            limits: Limits::One(10, 20),
         }]
     })]);
 
@@ -184,11 +186,11 @@ fn test_allocation_tables_one() {
     // Store has a table instance
 
     assert_eq!(1, engine.module_instance.get_table_addrs().len());
-    assert_eq!(0, engine.module_instance.get_table_addrs().get(0).unwrap().get());
+    assert_eq!(0, engine.module_instance.get_table_addrs().first().unwrap().get());
 
     assert_eq!(1, engine.store.tables.len());
     assert_eq!(10, engine.store.tables[0].elem.len());
-    assert!(engine.store.tables[0].elem.iter().all(|w| w == &None));
+    assert!(engine.store.tables[0].elem.iter().all(|w| w.is_none()));
     assert_eq!(Some(20), engine.store.tables[0].max);
 }
 
@@ -196,7 +198,8 @@ fn test_allocation_tables_one() {
 fn test_allocation_memories_zero() {
     let engine = allocation!(vec![Section::Memory(MemorySection {
         entries: vec![MemoryType {
-            limits: Limits::Zero(10)
+            limits: Limits::Zero(10),
+            index_type: IndexType::I32,
         }]
     })]);
 
@@ -204,19 +207,22 @@ fn test_allocation_memories_zero() {
     // Store has a memory instance
 
     assert_eq!(1, engine.module_instance.get_mem_addrs().len());
-    assert_eq!(0, engine.module_instance.get_mem_addrs().get(0).unwrap().get());
+    assert_eq!(0, engine.module_instance.get_mem_addrs().first().unwrap().get());
 
     assert_eq!(1, engine.store.memory.len());
-    assert_eq!(10 * 1024 * 64, engine.store.memory[0].data.len());
-    assert!(engine.store.memory[0].data.iter().all(|w| w == &0u8));
-    assert_eq!(None, engine.store.memory[0].max);
+    assert_eq!(10 * 1024 * 64, engine.store.memory[0].read().len());
+    let mut data = vec![1; 10 * 1024 * 64];
+    engine.store.memory[0].read().read_bytes(0, &mut data).unwrap();
+    assert!(data.iter().all(|w| w == &0u8));
+    assert_eq!(None, engine.store.memory[0].read().max);
 }
 
 #[test]
 fn test_allocation_memories_one() {
     let engine = allocation!(vec![Section::Memory(MemorySection {
         entries: vec![MemoryType {
-            limits: Limits::One(10, 20)
+            limits: Limits::One(10, 20),
+            index_type: IndexType::I32,
         }]
     })]);
 
@@ -224,12 +230,14 @@ fn test_allocation_memories_one() {
     // Store has a memory instance
 
     assert_eq!(1, engine.module_instance.get_mem_addrs().len());
-    assert_eq!(0, engine.module_instance.get_mem_addrs().get(0).unwrap().get());
+    assert_eq!(0, engine.module_instance.get_mem_addrs().first().unwrap().get());
 
     assert_eq!(1, engine.store.memory.len());
-    assert_eq!(10 * 1024 * 64, engine.store.memory[0].data.len());
-    assert!(engine.store.memory[0].data.iter().all(|w| w == &0u8));
-    assert_eq!(Some(20), engine.store.memory[0].max);
+    assert_eq!(10 * 1024 * 64, engine.store.memory[0].read().len());
+    let mut data = vec![1; 10 * 1024 * 64];
+    engine.store.memory[0].read().read_bytes(0, &mut data).unwrap();
+    assert!(data.iter().all(|w| w == &0u8));
+    assert_eq!(Some(20), engine.store.memory[0].read().max);
 }
 
 #[test]
@@ -248,7 +256,7 @@ fn test_allocation_globals() {
     // Store has a global instance
 
     assert_eq!(1, engine.module_instance.get_global_addrs().len());
-    assert_eq!(Some(&GlobalAddr::new(0)), engine.module_instance.get_global_addrs().get(0));
+    assert_eq!(Some(&GlobalAddr::new(0)), engine.module_instance.get_global_addrs().first());
 
     assert_eq!(1, engine.store.globals.len());
     assert_eq!(
@@ -265,7 +273,8 @@ fn test_allocation_exports() {
     let engine = allocation!(vec![
         Section::Memory(MemorySection {
             entries: vec![MemoryType {
-                limits: Limits::Zero(10)
+                limits: Limits::Zero(10),
+                index_type: IndexType::I32,
             }]
         }),
         Section::Export(ExportSection {
@@ -350,11 +359,8 @@ fn test_gcd() {
 
 #[test]
 fn test_run_add() {
-    let engine = test_run_engine!("add.wasm", 0, vec![I32(1), I32(2)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(3)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("add.wasm", 0, vec![I32(1), I32(2)]);
+    assert_eq!(vec![I32(3)], results)
 }
 
 #[test]
@@ -368,84 +374,60 @@ fn test_run_call() {
         i32.add))
      */
 
-    let engine = test_run_engine!("call.wasm", 0, vec![]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(43)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("call.wasm", 0, vec![]);
+    assert_eq!(vec![I32(43)], results)
 }
 
 #[test]
 fn test_run_gcd_loop() {
     //env_logger::init();
-    let engine = test_run_engine!("gcd.wasm", 1, vec![I32(50), I32(10)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(10)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("gcd.wasm", 1, vec![I32(50), I32(10)]);
+    assert_eq!(vec![I32(10)], results)
 }
 
 #[test]
 fn test_run_gcd_recursive() {
     //env_logger::init();
-    let engine = test_run_engine!("gcd.wasm", 2, vec![I32(50), I32(10)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(10)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("gcd.wasm", 2, vec![I32(50), I32(10)]);
+    assert_eq!(vec![I32(10)], results)
 }
 
 #[test]
 fn test_run_gcd_recursive2() {
     //env_logger::init();
-    let engine = test_run_engine!("gcd.wasm", 2, vec![I32(31), I32(674)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(1)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("gcd.wasm", 2, vec![I32(31), I32(674)]);
+    assert_eq!(vec![I32(1)], results)
 }
 
 #[test]
 fn test_run_incr_counter() {
     //env_logger::init();
-    let engine = test_run_engine!("incr_counter.wasm", 0, vec![]);
-    assert_eq!(None, engine.store.stack.last())
+    let (_, results) = test_run_engine!("incr_counter.wasm", 0, vec![]);
+    assert!(results.is_empty())
 }
 
 #[test]
 fn test_run_gt() {
-    let engine = test_run_engine!("gt.wasm", 0, vec![I32(10), I32(11)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(0)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("gt.wasm", 0, vec![I32(10), I32(11)]);
+    assert_eq!(vec![I32(0)], results)
 }
 
 #[test]
 fn test_run_gt_2() {
-    let engine = test_run_engine!("gt.wasm", 0, vec![I32(11), I32(10)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(1)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("gt.wasm", 0, vec![I32(11), I32(10)]);
+    assert_eq!(vec![I32(1)], results)
 }
 
 #[test]
 fn test_run_sub() {
-    let engine = test_run_engine!("sub.wasm", 0, vec![I32(10), I32(11)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(-1)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("sub.wasm", 0, vec![I32(10), I32(11)]);
+    assert_eq!(vec![I32(-1)], results)
 }
 
 #[test]
 fn test_run_sub2() {
-    let engine = test_run_engine!("sub.wasm", 0, vec![I32(11), I32(10)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(1)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("sub.wasm", 0, vec![I32(11), I32(10)]);
+    assert_eq!(vec![I32(1)], results)
 }
 
 /*
@@ -474,206 +456,143 @@ fn test_run_sub2() {
 
 #[test]
 fn test_run_local_tee() {
-    let engine = test_run_engine!("local.tee.wasm", 0, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(0))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("local.tee.wasm", 0, vec![]);
+    assert_eq!(vec![I32(0)], results)
 }
 
 #[test]
 fn test_run_local_tee_1() {
-    let engine = test_run_engine!("local.tee.wasm", 1, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I64(0))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("local.tee.wasm", 1, vec![]);
+    assert_eq!(vec![I64(0)], results)
 }
 
 #[test]
 fn test_run_local_tee_2() {
-    let engine = test_run_engine!("local.tee.wasm", 2, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(F32(0.0))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("local.tee.wasm", 2, vec![]);
+    assert_eq!(vec![F32(0.0)], results)
 }
 
 #[test]
 fn test_run_local_tee_3() {
-    let engine = test_run_engine!("local.tee.wasm", 3, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(F64(0.0))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("local.tee.wasm", 3, vec![]);
+    assert_eq!(vec![F64(0.0)], results)
 }
 
 #[test]
 fn test_run_local_tee_4() {
-    let engine = test_run_engine!("local.tee.wasm", 4, vec![I32(2)]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(10))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("local.tee.wasm", 4, vec![I32(2)]);
+    assert_eq!(vec![I32(10)], results)
 }
 
 #[test]
 fn test_run_local_tee_5() {
-    let engine = test_run_engine!("local.tee.wasm", 5, vec![I64(3)]);
-    assert_eq!(
-        Some(&StackContent::Value(I64(11))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("local.tee.wasm", 5, vec![I64(3)]);
+    assert_eq!(vec![I64(11)], results)
 }
 
 #[test]
 fn test_run_local_tee_6() {
-    let engine = test_run_engine!("local.tee.wasm", 6, vec![F32(4.4)]);
-    assert_eq!(
-        Some(&(StackContent::Value(F32(11.1)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("local.tee.wasm", 6, vec![F32(4.4)]);
+    assert_eq!(vec![F32(11.1)], results)
 }
 
 #[test]
 fn test_run_local_tee_7() {
-    let engine = test_run_engine!("local.tee.wasm", 7, vec![F64(5.5)]);
-    assert_eq!(
-        Some(&StackContent::Value(F64(12.2))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("local.tee.wasm", 7, vec![F64(5.5)]);
+    assert_eq!(vec![F64(12.2)], results)
 }
 
 #[test]
 fn test_run_as_loop_first_br_if_1() {
-    let engine = test_run_engine!("as_loop_br_if.wasm", 0, vec![I32(0)]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(2))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("as_loop_br_if.wasm", 0, vec![I32(0)]);
+    assert_eq!(vec![I32(2)], results)
 }
 
 #[test]
 fn test_run_as_loop_first_br_if_2() {
-    let engine = test_run_engine!("as_loop_br_if.wasm", 0, vec![I32(1)]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(3))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("as_loop_br_if.wasm", 0, vec![I32(1)]);
+    assert_eq!(vec![I32(3)], results)
 }
 
 #[test]
 fn test_run_br_if0() {
     //env_logger::init();
-    let engine = test_run_engine!("labels.wasm", 0, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(0x1d))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("labels.wasm", 0, vec![]);
+    assert_eq!(vec![I32(0x1d)], results)
 }
 
 #[test]
 fn test_run_br_if1() {
     //env_logger::init();
-    let engine = test_run_engine!("labels.wasm", 1, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(0x1))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("labels.wasm", 1, vec![]);
+    assert_eq!(vec![I32(0x1)], results)
 }
 
 /*
 #[test]
 fn test_run_br_if2() {
     //env_logger::init();
-    let engine = test_run_engine!("labels.wasm", 2, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(1))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("labels.wasm", 2, vec![]);
+    assert_eq!(vec![I32(1)], results)
 }*/
 
 #[test]
 fn test_run_br_if3() {
     //env_logger::init();
-    let engine = test_run_engine!("labels.wasm", 3, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(2))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("labels.wasm", 3, vec![]);
+    assert_eq!(vec![I32(2)], results)
 }
 
 /*
 #[test]
 fn test_run_br_if4() {
     //env_logger::init();
-    let engine = test_run_engine!("labels.wasm", 4, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(1))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("labels.wasm", 4, vec![]);
+    assert_eq!(vec![I32(1)], results)
 }
 
 #[test]
 fn test_run_loop1() {
     //env_logger::init();
-    let engine = test_run_engine!("loop.wasm", 0, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(5))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("loop.wasm", 0, vec![]);
+    assert_eq!(vec![I32(5)], results)
 }
 
 #[test]
 fn test_run_loop2() {
     //env_logger::init();
-    let engine = test_run_engine!("loop.wasm", 1, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(8))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("loop.wasm", 1, vec![]);
+    assert_eq!(vec![I32(8)], results)
 }
 */
 
 #[test]
 fn test_run_loop3() {
-    let engine = test_run_engine!("loop.wasm", 2, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(1))),
-        engine.store.stack.last()
-    );
+    let (_, results) = test_run_engine!("loop.wasm", 2, vec![]);
+    assert_eq!(vec![I32(1)], results);
 }
 
 /*
 #[test]
 fn test_run_loop4() {
     //env_logger::init();
-    let engine = test_run_engine!("loop.wasm", 3, vec![I32(8)]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(16))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("loop.wasm", 3, vec![I32(8)]);
+    assert_eq!(vec![I32(16)], results)
 }
 */
 
 #[test]
 fn test_run_loop5() {
     //env_logger::init();
-    let engine = test_run_engine!("loop.wasm", 4, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(2))),
-        engine.store.stack.last()
-    );
+    let (_, results) = test_run_engine!("loop.wasm", 4, vec![]);
+    assert_eq!(vec![I32(2)], results);
 }
 
 #[test]
 fn test_run_loop6() {
     //env_logger::init();
-    let engine = test_run_engine!("loop.wasm", 5, vec![]);
-    assert_eq!(
-        Some(&StackContent::Value(I32(3))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("loop.wasm", 5, vec![]);
+    assert_eq!(vec![I32(3)], results)
 }
 
 /*
@@ -686,106 +605,70 @@ fn test_run_loop6() {
 #[test]
 fn test_run_as_loop_mid_br1() {
     //env_logger::init();
-    let engine = test_run_engine!("as_loop_mid_br_if.wasm", 0, vec![I32(0)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(2)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("as_loop_mid_br_if.wasm", 0, vec![I32(0)]);
+    assert_eq!(vec![I32(2)], results)
 }
 
 #[test]
 fn test_run_as_loop_mid_br2() {
     //env_logger::init();
-    let engine = test_run_engine!("as_loop_mid_br_if.wasm", 0, vec![I32(1)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(4)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("as_loop_mid_br_if.wasm", 0, vec![I32(1)]);
+    assert_eq!(vec![I32(4)], results)
 }
 
 #[test]
 fn test_run_if_empty() {
     //env_logger::init();
-    let engine = test_run_engine!("if.wasm", 0, vec![I32(0)]);
-    assert_eq!(None, engine.store.stack.last())
+    let (_, results) = test_run_engine!("if.wasm", 0, vec![I32(0)]);
+    assert!(results.is_empty())
 }
 
 #[test]
 fn test_run_if_singular() {
     //env_logger::init();
-    let engine = test_run_engine!("if.wasm", 1, vec![I32(0)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(8)))),
-        engine.store.stack.last()
-    )
+    let (_, results) = test_run_engine!("if.wasm", 1, vec![I32(0)]);
+    assert_eq!(vec![I32(8)], results)
 }
 
 #[test]
 fn test_run_br_table() {
     //env_logger::init();
-    let mut engine = test_run_engine!("br_table.wasm", 0, vec![I32(0)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(103)))),
-        engine.store.stack.last()
-    );
-    engine = test_run_engine!("br_table.wasm", 0, vec![I32(1)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(102)))),
-        engine.store.stack.last()
-    );
-    engine = test_run_engine!("br_table.wasm", 0, vec![I32(2)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(101)))),
-        engine.store.stack.last()
-    );
-    engine = test_run_engine!("br_table.wasm", 0, vec![I32(3)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(100)))),
-        engine.store.stack.last()
-    );
-    engine = test_run_engine!("br_table.wasm", 0, vec![I32(4)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(104)))),
-        engine.store.stack.last()
-    );
-    engine = test_run_engine!("br_table.wasm", 0, vec![I32(-4)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(104)))),
-        engine.store.stack.last()
-    );
+    let (_, results) = test_run_engine!("br_table.wasm", 0, vec![I32(0)]);
+    assert_eq!(vec![I32(103)], results);
+    let (_, results) = test_run_engine!("br_table.wasm", 0, vec![I32(1)]);
+    assert_eq!(vec![I32(102)], results);
+    let (_, results) = test_run_engine!("br_table.wasm", 0, vec![I32(2)]);
+    assert_eq!(vec![I32(101)], results);
+    let (_, results) = test_run_engine!("br_table.wasm", 0, vec![I32(3)]);
+    assert_eq!(vec![I32(100)], results);
+    let (_, results) = test_run_engine!("br_table.wasm", 0, vec![I32(4)]);
+    assert_eq!(vec![I32(104)], results);
+    let (_, results) = test_run_engine!("br_table.wasm", 0, vec![I32(-4)]);
+    assert_eq!(vec![I32(104)], results);
 }
 
 #[test]
 fn test_run_memory_size() {
     //env_logger::init();
-    let engine = test_run_engine!("memory.wasm", 5, vec![]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(0)))),
-        engine.store.stack.last()
-    );
+    let (_, results) = test_run_engine!("memory.wasm", 5, vec![]);
+    assert_eq!(vec![I32(0)], results);
 }
 
 #[test]
 fn test_memory_redundancy() {
     //env_logger::init();
-    let mut engine = test_run_engine!("memory_redundancy.wasm", 1, vec![]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(128)))),
-        engine.store.stack.last()
-    );
+    let (mut engine, results) = test_run_engine!("memory_redundancy.wasm", 1, vec![]);
+    assert_eq!(vec![I32(128)], results);
 
     engine
         .invoke_exported_function(0, vec![])
         .expect("Invoke exported function failed");
 
-    engine
+    let results = engine
         .invoke_exported_function(2, vec![])
         .expect("Invoke exported function failed");
 
-    assert_eq!(
-        Some(&(StackContent::Value(I32(128)))),
-        engine.store.stack.last()
-    );
+    assert_eq!(vec![I32(128)], results);
 }
 
 #[test]
@@ -801,20 +684,14 @@ fn test_get_exported_global() {
 #[test]
 fn test_run_fib() {
     //env_logger::init();
-    let engine = test_run_engine!("fib.wasm", 1, vec![I32(10)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(55)))),
-        engine.store.stack.last()
-    );
+    let (_, results) = test_run_engine!("fib.wasm", 1, vec![I32(10)]);
+    assert_eq!(vec![I32(55)], results);
 }
 
 #[ignore]
 #[test]
 fn test_run_fib_higher() {
     //env_logger::init();
-    let engine = test_run_engine!("fib.wasm", 1, vec![I32(25)]);
-    assert_eq!(
-        Some(&(StackContent::Value(I32(75025)))),
-        engine.store.stack.last()
-    );
+    let (_, results) = test_run_engine!("fib.wasm", 1, vec![I32(25)]);
+    assert_eq!(vec![I32(75025)], results);
 }
@@ -1,68 +1,24 @@
-use crate::engine::module::ModuleInstance;
-use crate::engine::*;
+use super::instantiate;
 use crate::value::Value::*;
 use insta::assert_snapshot;
-use std::fs::File;
-use std::io::{self, Write};
-use validation::validate;
-use wasm_parser::{parse, read_wasm};
 
 macro_rules! wat {
-    ($name:expr, $input:expr, $invoke:expr, $init:expr) => {{
-        use std::process::Command;
-        use tempfile::tempdir;
+    ($name:expr, $input:expr, $invoke:expr, $init:expr, $expected:expr) => {{
+        let mut engine = instantiate($input);
 
-        let tmp_dir = tempdir().unwrap();
-        let file_path = tmp_dir.path().join("file.wat");
+        let results = match engine.invoke_exported_function_by_name($invoke, $init) {
+            Ok(results) => results,
+            Err(err) => {
+                error!("ERROR: {}", err);
+                err.chain()
+                    .skip(1)
+                    .for_each(|cause| error!("because: {}", cause));
 
-        {
-            let mut tmp_file = File::create(file_path.clone()).unwrap();
-            writeln!(tmp_file, $input).unwrap();
-            tmp_file.flush().unwrap();
-
-            println!("tmp_file {}", $input);
-        }
-
-        let output = Command::new("wat2wasm")
-            .arg(file_path)
-            .arg("-o")
-            .arg(tmp_dir.path().join("file.wasm").to_str().unwrap())
-            .output()
-            .expect("Command failed");
-
-        io::stdout().write_all(&output.stdout).unwrap();
-        io::stderr().write_all(&output.stderr).unwrap();
-
-        assert!(output.status.success());
-
-        let file_path = tmp_dir.path().join("file.wasm");
-
-        // Read it
-        let file = read_wasm!(file_path);
-        let module = parse(file).expect("Parsing failed");
-        assert!(validate(&module).is_ok());
-
-        let imports = Vec::new();
-
-        let (instance, functions) = ModuleInstance::new(&module);
-        let mut engine = Engine::new(
-            instance,
-            &functions,
-            &module,
-            Box::new(crate::debugger::RelativeProgramCounter::default()),
-            &imports,
-        )
-        .unwrap();
-
-        if let Err(err) = engine.invoke_exported_function_by_name($invoke, $init) {
-            error!("ERROR: {}", err);
-            err.chain()
-                .skip(1)
-                .for_each(|cause| error!("because: {}", cause));
-
-            panic!("Test failed");
-        }
+                panic!("Test failed");
+            }
+        };
 
+        assert_eq!($expected, results);
         assert_snapshot!($name, format!("{:#?}", engine));
 
         engine
@@ -75,13 +31,14 @@ fn test_block() {
         "test_block",
         "(module (func (result i32) (block (result i32) i32.const 1)) (export \"main\" (func 0)))",
         "main",
-        vec![]
+        vec![],
+        vec![I32(1)]
     );
 }
 
 #[test]
 fn test_block_add() {
-    wat!("test_block_add", "(module (func (result i32) (block (result i32) i32.const 1 i32.const 1 i32.add)) (export \"main\" (func 0)))", "main", vec![]);
+    wat!("test_block_add", "(module (func (result i32) (block (result i32) i32.const 1 i32.const 1 i32.add)) (export \"main\" (func 0)))", "main", vec![], vec![I32(2)]);
 }
 
 #[test]
@@ -101,6 +58,7 @@ fn test_call_indirect_mid() {
     ))
   )",
         "as-call_indirect-mid",
+        vec![I32(1)],
         vec![I32(1)]
     );
 }
@@ -120,7 +78,8 @@ fn test_nested_br_value() {
     )
   ))",
         "nested-br_table-value-index",
-        vec![]
+        vec![],
+        vec![I32(9)]
     );
 }
 
@@ -139,7 +98,8 @@ fn test_br_table_index() {
       )))   
     )",
         "nested-br_table-value-index",
-        vec![]
+        vec![],
+        vec![I32(9)]
     );
 }
 
@@ -164,7 +124,8 @@ fn test_br_table_value() {
     )) 
     )",
         "nested-br_table-value",
-        vec![]
+        vec![],
+        vec![I32(9)]
     );
 }
 
@@ -191,7 +152,8 @@ fn test_nested_block_value() {
           )
         )))))",
         "nested-block-value",
-        vec![I32(1)]
+        vec![I32(1)],
+        vec![I32(17)]
     );
 }
 
@@ -210,7 +172,8 @@ fn test_as_if_then() {
     )
   ))",
         "as-if-then",
-        vec![I32(1), I32(6)]
+        vec![I32(1), I32(6)],
+        vec![I32(3)]
     );
 }
 
@@ -224,7 +187,8 @@ fn test_br_table_num_num() {
         (i32.const 51) (i64.const 52)
     ))",
         "break-br_table-num-num",
-        vec![I32("4294967196".parse::<u32>().unwrap() as i32)]
+        vec![I32("4294967196".parse::<u32>().unwrap() as i32)],
+        vec![I32(50), I64(51)]
     );
 }
 
@@ -244,7 +208,8 @@ fn test_params_break_id() {
     (i32.add)
   ))",
         "params-id-break",
-        vec![]
+        vec![],
+        vec![I32(3)]
     );
 }
 
@@ -269,7 +234,8 @@ fn test_fac_ssa() {
     )
   ))",
         "fac-ssa",
-        vec![I64(3)]
+        vec![I64(3)],
+        vec![I64(6)]
     );
 }
 
@@ -284,7 +250,8 @@ fn test_as_return_values() {
   ))
     ",
         "as-return-values",
-        vec![]
+        vec![],
+        vec![I32(2), I64(7)]
     );
 }
 
@@ -318,6 +285,7 @@ fn test_add64_sat_u() {
         vec![
             I64("9223372036854775808".parse::<u64>().unwrap() as i64),
             I64("9223372036854775808".parse::<u64>().unwrap() as i64)
-        ]
+        ],
+        vec![I64(-1)]
     );
 }
@@ -1,5 +1,7 @@
-use crate::engine::stack::StackContent;
+use crate::engine::stack::Slot;
+use crate::engine::trap::Trap;
 use crate::value::Value::*;
+use anyhow::{bail, Result};
 use log::trace;
 use std::fmt;
 use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Rem, Shl, Shr, Sub};
@@ -11,6 +13,19 @@ pub enum Value {
     I64(i64),
     F32(f32),
     F64(f64),
+    /// A vector of packed integers or floats
+    V128(u128),
+    /// A function or null
+    FuncRef(Option<FuncAddr>),
+    /// An opaque reference of the host or null
+    ExternRef(Option<ExternAddr>),
+}
+
+/// A non-null reference, which is stored in tables
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Ref {
+    Func(FuncAddr),
+    Extern(ExternAddr),
 }
 
 pub type Arity = u32;
@@ -22,6 +37,9 @@ impl From<Value> for ValueType {
             Value::I64(_) => ValueType::I64,
             Value::F32(_) => ValueType::F32,
             Value::F64(_) => ValueType::F64,
+            Value::V128(_) => ValueType::V128,
+            Value::FuncRef(_) => ValueType::FuncRef,
+            Value::ExternRef(_) => ValueType::ExternRef,
         }
     }
 }
@@ -48,10 +66,12 @@ impl Value {
 
     pub fn signum(&self) -> f32 {
         match self {
-            Value::F32(k) => k.signum() as f32,
+            Value::F32(k) => k.signum(),
             Value::F64(k) => k.signum() as f32,
             Value::I32(k) => k.signum() as f32,
             Value::I64(k) => k.signum() as f32,
+            Value::V128(_) => panic!("A vector has no sign"),
+            Value::FuncRef(_) | Value::ExternRef(_) => panic!("A reference has no sign"),
         }
     }
 
@@ -62,6 +82,64 @@ impl Value {
     pub fn is_f64(&self) -> bool {
         matches!(self, Value::F64(_))
     }
+
+    /// The untagged representation on the stack.
+    /// A reference is its address plus one, therefore null is zero.
+    /// It is `None` for a `v128`, which occupies two slots.
+    pub fn to_bits(self) -> Option<Slot> {
+        let bits = match self {
+            Value::I32(v) => v as u32 as Slot,
+            Value::I64(v) => v as Slot,
+            Value::F32(v) => v.to_bits() as Slot,
+            Value::F64(v) => v.to_bits(),
+            Value::V128(_) => return None,
+            Value::FuncRef(addr) => addr.map_or(0, |addr| addr.get() as Slot + 1),
+            Value::ExternRef(addr) => addr.map_or(0, |addr| addr.get() as Slot + 1),
+        };
+
+        Some(bits)
+    }
+
+    /// Interprets the untagged representation as `ty`.
+    /// A `v128` is zero-extended, because only its low half fits into a slot.
+    pub fn from_bits(ty: ValueType, bits: Slot) -> Value {
+        match ty {
+            ValueType::I32 => Value::I32(bits as u32 as i32),
+            ValueType::I64 => Value::I64(bits as i64),
+            ValueType::F32 => Value::F32(f32::from_bits(bits as u32)),
+            ValueType::F64 => Value::F64(f64::from_bits(bits)),
+            ValueType::V128 => Value::V128(u128::from(bits)),
+            ValueType::FuncRef => {
+                Value::FuncRef(bits.checked_sub(1).map(|addr| FuncAddr::new(addr as usize)))
+            }
+            ValueType::ExternRef => {
+                Value::ExternRef(bits.checked_sub(1).map(|addr| ExternAddr::new(addr as usize)))
+            }
+        }
+    }
+
+    /// The null reference of the type `ty`
+    pub fn null(ty: ValueType) -> Value {
+        Value::from_bits(ty, 0)
+    }
+
+    /// The reference of the type `ty`, which is null for `None`
+    pub fn from_ref(ty: ValueType, reference: Option<Ref>) -> Value {
+        match reference {
+            Some(Ref::Func(addr)) => Value::FuncRef(Some(addr)),
+            Some(Ref::Extern(addr)) => Value::ExternRef(Some(addr)),
+            None => Value::null(ty),
+        }
+    }
+
+    /// The reference of the value, which is `None` for null and numbers
+    pub fn to_ref(self) -> Option<Ref> {
+        match self {
+            Value::FuncRef(addr) => addr.map(Ref::Func),
+            Value::ExternRef(addr) => addr.map(Ref::Extern),
+            _ => None,
+        }
+    }
 }
 
 impl Add for Value {
@@ -103,15 +181,17 @@ impl Mul for Value {
     }
 }
 
+/// Traps on an integer division by zero
 impl Div for Value {
-    type Output = Self;
-    fn div(self, other: Self) -> Self {
+    type Output = Result<Self>;
+    fn div(self, other: Self) -> Result<Self> {
         match (self, other) {
-            (I32(v1), I32(v2)) => I32(v1.wrapping_div(v2)),
-            (I64(v1), I64(v2)) => I64(v1.wrapping_div(v2)),
-            (F32(v1), F32(v2)) => F32(v1 / v2),
-            (F64(v1), F64(v2)) => F64(v1 / v2),
-            _ => panic!("Type missmatch during division"),
+            (I32(_), I32(0)) | (I64(_), I64(0)) => bail!(Trap::IntegerDivideByZero),
+            (I32(v1), I32(v2)) => Ok(I32(v1.wrapping_div(v2))),
+            (I64(v1), I64(v2)) => Ok(I64(v1.wrapping_div(v2))),
+            (F32(v1), F32(v2)) => Ok(F32(v1 / v2)),
+            (F64(v1), F64(v2)) => Ok(F64(v1 / v2)),
+            _ => bail!("Type missmatch during division"),
         }
     }
 }
@@ -171,15 +251,17 @@ impl Shr for Value {
     }
 }
 
+/// Traps on an integer remainder by zero
 impl Rem for Value {
-    type Output = Self;
-    fn rem(self, other: Self) -> Self {
+    type Output = Result<Self>;
+    fn rem(self, other: Self) -> Result<Self> {
         match (self, other) {
-            (I32(v1), I32(v2)) => I32(v1.wrapping_rem(v2)),
-            (I64(v1), I64(v2)) => I64(v1.wrapping_rem(v2)),
-            (F32(v1), F32(v2)) => F32(v1 % v2),
-            (F64(v1), F64(v2)) => F64(v1 % v2),
-            _ => panic!("Type missmatch during remainder"),
+            (I32(_), I32(0)) | (I64(_), I64(0)) => bail!(Trap::IntegerDivideByZero),
+            (I32(v1), I32(v2)) => Ok(I32(v1.wrapping_rem(v2))),
+            (I64(v1), I64(v2)) => Ok(I64(v1.wrapping_rem(v2))),
+            (F32(v1), F32(v2)) => Ok(F32(v1 % v2)),
+            (F64(v1), F64(v2)) => Ok(F64(v1 % v2)),
+            _ => bail!("Type missmatch during remainder"),
         }
     }
 }
@@ -189,9 +271,3 @@ impl fmt::Display for Value {
         write!(f, "{:?}", self)
     }
 }
-
-impl From<Value> for StackContent {
-    fn from(val: Value) -> StackContent {
-        StackContent::Value(val)
-    }
-}
@@ -0,0 +1,34 @@
+//! Error numbers of `wasi_snapshot_preview1`
+//! https://github.com/WebAssembly/WASI/blob/main/phases/snapshot/docs.md#errno
+
+pub type Errno = u16;
+
+pub const SUCCESS: Errno = 0;
+pub const ACCES: Errno = 2;
+pub const BADF: Errno = 8;
+pub const EXIST: Errno = 20;
+pub const FAULT: Errno = 21;
+pub const INVAL: Errno = 28;
+pub const IO: Errno = 29;
+pub const ISDIR: Errno = 31;
+pub const LOOP: Errno = 32;
+pub const NOENT: Errno = 44;
+pub const NOSYS: Errno = 52;
+pub const NOTDIR: Errno = 54;
+pub const NOTSUP: Errno = 58;
+pub const PERM: Errno = 63;
+pub const SPIPE: Errno = 70;
+pub const NOTCAPABLE: Errno = 76;
+
+/// Maps the host's io error to the closest errno
+pub(crate) fn from_io_error(err: &std::io::Error) -> Errno {
+    use std::io::ErrorKind::*;
+
+    match err.kind() {
+        NotFound => NOENT,
+        PermissionDenied => ACCES,
+        AlreadyExists => EXIST,
+        InvalidInput => INVAL,
+        _ => IO,
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+pub const STDIN: u32 = 0;
+pub const STDOUT: u32 = 1;
+pub const STDERR: u32 = 2;
+
+/// An open file descriptor of the guest
+pub enum Descriptor {
+    /// A stream which can only be read (e.g. stdin)
+    Reader(Box<dyn Read>),
+    /// A stream which can only be written (e.g. stdout)
+    Writer(Box<dyn Write>),
+    /// A file on the host
+    File(File),
+    /// A directory on the host.
+    /// `preopen` is the guest's name when the directory was preopened.
+    Dir {
+        path: PathBuf,
+        preopen: Option<String>,
+    },
+}
+
+// `Read` and `Write` objects do not implement `Debug`
+impl fmt::Debug for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Descriptor::Reader(_) => f.write_str("Reader"),
+            Descriptor::Writer(_) => f.write_str("Writer"),
+            Descriptor::File(file) => f.debug_tuple("File").field(file).finish(),
+            Descriptor::Dir { path, preopen } => f
+                .debug_struct("Dir")
+                .field("path", path)
+                .field("preopen", preopen)
+                .finish(),
+        }
+    }
+}
+
+/// The table of the open file descriptors
+#[derive(Debug, Default)]
+pub struct FdTable {
+    entries: BTreeMap<u32, Descriptor>,
+}
+
+impl FdTable {
+    /// Create the table with the host's stdio
+    pub fn with_stdio() -> Self {
+        let mut table = FdTable::default();
+
+        table.set(STDIN, Descriptor::Reader(Box::new(std::io::stdin())));
+        table.set(STDOUT, Descriptor::Writer(Box::new(std::io::stdout())));
+        table.set(STDERR, Descriptor::Writer(Box::new(std::io::stderr())));
+
+        table
+    }
+
+    pub fn get(&self, fd: u32) -> Option<&Descriptor> {
+        self.entries.get(&fd)
+    }
+
+    pub fn get_mut(&mut self, fd: u32) -> Option<&mut Descriptor> {
+        self.entries.get_mut(&fd)
+    }
+
+    /// Overwrite the descriptor at `fd`
+    pub fn set(&mut self, fd: u32, descriptor: Descriptor) {
+        self.entries.insert(fd, descriptor);
+    }
+
+    /// Insert the descriptor at the lowest free fd
+    pub fn insert(&mut self, descriptor: Descriptor) -> u32 {
+        let fd = (0..)
+            .find(|fd| !self.entries.contains_key(fd))
+            .expect("No free file descriptor");
+
+        self.entries.insert(fd, descriptor);
+
+        fd
+    }
+
+    pub fn remove(&mut self, fd: u32) -> Option<Descriptor> {
+        self.entries.remove(&fd)
+    }
+}
@@ -0,0 +1,569 @@
+//! Host implementation of `wasi_snapshot_preview1`.
+//! https://github.com/WebAssembly/WASI/blob/main/phases/snapshot/docs.md
+//!
+//! The functions are provided as `Import::Function` and
+//! share one `WasiCtx`, which holds the arguments, the
+//! environment and the open file descriptors.
+
+pub mod errno;
+pub mod fd;
+
+use self::errno::*;
+use self::fd::{Descriptor, FdTable, STDERR, STDIN, STDOUT};
+use crate::engine::func::Caller;
+use crate::engine::import_resolver::{Import, Imports};
+use crate::value::Value;
+use anyhow::{anyhow, bail, Result};
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use wasm_parser::core::{FunctionSignature, ValueType};
+
+/// The module name of the imports
+pub const MODULE: &str = "wasi_snapshot_preview1";
+
+// Rights
+const RIGHTS_FD_READ: u64 = 1 << 1;
+const RIGHTS_FD_WRITE: u64 = 1 << 6;
+
+// Lookup flags
+const LOOKUPFLAGS_SYMLINK_FOLLOW: u32 = 1 << 0;
+
+// Open flags
+const OFLAGS_CREAT: u32 = 1 << 0;
+const OFLAGS_DIRECTORY: u32 = 1 << 1;
+const OFLAGS_EXCL: u32 = 1 << 2;
+const OFLAGS_TRUNC: u32 = 1 << 3;
+
+// Fd flags
+const FDFLAGS_APPEND: u32 = 1 << 0;
+
+// File types
+const FILETYPE_CHARACTER_DEVICE: u8 = 2;
+const FILETYPE_DIRECTORY: u8 = 3;
+const FILETYPE_REGULAR_FILE: u8 = 4;
+
+// Clocks
+const CLOCK_REALTIME: u32 = 0;
+const CLOCK_MONOTONIC: u32 = 1;
+const CLOCK_PROCESS_CPUTIME: u32 = 2;
+const CLOCK_THREAD_CPUTIME: u32 = 3;
+
+/// The maximum of bytes, which are buffered by the host at once
+const CHUNK_SIZE: u32 = 64 * 1024;
+
+/// The error which is returned when the guest calls `proc_exit`.
+/// It is not a trap, but the regular end of the program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcExit(pub i32);
+
+impl fmt::Display for ProcExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Process exited with code {}", self.0)
+    }
+}
+
+impl std::error::Error for ProcExit {}
+
+/// Returns the exit code, when the error was caused by `proc_exit`
+pub fn exit_code(err: &anyhow::Error) -> Option<i32> {
+    err.downcast_ref::<ProcExit>().map(|exit| exit.0)
+}
+
+/// The state of the WASI host
+#[derive(Debug)]
+pub struct WasiCtx {
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    fds: FdTable,
+    start: Instant,
+}
+
+impl WasiCtx {
+    /// Create a new context with the host's stdio.
+    /// The first argument is the program's name.
+    pub fn new(args: Vec<String>, env: Vec<(String, String)>) -> Self {
+        Self {
+            args,
+            env,
+            fds: FdTable::with_stdio(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Grant the guest access to the `host` directory as `guest`
+    pub fn preopen_dir(&mut self, host: impl Into<PathBuf>, guest: impl Into<String>) -> Result<()> {
+        let path = host.into();
+
+        if !path.is_dir() {
+            bail!("Cannot preopen {:?}, because it is not a directory", path);
+        }
+
+        self.fds.insert(Descriptor::Dir {
+            path,
+            preopen: Some(guest.into()),
+        });
+
+        Ok(())
+    }
+
+    pub fn set_stdin(&mut self, reader: Box<dyn Read>) {
+        self.fds.set(STDIN, Descriptor::Reader(reader));
+    }
+
+    pub fn set_stdout(&mut self, writer: Box<dyn Write>) {
+        self.fds.set(STDOUT, Descriptor::Writer(writer));
+    }
+
+    pub fn set_stderr(&mut self, writer: Box<dyn Write>) {
+        self.fds.set(STDERR, Descriptor::Writer(writer));
+    }
+}
+
+/// Registers a function, which returns an errno.
+macro_rules! wasi_function {
+    ($imports:expr, $ctx:expr, $name:ident, [$($param:ident),*]) => {{
+        let ctx = Rc::clone($ctx);
+
+        $imports.push(Import::Function(
+            MODULE.to_string(),
+            stringify!($name).to_string(),
+            FunctionSignature {
+                param_types: vec![$(ValueType::$param),*],
+                return_types: vec![ValueType::I32],
+            },
+            Box::new(move |caller, args| {
+                debug!("WASI {} {:?}", stringify!($name), args);
+
+                let errno = match $name(&mut ctx.borrow_mut(), caller, args) {
+                    Ok(()) => SUCCESS,
+                    Err(errno) => errno,
+                };
+
+                Ok(vec![Value::I32(errno as i32)])
+            }),
+        ));
+    }};
+}
+
+/// Creates the imports of `wasi_snapshot_preview1`,
+/// which are all backed by `ctx`.
+pub fn imports(ctx: &Rc<RefCell<WasiCtx>>) -> Imports {
+    let mut imports = Imports::new();
+
+    wasi_function!(imports, ctx, args_get, [I32, I32]);
+    wasi_function!(imports, ctx, args_sizes_get, [I32, I32]);
+    wasi_function!(imports, ctx, environ_get, [I32, I32]);
+    wasi_function!(imports, ctx, environ_sizes_get, [I32, I32]);
+    wasi_function!(imports, ctx, clock_time_get, [I32, I64, I32]);
+    wasi_function!(imports, ctx, random_get, [I32, I32]);
+    wasi_function!(imports, ctx, fd_write, [I32, I32, I32, I32]);
+    wasi_function!(imports, ctx, fd_read, [I32, I32, I32, I32]);
+    wasi_function!(imports, ctx, fd_close, [I32]);
+    wasi_function!(imports, ctx, fd_seek, [I32, I64, I32, I32]);
+    wasi_function!(imports, ctx, fd_fdstat_get, [I32, I32]);
+    wasi_function!(imports, ctx, fd_prestat_get, [I32, I32]);
+    wasi_function!(imports, ctx, fd_prestat_dir_name, [I32, I32, I32]);
+    wasi_function!(
+        imports,
+        ctx,
+        path_open,
+        [I32, I32, I32, I32, I32, I64, I64, I32, I32]
+    );
+
+    imports.push(Import::Function(
+        MODULE.to_string(),
+        "proc_exit".to_string(),
+        FunctionSignature {
+            param_types: vec![ValueType::I32],
+            return_types: vec![],
+        },
+        Box::new(|_, args| match args {
+            [Value::I32(code)] => Err(anyhow!(ProcExit(*code))),
+            _ => Err(anyhow!("Invalid arguments for proc_exit {:?}", args)),
+        }),
+    ));
+
+    imports
+}
+
+fn arg_u32(args: &[Value], idx: usize) -> Result<u32, Errno> {
+    match args.get(idx) {
+        Some(Value::I32(v)) => Ok(*v as u32),
+        _ => Err(INVAL),
+    }
+}
+
+fn arg_u64(args: &[Value], idx: usize) -> Result<u64, Errno> {
+    match args.get(idx) {
+        Some(Value::I64(v)) => Ok(*v as u64),
+        _ => Err(INVAL),
+    }
+}
+
+/// Adds `n` to the guest's pointer
+fn offset(ptr: u32, n: u32) -> Result<u32, Errno> {
+    ptr.checked_add(n).ok_or(FAULT)
+}
+
+fn read_bytes(caller: &Caller, ptr: u32, len: u32) -> Result<Vec<u8>, Errno> {
+    caller
+        .read_memory(ptr as usize, len as usize)
+        .map_err(|_| FAULT)
+}
+
+fn write_bytes(caller: &mut Caller, ptr: u32, bytes: &[u8]) -> Result<(), Errno> {
+    caller
+        .write_memory(ptr as usize, bytes)
+        .map_err(|_| FAULT)
+}
+
+/// Checks the guest's range, before the host buffers it
+fn check_bytes(caller: &Caller, ptr: u32, len: u32) -> Result<(), Errno> {
+    caller
+        .check_memory(ptr as usize, len as usize)
+        .map_err(|_| FAULT)
+}
+
+/// Splits the checked range into chunks of at most `CHUNK_SIZE` bytes as (ptr, len)
+fn chunks(ptr: u32, len: u32) -> impl Iterator<Item = (u32, u32)> {
+    (0..len)
+        .step_by(CHUNK_SIZE as usize)
+        .map(move |i| (ptr + i, (len - i).min(CHUNK_SIZE)))
+}
+
+fn read_u32(caller: &Caller, ptr: u32) -> Result<u32, Errno> {
+    let mut b = [0; 4];
+    b.copy_from_slice(&read_bytes(caller, ptr, 4)?);
+
+    Ok(u32::from_le_bytes(b))
+}
+
+fn write_u32(caller: &mut Caller, ptr: u32, value: u32) -> Result<(), Errno> {
+    write_bytes(caller, ptr, &value.to_le_bytes())
+}
+
+fn write_u64(caller: &mut Caller, ptr: u32, value: u64) -> Result<(), Errno> {
+    write_bytes(caller, ptr, &value.to_le_bytes())
+}
+
+/// Reads the `iovec`s or `ciovec`s as (buf, buf_len)
+fn read_iovs(caller: &Caller, iovs: u32, iovs_len: u32) -> Result<Vec<(u32, u32)>, Errno> {
+    (0..iovs_len)
+        .map(|i| {
+            let iov = offset(iovs, i.checked_mul(8).ok_or(FAULT)?)?;
+            Ok((read_u32(caller, iov)?, read_u32(caller, offset(iov, 4)?)?))
+        })
+        .collect()
+}
+
+/// Sums up the lengths of the `iovs`, which must fit into the
+/// returned count of bytes like for `readv` and `writev`.
+fn iovs_len(iovs: &[(u32, u32)]) -> Result<u32, Errno> {
+    iovs.iter()
+        .try_fold(0u32, |sum, &(_, buf_len)| sum.checked_add(buf_len))
+        .ok_or(INVAL)
+}
+
+/// Writes the strings as null-terminated strings into `buf`
+/// and their pointers into `ptrs`.
+fn write_strings(caller: &mut Caller, strings: &[String], ptrs: u32, buf: u32) -> Result<(), Errno> {
+    let mut ptr = buf;
+
+    for (i, string) in strings.iter().enumerate() {
+        write_u32(caller, offset(ptrs, i as u32 * 4)?, ptr)?;
+        write_bytes(caller, ptr, string.as_bytes())?;
+        write_bytes(caller, offset(ptr, string.len() as u32)?, &[0])?;
+
+        ptr = offset(ptr, string.len() as u32 + 1)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the count and the size of the buffer of the strings
+fn write_strings_sizes(caller: &mut Caller, strings: &[String], count: u32, size: u32) -> Result<(), Errno> {
+    let buf_size: usize = strings.iter().map(|w| w.len() + 1).sum();
+
+    write_u32(caller, count, strings.len() as u32)?;
+    write_u32(caller, size, buf_size as u32)
+}
+
+fn environ_strings(ctx: &WasiCtx) -> Vec<String> {
+    ctx.env
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect()
+}
+
+fn args_get(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    write_strings(caller, &ctx.args, arg_u32(args, 0)?, arg_u32(args, 1)?)
+}
+
+fn args_sizes_get(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    write_strings_sizes(caller, &ctx.args, arg_u32(args, 0)?, arg_u32(args, 1)?)
+}
+
+fn environ_get(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    write_strings(caller, &environ_strings(ctx), arg_u32(args, 0)?, arg_u32(args, 1)?)
+}
+
+fn environ_sizes_get(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    write_strings_sizes(caller, &environ_strings(ctx), arg_u32(args, 0)?, arg_u32(args, 1)?)
+}
+
+fn clock_time_get(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    let time = match arg_u32(args, 0)? {
+        CLOCK_REALTIME => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| IO)?,
+        CLOCK_MONOTONIC => ctx.start.elapsed(),
+        // The host does not measure the CPU time of the guest
+        CLOCK_PROCESS_CPUTIME | CLOCK_THREAD_CPUTIME => return Err(NOTSUP),
+        _ => return Err(INVAL),
+    };
+
+    write_u64(caller, arg_u32(args, 2)?, time.as_nanos() as u64)
+}
+
+fn random_get(_ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    let (buf, buf_len) = (arg_u32(args, 0)?, arg_u32(args, 1)?);
+    check_bytes(caller, buf, buf_len)?;
+
+    for (ptr, len) in chunks(buf, buf_len) {
+        let mut bytes = vec![0; len as usize];
+        getrandom::getrandom(&mut bytes).map_err(|_| IO)?;
+
+        write_bytes(caller, ptr, &bytes)?;
+    }
+
+    Ok(())
+}
+
+fn fd_write(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    let iovs = read_iovs(caller, arg_u32(args, 1)?, arg_u32(args, 2)?)?;
+    iovs_len(&iovs)?;
+
+    let mut written: u32 = 0;
+    for (buf, buf_len) in iovs {
+        let bytes = read_bytes(caller, buf, buf_len)?;
+
+        match ctx.fds.get_mut(arg_u32(args, 0)?).ok_or(BADF)? {
+            Descriptor::Writer(writer) => writer.write_all(&bytes),
+            Descriptor::File(file) => file.write_all(&bytes),
+            Descriptor::Reader(_) | Descriptor::Dir { .. } => return Err(BADF),
+        }
+        .map_err(|err| from_io_error(&err))?;
+
+        written = written.checked_add(buf_len).ok_or(INVAL)?;
+    }
+
+    if let Some(Descriptor::Writer(writer)) = ctx.fds.get_mut(arg_u32(args, 0)?) {
+        writer.flush().map_err(|err| from_io_error(&err))?;
+    }
+
+    write_u32(caller, arg_u32(args, 3)?, written)
+}
+
+fn fd_read(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    let iovs = read_iovs(caller, arg_u32(args, 1)?, arg_u32(args, 2)?)?;
+    iovs_len(&iovs)?;
+
+    let mut read: u32 = 0;
+    'iovs: for (buf, buf_len) in iovs {
+        check_bytes(caller, buf, buf_len)?;
+
+        for (ptr, len) in chunks(buf, buf_len) {
+            let mut bytes = vec![0; len as usize];
+
+            let n = match ctx.fds.get_mut(arg_u32(args, 0)?).ok_or(BADF)? {
+                Descriptor::Reader(reader) => reader.read(&mut bytes),
+                Descriptor::File(file) => file.read(&mut bytes),
+                Descriptor::Writer(_) | Descriptor::Dir { .. } => return Err(BADF),
+            }
+            .map_err(|err| from_io_error(&err))?;
+
+            write_bytes(caller, ptr, &bytes[..n])?;
+            read = read.checked_add(n as u32).ok_or(INVAL)?;
+
+            // Do not block for the next chunk
+            if n < bytes.len() {
+                break 'iovs;
+            }
+        }
+    }
+
+    write_u32(caller, arg_u32(args, 3)?, read)
+}
+
+fn fd_close(ctx: &mut WasiCtx, _caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    ctx.fds.remove(arg_u32(args, 0)?).ok_or(BADF)?;
+
+    Ok(())
+}
+
+fn fd_seek(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    let offset = arg_u64(args, 1)? as i64;
+
+    let pos = match arg_u32(args, 2)? {
+        0 => SeekFrom::Start(offset as u64),
+        1 => SeekFrom::Current(offset),
+        2 => SeekFrom::End(offset),
+        _ => return Err(INVAL),
+    };
+
+    let new_offset = match ctx.fds.get_mut(arg_u32(args, 0)?).ok_or(BADF)? {
+        Descriptor::File(file) => file.seek(pos).map_err(|err| from_io_error(&err))?,
+        Descriptor::Reader(_) | Descriptor::Writer(_) => return Err(SPIPE),
+        Descriptor::Dir { .. } => return Err(ISDIR),
+    };
+
+    write_u64(caller, arg_u32(args, 3)?, new_offset)
+}
+
+fn fd_fdstat_get(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    let (filetype, rights) = match ctx.fds.get(arg_u32(args, 0)?).ok_or(BADF)? {
+        Descriptor::Reader(_) => (FILETYPE_CHARACTER_DEVICE, RIGHTS_FD_READ),
+        Descriptor::Writer(_) => (FILETYPE_CHARACTER_DEVICE, RIGHTS_FD_WRITE),
+        Descriptor::File(_) => (FILETYPE_REGULAR_FILE, u64::MAX),
+        Descriptor::Dir { .. } => (FILETYPE_DIRECTORY, u64::MAX),
+    };
+
+    // struct fdstat { filetype: u8, flags: u16, rights_base: u64, rights_inheriting: u64 }
+    let buf = arg_u32(args, 1)?;
+    write_bytes(caller, buf, &[filetype, 0, 0, 0, 0, 0, 0, 0])?;
+    write_u64(caller, offset(buf, 8)?, rights)?;
+    write_u64(caller, offset(buf, 16)?, rights)
+}
+
+fn fd_prestat_get(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    match ctx.fds.get(arg_u32(args, 0)?).ok_or(BADF)? {
+        Descriptor::Dir {
+            preopen: Some(name),
+            ..
+        } => {
+            // struct prestat { tag: u8, name_len: u32 }
+            let buf = arg_u32(args, 1)?;
+            write_u32(caller, buf, 0)?;
+            write_u32(caller, offset(buf, 4)?, name.len() as u32)
+        }
+        _ => Err(BADF),
+    }
+}
+
+fn fd_prestat_dir_name(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    match ctx.fds.get(arg_u32(args, 0)?).ok_or(BADF)? {
+        Descriptor::Dir {
+            preopen: Some(name),
+            ..
+        } => {
+            if (arg_u32(args, 2)? as usize) < name.len() {
+                return Err(INVAL);
+            }
+
+            write_bytes(caller, arg_u32(args, 1)?, name.as_bytes())
+        }
+        _ => Err(BADF),
+    }
+}
+
+fn is_symlink(path: &Path) -> bool {
+    path.symlink_metadata()
+        .map(|metadata| metadata.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+/// Resolves the guest's `path` relative to the directory `dir`.
+/// The path must not escape the directory, neither by `..`
+/// nor by a symlink, which points outside of it.
+fn resolve_path(dir: &Path, path: &str) -> Result<PathBuf, Errno> {
+    let root = dir.canonicalize().map_err(|err| from_io_error(&err))?;
+    let mut resolved = dir.to_path_buf();
+
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => {
+                resolved.push(part);
+
+                // A dangling symlink cannot be checked and is refused as well
+                if is_symlink(&resolved) {
+                    match resolved.canonicalize() {
+                        Ok(target) if target.starts_with(&root) => {}
+                        _ => return Err(NOTCAPABLE),
+                    }
+                }
+            }
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(NOTCAPABLE)
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+fn path_open(ctx: &mut WasiCtx, caller: &mut Caller, args: &[Value]) -> Result<(), Errno> {
+    let dir = match ctx.fds.get(arg_u32(args, 0)?).ok_or(BADF)? {
+        Descriptor::Dir { path, .. } => path.clone(),
+        _ => return Err(NOTDIR),
+    };
+
+    let path = read_bytes(caller, arg_u32(args, 2)?, arg_u32(args, 3)?)?;
+    let path = String::from_utf8(path).map_err(|_| INVAL)?;
+    let path = resolve_path(&dir, &path)?;
+
+    // Without `SYMLINK_FOLLOW` the last component must not be a symlink like for `O_NOFOLLOW`
+    if arg_u32(args, 1)? & LOOKUPFLAGS_SYMLINK_FOLLOW == 0 && is_symlink(&path) {
+        return Err(LOOP);
+    }
+
+    let oflags = arg_u32(args, 4)?;
+    let rights = arg_u64(args, 5)?;
+    let fdflags = arg_u32(args, 7)?;
+
+    let append = fdflags & FDFLAGS_APPEND != 0;
+    let write = rights & RIGHTS_FD_WRITE != 0 || append;
+
+    let descriptor = if oflags & OFLAGS_DIRECTORY != 0 || path.is_dir() {
+        if !path.is_dir() {
+            return Err(NOTDIR);
+        }
+
+        if write {
+            return Err(ISDIR);
+        }
+
+        Descriptor::Dir {
+            path,
+            preopen: None,
+        }
+    } else {
+        let create = oflags & OFLAGS_CREAT != 0;
+        let truncate = oflags & OFLAGS_TRUNC != 0;
+
+        if (create || truncate) && !write {
+            return Err(PERM);
+        }
+
+        let file = OpenOptions::new()
+            .read(rights & RIGHTS_FD_READ != 0 || !write)
+            .write(write)
+            .append(append)
+            .truncate(truncate)
+            .create(create)
+            .create_new(create && oflags & OFLAGS_EXCL != 0)
+            .open(&path)
+            .map_err(|err| from_io_error(&err))?;
+
+        Descriptor::File(file)
+    };
+
+    let fd = ctx.fds.insert(descriptor);
+
+    write_u32(caller, arg_u32(args, 8)?, fd)
+}
@@ -1,14 +1,16 @@
-use crate::json::{ActionType, AssertReturn, Command, FailedCommand};
+use crate::json::{ActionType, AssertReturn, AssertTrap, Command, FailedCommand};
 use anyhow::{Context, Result};
 use funky::debugger::RelativeProgramCounter;
 use funky::engine::import_resolver::{Import, Imports};
+use funky::engine::linker::Linker;
+use funky::engine::memory::MemoryInstance;
 use funky::engine::module::ModuleInstance;
-use funky::engine::stack::StackContent;
 use funky::engine::store::GlobalInstance;
 use funky::engine::Engine;
 use funky::engine::TableInstance;
 use funky::value::Value;
-use funky::{parse, validate};
+use wasm_parser::core::{FunctionSignature, ModuleAddr, ValueType};
+use funky::{parse, validate, Trap};
 use log::debug;
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -80,7 +82,7 @@ impl TestFile {
 
     pub fn get_cases(&self) -> impl Iterator<Item=&Command> {
         self.commands.iter().filter(
-            |x| matches!(x, Command::Module(_) | Command::AssertReturn(_) | Command::Action(_)),
+            |x| matches!(x, Command::Module(_) | Command::AssertReturn(_) | Command::AssertTrap(_) | Command::AssertExhaustion(_) | Command::Action(_)),
         )
     }
 
@@ -94,21 +96,31 @@ impl TestFile {
             .collect::<Vec<_>>()
     }
 
-    pub fn setup(&mut self, name: &str) -> Result<Engine> {
+    /// Instantiates the module of the file `name`.
+    /// All modules of a test file share the store of one engine.
+    pub fn setup(&mut self, engine: &mut Option<Engine>, linker: &Linker, name: &str) -> Result<ModuleAddr> {
         let reader = self.read_wasm(&format!("testsuite/{}", name))?;
         let module = parse(reader).context(format!("Parsing failed for {}", name))?;
         let validation = validate(&module); //TODO check validation
-        let (mi, functions) = ModuleInstance::new(&module);
 
         let spectest_import = self.get_spectest_import();
 
-        Engine::new(
-            mi,
-            &functions,
-            &module,
-            Box::new(RelativeProgramCounter::default()),
-            &spectest_import,
-        )
+        match engine {
+            Some(engine) => linker.instantiate(engine, &module, spectest_import),
+            None => {
+                let (mi, functions) = ModuleInstance::new(&module);
+
+                let instance = engine.insert(Engine::new(
+                    mi,
+                    &functions,
+                    &module,
+                    Box::new(RelativeProgramCounter::default()),
+                    spectest_import,
+                )?);
+
+                Ok(instance.get_module_addr().clone())
+            }
+        }
     }
 
     fn read_wasm(&self, fs: &str) -> Result<Vec<u8>> {
@@ -125,6 +137,8 @@ impl TestFile {
     }
 
     pub fn run_cases(&mut self, statistic: &mut Statistic) -> Result<()> {
+        let mut engine = None;
+        let mut linker = Linker::new();
         let mut named_modules = HashMap::new();
 
         let cases = self.commands.clone();
@@ -136,35 +150,50 @@ impl TestFile {
         for case in cases {
             match case {
                 Command::Module(m) => {
+                    let addr = self.setup(&mut engine, &linker, &m.filename)?;
+
                     if let Some(ref name) = m.name {
-                        let engine = self.setup(name)?;
-                        named_modules.insert(name.clone(), engine);
-                    } else {
-                        // Save unnamed modules as "last"
-                        let mut engine = self.setup(&m.filename)?;
-                        named_modules.remove("last"); // Delete old value
-                        named_modules.insert("last".to_string(), engine);
+                        named_modules.insert(name.clone(), addr.clone());
                     }
 
+                    // Save every module also as "last"
+                    named_modules.insert("last".to_string(), addr);
+
                     statistic.success();
                 }
+                Command::Register(ref r) => {
+                    let name = r.name.as_deref().unwrap_or("last");
+                    let addr = named_modules
+                        .get(name)
+                        .with_context(|| format!("Cannot register unknown module {}", name))?;
+
+                    linker.register(r.as_name.clone(), addr.clone());
+                }
                 Command::AssertReturn(ref x) => {
-                    let mut engine;
+                    let name = x.action.module.as_deref().unwrap_or("last");
+                    let engine = engine.as_mut().context("No module was instantiated")?;
 
-                    if let Some(ref name) = x.action.module {
-                        engine = named_modules.get_mut(name).unwrap();
-                    } else {
-                        engine = named_modules.get_mut("last").unwrap();
-                    }
+                    engine.select_module(named_modules.get(name).unwrap())?;
 
                     let mut actuals = Vec::new();
-                    let result = self.run_assert_return(&mut engine, x, &mut actuals);
+                    let result = self.run_assert_return(engine, x, &mut actuals);
 
                     match result {
                         true => statistic.success(),
                         false => statistic.failed(actuals, case),
                     }
                 }
+                Command::AssertTrap(ref x) | Command::AssertExhaustion(ref x) => {
+                    let name = x.action.module.as_deref().unwrap_or("last");
+                    let engine = engine.as_mut().context("No module was instantiated")?;
+
+                    engine.select_module(named_modules.get(name).unwrap())?;
+
+                    match self.run_assert_trap(engine, x) {
+                        true => statistic.success(),
+                        false => statistic.failed(Vec::new(), case),
+                    }
+                }
                 _ => {}
             }
         }
@@ -178,10 +207,10 @@ impl TestFile {
         case: &AssertReturn,
         actuals: &mut Vec<Value>,
     ) -> bool {
-        let expected: Vec<_> = case.get_expected().iter().copied().collect();
+        let expected: Vec<_> = case.get_expected().to_vec();
         let args = case.get_args();
 
-        let mut extracted_actuals = match case.action.ty {
+        let results = match case.action.ty {
             ActionType::Invoke => {
                 debug!(
                     "Invoking with {} and {:?}",
@@ -189,35 +218,20 @@ impl TestFile {
                     args
                 );
 
-                if let Err(err) =
                 engine.invoke_exported_function_by_name(&case.action.field, args)
-                {
-                    debug!("failed for lineno {}", case.line);
-                    return false;
-                }
-
-                let mut actuals = Vec::new();
-
-                while let Some(StackContent::Value(val)) = engine.store.stack.pop() {
-                    actuals.push(val);
-                }
-
-                actuals.into_iter().rev().collect()
-            }
-            ActionType::Get => {
-                let res = engine.get(&case.action.field);
-                if let Err(ref err) = res {
-                    debug!("failed for lineno {}", case.line);
-                    return false;
-                } else {
-                    vec![res.unwrap()]
-                }
             }
+            ActionType::Get => engine.get(&case.action.field).map(|w| vec![w]),
         };
 
-        //actuals.append(&mut extracted_actuals.into_iter().rev().collect());
+        match results {
+            Ok(results) => *actuals = results,
+            Err(_) => {
+                debug!("failed for lineno {}", case.line);
+                return false;
+            }
+        }
 
-        let mut total_do_match = true;
+        let mut total_do_match = actuals.len() == expected.len();
         for i in 0..actuals.len() {
             let do_match = match (actuals.get(i), expected.get(i)) {
                 (Some(Value::F32(f1)), Some(Value::F32(f2))) if f1.is_nan() && f2.is_nan() => true,
@@ -233,6 +247,31 @@ impl TestFile {
         total_do_match
     }
 
+    /// Checks that invoking the action traps with the expected message
+    fn run_assert_trap(&self, engine: &mut Engine, case: &AssertTrap) -> bool {
+        let args = case.get_args();
+
+        let result = match case.action.ty {
+            ActionType::Invoke => engine.invoke_exported_function_by_name(&case.action.field, args),
+            ActionType::Get => engine.get(&case.action.field).map(|w| vec![w]),
+        };
+
+        match result {
+            Ok(_) => {
+                debug!("no trap for lineno {}", case.line);
+                false
+            }
+            Err(err) => match Trap::from_error(&err) {
+                // Some messages of the spec tests have a suffix
+                Some(trap) => case.text.starts_with(&trap.to_string()),
+                None => {
+                    debug!("failed without trap for lineno {}: {:?}", case.line, err);
+                    false
+                }
+            },
+        }
+    }
+
     /// The default imports
     fn get_spectest_import(&self) -> Imports {
         let mut imports = Imports::new();
@@ -259,12 +298,42 @@ impl TestFile {
             "global_f64".to_string(),
             GlobalInstance::immutable(funky::value::Value::F64(666.6)),
         ));
+        imports.push(Import::Memory(
+            module.clone(),
+            "memory".to_string(),
+            MemoryInstance::new(1, Some(2)).unwrap().shared(),
+        ));
         imports.push(Import::Table(
-            module,
+            module.clone(),
             "table".to_string(),
-            TableInstance::new(10, Some(20)),
+            TableInstance::new(ValueType::FuncRef, 10, Some(20)),
         ));
 
+        let prints = [
+            ("print", vec![]),
+            ("print_i32", vec![ValueType::I32]),
+            ("print_i64", vec![ValueType::I64]),
+            ("print_f32", vec![ValueType::F32]),
+            ("print_f64", vec![ValueType::F64]),
+            ("print_i32_f32", vec![ValueType::I32, ValueType::F32]),
+            ("print_f64_f64", vec![ValueType::F64, ValueType::F64]),
+        ];
+
+        for (name, param_types) in prints.iter() {
+            imports.push(Import::Function(
+                module.clone(),
+                name.to_string(),
+                FunctionSignature {
+                    param_types: param_types.clone(),
+                    return_types: vec![],
+                },
+                Box::new(|_, args| {
+                    debug!("spectest print {:?}", args);
+                    Ok(vec![])
+                }),
+            ));
+        }
+
         imports
     }
 }
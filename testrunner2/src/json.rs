@@ -1,4 +1,5 @@
 use funky::value::Value;
+use wasm_parser::core::ExternAddr;
 use serde::Deserialize;
 use crate::core::*;
 
@@ -12,15 +13,15 @@ pub(crate) enum Command {
     #[serde(rename = "assert_invalid")]
     AssertInvalid(AssertInvalid),
     #[serde(rename = "assert_trap")]
-    AssertTrap, //TODO
+    AssertTrap(AssertTrap),
     #[serde(rename = "assert_malformed")]
     AssertMalformed, //TODO
     #[serde(rename = "register")]
-    Register, //TODO
+    Register(Register),
     #[serde(rename = "assert_unlinkable")]
     AssertUnlinkable, //TODO
     #[serde(rename = "assert_exhaustion")]
-    AssertExhaustion, //TODO
+    AssertExhaustion(AssertTrap),
     #[serde(rename = "action")]
     Action(AssertReturn),
     #[serde(rename = "assert_uninstantiable")]
@@ -59,6 +60,34 @@ pub(crate) struct Module {
     pub name: Option<String>,
 }
 
+/// The action must fail with the trap `text`
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub(crate) struct AssertTrap {
+    pub line: usize,
+    pub action: Action,
+    pub text: String,
+}
+
+impl AssertTrap {
+    pub fn get_args(&self) -> Vec<Value> {
+        self.action
+            .args
+            .iter()
+            .map(|w| w.clone().into())
+            .collect::<Vec<_>>()
+    }
+}
+
+/// Registers the module `name` (or the last module)
+/// for the imports of later modules
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub(crate) struct Register {
+    line: usize,
+    pub name: Option<String>,
+    #[serde(rename = "as")]
+    pub as_name: String,
+}
+
 #[derive(Debug, Clone, Deserialize, PartialEq)]
 pub(crate) struct AssertReturn {
     pub line: usize,
@@ -156,6 +185,11 @@ impl From<Argument> for Value {
 
                 Value::F64(from_bits_f64(e.value.parse().unwrap()))
             }
+            "externref" => match e.value.as_str() {
+                "null" => Value::ExternRef(None),
+                v => Value::ExternRef(Some(ExternAddr::new(v.parse().unwrap()))),
+            },
+            "funcref" if e.value.as_str() == "null" => Value::FuncRef(None),
             _ => panic!(""),
         };
 
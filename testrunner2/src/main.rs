@@ -115,6 +115,7 @@ fn report_total(total: f64) -> Result<()> {
         .read(false)
         .write(true)
         .create(true)
+        .truncate(true)
         .open(".testrunner")
         .context("Cannot create .testrunner")?;
 
@@ -137,7 +138,7 @@ fn get_testfiles() -> Vec<TestFile> {
                 .into_string()
                 .unwrap()
                 .split('.')
-                .last()
+                .next_back()
                 .unwrap()
                 == "json"
         })
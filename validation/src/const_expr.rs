@@ -0,0 +1,91 @@
+//! The evaluation of constant expressions, which are used as the initializers
+//! of globals and as the offsets of active segments.
+//! Validation evaluates them over types and instantiation over values.
+//! https://github.com/WebAssembly/extended-const
+
+use anyhow::{anyhow, Result};
+use wasm_parser::core::*;
+
+/// The arithmetic instructions of the extended-const proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    I32Add,
+    I32Sub,
+    I32Mul,
+    I64Add,
+    I64Sub,
+    I64Mul,
+}
+
+impl BinOp {
+    /// The type of the operands and the result
+    pub fn ty(&self) -> ValueType {
+        match self {
+            BinOp::I32Add | BinOp::I32Sub | BinOp::I32Mul => ValueType::I32,
+            BinOp::I64Add | BinOp::I64Sub | BinOp::I64Mul => ValueType::I64,
+        }
+    }
+}
+
+/// Defines what the instructions of a constant expression evaluate to
+pub trait ConstContext {
+    type Value;
+
+    fn i32(&self, v: i32) -> Result<Self::Value>;
+    fn i64(&self, v: i64) -> Result<Self::Value>;
+    fn f32(&self, v: f32) -> Result<Self::Value>;
+    fn f64(&self, v: f64) -> Result<Self::Value>;
+    fn v128(&self, v: u128) -> Result<Self::Value>;
+    fn ref_null(&self, ty: ValueType) -> Result<Self::Value>;
+    fn ref_func(&self, idx: FuncIdx) -> Result<Self::Value>;
+    fn global_get(&self, idx: GlobalIdx) -> Result<Self::Value>;
+    fn binary(&self, op: BinOp, lhs: Self::Value, rhs: Self::Value) -> Result<Self::Value>;
+}
+
+/// Evaluates the constant expression `init`, which must produce exactly one value
+pub fn eval_const_expr<C: ConstContext>(ctx: &C, init: &[InstructionWrapper]) -> Result<C::Value> {
+    use wasm_parser::core::Instruction::*;
+
+    let mut stack = Vec::new();
+
+    for instr in init.iter().map(|w| w.get_instruction()) {
+        let value = match instr {
+            OP_I32_CONST(v) => ctx.i32(*v)?,
+            OP_I64_CONST(v) => ctx.i64(*v)?,
+            OP_F32_CONST(v) => ctx.f32(*v)?,
+            OP_F64_CONST(v) => ctx.f64(*v)?,
+            OP_V128_CONST(v) => ctx.v128(*v)?,
+            OP_REF_NULL(ty) => ctx.ref_null(*ty)?,
+            OP_REF_FUNC(idx) => ctx.ref_func(*idx)?,
+            OP_GLOBAL_GET(idx) => ctx.global_get(*idx)?,
+            OP_I32_ADD | OP_I32_SUB | OP_I32_MUL | OP_I64_ADD | OP_I64_SUB | OP_I64_MUL => {
+                let op = match instr {
+                    OP_I32_ADD => BinOp::I32Add,
+                    OP_I32_SUB => BinOp::I32Sub,
+                    OP_I32_MUL => BinOp::I32Mul,
+                    OP_I64_ADD => BinOp::I64Add,
+                    OP_I64_SUB => BinOp::I64Sub,
+                    _ => BinOp::I64Mul,
+                };
+
+                let (rhs, lhs) = match (stack.pop(), stack.pop()) {
+                    (Some(rhs), Some(lhs)) => (rhs, lhs),
+                    _ => return Err(anyhow!("{:?} in constant expression expected two operands", instr)),
+                };
+
+                ctx.binary(op, lhs, rhs)?
+            }
+            _ => {
+                return Err(anyhow!("{:?} is not allowed in a constant expression", instr));
+            }
+        };
+
+        stack.push(value);
+    }
+
+    match stack.len() {
+        0 => Err(anyhow!("No expr to evaluate")),
+        1 => Ok(stack.pop().unwrap()),
+        n => Err(anyhow!("Constant expression produces {} values instead of one", n)),
+    }
+}
@@ -15,6 +15,20 @@ pub(crate) fn get_types(module: &Module) -> Vec<&FunctionSignature> {
     ty
 }
 
+pub fn get_code(module: &Module) -> Vec<&FunctionBody> {
+    let ty: Vec<_> = module
+        .sections
+        .iter()
+        .filter_map(|ref w| match w {
+            Section::Code(t) => Some(&t.entries),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+
+    ty
+}
+
 pub fn get_exports(module: &Module) -> Vec<&ExportEntry> {
     let ty: Vec<_> = module
         .sections
@@ -70,6 +84,19 @@ pub fn get_elements(module: &Module) -> Vec<&ElementSegment> {
     ty
 }
 
+pub fn get_data_count(module: &Module) -> Vec<&DataCountSection> {
+    let ty: Vec<_> = module
+        .sections
+        .iter()
+        .filter_map(|ref w| match w {
+            Section::DataCount(t) => Some(t),
+            _ => None,
+        })
+        .collect();
+
+    ty
+}
+
 pub fn get_data(module: &Module) -> Vec<&DataSegment> {
     let ty: Vec<_> = module
         .sections
@@ -153,8 +180,8 @@ pub fn get_tables(module: &Module) -> Vec<&TableType> {
     all
 }
 
-pub fn get_mems(module: &Module) -> Vec<&MemoryType> {
-    let ty: Vec<_> = module
+pub fn get_defined_mems(module: &Module) -> Vec<&MemoryType> {
+    module
         .sections
         .iter()
         .filter_map(|ref w| match w {
@@ -162,7 +189,18 @@ pub fn get_mems(module: &Module) -> Vec<&MemoryType> {
             _ => None,
         })
         .flatten()
-        .collect();
+        .collect()
+}
+
+pub fn get_imported_mems(module: &Module) -> Vec<&ImportEntry> {
+    get_imports(module)
+        .into_iter()
+        .filter(|x| matches!(&x.desc, ImportDesc::Memory { .. }))
+        .collect()
+}
+
+pub fn get_mems(module: &Module) -> Vec<&MemoryType> {
+    let ty = get_defined_mems(module);
 
     let imported: Vec<_> = module
         .sections
@@ -209,6 +247,30 @@ pub fn get_globals(module: &Module) -> (Vec<&GlobalVariable>, Vec<&GlobalType>)
     (ty, all)
 }
 
+/// The types of the defined tags
+pub fn get_defined_tags(module: &Module) -> Vec<TypeIdx> {
+    module
+        .sections
+        .iter()
+        .filter_map(|ref w| match w {
+            Section::Tag(t) => Some(&t.types),
+            _ => None,
+        })
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// The types of all tags. The imported tags come first.
+pub fn get_tags(module: &Module) -> Vec<TypeIdx> {
+    let imported = get_imports(module).into_iter().filter_map(|entry| match entry.desc {
+        ImportDesc::Tag { ty } => Some(ty),
+        _ => None,
+    });
+
+    imported.chain(get_defined_tags(module)).collect()
+}
+
 pub fn get_defined_globals(module: &Module) -> Vec<&GlobalVariable> {
     let ty: Vec<_> = module
         .sections
@@ -249,3 +311,27 @@ pub fn get_imported_globals(module: &Module) -> Vec<&ImportEntry> {
 
     imported
 }
+
+/// The functions, which are referenced outside of the code.
+/// Only them can be referenced by `ref.func`.
+/// https://webassembly.github.io/spec/core/valid/modules.html#valid-module
+pub fn get_declared_refs(module: &Module) -> Vec<FuncIdx> {
+    let elements = get_elements(module)
+        .into_iter()
+        .flat_map(|e| e.init.iter().flatten().copied());
+
+    let exports = get_exports(module).into_iter().filter_map(|e| match e.kind {
+        ExternalKindType::Function { ty } => Some(ty),
+        _ => None,
+    });
+
+    let globals = get_defined_globals(module)
+        .into_iter()
+        .flat_map(|g| g.init.iter())
+        .filter_map(|instr| match instr.get_instruction() {
+            Instruction::OP_REF_FUNC(idx) => Some(*idx),
+            _ => None,
+        });
+
+    elements.chain(exports).chain(globals).collect()
+}
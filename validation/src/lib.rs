@@ -2,9 +2,11 @@ use anyhow::{anyhow, Result};
 use wasm_parser::core::*;
 use wasm_parser::Module;
 
+pub mod const_expr;
 pub mod extract;
 //pub mod instructions;
 
+use const_expr::*;
 use extract::*;
 
 use log::{debug, error};
@@ -16,6 +18,7 @@ pub struct FuncType {
 }
 
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 struct Context<'a> {
     types: Vec<&'a FunctionSignature>,
     functions: Vec<FuncType>,
@@ -23,14 +26,21 @@ struct Context<'a> {
     mems: Vec<&'a MemoryType>,
     global_entries: Vec<&'a GlobalVariable>,
     globals_ty: Vec<&'a GlobalType>,
+    elems: Vec<&'a ElementSegment>,
+    /// The types of the tags
+    tags: Vec<TypeIdx>,
+    /// The number of data segments, when the data count section is defined
+    data_count: Option<u32>,
+    /// The functions, which can be referenced by `ref.func`
+    refs: Vec<FuncIdx>,
     locals: Vec<()>,  //TODO
     labels: Vec<()>,  //TODO
     _return: Vec<()>, //TODO
 }
 
 pub fn validate(module: &Module) -> Result<()> {
-    let types = get_types(&module);
-    let mod_ty = get_funcs(&module);
+    let types = get_types(module);
+    let mod_ty = get_funcs(module);
     let functions: Vec<Result<_>> = mod_ty
         .iter()
         .map(|w| get_ty_of_function(&types, *w as usize))
@@ -43,9 +53,13 @@ pub fn validate(module: &Module) -> Result<()> {
         }
     }
 
-    let tables = get_tables(&module);
-    let mems = get_mems(&module);
-    let (global_entries, globals_ty) = get_globals(&module);
+    let tables = get_tables(module);
+    let mems = get_mems(module);
+    let (global_entries, globals_ty) = get_globals(module);
+    let elems = get_elements(module);
+    let tags = get_tags(module);
+    let data_count = get_data_count(module).first().map(|section| section.count);
+    let refs = get_declared_refs(module);
 
     let c = Context {
         types,
@@ -54,20 +68,24 @@ pub fn validate(module: &Module) -> Result<()> {
         mems,
         global_entries,
         globals_ty,
+        elems,
+        tags,
+        data_count,
+        refs,
         locals: Vec::new(),
         labels: Vec::new(),
         _return: Vec::new(),
     };
 
-    c.validate(&module)?;
+    c.validate(module)?;
 
     Ok(())
 }
 
 impl<'a> Context<'a> {
     pub fn get_c_prime(&self) -> Self {
-        let copied = (&self.globals_ty).to_vec(); //TODO is this really copied?
-        let copied2 = (&self.global_entries).to_vec(); //TODO is this really copied?
+        let copied = self.globals_ty.to_vec(); //TODO is this really copied?
+        let copied2 = self.global_entries.to_vec(); //TODO is this really copied?
 
         Context {
             types: Vec::new(),
@@ -76,6 +94,10 @@ impl<'a> Context<'a> {
             mems: Vec::new(),
             global_entries: copied2,
             globals_ty: copied,
+            elems: Vec::new(),
+            tags: Vec::new(),
+            data_count: None,
+            refs: Vec::new(),
             locals: Vec::new(),
             labels: Vec::new(),
             _return: Vec::new(),
@@ -102,7 +124,14 @@ impl<'a> Context<'a> {
         //https://webassembly.github.io/spec/core/valid/types.html#table-types
 
         // table tables are valid when the limit is in u32 range
-        // that's statically guaranted
+        // that's statically guaranted, but only memories can be shared
+
+        for table in self.tables.iter() {
+            if let Limits::Shared(..) = table.limits {
+                return Err(anyhow!("Table cannot be shared"));
+            }
+        }
+
         debug!("TablesTypes are valid");
 
         // Check mem
@@ -113,13 +142,21 @@ impl<'a> Context<'a> {
 
         debug!("MemoryTypes are valid");
 
+        // Check tag
+
+        for ty in self.tags.iter() {
+            check_tag_ty(*ty, &self.types)?;
+        }
+
+        debug!("TagTypes are valid");
+
         // Check global
 
         for entry in self.global_entries.iter() {
             // For each global under the Context C'
 
             let init = &entry.init;
-            let init_expr_ty = get_expr_const_ty_global(init, &c_prime.globals_ty)?;
+            let init_expr_ty = get_expr_const_ty(init, &c_prime.globals_ty)?;
 
             if entry.ty.value_type != init_expr_ty {
                 //Expr has not the same type as the global
@@ -131,9 +168,8 @@ impl<'a> Context<'a> {
 
         // Check elem
 
-        let elements = get_elements(module);
-        for elem in elements {
-            check_elem_ty(elem, &self.tables, &self.functions)?;
+        for elem in self.elems.iter() {
+            check_elem_ty(elem, &self.tables, &self.functions, &self.globals_ty)?;
         }
 
         debug!("Elements are valid");
@@ -141,17 +177,43 @@ impl<'a> Context<'a> {
         // Check data
 
         let data = get_data(module);
-        for d in data {
-            check_data_ty(d, &self.mems)?;
+        for d in data.iter() {
+            check_data_ty(d, &self.mems, &self.globals_ty)?;
+        }
+
+        if let Some(count) = self.data_count {
+            if count as usize != data.len() {
+                return Err(anyhow!(
+                    "Data count {} does not match the {} data segments",
+                    count,
+                    data.len()
+                ));
+            }
         }
 
         debug!("Data is valid");
 
+        // Check code
+
+        // The imported functions are first in the index space
+        let code = get_code(module);
+        let imported = self
+            .functions
+            .len()
+            .checked_sub(code.len())
+            .ok_or_else(|| anyhow!("There are more function bodies than functions"))?;
+
+        for (body, ty) in code.iter().zip(&self.functions[imported..]) {
+            self.check_instructions(&body.code, &ty.return_types)?;
+        }
+
+        debug!("Instructions are valid");
+
         // Start
 
         let start = get_start(module);
 
-        if let Some(s) = start.get(0) {
+        if let Some(s) = start.first() {
             check_start(s, &self.functions)?;
         }
 
@@ -178,6 +240,7 @@ impl<'a> Context<'a> {
                 &self.tables,
                 &self.mems,
                 &self.globals_ty,
+                &self.tags,
             )?;
         }
 
@@ -193,14 +256,6 @@ impl<'a> Context<'a> {
 }
 
 fn check_lengths(c: &Context) -> Result<()> {
-    // tables must not be larger than 1
-
-    if c.tables.len() > 1 {
-        return Err(anyhow!("More than one table"));
-    }
-
-    debug!("Table size is ok");
-
     // Memory must not be larger than 1
 
     if c.mems.len() > 1 {
@@ -227,56 +282,104 @@ fn check_export_names(exports: &[&ExportEntry]) -> Result<()> {
     Ok(())
 }
 
-/// Evalutes the expr `init` and checks if it returns const
-fn get_expr_const_ty_global(
-    init: &[InstructionWrapper],
-    globals_ty: &[&GlobalType],
-) -> Result<ValueType> {
-    use wasm_parser::core::Instruction::*;
-
-    if init.is_empty() {
-        return Err(anyhow!("No expr to evaluate"));
-    }
-
-    match init.get(0).unwrap().get_instruction() {
-        OP_I32_CONST(_) => Ok(ValueType::I32),
-        OP_I64_CONST(_) => Ok(ValueType::I64),
-        OP_F32_CONST(_) => Ok(ValueType::F32),
-        OP_F64_CONST(_) => Ok(ValueType::F64),
-        OP_GLOBAL_GET(lidx) => match globals_ty.get(*lidx as usize).as_ref() {
-            Some(global) => {
-                if global.mu == Mu::Var {
-                    return Err(anyhow!("Global var is mutable"));
-                }
+/// Evaluates the types of constant expressions
+struct ConstTypes<'a, 'b> {
+    globals_ty: &'b [&'a GlobalType],
+}
 
-                Ok(global.value_type)
-            }
+impl ConstContext for ConstTypes<'_, '_> {
+    type Value = ValueType;
+
+    fn i32(&self, _: i32) -> Result<ValueType> {
+        Ok(ValueType::I32)
+    }
+
+    fn i64(&self, _: i64) -> Result<ValueType> {
+        Ok(ValueType::I64)
+    }
+
+    fn f32(&self, _: f32) -> Result<ValueType> {
+        Ok(ValueType::F32)
+    }
+
+    fn f64(&self, _: f64) -> Result<ValueType> {
+        Ok(ValueType::F64)
+    }
+
+    fn v128(&self, _: u128) -> Result<ValueType> {
+        Ok(ValueType::V128)
+    }
+
+    fn ref_null(&self, ty: ValueType) -> Result<ValueType> {
+        if !ty.is_ref() {
+            return Err(anyhow!("ref.null expected a reference type, got {:?}", ty));
+        }
+
+        Ok(ty)
+    }
+
+    fn ref_func(&self, _: FuncIdx) -> Result<ValueType> {
+        Ok(ValueType::FuncRef)
+    }
+
+    fn global_get(&self, idx: GlobalIdx) -> Result<ValueType> {
+        match self.globals_ty.get(idx as usize) {
+            Some(global) if global.mu == Mu::Var => Err(anyhow!("Global var is mutable")),
+            Some(global) => Ok(global.value_type),
             None => Err(anyhow!("Global does not exist")),
-        },
-        _ => Err(anyhow!("Wrong expression")),
+        }
     }
+
+    fn binary(&self, op: BinOp, lhs: ValueType, rhs: ValueType) -> Result<ValueType> {
+        if lhs != op.ty() || rhs != op.ty() {
+            return Err(anyhow!("{:?} expected operands of {:?}, got {:?} and {:?}", op, op.ty(), lhs, rhs));
+        }
+
+        Ok(op.ty())
+    }
+}
+
+/// Evaluates the type of the constant expression `init`
+fn get_expr_const_ty(init: &[InstructionWrapper], globals_ty: &[&GlobalType]) -> Result<ValueType> {
+    eval_const_expr(&ConstTypes { globals_ty }, init)
+}
+
+/// Checks if the constant expression `init` has the type `expected`
+fn check_expr_const_ty(init: &[InstructionWrapper], globals_ty: &[&GlobalType], expected: ValueType) -> Result<()> {
+    let ty = get_expr_const_ty(init, globals_ty)?;
+
+    if ty != expected {
+        return Err(anyhow!("Expression is not a {:?} const, but {:?}", expected, ty));
+    }
+
+    Ok(())
 }
 
 fn check_elem_ty(
     elem_ty: &ElementSegment,
     tables: &[&TableType],
     func_ty: &[FuncType],
+    globals_ty: &[&GlobalType],
 ) -> Result<bool> {
     debug!("check_elem_ty");
     //https://webassembly.github.io/spec/core/valid/modules.html#element-segments
 
-    let table_idx = &elem_ty.table;
-    let offset = &elem_ty.offset;
-    let funcs_idx = &elem_ty.init;
+    if let ElementMode::Active { table, offset } = &elem_ty.mode {
+        match tables.get(*table as usize) {
+            None => return Err(anyhow!("No table defined for element's index")),
+            Some(t) if t.element_type != elem_ty.ty => {
+                return Err(anyhow!("Element segment has not the type of the table"))
+            }
+            _ => {}
+        }
 
-    if tables.get(*table_idx as usize).is_none() {
-        return Err(anyhow!("No table defined for element's index"));
+        check_expr_const_ty(offset, globals_ty, ValueType::I32)?;
     }
 
-    get_expr_const_i32_ty(offset)?;
-
-    let not_def_funcs: Vec<_> = funcs_idx
+    let not_def_funcs: Vec<_> = elem_ty
+        .init
         .iter()
+        .flatten()
         .filter(|w| func_ty.get(**w as usize).is_none())
         .collect();
 
@@ -291,29 +394,22 @@ fn check_elem_ty(
     Ok(true)
 }
 
-/// Evalutes the expr `init` and checks if it returns const and I32
-fn get_expr_const_i32_ty(init: &[InstructionWrapper]) -> Result<ValueType> {
-    if init.is_empty() {
-        return Err(anyhow!("No expr to evaluate"));
-    }
-
-    match init.get(0).unwrap().get_instruction() {
-        Instruction::OP_I32_CONST(_) => Ok(ValueType::I32),
-        _ => Err(anyhow!("Expression is not a I32 const")),
-    }
-}
-
-fn check_data_ty(data_ty: &DataSegment, memtypes: &[&MemoryType]) -> Result<bool> {
+fn check_data_ty(data_ty: &DataSegment, memtypes: &[&MemoryType], globals_ty: &[&GlobalType]) -> Result<bool> {
     //https://webassembly.github.io/spec/core/valid/modules.html#data-segments
 
-    let mem_idx = data_ty.data;
-    let offset = &data_ty.offset;
+    if let DataMode::Active { memory, offset } = &data_ty.mode {
+        let memtype = memtypes
+            .get(*memory as usize)
+            .unwrap_or_else(|| panic!("Memory does not exist"));
 
-    if memtypes.get(mem_idx as usize).is_none() {
-        panic!("Memory does not exist");
-    }
+        // The offset is an address of the memory's index type
+        let expected = match memtype.index_type {
+            IndexType::I32 => ValueType::I32,
+            IndexType::I64 => ValueType::I64,
+        };
 
-    get_expr_const_i32_ty(&offset)?;
+        check_expr_const_ty(offset, globals_ty, expected)?;
+    }
 
     Ok(true)
 }
@@ -324,7 +420,7 @@ fn check_start(start: &StartSection, functypes: &[FuncType]) -> Result<bool> {
     let fidx = start.index;
 
     if let Some(f) = functypes.get(fidx as usize).as_ref() {
-        if !f.param_types.is_empty() && !f.return_types.is_empty() {
+        if !f.param_types.is_empty() || !f.return_types.is_empty() {
             error!("Function {:?}", f);
             return Err(anyhow!("Function is not a valid start function"));
         }
@@ -333,6 +429,400 @@ fn check_start(start: &StartSection, functypes: &[FuncType]) -> Result<bool> {
     Ok(true)
 }
 
+impl<'a> Context<'a> {
+    /// Checks the indices of the instructions, which refer
+    /// to the definitions of the module.
+    /// The blocks can have several parameters and results,
+    /// which are defined by a function type.
+    /// `results` are the return types of the function of the code.
+    fn check_instructions(&self, code: &[InstructionWrapper], results: &[ValueType]) -> Result<()> {
+        use wasm_parser::core::Instruction::*;
+
+        for instruction in code {
+            match instruction.get_instruction() {
+                OP_BLOCK(ty, block) | OP_LOOP(ty, block) | OP_IF(ty, block) => {
+                    check_block_type(ty, &self.types)?;
+                    self.check_instructions(block.get_instructions(), results)?;
+                }
+                OP_IF_AND_ELSE(ty, block_1, block_2) => {
+                    check_block_type(ty, &self.types)?;
+                    self.check_instructions(block_1.get_instructions(), results)?;
+                    self.check_instructions(block_2.get_instructions(), results)?;
+                }
+                OP_TRY(ty, block, catches) => {
+                    check_block_type(ty, &self.types)?;
+                    self.check_instructions(block.get_instructions(), results)?;
+
+                    for catch in catches {
+                        let handler = match catch {
+                            Catch::Tag(tag_idx, handler) => {
+                                self.check_tag_idx(*tag_idx)?;
+                                handler
+                            }
+                            Catch::All(handler) => handler,
+                        };
+
+                        self.check_instructions(handler.get_instructions(), results)?;
+                    }
+                }
+                OP_TRY_DELEGATE(ty, block, _) => {
+                    check_block_type(ty, &self.types)?;
+                    self.check_instructions(block.get_instructions(), results)?;
+                }
+                OP_THROW(tag_idx) => {
+                    self.check_tag_idx(*tag_idx)?;
+                }
+                OP_MEMORY_INIT(data_idx) => {
+                    self.check_memory()?;
+                    self.check_data_idx(*data_idx)?;
+                }
+                OP_DATA_DROP(data_idx) => {
+                    self.check_data_idx(*data_idx)?;
+                }
+                OP_MEMORY_COPY | OP_MEMORY_FILL => {
+                    self.check_memory()?;
+                }
+                OP_TABLE_INIT(elem_idx, table_idx) => {
+                    let table = self.check_table_idx(*table_idx)?;
+                    let elem = self.check_elem_idx(*elem_idx)?;
+
+                    if table.element_type != elem.ty {
+                        return Err(anyhow!("Element segment {} has not the type of the table", elem_idx));
+                    }
+                }
+                OP_ELEM_DROP(elem_idx) => {
+                    self.check_elem_idx(*elem_idx)?;
+                }
+                OP_TABLE_COPY(dst, src) => {
+                    let dst_table = self.check_table_idx(*dst)?;
+                    let src_table = self.check_table_idx(*src)?;
+
+                    if dst_table.element_type != src_table.element_type {
+                        return Err(anyhow!("Table {} and table {} have different types", dst, src));
+                    }
+                }
+                OP_CALL_INDIRECT(_, table_idx) => {
+                    let table = self.check_table_idx(*table_idx)?;
+
+                    if table.element_type != ValueType::FuncRef {
+                        return Err(anyhow!("Table {} of call_indirect is not a funcref table", table_idx));
+                    }
+                }
+                OP_RETURN_CALL(func_idx) => {
+                    let callee = self
+                        .functions
+                        .get(*func_idx as usize)
+                        .ok_or_else(|| anyhow!("Function {} is not defined", func_idx))?;
+
+                    // The callee returns to the caller of the function
+                    if callee.return_types != results {
+                        return Err(anyhow!(
+                            "return_call of function {} returns {:?}, but expected {:?}",
+                            func_idx,
+                            callee.return_types,
+                            results
+                        ));
+                    }
+                }
+                OP_RETURN_CALL_INDIRECT(type_idx, table_idx) => {
+                    let table = self.check_table_idx(*table_idx)?;
+
+                    if table.element_type != ValueType::FuncRef {
+                        return Err(anyhow!(
+                            "Table {} of return_call_indirect is not a funcref table",
+                            table_idx
+                        ));
+                    }
+
+                    let ty = self
+                        .types
+                        .get(*type_idx as usize)
+                        .ok_or_else(|| anyhow!("Function type {} is not defined", type_idx))?;
+
+                    if ty.return_types != results {
+                        return Err(anyhow!(
+                            "return_call_indirect of type {} returns {:?}, but expected {:?}",
+                            type_idx,
+                            ty.return_types,
+                            results
+                        ));
+                    }
+                }
+                OP_TABLE_GET(table_idx)
+                | OP_TABLE_SET(table_idx)
+                | OP_TABLE_SIZE(table_idx)
+                | OP_TABLE_GROW(table_idx)
+                | OP_TABLE_FILL(table_idx) => {
+                    self.check_table_idx(*table_idx)?;
+                }
+                OP_REF_NULL(ty) if !ty.is_ref() => {
+                    return Err(anyhow!("ref.null expected a reference type, got {:?}", ty));
+                }
+                OP_REF_FUNC(func_idx) => {
+                    if self.functions.get(*func_idx as usize).is_none() {
+                        return Err(anyhow!("Function {} is not defined", func_idx));
+                    }
+
+                    if !self.refs.contains(func_idx) {
+                        return Err(anyhow!("Function {} is not declared for ref.func", func_idx));
+                    }
+                }
+                OP_SELECT_T(types) if types.len() != 1 => {
+                    return Err(anyhow!("Typed select expected one type, got {}", types.len()));
+                }
+                OP_I32_LOAD(arg)
+                | OP_I64_LOAD(arg)
+                | OP_F32_LOAD(arg)
+                | OP_F64_LOAD(arg)
+                | OP_I32_LOAD_8_s(arg)
+                | OP_I32_LOAD_8_u(arg)
+                | OP_I32_LOAD_16_s(arg)
+                | OP_I32_LOAD_16_u(arg)
+                | OP_I64_LOAD_8_s(arg)
+                | OP_I64_LOAD_8_u(arg)
+                | OP_I64_LOAD_16_s(arg)
+                | OP_I64_LOAD_16_u(arg)
+                | OP_I64_LOAD_32_s(arg)
+                | OP_I64_LOAD_32_u(arg)
+                | OP_I32_STORE(arg)
+                | OP_I64_STORE(arg)
+                | OP_F32_STORE(arg)
+                | OP_F64_STORE(arg)
+                | OP_I32_STORE_8(arg)
+                | OP_I32_STORE_16(arg)
+                | OP_I64_STORE_8(arg)
+                | OP_I64_STORE_16(arg)
+                | OP_I64_STORE_32(arg) => {
+                    self.check_offset(arg)?;
+                }
+                OP_V128_LOAD(arg) | OP_V128_STORE(arg) => {
+                    self.check_memarg(arg, 16)?;
+                }
+                OP_V128_LOAD8X8_S(arg)
+                | OP_V128_LOAD8X8_U(arg)
+                | OP_V128_LOAD16X4_S(arg)
+                | OP_V128_LOAD16X4_U(arg)
+                | OP_V128_LOAD32X2_S(arg)
+                | OP_V128_LOAD32X2_U(arg)
+                | OP_V128_LOAD64_SPLAT(arg)
+                | OP_V128_LOAD64_ZERO(arg) => {
+                    self.check_memarg(arg, 8)?;
+                }
+                OP_V128_LOAD8_SPLAT(arg) => self.check_memarg(arg, 1)?,
+                OP_V128_LOAD16_SPLAT(arg) => self.check_memarg(arg, 2)?,
+                OP_V128_LOAD32_SPLAT(arg) | OP_V128_LOAD32_ZERO(arg) => {
+                    self.check_memarg(arg, 4)?;
+                }
+                OP_V128_LOAD8_LANE(arg, lane) | OP_V128_STORE8_LANE(arg, lane) => {
+                    self.check_memarg(arg, 1)?;
+                    check_lane(*lane, 16)?;
+                }
+                OP_V128_LOAD16_LANE(arg, lane) | OP_V128_STORE16_LANE(arg, lane) => {
+                    self.check_memarg(arg, 2)?;
+                    check_lane(*lane, 8)?;
+                }
+                OP_V128_LOAD32_LANE(arg, lane) | OP_V128_STORE32_LANE(arg, lane) => {
+                    self.check_memarg(arg, 4)?;
+                    check_lane(*lane, 4)?;
+                }
+                OP_V128_LOAD64_LANE(arg, lane) | OP_V128_STORE64_LANE(arg, lane) => {
+                    self.check_memarg(arg, 8)?;
+                    check_lane(*lane, 2)?;
+                }
+                OP_I8X16_EXTRACT_LANE_S(lane)
+                | OP_I8X16_EXTRACT_LANE_U(lane)
+                | OP_I8X16_REPLACE_LANE(lane) => check_lane(*lane, 16)?,
+                OP_I16X8_EXTRACT_LANE_S(lane)
+                | OP_I16X8_EXTRACT_LANE_U(lane)
+                | OP_I16X8_REPLACE_LANE(lane) => check_lane(*lane, 8)?,
+                OP_I32X4_EXTRACT_LANE(lane)
+                | OP_I32X4_REPLACE_LANE(lane)
+                | OP_F32X4_EXTRACT_LANE(lane)
+                | OP_F32X4_REPLACE_LANE(lane) => check_lane(*lane, 4)?,
+                OP_I64X2_EXTRACT_LANE(lane)
+                | OP_I64X2_REPLACE_LANE(lane)
+                | OP_F64X2_EXTRACT_LANE(lane)
+                | OP_F64X2_REPLACE_LANE(lane) => check_lane(*lane, 2)?,
+                OP_I8X16_SHUFFLE(lanes) => {
+                    // The lanes select from both operands
+                    for lane in lanes {
+                        check_lane(*lane, 32)?;
+                    }
+                }
+                OP_I32_ATOMIC_LOAD8_U(arg)
+                | OP_I64_ATOMIC_LOAD8_U(arg)
+                | OP_I32_ATOMIC_STORE8(arg)
+                | OP_I64_ATOMIC_STORE8(arg)
+                | OP_I32_ATOMIC_RMW8_ADD_U(arg)
+                | OP_I64_ATOMIC_RMW8_ADD_U(arg)
+                | OP_I32_ATOMIC_RMW8_SUB_U(arg)
+                | OP_I64_ATOMIC_RMW8_SUB_U(arg)
+                | OP_I32_ATOMIC_RMW8_AND_U(arg)
+                | OP_I64_ATOMIC_RMW8_AND_U(arg)
+                | OP_I32_ATOMIC_RMW8_OR_U(arg)
+                | OP_I64_ATOMIC_RMW8_OR_U(arg)
+                | OP_I32_ATOMIC_RMW8_XOR_U(arg)
+                | OP_I64_ATOMIC_RMW8_XOR_U(arg)
+                | OP_I32_ATOMIC_RMW8_XCHG_U(arg)
+                | OP_I64_ATOMIC_RMW8_XCHG_U(arg)
+                | OP_I32_ATOMIC_RMW8_CMPXCHG_U(arg)
+                | OP_I64_ATOMIC_RMW8_CMPXCHG_U(arg) => {
+                    self.check_atomic_memarg(arg, 1)?;
+                }
+                OP_I32_ATOMIC_LOAD16_U(arg)
+                | OP_I64_ATOMIC_LOAD16_U(arg)
+                | OP_I32_ATOMIC_STORE16(arg)
+                | OP_I64_ATOMIC_STORE16(arg)
+                | OP_I32_ATOMIC_RMW16_ADD_U(arg)
+                | OP_I64_ATOMIC_RMW16_ADD_U(arg)
+                | OP_I32_ATOMIC_RMW16_SUB_U(arg)
+                | OP_I64_ATOMIC_RMW16_SUB_U(arg)
+                | OP_I32_ATOMIC_RMW16_AND_U(arg)
+                | OP_I64_ATOMIC_RMW16_AND_U(arg)
+                | OP_I32_ATOMIC_RMW16_OR_U(arg)
+                | OP_I64_ATOMIC_RMW16_OR_U(arg)
+                | OP_I32_ATOMIC_RMW16_XOR_U(arg)
+                | OP_I64_ATOMIC_RMW16_XOR_U(arg)
+                | OP_I32_ATOMIC_RMW16_XCHG_U(arg)
+                | OP_I64_ATOMIC_RMW16_XCHG_U(arg)
+                | OP_I32_ATOMIC_RMW16_CMPXCHG_U(arg)
+                | OP_I64_ATOMIC_RMW16_CMPXCHG_U(arg) => {
+                    self.check_atomic_memarg(arg, 2)?;
+                }
+                OP_MEMORY_ATOMIC_NOTIFY(arg)
+                | OP_MEMORY_ATOMIC_WAIT32(arg)
+                | OP_I32_ATOMIC_LOAD(arg)
+                | OP_I64_ATOMIC_LOAD32_U(arg)
+                | OP_I32_ATOMIC_STORE(arg)
+                | OP_I64_ATOMIC_STORE32(arg)
+                | OP_I32_ATOMIC_RMW_ADD(arg)
+                | OP_I64_ATOMIC_RMW32_ADD_U(arg)
+                | OP_I32_ATOMIC_RMW_SUB(arg)
+                | OP_I64_ATOMIC_RMW32_SUB_U(arg)
+                | OP_I32_ATOMIC_RMW_AND(arg)
+                | OP_I64_ATOMIC_RMW32_AND_U(arg)
+                | OP_I32_ATOMIC_RMW_OR(arg)
+                | OP_I64_ATOMIC_RMW32_OR_U(arg)
+                | OP_I32_ATOMIC_RMW_XOR(arg)
+                | OP_I64_ATOMIC_RMW32_XOR_U(arg)
+                | OP_I32_ATOMIC_RMW_XCHG(arg)
+                | OP_I64_ATOMIC_RMW32_XCHG_U(arg)
+                | OP_I32_ATOMIC_RMW_CMPXCHG(arg)
+                | OP_I64_ATOMIC_RMW32_CMPXCHG_U(arg) => {
+                    self.check_atomic_memarg(arg, 4)?;
+                }
+                OP_MEMORY_ATOMIC_WAIT64(arg)
+                | OP_I64_ATOMIC_LOAD(arg)
+                | OP_I64_ATOMIC_STORE(arg)
+                | OP_I64_ATOMIC_RMW_ADD(arg)
+                | OP_I64_ATOMIC_RMW_SUB(arg)
+                | OP_I64_ATOMIC_RMW_AND(arg)
+                | OP_I64_ATOMIC_RMW_OR(arg)
+                | OP_I64_ATOMIC_RMW_XOR(arg)
+                | OP_I64_ATOMIC_RMW_XCHG(arg)
+                | OP_I64_ATOMIC_RMW_CMPXCHG(arg) => {
+                    self.check_atomic_memarg(arg, 8)?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_memory(&self) -> Result<&'a MemoryType> {
+        self.mems
+            .first()
+            .copied()
+            .ok_or_else(|| anyhow!("Memory 0 is not defined"))
+    }
+
+    /// The alignment must not be larger than the `size` of the access
+    fn check_memarg(&self, arg: &MemArg, size: u32) -> Result<()> {
+        self.check_offset(arg)?;
+
+        if arg.align > size.trailing_zeros() {
+            return Err(anyhow!("Alignment 2^{} is larger than {} bytes", arg.align, size));
+        }
+
+        Ok(())
+    }
+
+    /// The offset must be an address of the memory's index type
+    fn check_offset(&self, arg: &MemArg) -> Result<()> {
+        let memory = self.check_memory()?;
+
+        if memory.index_type == IndexType::I32 && arg.offset > u32::MAX as u64 {
+            return Err(anyhow!("Offset {} is out of the 32-bit address space", arg.offset));
+        }
+
+        Ok(())
+    }
+
+    /// The alignment of atomics must be exactly the `size` of the access
+    fn check_atomic_memarg(&self, arg: &MemArg, size: u32) -> Result<()> {
+        self.check_offset(arg)?;
+
+        if arg.align != size.trailing_zeros() {
+            return Err(anyhow!("Alignment 2^{} of an atomic is not {} bytes", arg.align, size));
+        }
+
+        Ok(())
+    }
+
+    fn check_tag_idx(&self, tag_idx: TagIdx) -> Result<()> {
+        if tag_idx as usize >= self.tags.len() {
+            return Err(anyhow!("Tag {} is not defined", tag_idx));
+        }
+
+        Ok(())
+    }
+
+    fn check_table_idx(&self, table_idx: TableIdx) -> Result<&'a TableType> {
+        self.tables
+            .get(table_idx as usize)
+            .copied()
+            .ok_or_else(|| anyhow!("Table {} is not defined", table_idx))
+    }
+
+    fn check_elem_idx(&self, elem_idx: ElemIdx) -> Result<&'a ElementSegment> {
+        self.elems
+            .get(elem_idx as usize)
+            .copied()
+            .ok_or_else(|| anyhow!("Element segment {} is not defined", elem_idx))
+    }
+
+    /// The data segments can only be referred with a data count section,
+    /// because the code section is before the data section.
+    fn check_data_idx(&self, data_idx: DataIdx) -> Result<()> {
+        match self.data_count {
+            None => Err(anyhow!("Data count section is required for data segment {}", data_idx)),
+            Some(count) if data_idx >= count => {
+                Err(anyhow!("Data segment {} is not defined", data_idx))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check_block_type(ty: &BlockType, types: &[&FunctionSignature]) -> Result<()> {
+    match ty {
+        BlockType::FuncTy(idx) if types.get(*idx as usize).is_none() => {
+            Err(anyhow!("Function type {} of the block is not defined", idx))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The lane index must be smaller than the number of lanes
+fn check_lane(lane: LaneIdx, lanes: u8) -> Result<()> {
+    if lane >= lanes {
+        return Err(anyhow!("Lane {} is out of bounds for {} lanes", lane, lanes));
+    }
+
+    Ok(())
+}
+
 fn check_import_ty(import_ty: &ImportEntry, types: &[&FunctionSignature]) -> bool {
     check_import_desc(&import_ty.desc, types)
 }
@@ -343,6 +833,7 @@ fn check_export_ty(
     tabletypes: &[&TableType],
     memtypes: &[&MemoryType],
     globaltypes: &[&GlobalType],
+    tagtypes: &[TypeIdx],
 ) -> Result<bool> {
     //https://webassembly.github.io/spec/core/valid/modules.html#exports
 
@@ -360,6 +851,7 @@ fn check_export_ty(
         ExternalKindType::Table { ty } => exists!(tabletypes, ty, "Table does not exist"),
         ExternalKindType::Memory { ty } => exists!(memtypes, ty, "Memory does not exist"),
         ExternalKindType::Global { ty } => exists!(globaltypes, ty, "Global does not exist"),
+        ExternalKindType::Tag { ty } => exists!(tagtypes, ty, "Tag does not exist"),
     }
 }
 
@@ -379,15 +871,35 @@ fn check_import_desc(e: &ImportDesc, types: &[&FunctionSignature]) -> bool {
     match e {
         ImportDesc::Function { ty } => get_ty_of_function(types, *ty as usize).is_ok(),
         ImportDesc::Table { .. } => true, //Limits are u32 that's why they are valid
-        ImportDesc::Memory { ty } => check_memory_ty(&ty).is_ok(),
+        ImportDesc::Memory { ty } => check_memory_ty(ty).is_ok(),
         ImportDesc::Global { .. } => true, // this is true, because `mut` is always correct and `valuetype` was correctly parsed
+        ImportDesc::Tag { ty } => check_tag_ty(*ty, types).is_ok(),
+    }
+}
+
+/// The type of a tag must exist and must not have results
+fn check_tag_ty(ty: TypeIdx, types: &[&FunctionSignature]) -> Result<()> {
+    let signature = types
+        .get(ty as usize)
+        .ok_or_else(|| anyhow!("Type {} of the tag does not exist", ty))?;
+
+    if !signature.return_types.is_empty() {
+        return Err(anyhow!("Type {} of the tag has results", ty));
     }
+
+    Ok(())
 }
 
+/// The limits are at most 2^16 pages for 32-bit memories and 2^48 pages for 64-bit memories
 fn check_memory_ty(memory: &MemoryType) -> Result<()> {
+    let max_pages = match memory.index_type {
+        IndexType::I32 => 1 << 16,
+        IndexType::I64 => 1 << 48,
+    };
+
     let b = match memory.limits {
-        Limits::Zero(n) => n < 2u32.checked_pow(16).unwrap(), //cannot overflow
-        Limits::One(n, m) => n < 2u32.checked_pow(16).unwrap() && m < 2u32.checked_pow(16).unwrap(), //cannot overflow
+        Limits::Zero(n) => n <= max_pages,
+        Limits::One(n, m) | Limits::Shared(n, m) => n <= max_pages && m <= max_pages,
     };
 
     if b {
@@ -479,7 +991,7 @@ mod tests {
 
         let w = TableSection {
             entries: vec![TableType {
-                element_type: 0x70, //default
+                element_type: ValueType::FuncRef, //default
                 limits: Limits::Zero(10),
             }],
         };
@@ -521,6 +1033,7 @@ mod tests {
         let w = MemorySection {
             entries: vec![MemoryType {
                 limits: Limits::Zero(10),
+                index_type: IndexType::I32,
             }],
         };
 
@@ -653,7 +1166,7 @@ mod tests {
                 name: "test1".to_string(),
                 desc: ImportDesc::Table {
                     ty: TableType {
-                        element_type: 0x70,
+                        element_type: ValueType::FuncRef,
                         limits: Limits::Zero(0),
                     },
                 },
@@ -676,6 +1189,7 @@ mod tests {
                 desc: ImportDesc::Memory {
                     ty: MemoryType {
                         limits: Limits::Zero(0),
+                        index_type: IndexType::I32,
                     },
                 },
             }],
@@ -696,7 +1210,8 @@ mod tests {
                 name: "test1".to_string(),
                 desc: ImportDesc::Memory {
                     ty: MemoryType {
-                        limits: Limits::Zero(u32::max_value()),
+                        limits: Limits::Zero(u64::from(u32::MAX)),
+                        index_type: IndexType::I32,
                     },
                 },
             }],
@@ -732,18 +1247,18 @@ mod tests {
     }
 
     #[test]
-    fn test_double_tables() {
+    fn test_several_tables() {
         let k = TableSection {
             entries: vec![TableType {
-                element_type: 0x70,
-                limits: Limits::Zero(0 as u32),
+                element_type: ValueType::FuncRef,
+                limits: Limits::Zero(0),
             }],
         };
 
         let w = TableSection {
             entries: vec![TableType {
-                element_type: 0x70,
-                limits: Limits::Zero(0 as u32),
+                element_type: ValueType::FuncRef,
+                limits: Limits::Zero(0),
             }],
         };
 
@@ -751,20 +1266,22 @@ mod tests {
             sections: vec![Section::Table(k), Section::Table(w)],
         };
 
-        assert!(validate(&module).is_err());
+        assert!(validate(&module).is_ok());
     }
 
     #[test]
     fn test_double_memory() {
         let k = MemorySection {
             entries: vec![MemoryType {
-                limits: Limits::Zero(0 as u32),
+                limits: Limits::Zero(0),
+                index_type: IndexType::I32,
             }],
         };
 
         let w = MemorySection {
             entries: vec![MemoryType {
-                limits: Limits::Zero(0 as u32),
+                limits: Limits::Zero(0),
+                index_type: IndexType::I32,
             }],
         };
 
@@ -779,7 +1296,8 @@ mod tests {
     fn test_memory_exhaust() {
         let k = MemorySection {
             entries: vec![MemoryType {
-                limits: Limits::Zero(u32::max_value()),
+                limits: Limits::Zero(u64::from(u32::MAX)),
+                index_type: IndexType::I32,
             }],
         };
 
@@ -793,12 +1311,487 @@ mod tests {
     #[test]
     fn test_memory_exhaust_function() {
         let ty = MemoryType {
-            limits: Limits::Zero(u32::max_value()),
+            limits: Limits::Zero(u64::from(u32::MAX)),
+            index_type: IndexType::I32,
         };
 
         assert!(check_memory_ty(&ty).is_err());
     }
 
+    /// A module with a constant global of the type and initializer of each entry
+    fn module_with_globals(globals: Vec<(ValueType, Vec<Instruction>)>) -> Module {
+        let mut counter = Counter::default();
+
+        let globals = globals
+            .into_iter()
+            .map(|(value_type, init)| GlobalVariable {
+                ty: GlobalType {
+                    value_type,
+                    mu: Mu::Const,
+                },
+                init: InstructionWrapper::wrap_instructions(&mut counter, init),
+            })
+            .collect();
+
+        Module {
+            sections: vec![Section::Global(GlobalSection { globals })],
+        }
+    }
+
+    #[test]
+    fn test_extended_const() {
+        use Instruction::*;
+
+        let module = module_with_globals(vec![
+            (ValueType::I32, vec![OP_I32_CONST(1)]),
+            (
+                ValueType::I32,
+                vec![OP_GLOBAL_GET(0), OP_I32_CONST(4), OP_I32_ADD, OP_I32_CONST(2), OP_I32_MUL],
+            ),
+            (ValueType::I64, vec![OP_I64_CONST(1), OP_I64_CONST(2), OP_I64_SUB]),
+        ]);
+        assert!(validate(&module).is_ok());
+
+        // The operands must have the type of the instruction
+        let module = module_with_globals(vec![(ValueType::I64, vec![OP_I32_CONST(1), OP_I32_CONST(2), OP_I64_ADD])]);
+        assert!(validate(&module).is_err());
+
+        // The result must have the type of the global
+        let module = module_with_globals(vec![(ValueType::I64, vec![OP_I32_CONST(1), OP_I32_CONST(2), OP_I32_ADD])]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_invalid_const_expr() {
+        use Instruction::*;
+
+        let inits = [
+            vec![],
+            vec![OP_I32_CONST(1), OP_I32_CONST(2), OP_I32_DIV_S],
+            vec![OP_I32_CONST(1), OP_I32_ADD],
+            vec![OP_I32_CONST(1), OP_I32_CONST(2)],
+        ];
+
+        for init in inits.iter() {
+            let module = module_with_globals(vec![(ValueType::I32, init.clone())]);
+            assert!(validate(&module).is_err());
+        }
+    }
+
+    #[test]
+    fn test_memory64_limits() {
+        let ty = MemoryType {
+            limits: Limits::One(0, 1 << 48),
+            index_type: IndexType::I64,
+        };
+        assert!(check_memory_ty(&ty).is_ok());
+
+        let ty = MemoryType {
+            limits: Limits::Zero((1 << 48) + 1),
+            index_type: IndexType::I64,
+        };
+        assert!(check_memory_ty(&ty).is_err());
+    }
+
+    fn module_with_block(ty: BlockType) -> Module {
+        let mut counter = Counter::default();
+
+        let block = Instruction::OP_BLOCK(ty, CodeBlock::new(&mut counter, vec![Instruction::OP_NOP]));
+
+        let t = TypeSection {
+            entries: vec![
+                FunctionSignature::empty(),
+                FunctionSignature {
+                    param_types: vec![ValueType::I32],
+                    return_types: vec![ValueType::I32, ValueType::I64],
+                },
+            ],
+        };
+
+        let c = CodeSection {
+            entries: vec![FunctionBody {
+                locals: vec![],
+                code: InstructionWrapper::wrap_instructions(&mut counter, vec![block]),
+            }],
+        };
+
+        Module {
+            sections: vec![
+                Section::Type(t),
+                Section::Function(FunctionSection { types: vec![0] }),
+                Section::Code(c),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_block_with_func_type() {
+        assert!(validate(&module_with_block(BlockType::FuncTy(1))).is_ok());
+    }
+
+    #[test]
+    fn test_block_with_undefined_func_type() {
+        assert!(validate(&module_with_block(BlockType::FuncTy(2))).is_err());
+    }
+
+    #[test]
+    fn test_start_function_with_results() {
+        let module = Module {
+            sections: vec![
+                Section::Type(TypeSection {
+                    entries: vec![FunctionSignature {
+                        param_types: vec![],
+                        return_types: vec![ValueType::I32, ValueType::I32],
+                    }],
+                }),
+                Section::Function(FunctionSection { types: vec![0] }),
+                Section::Start(StartSection { index: 0 }),
+            ],
+        };
+
+        assert!(validate(&module).is_err());
+    }
+
+    /// A module with a memory, a passive data segment and the function `code`
+    fn module_with_data(data_count: Option<u32>, code: Vec<Instruction>) -> Module {
+        let mut counter = Counter::default();
+
+        let mut sections = vec![
+            Section::Type(TypeSection {
+                entries: vec![FunctionSignature::empty()],
+            }),
+            Section::Function(FunctionSection { types: vec![0] }),
+            Section::Memory(MemorySection {
+                entries: vec![MemoryType {
+                    limits: Limits::Zero(1),
+                    index_type: IndexType::I32,
+                }],
+            }),
+        ];
+
+        if let Some(count) = data_count {
+            sections.push(Section::DataCount(DataCountSection { count }));
+        }
+
+        sections.push(Section::Code(CodeSection {
+            entries: vec![FunctionBody {
+                locals: vec![],
+                code: InstructionWrapper::wrap_instructions(&mut counter, code),
+            }],
+        }));
+        sections.push(Section::Data(DataSection {
+            entries: vec![DataSegment {
+                mode: DataMode::Passive,
+                init: vec![1, 2, 3],
+            }],
+        }));
+
+        Module { sections }
+    }
+
+    #[test]
+    fn test_data_drop_with_data_count() {
+        let module = module_with_data(Some(1), vec![Instruction::OP_DATA_DROP(0)]);
+        assert!(validate(&module).is_ok());
+    }
+
+    #[test]
+    fn test_data_drop_without_data_count() {
+        let module = module_with_data(None, vec![Instruction::OP_DATA_DROP(0)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_data_drop_with_undefined_segment() {
+        let module = module_with_data(Some(1), vec![Instruction::OP_DATA_DROP(1)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_data_count_mismatch() {
+        assert!(validate(&module_with_data(Some(2), vec![])).is_err());
+    }
+
+    #[test]
+    fn test_elem_drop_with_undefined_segment() {
+        let module = module_with_data(None, vec![Instruction::OP_ELEM_DROP(0)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_table_copy_without_table() {
+        let module = module_with_data(None, vec![Instruction::OP_TABLE_COPY(0, 0)]);
+        assert!(validate(&module).is_err());
+    }
+
+    /// A module with the tables of `tables` and the exported function `code`
+    fn module_with_tables(tables: Vec<ValueType>, code: Vec<Instruction>) -> Module {
+        let mut counter = Counter::default();
+
+        Module {
+            sections: vec![
+                Section::Type(TypeSection {
+                    entries: vec![FunctionSignature::empty()],
+                }),
+                Section::Function(FunctionSection { types: vec![0, 0] }),
+                Section::Table(TableSection {
+                    entries: tables
+                        .into_iter()
+                        .map(|element_type| TableType {
+                            element_type,
+                            limits: Limits::Zero(1),
+                        })
+                        .collect(),
+                }),
+                Section::Export(ExportSection {
+                    entries: vec![ExportEntry {
+                        name: "main".to_string(),
+                        kind: ExternalKindType::Function { ty: 0 },
+                    }],
+                }),
+                Section::Code(CodeSection {
+                    entries: vec![
+                        FunctionBody {
+                            locals: vec![],
+                            code: InstructionWrapper::wrap_instructions(&mut counter, code),
+                        },
+                        FunctionBody {
+                            locals: vec![],
+                            code: vec![],
+                        },
+                    ],
+                }),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_ref_func_declared_by_export() {
+        let module = module_with_tables(vec![], vec![Instruction::OP_REF_FUNC(0)]);
+        assert!(validate(&module).is_ok());
+    }
+
+    #[test]
+    fn test_ref_func_undeclared() {
+        let module = module_with_tables(vec![], vec![Instruction::OP_REF_FUNC(1)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_call_indirect_with_table_index() {
+        let tables = vec![ValueType::ExternRef, ValueType::FuncRef];
+
+        let module = module_with_tables(tables.clone(), vec![Instruction::OP_CALL_INDIRECT(0, 1)]);
+        assert!(validate(&module).is_ok());
+
+        let module = module_with_tables(tables, vec![Instruction::OP_CALL_INDIRECT(0, 0)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_table_copy_with_different_types() {
+        let tables = vec![ValueType::ExternRef, ValueType::FuncRef];
+
+        let module = module_with_tables(tables, vec![Instruction::OP_TABLE_COPY(0, 1)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_table_get_undefined_table() {
+        let module = module_with_tables(vec![ValueType::FuncRef], vec![Instruction::OP_TABLE_GET(1)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_typed_select_with_several_types() {
+        let module = module_with_tables(
+            vec![],
+            vec![Instruction::OP_SELECT_T(vec![ValueType::I32, ValueType::I32])],
+        );
+        assert!(validate(&module).is_err());
+    }
+
+    fn memarg(align: u32) -> MemArg {
+        MemArg { align, offset: 0 }
+    }
+
+    #[test]
+    fn test_extract_lane_out_of_bounds() {
+        let module = module_with_tables(vec![], vec![Instruction::OP_I32X4_EXTRACT_LANE(3)]);
+        assert!(validate(&module).is_ok());
+
+        let module = module_with_tables(vec![], vec![Instruction::OP_I32X4_EXTRACT_LANE(4)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_shuffle_lane_out_of_bounds() {
+        let module = module_with_tables(vec![], vec![Instruction::OP_I8X16_SHUFFLE([31; 16])]);
+        assert!(validate(&module).is_ok());
+
+        let module = module_with_tables(vec![], vec![Instruction::OP_I8X16_SHUFFLE([32; 16])]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_v128_load_alignment() {
+        let module = module_with_data(None, vec![Instruction::OP_V128_LOAD(memarg(4))]);
+        assert!(validate(&module).is_ok());
+
+        let module = module_with_data(None, vec![Instruction::OP_V128_LOAD(memarg(5))]);
+        assert!(validate(&module).is_err());
+
+        let module = module_with_data(None, vec![Instruction::OP_V128_LOAD32_SPLAT(memarg(3))]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_v128_load_lane() {
+        let module = module_with_data(None, vec![Instruction::OP_V128_LOAD64_LANE(memarg(3), 1)]);
+        assert!(validate(&module).is_ok());
+
+        let module = module_with_data(None, vec![Instruction::OP_V128_LOAD64_LANE(memarg(3), 2)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_v128_store_without_memory() {
+        let module = module_with_tables(vec![], vec![Instruction::OP_V128_STORE(memarg(0))]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_return_call() {
+        let module = module_with_tables(vec![], vec![Instruction::OP_RETURN_CALL(1)]);
+        assert!(validate(&module).is_ok());
+
+        let module = module_with_tables(vec![], vec![Instruction::OP_RETURN_CALL(2)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_return_call_indirect_with_table_index() {
+        let tables = vec![ValueType::ExternRef, ValueType::FuncRef];
+
+        let module = module_with_tables(
+            tables.clone(),
+            vec![Instruction::OP_I32_CONST(0), Instruction::OP_RETURN_CALL_INDIRECT(0, 1)],
+        );
+        assert!(validate(&module).is_ok());
+
+        let module = module_with_tables(
+            tables,
+            vec![Instruction::OP_I32_CONST(0), Instruction::OP_RETURN_CALL_INDIRECT(0, 0)],
+        );
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_atomic_alignment() {
+        let module = module_with_data(None, vec![Instruction::OP_I32_ATOMIC_LOAD(memarg(2))]);
+        assert!(validate(&module).is_ok());
+
+        // Unlike plain loads, a smaller alignment is invalid
+        let module = module_with_data(None, vec![Instruction::OP_I32_ATOMIC_LOAD(memarg(0))]);
+        assert!(validate(&module).is_err());
+
+        let module = module_with_data(None, vec![Instruction::OP_I64_ATOMIC_RMW8_ADD_U(memarg(0))]);
+        assert!(validate(&module).is_ok());
+
+        let module = module_with_data(None, vec![Instruction::OP_MEMORY_ATOMIC_WAIT64(memarg(2))]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_offset_of_index_type() {
+        let load = Instruction::OP_I64_LOAD(MemArg {
+            align: 3,
+            offset: 1 << 32,
+        });
+
+        // The offset must fit into the address space of the memory
+        let mut module = module_with_data(None, vec![load.clone()]);
+        assert!(validate(&module).is_err());
+
+        match &mut module.sections[2] {
+            Section::Memory(section) => section.entries[0].index_type = IndexType::I64,
+            _ => unreachable!(),
+        }
+        assert!(validate(&module).is_ok());
+    }
+
+    #[test]
+    fn test_atomic_without_memory() {
+        let module = module_with_tables(vec![], vec![Instruction::OP_MEMORY_ATOMIC_NOTIFY(memarg(2))]);
+        assert!(validate(&module).is_err());
+    }
+
+    /// A module with the tags of the types `tags` and the function `code`.
+    /// The type 0 is empty and the type 1 has a result.
+    fn module_with_tags(tags: Vec<TypeIdx>, code: Vec<Instruction>) -> Module {
+        let mut counter = Counter::default();
+
+        Module {
+            sections: vec![
+                Section::Type(TypeSection {
+                    entries: vec![
+                        FunctionSignature::empty(),
+                        FunctionSignature {
+                            param_types: vec![],
+                            return_types: vec![ValueType::I32],
+                        },
+                    ],
+                }),
+                Section::Function(FunctionSection { types: vec![0] }),
+                Section::Tag(TagSection { types: tags }),
+                Section::Code(CodeSection {
+                    entries: vec![FunctionBody {
+                        locals: vec![],
+                        code: InstructionWrapper::wrap_instructions(&mut counter, code),
+                    }],
+                }),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_throw_tag() {
+        let module = module_with_tags(vec![0], vec![Instruction::OP_THROW(0)]);
+        assert!(validate(&module).is_ok());
+
+        let module = module_with_tags(vec![0], vec![Instruction::OP_THROW(1)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_tag_with_results() {
+        let module = module_with_tags(vec![1], vec![]);
+        assert!(validate(&module).is_err());
+
+        let module = module_with_tags(vec![2], vec![]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn test_try_with_undefined_tag() {
+        let mut counter = Counter::default();
+
+        for &(tag_idx, valid) in &[(0, true), (1, false)] {
+            let handler = CodeBlock::new(&mut counter, vec![Instruction::OP_RETHROW(0)]);
+            let body = CodeBlock::new(&mut counter, vec![]);
+
+            let module = module_with_tags(
+                vec![0],
+                vec![Instruction::OP_TRY(
+                    BlockType::Empty,
+                    body,
+                    vec![Catch::Tag(tag_idx, handler)],
+                )],
+            );
+            assert_eq!(valid, validate(&module).is_ok());
+        }
+    }
+
     macro_rules! test_file {
         ($fs_name:expr) => {
             let file = read_wasm!(&format!("../wasm_parser/test_files/{}", $fs_name));
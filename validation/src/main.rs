@@ -35,10 +35,10 @@ fn main() {
 
     let module : Module = serde_json::from_str(&read_file(args.arg_input)).expect("Converting file failed. Is the AST in json?");
 
-    let result = validate(&module).unwrap();
+    validate(&module).unwrap();
 
     if !args.flag_no_output {
-        println!("{:#?}", result);
+        println!("The module is valid");
     }
 }
 
@@ -1,10 +1,11 @@
-#![allow(clippy::clippy::upper_case_acronyms)]
+#![allow(clippy::upper_case_acronyms)]
 
 use custom_display::CustomDisplay;
 use serde::{Deserialize, Serialize};
 
 pub type FuncIdx = u32;
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub type TypeIdx = u32;
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct FuncAddr(usize);
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TableAddr(usize);
@@ -16,8 +17,24 @@ pub type MemoryIdx = u32;
 pub type GlobalIdx = u32;
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GlobalAddr(usize);
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleAddr(usize);
 pub type LabelIdx = u32;
 pub type LocalIdx = u32;
+pub type DataIdx = u32;
+pub type ElemIdx = u32;
+/// The index of a lane in a `v128`
+pub type LaneIdx = u8;
+pub type TagIdx = u32;
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TagAddr(usize);
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataAddr(usize);
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ElemAddr(usize);
+/// An opaque reference of the host, which is passed through wasm
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ExternAddr(usize);
 
 macro_rules! impl_addr {
     ($name:ident) => {
@@ -36,7 +53,12 @@ macro_rules! impl_addr {
 impl_addr!(FuncAddr);
 impl_addr!(TableAddr);
 impl_addr!(MemoryAddr);
+impl_addr!(ModuleAddr);
 impl_addr!(GlobalAddr);
+impl_addr!(DataAddr);
+impl_addr!(ElemAddr);
+impl_addr!(ExternAddr);
+impl_addr!(TagAddr);
 
 pub type Expr = Vec<InstructionWrapper>;
 
@@ -64,6 +86,16 @@ pub enum ValueType {
     I64,
     F32,
     F64,
+    V128,
+    FuncRef,
+    ExternRef,
+}
+
+impl ValueType {
+    /// Returns true for `funcref` and `externref`
+    pub fn is_ref(&self) -> bool {
+        matches!(self, ValueType::FuncRef | ValueType::ExternRef)
+    }
 }
 
 //type TypeIdx = u32;
@@ -89,6 +121,8 @@ pub enum ImportDesc {
     Table { ty: TableType },
     Memory { ty: MemoryType },
     Global { ty: GlobalType },
+    /// The tag has the type `ty`
+    Tag { ty: TypeIdx },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Copy)]
@@ -97,6 +131,7 @@ pub enum ExternalKindType {
     Table { ty: TableIdx },
     Memory { ty: MemoryIdx },
     Global { ty: GlobalIdx },
+    Tag { ty: TagIdx },
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -113,6 +148,8 @@ pub enum Section {
     Element(ElementSection),
     Code(CodeSection),
     Data(DataSection),
+    DataCount(DataCountSection),
+    Tag(TagSection),
     Name(NameSection),
 }
 
@@ -146,6 +183,13 @@ pub struct MemorySection {
     pub entries: Vec<MemoryType>,
 }
 
+/// The tags of the exceptions. A tag is defined by
+/// the type of its payload, which has no results.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct TagSection {
+    pub types: Vec<TypeIdx>,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct GlobalSection {
     pub globals: Vec<GlobalVariable>,
@@ -176,6 +220,13 @@ pub struct DataSection {
     pub entries: Vec<DataSegment>,
 }
 
+/// The number of data segments, which is required
+/// when the code refers to data segments.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DataCountSection {
+    pub count: u32,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct NameSection {
     pub name_type: u8,
@@ -185,13 +236,22 @@ pub struct NameSection {
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TableType {
-    pub element_type: u8, //0x70 for future ref
+    pub element_type: ValueType,
     pub limits: Limits,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MemoryType {
     pub limits: Limits,
+    pub index_type: IndexType,
+}
+
+/// The type of the addresses of a memory.
+/// Memories of the memory64 proposal are addressed by `i64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexType {
+    I32,
+    I64,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -218,20 +278,40 @@ pub struct ExportEntry {
     pub kind: ExternalKindType,
 }
 
+/// An element segment initializes a table.
+/// The entries of `init` are functions or null references.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ElementSegment {
-    pub table: TableIdx,
-    pub offset: Expr,
-    pub init: Vec<FuncIdx>,
+    pub ty: ValueType,
+    pub mode: ElementMode,
+    pub init: Vec<Option<FuncIdx>>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum ElementMode {
+    /// The segment is copied into the table at instantiation
+    Active { table: TableIdx, offset: Expr },
+    /// The segment is only copied by `table.init`
+    Passive,
+    /// The segment only declares the functions for `ref.func`
+    Declarative,
 }
 
+/// A data segment initializes a memory.
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct DataSegment {
-    pub data: MemoryIdx,
-    pub offset: Expr,
+    pub mode: DataMode,
     pub init: Vec<u8>,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub enum DataMode {
+    /// The segment is copied into the memory at instantiation
+    Active { memory: MemoryIdx, offset: Expr },
+    /// The segment is only copied by `memory.init`
+    Passive,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FunctionBody {
     pub locals: Vec<LocalEntry>,
@@ -251,13 +331,15 @@ pub struct LocalEntry {
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Limits {
-    Zero(u32),
-    One(u32, u32),
+    Zero(u64),
+    One(u64, u64),
+    /// The limits of a `shared` memory, which always has a maximum
+    Shared(u64, u64),
 }
 
 /// A helper struct to count codeblocks
 /// and instruction ids.
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Default, Serialize, Deserialize)]
 pub struct Counter {
     value: usize,
     instruction_id: usize,
@@ -287,15 +369,6 @@ impl Counter {
     }
 }
 
-impl Default for Counter {
-    fn default() -> Self {
-        Counter {
-            value: 0,
-            instruction_id: 0,
-        }
-    }
-}
-
 /// A wrapper time for a series of instructions.
 /// `CodeBlocks` are used in in operation codes which wrap instructions.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -332,6 +405,14 @@ impl CodeBlock {
         min <= cmp && cmp <= max
     }
 
+    /// Creates a copy with the same `id`, but without instructions.
+    pub fn without_instructions(&self) -> Self {
+        Self {
+            id: self.id,
+            instructions: Vec::new(),
+        }
+    }
+
     pub fn iter(&self) -> std::slice::Iter<'_, InstructionWrapper> {
         self.instructions.iter()
     }
@@ -342,6 +423,15 @@ impl CodeBlock {
     }
 }
 
+/// A handler of `try`, which runs its block for a caught exception
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Catch {
+    /// Catches the exceptions of the tag and pushes their payload
+    Tag(TagIdx, CodeBlock),
+    /// Catches every exception without a payload
+    All(CodeBlock),
+}
+
 /// Wrapper for the opcodes
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct InstructionWrapper {
@@ -395,11 +485,26 @@ pub enum Instruction {
     OP_BR_TABLE(Vec<LabelIdx>, LabelIdx),
     OP_RETURN,
     OP_CALL(FuncIdx),
-    OP_CALL_INDIRECT(FuncIdx),
+    OP_CALL_INDIRECT(TypeIdx, TableIdx),
+    OP_RETURN_CALL(FuncIdx),
+    OP_RETURN_CALL_INDIRECT(TypeIdx, TableIdx),
+
+    // Exception
+    OP_TRY(BlockType, CodeBlock, Vec<Catch>),
+    /// The exceptions of the block are thrown at the label
+    OP_TRY_DELEGATE(BlockType, CodeBlock, LabelIdx),
+    OP_THROW(TagIdx),
+    OP_RETHROW(LabelIdx),
+
+    // Reference
+    OP_REF_NULL(ValueType),
+    OP_REF_IS_NULL,
+    OP_REF_FUNC(FuncIdx),
 
     // Param
     OP_DROP,
     OP_SELECT,
+    OP_SELECT_T(Vec<ValueType>),
 
     // Var
     OP_LOCAL_GET(LocalIdx),
@@ -408,6 +513,13 @@ pub enum Instruction {
     OP_GLOBAL_GET(GlobalIdx),
     OP_GLOBAL_SET(GlobalIdx),
 
+    // Table
+    OP_TABLE_GET(TableIdx),
+    OP_TABLE_SET(TableIdx),
+    OP_TABLE_SIZE(TableIdx),
+    OP_TABLE_GROW(TableIdx),
+    OP_TABLE_FILL(TableIdx),
+
     // Mem
     OP_I32_LOAD(MemArg),
     OP_I64_LOAD(MemArg),
@@ -434,6 +546,15 @@ pub enum Instruction {
     OP_I64_STORE_32(MemArg),
     OP_MEMORY_SIZE,
     OP_MEMORY_GROW,
+    OP_MEMORY_INIT(DataIdx),
+    OP_DATA_DROP(DataIdx),
+    OP_MEMORY_COPY,
+    OP_MEMORY_FILL,
+
+    // Bulk table
+    OP_TABLE_INIT(ElemIdx, TableIdx),
+    OP_ELEM_DROP(ElemIdx),
+    OP_TABLE_COPY(TableIdx, TableIdx), // destination, source
 
     // Num
     OP_I32_CONST(i32),
@@ -587,12 +708,320 @@ pub enum Instruction {
     OP_I64_TRUNC_SAT_F32_U,
     OP_I64_TRUNC_SAT_F64_S,
     OP_I64_TRUNC_SAT_F64_U,
+
+    // Vector
+    OP_V128_LOAD(MemArg),
+    OP_V128_LOAD8X8_S(MemArg),
+    OP_V128_LOAD8X8_U(MemArg),
+    OP_V128_LOAD16X4_S(MemArg),
+    OP_V128_LOAD16X4_U(MemArg),
+    OP_V128_LOAD32X2_S(MemArg),
+    OP_V128_LOAD32X2_U(MemArg),
+    OP_V128_LOAD8_SPLAT(MemArg),
+    OP_V128_LOAD16_SPLAT(MemArg),
+    OP_V128_LOAD32_SPLAT(MemArg),
+    OP_V128_LOAD64_SPLAT(MemArg),
+    OP_V128_STORE(MemArg),
+    OP_V128_CONST(u128),
+    OP_I8X16_SHUFFLE([LaneIdx; 16]),
+    OP_I8X16_SWIZZLE,
+    OP_I8X16_SPLAT,
+    OP_I16X8_SPLAT,
+    OP_I32X4_SPLAT,
+    OP_I64X2_SPLAT,
+    OP_F32X4_SPLAT,
+    OP_F64X2_SPLAT,
+    OP_I8X16_EXTRACT_LANE_S(LaneIdx),
+    OP_I8X16_EXTRACT_LANE_U(LaneIdx),
+    OP_I8X16_REPLACE_LANE(LaneIdx),
+    OP_I16X8_EXTRACT_LANE_S(LaneIdx),
+    OP_I16X8_EXTRACT_LANE_U(LaneIdx),
+    OP_I16X8_REPLACE_LANE(LaneIdx),
+    OP_I32X4_EXTRACT_LANE(LaneIdx),
+    OP_I32X4_REPLACE_LANE(LaneIdx),
+    OP_I64X2_EXTRACT_LANE(LaneIdx),
+    OP_I64X2_REPLACE_LANE(LaneIdx),
+    OP_F32X4_EXTRACT_LANE(LaneIdx),
+    OP_F32X4_REPLACE_LANE(LaneIdx),
+    OP_F64X2_EXTRACT_LANE(LaneIdx),
+    OP_F64X2_REPLACE_LANE(LaneIdx),
+    OP_I8X16_EQ,
+    OP_I8X16_NE,
+    OP_I8X16_LT_S,
+    OP_I8X16_LT_U,
+    OP_I8X16_GT_S,
+    OP_I8X16_GT_U,
+    OP_I8X16_LE_S,
+    OP_I8X16_LE_U,
+    OP_I8X16_GE_S,
+    OP_I8X16_GE_U,
+    OP_I16X8_EQ,
+    OP_I16X8_NE,
+    OP_I16X8_LT_S,
+    OP_I16X8_LT_U,
+    OP_I16X8_GT_S,
+    OP_I16X8_GT_U,
+    OP_I16X8_LE_S,
+    OP_I16X8_LE_U,
+    OP_I16X8_GE_S,
+    OP_I16X8_GE_U,
+    OP_I32X4_EQ,
+    OP_I32X4_NE,
+    OP_I32X4_LT_S,
+    OP_I32X4_LT_U,
+    OP_I32X4_GT_S,
+    OP_I32X4_GT_U,
+    OP_I32X4_LE_S,
+    OP_I32X4_LE_U,
+    OP_I32X4_GE_S,
+    OP_I32X4_GE_U,
+    OP_F32X4_EQ,
+    OP_F32X4_NE,
+    OP_F32X4_LT,
+    OP_F32X4_GT,
+    OP_F32X4_LE,
+    OP_F32X4_GE,
+    OP_F64X2_EQ,
+    OP_F64X2_NE,
+    OP_F64X2_LT,
+    OP_F64X2_GT,
+    OP_F64X2_LE,
+    OP_F64X2_GE,
+    OP_V128_NOT,
+    OP_V128_AND,
+    OP_V128_ANDNOT,
+    OP_V128_OR,
+    OP_V128_XOR,
+    OP_V128_BITSELECT,
+    OP_V128_ANY_TRUE,
+    OP_V128_LOAD8_LANE(MemArg, LaneIdx),
+    OP_V128_LOAD16_LANE(MemArg, LaneIdx),
+    OP_V128_LOAD32_LANE(MemArg, LaneIdx),
+    OP_V128_LOAD64_LANE(MemArg, LaneIdx),
+    OP_V128_STORE8_LANE(MemArg, LaneIdx),
+    OP_V128_STORE16_LANE(MemArg, LaneIdx),
+    OP_V128_STORE32_LANE(MemArg, LaneIdx),
+    OP_V128_STORE64_LANE(MemArg, LaneIdx),
+    OP_V128_LOAD32_ZERO(MemArg),
+    OP_V128_LOAD64_ZERO(MemArg),
+    OP_F32X4_DEMOTE_F64X2_ZERO,
+    OP_F64X2_PROMOTE_LOW_F32X4,
+    OP_I8X16_ABS,
+    OP_I8X16_NEG,
+    OP_I8X16_POPCNT,
+    OP_I8X16_ALL_TRUE,
+    OP_I8X16_BITMASK,
+    OP_I8X16_NARROW_I16X8_S,
+    OP_I8X16_NARROW_I16X8_U,
+    OP_F32X4_CEIL,
+    OP_F32X4_FLOOR,
+    OP_F32X4_TRUNC,
+    OP_F32X4_NEAREST,
+    OP_I8X16_SHL,
+    OP_I8X16_SHR_S,
+    OP_I8X16_SHR_U,
+    OP_I8X16_ADD,
+    OP_I8X16_ADD_SAT_S,
+    OP_I8X16_ADD_SAT_U,
+    OP_I8X16_SUB,
+    OP_I8X16_SUB_SAT_S,
+    OP_I8X16_SUB_SAT_U,
+    OP_F64X2_CEIL,
+    OP_F64X2_FLOOR,
+    OP_I8X16_MIN_S,
+    OP_I8X16_MIN_U,
+    OP_I8X16_MAX_S,
+    OP_I8X16_MAX_U,
+    OP_F64X2_TRUNC,
+    OP_I8X16_AVGR_U,
+    OP_I16X8_EXTADD_PAIRWISE_I8X16_S,
+    OP_I16X8_EXTADD_PAIRWISE_I8X16_U,
+    OP_I32X4_EXTADD_PAIRWISE_I16X8_S,
+    OP_I32X4_EXTADD_PAIRWISE_I16X8_U,
+    OP_I16X8_ABS,
+    OP_I16X8_NEG,
+    OP_I16X8_Q15MULR_SAT_S,
+    OP_I16X8_ALL_TRUE,
+    OP_I16X8_BITMASK,
+    OP_I16X8_NARROW_I32X4_S,
+    OP_I16X8_NARROW_I32X4_U,
+    OP_I16X8_EXTEND_LOW_I8X16_S,
+    OP_I16X8_EXTEND_HIGH_I8X16_S,
+    OP_I16X8_EXTEND_LOW_I8X16_U,
+    OP_I16X8_EXTEND_HIGH_I8X16_U,
+    OP_I16X8_SHL,
+    OP_I16X8_SHR_S,
+    OP_I16X8_SHR_U,
+    OP_I16X8_ADD,
+    OP_I16X8_ADD_SAT_S,
+    OP_I16X8_ADD_SAT_U,
+    OP_I16X8_SUB,
+    OP_I16X8_SUB_SAT_S,
+    OP_I16X8_SUB_SAT_U,
+    OP_F64X2_NEAREST,
+    OP_I16X8_MUL,
+    OP_I16X8_MIN_S,
+    OP_I16X8_MIN_U,
+    OP_I16X8_MAX_S,
+    OP_I16X8_MAX_U,
+    OP_I16X8_AVGR_U,
+    OP_I16X8_EXTMUL_LOW_I8X16_S,
+    OP_I16X8_EXTMUL_HIGH_I8X16_S,
+    OP_I16X8_EXTMUL_LOW_I8X16_U,
+    OP_I16X8_EXTMUL_HIGH_I8X16_U,
+    OP_I32X4_ABS,
+    OP_I32X4_NEG,
+    OP_I32X4_ALL_TRUE,
+    OP_I32X4_BITMASK,
+    OP_I32X4_EXTEND_LOW_I16X8_S,
+    OP_I32X4_EXTEND_HIGH_I16X8_S,
+    OP_I32X4_EXTEND_LOW_I16X8_U,
+    OP_I32X4_EXTEND_HIGH_I16X8_U,
+    OP_I32X4_SHL,
+    OP_I32X4_SHR_S,
+    OP_I32X4_SHR_U,
+    OP_I32X4_ADD,
+    OP_I32X4_SUB,
+    OP_I32X4_MUL,
+    OP_I32X4_MIN_S,
+    OP_I32X4_MIN_U,
+    OP_I32X4_MAX_S,
+    OP_I32X4_MAX_U,
+    OP_I32X4_DOT_I16X8_S,
+    OP_I32X4_EXTMUL_LOW_I16X8_S,
+    OP_I32X4_EXTMUL_HIGH_I16X8_S,
+    OP_I32X4_EXTMUL_LOW_I16X8_U,
+    OP_I32X4_EXTMUL_HIGH_I16X8_U,
+    OP_I64X2_ABS,
+    OP_I64X2_NEG,
+    OP_I64X2_ALL_TRUE,
+    OP_I64X2_BITMASK,
+    OP_I64X2_EXTEND_LOW_I32X4_S,
+    OP_I64X2_EXTEND_HIGH_I32X4_S,
+    OP_I64X2_EXTEND_LOW_I32X4_U,
+    OP_I64X2_EXTEND_HIGH_I32X4_U,
+    OP_I64X2_SHL,
+    OP_I64X2_SHR_S,
+    OP_I64X2_SHR_U,
+    OP_I64X2_ADD,
+    OP_I64X2_SUB,
+    OP_I64X2_MUL,
+    OP_I64X2_EQ,
+    OP_I64X2_NE,
+    OP_I64X2_LT_S,
+    OP_I64X2_GT_S,
+    OP_I64X2_LE_S,
+    OP_I64X2_GE_S,
+    OP_I64X2_EXTMUL_LOW_I32X4_S,
+    OP_I64X2_EXTMUL_HIGH_I32X4_S,
+    OP_I64X2_EXTMUL_LOW_I32X4_U,
+    OP_I64X2_EXTMUL_HIGH_I32X4_U,
+    OP_F32X4_ABS,
+    OP_F32X4_NEG,
+    OP_F32X4_SQRT,
+    OP_F32X4_ADD,
+    OP_F32X4_SUB,
+    OP_F32X4_MUL,
+    OP_F32X4_DIV,
+    OP_F32X4_MIN,
+    OP_F32X4_MAX,
+    OP_F32X4_PMIN,
+    OP_F32X4_PMAX,
+    OP_F64X2_ABS,
+    OP_F64X2_NEG,
+    OP_F64X2_SQRT,
+    OP_F64X2_ADD,
+    OP_F64X2_SUB,
+    OP_F64X2_MUL,
+    OP_F64X2_DIV,
+    OP_F64X2_MIN,
+    OP_F64X2_MAX,
+    OP_F64X2_PMIN,
+    OP_F64X2_PMAX,
+    OP_I32X4_TRUNC_SAT_F32X4_S,
+    OP_I32X4_TRUNC_SAT_F32X4_U,
+    OP_F32X4_CONVERT_I32X4_S,
+    OP_F32X4_CONVERT_I32X4_U,
+    OP_I32X4_TRUNC_SAT_F64X2_S_ZERO,
+    OP_I32X4_TRUNC_SAT_F64X2_U_ZERO,
+    OP_F64X2_CONVERT_LOW_I32X4_S,
+    OP_F64X2_CONVERT_LOW_I32X4_U,
+
+    // Atomic
+    OP_MEMORY_ATOMIC_NOTIFY(MemArg),
+    OP_MEMORY_ATOMIC_WAIT32(MemArg),
+    OP_MEMORY_ATOMIC_WAIT64(MemArg),
+    OP_ATOMIC_FENCE,
+    OP_I32_ATOMIC_LOAD(MemArg),
+    OP_I64_ATOMIC_LOAD(MemArg),
+    OP_I32_ATOMIC_LOAD8_U(MemArg),
+    OP_I32_ATOMIC_LOAD16_U(MemArg),
+    OP_I64_ATOMIC_LOAD8_U(MemArg),
+    OP_I64_ATOMIC_LOAD16_U(MemArg),
+    OP_I64_ATOMIC_LOAD32_U(MemArg),
+    OP_I32_ATOMIC_STORE(MemArg),
+    OP_I64_ATOMIC_STORE(MemArg),
+    OP_I32_ATOMIC_STORE8(MemArg),
+    OP_I32_ATOMIC_STORE16(MemArg),
+    OP_I64_ATOMIC_STORE8(MemArg),
+    OP_I64_ATOMIC_STORE16(MemArg),
+    OP_I64_ATOMIC_STORE32(MemArg),
+    OP_I32_ATOMIC_RMW_ADD(MemArg),
+    OP_I64_ATOMIC_RMW_ADD(MemArg),
+    OP_I32_ATOMIC_RMW8_ADD_U(MemArg),
+    OP_I32_ATOMIC_RMW16_ADD_U(MemArg),
+    OP_I64_ATOMIC_RMW8_ADD_U(MemArg),
+    OP_I64_ATOMIC_RMW16_ADD_U(MemArg),
+    OP_I64_ATOMIC_RMW32_ADD_U(MemArg),
+    OP_I32_ATOMIC_RMW_SUB(MemArg),
+    OP_I64_ATOMIC_RMW_SUB(MemArg),
+    OP_I32_ATOMIC_RMW8_SUB_U(MemArg),
+    OP_I32_ATOMIC_RMW16_SUB_U(MemArg),
+    OP_I64_ATOMIC_RMW8_SUB_U(MemArg),
+    OP_I64_ATOMIC_RMW16_SUB_U(MemArg),
+    OP_I64_ATOMIC_RMW32_SUB_U(MemArg),
+    OP_I32_ATOMIC_RMW_AND(MemArg),
+    OP_I64_ATOMIC_RMW_AND(MemArg),
+    OP_I32_ATOMIC_RMW8_AND_U(MemArg),
+    OP_I32_ATOMIC_RMW16_AND_U(MemArg),
+    OP_I64_ATOMIC_RMW8_AND_U(MemArg),
+    OP_I64_ATOMIC_RMW16_AND_U(MemArg),
+    OP_I64_ATOMIC_RMW32_AND_U(MemArg),
+    OP_I32_ATOMIC_RMW_OR(MemArg),
+    OP_I64_ATOMIC_RMW_OR(MemArg),
+    OP_I32_ATOMIC_RMW8_OR_U(MemArg),
+    OP_I32_ATOMIC_RMW16_OR_U(MemArg),
+    OP_I64_ATOMIC_RMW8_OR_U(MemArg),
+    OP_I64_ATOMIC_RMW16_OR_U(MemArg),
+    OP_I64_ATOMIC_RMW32_OR_U(MemArg),
+    OP_I32_ATOMIC_RMW_XOR(MemArg),
+    OP_I64_ATOMIC_RMW_XOR(MemArg),
+    OP_I32_ATOMIC_RMW8_XOR_U(MemArg),
+    OP_I32_ATOMIC_RMW16_XOR_U(MemArg),
+    OP_I64_ATOMIC_RMW8_XOR_U(MemArg),
+    OP_I64_ATOMIC_RMW16_XOR_U(MemArg),
+    OP_I64_ATOMIC_RMW32_XOR_U(MemArg),
+    OP_I32_ATOMIC_RMW_XCHG(MemArg),
+    OP_I64_ATOMIC_RMW_XCHG(MemArg),
+    OP_I32_ATOMIC_RMW8_XCHG_U(MemArg),
+    OP_I32_ATOMIC_RMW16_XCHG_U(MemArg),
+    OP_I64_ATOMIC_RMW8_XCHG_U(MemArg),
+    OP_I64_ATOMIC_RMW16_XCHG_U(MemArg),
+    OP_I64_ATOMIC_RMW32_XCHG_U(MemArg),
+    OP_I32_ATOMIC_RMW_CMPXCHG(MemArg),
+    OP_I64_ATOMIC_RMW_CMPXCHG(MemArg),
+    OP_I32_ATOMIC_RMW8_CMPXCHG_U(MemArg),
+    OP_I32_ATOMIC_RMW16_CMPXCHG_U(MemArg),
+    OP_I64_ATOMIC_RMW8_CMPXCHG_U(MemArg),
+    OP_I64_ATOMIC_RMW16_CMPXCHG_U(MemArg),
+    OP_I64_ATOMIC_RMW32_CMPXCHG_U(MemArg),
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct MemArg {
     pub align: u32,
-    pub offset: u32,
+    /// The offset is 64 bits wide for memories of the index type `I64`
+    pub offset: u64,
 }
 
 impl std::convert::From<u8> for ValueType {
@@ -605,6 +1034,9 @@ impl std::convert::From<u8> for ValueType {
             0x7E => Self::I64,
             0x7D => Self::F32,
             0x7C => Self::F64,
+            0x7B => Self::V128,
+            0x70 => Self::FuncRef,
+            0x6F => Self::ExternRef,
             _ => panic!("wrong value type"),
         }
     }
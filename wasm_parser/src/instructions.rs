@@ -1,5 +1,6 @@
 use log::debug;
 use nom::bytes::complete::take;
+use nom::error::ErrorKind;
 use nom::multi::count;
 use nom::IResult;
 
@@ -8,10 +9,13 @@ use crate::{take_blocktype, take_f32, take_f64, take_leb_i32, take_leb_i64};
 
 const END_INSTR: &[u8] = &[0x0B];
 const END_IF_BLOCK: &[u8] = &[0x05];
+const CATCH: u8 = 0x07;
+const DELEGATE: u8 = 0x18;
+const CATCH_ALL: u8 = 0x19;
 
-pub(crate) fn parse_instr<'a, 'b>(
+pub(crate) fn parse_instr<'a>(
     i: &'a [u8],
-    counter: &'b mut Counter,
+    counter: &mut Counter,
 ) -> IResult<&'a [u8], Instruction> {
     debug!("parse_instr");
     debug!("---------------");
@@ -30,9 +34,33 @@ pub(crate) fn parse_instr<'a, 'b>(
         0x0F => (i, Instruction::OP_RETURN),
         0x10 => take_call(i)?,
         0x11 => take_call_indirect(i)?,
+        0x12 => {
+            let (i, func_idx) = crate::take_leb_u32(i)?;
+            (i, Instruction::OP_RETURN_CALL(func_idx))
+        }
+        0x13 => {
+            let (i, type_idx) = crate::take_leb_u32(i)?;
+            let (i, table_idx) = crate::take_leb_u32(i)?;
+            (i, Instruction::OP_RETURN_CALL_INDIRECT(type_idx, table_idx))
+        }
+        // Exception
+        0x06 => take_try(i, counter)?,
+        0x08 => {
+            let (i, tag_idx) = crate::take_leb_u32(i)?;
+            (i, Instruction::OP_THROW(tag_idx))
+        }
+        0x09 => {
+            let (i, label_idx) = crate::take_leb_u32(i)?;
+            (i, Instruction::OP_RETHROW(label_idx))
+        }
         // Parametric
         0x1A => (i, Instruction::OP_DROP),
         0x1B => (i, Instruction::OP_SELECT),
+        0x1C => {
+            let (i, times) = crate::take_leb_u32(i)?;
+            let (i, types) = count(crate::take_valtype, times as usize)(i)?;
+            (i, Instruction::OP_SELECT_T(types))
+        }
         // Var
         0x20 => {
             let (i, idx) = crate::take_leb_u32(i)?;
@@ -59,6 +87,15 @@ pub(crate) fn parse_instr<'a, 'b>(
             let block = Instruction::OP_GLOBAL_SET(idx);
             (i, block)
         }
+        // Table
+        0x25 => {
+            let (i, idx) = crate::take_leb_u32(i)?;
+            (i, Instruction::OP_TABLE_GET(idx))
+        }
+        0x26 => {
+            let (i, idx) = crate::take_leb_u32(i)?;
+            (i, Instruction::OP_TABLE_SET(idx))
+        }
         // Memory
         0x28 => {
             let (i, m) = take_memarg(i)?;
@@ -344,31 +381,88 @@ pub(crate) fn parse_instr<'a, 'b>(
         0xc3 => (i, Instruction::OP_I64_EXTEND16_S),
         0xc4 => (i, Instruction::OP_I64_EXTEND32_S),
 
+        // Reference
+        0xD0 => {
+            let (i, ty) = crate::take_valtype(i)?;
+            (i, Instruction::OP_REF_NULL(ty))
+        }
+        0xD1 => (i, Instruction::OP_REF_IS_NULL),
+        0xD2 => {
+            let (i, idx) = crate::take_leb_u32(i)?;
+            (i, Instruction::OP_REF_FUNC(idx))
+        }
         0xfc => {
-            let (i, m) = take(1u8)(i)?;
-            (
-                i,
-                match m {
-                    [0x00] => Instruction::OP_I32_TRUNC_SAT_F32_S,
-                    [0x01] => Instruction::OP_I32_TRUNC_SAT_F32_U,
-                    [0x02] => Instruction::OP_I32_TRUNC_SAT_F64_S,
-                    [0x03] => Instruction::OP_I32_TRUNC_SAT_F64_U,
-
-                    [0x04] => Instruction::OP_I64_TRUNC_SAT_F32_S,
-                    [0x05] => Instruction::OP_I64_TRUNC_SAT_F32_U,
-                    [0x06] => Instruction::OP_I64_TRUNC_SAT_F64_S,
-                    [0x07] => Instruction::OP_I64_TRUNC_SAT_F64_U,
-                    _ => panic!("Invalid saturating instruction"),
-                },
-            )
+            let (i, m) = crate::take_leb_u32(i)?;
+            match m {
+                0x00 => (i, Instruction::OP_I32_TRUNC_SAT_F32_S),
+                0x01 => (i, Instruction::OP_I32_TRUNC_SAT_F32_U),
+                0x02 => (i, Instruction::OP_I32_TRUNC_SAT_F64_S),
+                0x03 => (i, Instruction::OP_I32_TRUNC_SAT_F64_U),
+
+                0x04 => (i, Instruction::OP_I64_TRUNC_SAT_F32_S),
+                0x05 => (i, Instruction::OP_I64_TRUNC_SAT_F32_U),
+                0x06 => (i, Instruction::OP_I64_TRUNC_SAT_F64_S),
+                0x07 => (i, Instruction::OP_I64_TRUNC_SAT_F64_U),
+
+                // Bulk memory
+                0x08 => {
+                    let (i, data_idx) = crate::take_leb_u32(i)?;
+                    let (i, _) = take_zero_byte(i)?;
+                    (i, Instruction::OP_MEMORY_INIT(data_idx))
+                }
+                0x09 => {
+                    let (i, data_idx) = crate::take_leb_u32(i)?;
+                    (i, Instruction::OP_DATA_DROP(data_idx))
+                }
+                0x0A => {
+                    let (i, _) = take_zero_byte(i)?;
+                    let (i, _) = take_zero_byte(i)?;
+                    (i, Instruction::OP_MEMORY_COPY)
+                }
+                0x0B => {
+                    let (i, _) = take_zero_byte(i)?;
+                    (i, Instruction::OP_MEMORY_FILL)
+                }
+                0x0C => {
+                    let (i, elem_idx) = crate::take_leb_u32(i)?;
+                    let (i, table_idx) = crate::take_leb_u32(i)?;
+                    (i, Instruction::OP_TABLE_INIT(elem_idx, table_idx))
+                }
+                0x0D => {
+                    let (i, elem_idx) = crate::take_leb_u32(i)?;
+                    (i, Instruction::OP_ELEM_DROP(elem_idx))
+                }
+                0x0E => {
+                    let (i, dst) = crate::take_leb_u32(i)?;
+                    let (i, src) = crate::take_leb_u32(i)?;
+                    (i, Instruction::OP_TABLE_COPY(dst, src))
+                }
+
+                // Table
+                0x0F => {
+                    let (i, idx) = crate::take_leb_u32(i)?;
+                    (i, Instruction::OP_TABLE_GROW(idx))
+                }
+                0x10 => {
+                    let (i, idx) = crate::take_leb_u32(i)?;
+                    (i, Instruction::OP_TABLE_SIZE(idx))
+                }
+                0x11 => {
+                    let (i, idx) = crate::take_leb_u32(i)?;
+                    (i, Instruction::OP_TABLE_FILL(idx))
+                }
+                _ => return invalid_opcode(i, 0xFC, m),
+            }
         }
+        0xfd => take_vector(i)?,
+        0xfe => take_atomic(i)?,
         _ => panic!("unknown instruction {}", instr[0]),
     };
 
     Ok((i, expr))
 }
 
-fn take_block<'a, 'b>(i: &'b [u8], counter: &'a mut Counter) -> IResult<&'b [u8], Instruction> {
+fn take_block<'b>(i: &'b [u8], counter: &mut Counter) -> IResult<&'b [u8], Instruction> {
     let (mut i, block_ty) = take_blocktype(i)?;
 
     //let (i, instructions) = take_expr(i)?;
@@ -394,7 +488,7 @@ fn take_block<'a, 'b>(i: &'b [u8], counter: &'a mut Counter) -> IResult<&'b [u8]
     Ok((i, block))
 }
 
-fn take_loop<'a, 'b>(i: &'b [u8], counter: &'a mut Counter) -> IResult<&'b [u8], Instruction> {
+fn take_loop<'b>(i: &'b [u8], counter: &mut Counter) -> IResult<&'b [u8], Instruction> {
     let (mut i, block_ty) = take_blocktype(i)?;
 
     let mut instructions = Vec::new();
@@ -419,9 +513,9 @@ fn take_loop<'a, 'b>(i: &'b [u8], counter: &'a mut Counter) -> IResult<&'b [u8],
     Ok((i, block))
 }
 
-fn take_conditional<'a, 'b>(
+fn take_conditional<'b>(
     i: &'b [u8],
-    counter: &'a mut Counter,
+    counter: &mut Counter,
 ) -> IResult<&'b [u8], Instruction> {
     debug!("take_conditional");
 
@@ -479,6 +573,76 @@ fn take_conditional<'a, 'b>(
     ))
 }
 
+/// Takes the instructions until one of the `terminators`, which is not consumed
+fn take_instructions_until<'b>(
+    mut i: &'b [u8],
+    counter: &mut Counter,
+    terminators: &[u8],
+) -> IResult<&'b [u8], Vec<Instruction>> {
+    let mut instructions = Vec::new();
+
+    loop {
+        let (_, k) = take(1u8)(i)?;
+
+        if terminators.contains(&k[0]) {
+            break;
+        }
+
+        let (w, ii) = parse_instr(i, counter)?;
+        i = w;
+        instructions.push(ii);
+    }
+
+    Ok((i, instructions))
+}
+
+/// The block of `try` is either followed by its handlers or by `delegate`
+fn take_try<'b>(i: &'b [u8], counter: &mut Counter) -> IResult<&'b [u8], Instruction> {
+    debug!("take_try");
+
+    let (i, blockty) = take_blocktype(i)?;
+    let (mut i, instructions) =
+        take_instructions_until(i, counter, &[CATCH, CATCH_ALL, DELEGATE, END_INSTR[0]])?;
+
+    let mut catches = Vec::new();
+
+    loop {
+        let (w, k) = take(1u8)(i)?;
+        i = w;
+
+        match k[0] {
+            CATCH => {
+                let (w, tag_idx) = crate::take_leb_u32(i)?;
+                let (w, handler) = take_instructions_until(w, counter, &[CATCH, CATCH_ALL, END_INSTR[0]])?;
+                i = w;
+
+                catches.push(Catch::Tag(tag_idx, CodeBlock::new(counter, handler)));
+            }
+            CATCH_ALL => {
+                let (w, handler) = take_instructions_until(i, counter, &[END_INSTR[0]])?;
+                i = w;
+
+                catches.push(Catch::All(CodeBlock::new(counter, handler)));
+            }
+            DELEGATE => {
+                assert!(catches.is_empty(), "delegate cannot follow catch");
+                let (i, label_idx) = crate::take_leb_u32(i)?;
+
+                return Ok((
+                    i,
+                    Instruction::OP_TRY_DELEGATE(blockty, CodeBlock::new(counter, instructions), label_idx),
+                ));
+            }
+            _ => break,
+        }
+    }
+
+    Ok((
+        i,
+        Instruction::OP_TRY(blockty, CodeBlock::new(counter, instructions), catches),
+    ))
+}
+
 fn take_br(i: &[u8]) -> IResult<&[u8], Instruction> {
     let (i, labelidx) = crate::take_leb_u32(i)?;
 
@@ -516,18 +680,687 @@ fn take_call(i: &[u8]) -> IResult<&[u8], Instruction> {
 
 fn take_call_indirect(i: &[u8]) -> IResult<&[u8], Instruction> {
     let (i, type_idx) = crate::take_leb_u32(i)?;
-    let (i, b) = take(1u8)(i)?;
-
-    assert_eq!(b, &[0x00]);
+    let (i, table_idx) = crate::take_leb_u32(i)?;
 
-    let block = Instruction::OP_CALL_INDIRECT(type_idx);
+    let block = Instruction::OP_CALL_INDIRECT(type_idx, table_idx);
 
     Ok((i, block))
 }
 
+/// The memory index of the bulk memory instructions, which must be zero
+fn take_zero_byte(i: &[u8]) -> IResult<&[u8], &[u8]> {
+    let (i, m) = take(1u8)(i)?;
+    assert_eq!([0x00], m);
+    Ok((i, m))
+}
+
+/// The vector instructions are prefixed with `0xFD` and a LEB128 encoded opcode
+/// Fails the parsing on the unknown opcode `m` after the `prefix`
+fn invalid_opcode<O>(i: &[u8], prefix: u8, m: u32) -> IResult<&[u8], O> {
+    debug!("Invalid {:#X} instruction {}", prefix, m);
+    Err(nom::Err::Failure((i, ErrorKind::Switch)))
+}
+
+fn take_vector(i: &[u8]) -> IResult<&[u8], Instruction> {
+    let (i, m) = crate::take_leb_u32(i)?;
+    debug!("vector instruction {:x?}", m);
+
+    let instr = match m {
+        0x00 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD(m))
+        }
+        0x01 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD8X8_S(m))
+        }
+        0x02 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD8X8_U(m))
+        }
+        0x03 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD16X4_S(m))
+        }
+        0x04 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD16X4_U(m))
+        }
+        0x05 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD32X2_S(m))
+        }
+        0x06 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD32X2_U(m))
+        }
+        0x07 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD8_SPLAT(m))
+        }
+        0x08 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD16_SPLAT(m))
+        }
+        0x09 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD32_SPLAT(m))
+        }
+        0x0A => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD64_SPLAT(m))
+        }
+        0x0B => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_STORE(m))
+        }
+        0x0C => {
+            let (i, bytes) = take(16u8)(i)?;
+            let mut v = [0; 16];
+            v.copy_from_slice(bytes);
+            (i, Instruction::OP_V128_CONST(u128::from_le_bytes(v)))
+        }
+        0x0D => {
+            let (i, bytes) = take(16u8)(i)?;
+            let mut lanes = [0; 16];
+            lanes.copy_from_slice(bytes);
+            (i, Instruction::OP_I8X16_SHUFFLE(lanes))
+        }
+        0x0E => (i, Instruction::OP_I8X16_SWIZZLE),
+        0x0F => (i, Instruction::OP_I8X16_SPLAT),
+        0x10 => (i, Instruction::OP_I16X8_SPLAT),
+        0x11 => (i, Instruction::OP_I32X4_SPLAT),
+        0x12 => (i, Instruction::OP_I64X2_SPLAT),
+        0x13 => (i, Instruction::OP_F32X4_SPLAT),
+        0x14 => (i, Instruction::OP_F64X2_SPLAT),
+        0x15 => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_I8X16_EXTRACT_LANE_S(lane))
+        }
+        0x16 => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_I8X16_EXTRACT_LANE_U(lane))
+        }
+        0x17 => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_I8X16_REPLACE_LANE(lane))
+        }
+        0x18 => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_I16X8_EXTRACT_LANE_S(lane))
+        }
+        0x19 => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_I16X8_EXTRACT_LANE_U(lane))
+        }
+        0x1A => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_I16X8_REPLACE_LANE(lane))
+        }
+        0x1B => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_I32X4_EXTRACT_LANE(lane))
+        }
+        0x1C => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_I32X4_REPLACE_LANE(lane))
+        }
+        0x1D => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_I64X2_EXTRACT_LANE(lane))
+        }
+        0x1E => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_I64X2_REPLACE_LANE(lane))
+        }
+        0x1F => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_F32X4_EXTRACT_LANE(lane))
+        }
+        0x20 => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_F32X4_REPLACE_LANE(lane))
+        }
+        0x21 => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_F64X2_EXTRACT_LANE(lane))
+        }
+        0x22 => {
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_F64X2_REPLACE_LANE(lane))
+        }
+        0x23 => (i, Instruction::OP_I8X16_EQ),
+        0x24 => (i, Instruction::OP_I8X16_NE),
+        0x25 => (i, Instruction::OP_I8X16_LT_S),
+        0x26 => (i, Instruction::OP_I8X16_LT_U),
+        0x27 => (i, Instruction::OP_I8X16_GT_S),
+        0x28 => (i, Instruction::OP_I8X16_GT_U),
+        0x29 => (i, Instruction::OP_I8X16_LE_S),
+        0x2A => (i, Instruction::OP_I8X16_LE_U),
+        0x2B => (i, Instruction::OP_I8X16_GE_S),
+        0x2C => (i, Instruction::OP_I8X16_GE_U),
+        0x2D => (i, Instruction::OP_I16X8_EQ),
+        0x2E => (i, Instruction::OP_I16X8_NE),
+        0x2F => (i, Instruction::OP_I16X8_LT_S),
+        0x30 => (i, Instruction::OP_I16X8_LT_U),
+        0x31 => (i, Instruction::OP_I16X8_GT_S),
+        0x32 => (i, Instruction::OP_I16X8_GT_U),
+        0x33 => (i, Instruction::OP_I16X8_LE_S),
+        0x34 => (i, Instruction::OP_I16X8_LE_U),
+        0x35 => (i, Instruction::OP_I16X8_GE_S),
+        0x36 => (i, Instruction::OP_I16X8_GE_U),
+        0x37 => (i, Instruction::OP_I32X4_EQ),
+        0x38 => (i, Instruction::OP_I32X4_NE),
+        0x39 => (i, Instruction::OP_I32X4_LT_S),
+        0x3A => (i, Instruction::OP_I32X4_LT_U),
+        0x3B => (i, Instruction::OP_I32X4_GT_S),
+        0x3C => (i, Instruction::OP_I32X4_GT_U),
+        0x3D => (i, Instruction::OP_I32X4_LE_S),
+        0x3E => (i, Instruction::OP_I32X4_LE_U),
+        0x3F => (i, Instruction::OP_I32X4_GE_S),
+        0x40 => (i, Instruction::OP_I32X4_GE_U),
+        0x41 => (i, Instruction::OP_F32X4_EQ),
+        0x42 => (i, Instruction::OP_F32X4_NE),
+        0x43 => (i, Instruction::OP_F32X4_LT),
+        0x44 => (i, Instruction::OP_F32X4_GT),
+        0x45 => (i, Instruction::OP_F32X4_LE),
+        0x46 => (i, Instruction::OP_F32X4_GE),
+        0x47 => (i, Instruction::OP_F64X2_EQ),
+        0x48 => (i, Instruction::OP_F64X2_NE),
+        0x49 => (i, Instruction::OP_F64X2_LT),
+        0x4A => (i, Instruction::OP_F64X2_GT),
+        0x4B => (i, Instruction::OP_F64X2_LE),
+        0x4C => (i, Instruction::OP_F64X2_GE),
+        0x4D => (i, Instruction::OP_V128_NOT),
+        0x4E => (i, Instruction::OP_V128_AND),
+        0x4F => (i, Instruction::OP_V128_ANDNOT),
+        0x50 => (i, Instruction::OP_V128_OR),
+        0x51 => (i, Instruction::OP_V128_XOR),
+        0x52 => (i, Instruction::OP_V128_BITSELECT),
+        0x53 => (i, Instruction::OP_V128_ANY_TRUE),
+        0x54 => {
+            let (i, m) = take_memarg(i)?;
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_V128_LOAD8_LANE(m, lane))
+        }
+        0x55 => {
+            let (i, m) = take_memarg(i)?;
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_V128_LOAD16_LANE(m, lane))
+        }
+        0x56 => {
+            let (i, m) = take_memarg(i)?;
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_V128_LOAD32_LANE(m, lane))
+        }
+        0x57 => {
+            let (i, m) = take_memarg(i)?;
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_V128_LOAD64_LANE(m, lane))
+        }
+        0x58 => {
+            let (i, m) = take_memarg(i)?;
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_V128_STORE8_LANE(m, lane))
+        }
+        0x59 => {
+            let (i, m) = take_memarg(i)?;
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_V128_STORE16_LANE(m, lane))
+        }
+        0x5A => {
+            let (i, m) = take_memarg(i)?;
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_V128_STORE32_LANE(m, lane))
+        }
+        0x5B => {
+            let (i, m) = take_memarg(i)?;
+            let (i, lane) = take_lane(i)?;
+            (i, Instruction::OP_V128_STORE64_LANE(m, lane))
+        }
+        0x5C => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD32_ZERO(m))
+        }
+        0x5D => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_V128_LOAD64_ZERO(m))
+        }
+        0x5E => (i, Instruction::OP_F32X4_DEMOTE_F64X2_ZERO),
+        0x5F => (i, Instruction::OP_F64X2_PROMOTE_LOW_F32X4),
+        0x60 => (i, Instruction::OP_I8X16_ABS),
+        0x61 => (i, Instruction::OP_I8X16_NEG),
+        0x62 => (i, Instruction::OP_I8X16_POPCNT),
+        0x63 => (i, Instruction::OP_I8X16_ALL_TRUE),
+        0x64 => (i, Instruction::OP_I8X16_BITMASK),
+        0x65 => (i, Instruction::OP_I8X16_NARROW_I16X8_S),
+        0x66 => (i, Instruction::OP_I8X16_NARROW_I16X8_U),
+        0x67 => (i, Instruction::OP_F32X4_CEIL),
+        0x68 => (i, Instruction::OP_F32X4_FLOOR),
+        0x69 => (i, Instruction::OP_F32X4_TRUNC),
+        0x6A => (i, Instruction::OP_F32X4_NEAREST),
+        0x6B => (i, Instruction::OP_I8X16_SHL),
+        0x6C => (i, Instruction::OP_I8X16_SHR_S),
+        0x6D => (i, Instruction::OP_I8X16_SHR_U),
+        0x6E => (i, Instruction::OP_I8X16_ADD),
+        0x6F => (i, Instruction::OP_I8X16_ADD_SAT_S),
+        0x70 => (i, Instruction::OP_I8X16_ADD_SAT_U),
+        0x71 => (i, Instruction::OP_I8X16_SUB),
+        0x72 => (i, Instruction::OP_I8X16_SUB_SAT_S),
+        0x73 => (i, Instruction::OP_I8X16_SUB_SAT_U),
+        0x74 => (i, Instruction::OP_F64X2_CEIL),
+        0x75 => (i, Instruction::OP_F64X2_FLOOR),
+        0x76 => (i, Instruction::OP_I8X16_MIN_S),
+        0x77 => (i, Instruction::OP_I8X16_MIN_U),
+        0x78 => (i, Instruction::OP_I8X16_MAX_S),
+        0x79 => (i, Instruction::OP_I8X16_MAX_U),
+        0x7A => (i, Instruction::OP_F64X2_TRUNC),
+        0x7B => (i, Instruction::OP_I8X16_AVGR_U),
+        0x7C => (i, Instruction::OP_I16X8_EXTADD_PAIRWISE_I8X16_S),
+        0x7D => (i, Instruction::OP_I16X8_EXTADD_PAIRWISE_I8X16_U),
+        0x7E => (i, Instruction::OP_I32X4_EXTADD_PAIRWISE_I16X8_S),
+        0x7F => (i, Instruction::OP_I32X4_EXTADD_PAIRWISE_I16X8_U),
+        0x80 => (i, Instruction::OP_I16X8_ABS),
+        0x81 => (i, Instruction::OP_I16X8_NEG),
+        0x82 => (i, Instruction::OP_I16X8_Q15MULR_SAT_S),
+        0x83 => (i, Instruction::OP_I16X8_ALL_TRUE),
+        0x84 => (i, Instruction::OP_I16X8_BITMASK),
+        0x85 => (i, Instruction::OP_I16X8_NARROW_I32X4_S),
+        0x86 => (i, Instruction::OP_I16X8_NARROW_I32X4_U),
+        0x87 => (i, Instruction::OP_I16X8_EXTEND_LOW_I8X16_S),
+        0x88 => (i, Instruction::OP_I16X8_EXTEND_HIGH_I8X16_S),
+        0x89 => (i, Instruction::OP_I16X8_EXTEND_LOW_I8X16_U),
+        0x8A => (i, Instruction::OP_I16X8_EXTEND_HIGH_I8X16_U),
+        0x8B => (i, Instruction::OP_I16X8_SHL),
+        0x8C => (i, Instruction::OP_I16X8_SHR_S),
+        0x8D => (i, Instruction::OP_I16X8_SHR_U),
+        0x8E => (i, Instruction::OP_I16X8_ADD),
+        0x8F => (i, Instruction::OP_I16X8_ADD_SAT_S),
+        0x90 => (i, Instruction::OP_I16X8_ADD_SAT_U),
+        0x91 => (i, Instruction::OP_I16X8_SUB),
+        0x92 => (i, Instruction::OP_I16X8_SUB_SAT_S),
+        0x93 => (i, Instruction::OP_I16X8_SUB_SAT_U),
+        0x94 => (i, Instruction::OP_F64X2_NEAREST),
+        0x95 => (i, Instruction::OP_I16X8_MUL),
+        0x96 => (i, Instruction::OP_I16X8_MIN_S),
+        0x97 => (i, Instruction::OP_I16X8_MIN_U),
+        0x98 => (i, Instruction::OP_I16X8_MAX_S),
+        0x99 => (i, Instruction::OP_I16X8_MAX_U),
+        0x9B => (i, Instruction::OP_I16X8_AVGR_U),
+        0x9C => (i, Instruction::OP_I16X8_EXTMUL_LOW_I8X16_S),
+        0x9D => (i, Instruction::OP_I16X8_EXTMUL_HIGH_I8X16_S),
+        0x9E => (i, Instruction::OP_I16X8_EXTMUL_LOW_I8X16_U),
+        0x9F => (i, Instruction::OP_I16X8_EXTMUL_HIGH_I8X16_U),
+        0xA0 => (i, Instruction::OP_I32X4_ABS),
+        0xA1 => (i, Instruction::OP_I32X4_NEG),
+        0xA3 => (i, Instruction::OP_I32X4_ALL_TRUE),
+        0xA4 => (i, Instruction::OP_I32X4_BITMASK),
+        0xA7 => (i, Instruction::OP_I32X4_EXTEND_LOW_I16X8_S),
+        0xA8 => (i, Instruction::OP_I32X4_EXTEND_HIGH_I16X8_S),
+        0xA9 => (i, Instruction::OP_I32X4_EXTEND_LOW_I16X8_U),
+        0xAA => (i, Instruction::OP_I32X4_EXTEND_HIGH_I16X8_U),
+        0xAB => (i, Instruction::OP_I32X4_SHL),
+        0xAC => (i, Instruction::OP_I32X4_SHR_S),
+        0xAD => (i, Instruction::OP_I32X4_SHR_U),
+        0xAE => (i, Instruction::OP_I32X4_ADD),
+        0xB1 => (i, Instruction::OP_I32X4_SUB),
+        0xB5 => (i, Instruction::OP_I32X4_MUL),
+        0xB6 => (i, Instruction::OP_I32X4_MIN_S),
+        0xB7 => (i, Instruction::OP_I32X4_MIN_U),
+        0xB8 => (i, Instruction::OP_I32X4_MAX_S),
+        0xB9 => (i, Instruction::OP_I32X4_MAX_U),
+        0xBA => (i, Instruction::OP_I32X4_DOT_I16X8_S),
+        0xBC => (i, Instruction::OP_I32X4_EXTMUL_LOW_I16X8_S),
+        0xBD => (i, Instruction::OP_I32X4_EXTMUL_HIGH_I16X8_S),
+        0xBE => (i, Instruction::OP_I32X4_EXTMUL_LOW_I16X8_U),
+        0xBF => (i, Instruction::OP_I32X4_EXTMUL_HIGH_I16X8_U),
+        0xC0 => (i, Instruction::OP_I64X2_ABS),
+        0xC1 => (i, Instruction::OP_I64X2_NEG),
+        0xC3 => (i, Instruction::OP_I64X2_ALL_TRUE),
+        0xC4 => (i, Instruction::OP_I64X2_BITMASK),
+        0xC7 => (i, Instruction::OP_I64X2_EXTEND_LOW_I32X4_S),
+        0xC8 => (i, Instruction::OP_I64X2_EXTEND_HIGH_I32X4_S),
+        0xC9 => (i, Instruction::OP_I64X2_EXTEND_LOW_I32X4_U),
+        0xCA => (i, Instruction::OP_I64X2_EXTEND_HIGH_I32X4_U),
+        0xCB => (i, Instruction::OP_I64X2_SHL),
+        0xCC => (i, Instruction::OP_I64X2_SHR_S),
+        0xCD => (i, Instruction::OP_I64X2_SHR_U),
+        0xCE => (i, Instruction::OP_I64X2_ADD),
+        0xD1 => (i, Instruction::OP_I64X2_SUB),
+        0xD5 => (i, Instruction::OP_I64X2_MUL),
+        0xD6 => (i, Instruction::OP_I64X2_EQ),
+        0xD7 => (i, Instruction::OP_I64X2_NE),
+        0xD8 => (i, Instruction::OP_I64X2_LT_S),
+        0xD9 => (i, Instruction::OP_I64X2_GT_S),
+        0xDA => (i, Instruction::OP_I64X2_LE_S),
+        0xDB => (i, Instruction::OP_I64X2_GE_S),
+        0xDC => (i, Instruction::OP_I64X2_EXTMUL_LOW_I32X4_S),
+        0xDD => (i, Instruction::OP_I64X2_EXTMUL_HIGH_I32X4_S),
+        0xDE => (i, Instruction::OP_I64X2_EXTMUL_LOW_I32X4_U),
+        0xDF => (i, Instruction::OP_I64X2_EXTMUL_HIGH_I32X4_U),
+        0xE0 => (i, Instruction::OP_F32X4_ABS),
+        0xE1 => (i, Instruction::OP_F32X4_NEG),
+        0xE3 => (i, Instruction::OP_F32X4_SQRT),
+        0xE4 => (i, Instruction::OP_F32X4_ADD),
+        0xE5 => (i, Instruction::OP_F32X4_SUB),
+        0xE6 => (i, Instruction::OP_F32X4_MUL),
+        0xE7 => (i, Instruction::OP_F32X4_DIV),
+        0xE8 => (i, Instruction::OP_F32X4_MIN),
+        0xE9 => (i, Instruction::OP_F32X4_MAX),
+        0xEA => (i, Instruction::OP_F32X4_PMIN),
+        0xEB => (i, Instruction::OP_F32X4_PMAX),
+        0xEC => (i, Instruction::OP_F64X2_ABS),
+        0xED => (i, Instruction::OP_F64X2_NEG),
+        0xEF => (i, Instruction::OP_F64X2_SQRT),
+        0xF0 => (i, Instruction::OP_F64X2_ADD),
+        0xF1 => (i, Instruction::OP_F64X2_SUB),
+        0xF2 => (i, Instruction::OP_F64X2_MUL),
+        0xF3 => (i, Instruction::OP_F64X2_DIV),
+        0xF4 => (i, Instruction::OP_F64X2_MIN),
+        0xF5 => (i, Instruction::OP_F64X2_MAX),
+        0xF6 => (i, Instruction::OP_F64X2_PMIN),
+        0xF7 => (i, Instruction::OP_F64X2_PMAX),
+        0xF8 => (i, Instruction::OP_I32X4_TRUNC_SAT_F32X4_S),
+        0xF9 => (i, Instruction::OP_I32X4_TRUNC_SAT_F32X4_U),
+        0xFA => (i, Instruction::OP_F32X4_CONVERT_I32X4_S),
+        0xFB => (i, Instruction::OP_F32X4_CONVERT_I32X4_U),
+        0xFC => (i, Instruction::OP_I32X4_TRUNC_SAT_F64X2_S_ZERO),
+        0xFD => (i, Instruction::OP_I32X4_TRUNC_SAT_F64X2_U_ZERO),
+        0xFE => (i, Instruction::OP_F64X2_CONVERT_LOW_I32X4_S),
+        0xFF => (i, Instruction::OP_F64X2_CONVERT_LOW_I32X4_U),
+        _ => return invalid_opcode(i, 0xFD, m),
+    };
+
+    Ok(instr)
+}
+
+fn take_atomic(i: &[u8]) -> IResult<&[u8], Instruction> {
+    let (i, m) = crate::take_leb_u32(i)?;
+    debug!("atomic instruction {:x?}", m);
+
+    let instr = match m {
+        0x00 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_MEMORY_ATOMIC_NOTIFY(m))
+        }
+        0x01 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_MEMORY_ATOMIC_WAIT32(m))
+        }
+        0x02 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_MEMORY_ATOMIC_WAIT64(m))
+        }
+        0x03 => {
+            let (i, _) = take_zero_byte(i)?;
+            (i, Instruction::OP_ATOMIC_FENCE)
+        }
+        0x10 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_LOAD(m))
+        }
+        0x11 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_LOAD(m))
+        }
+        0x12 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_LOAD8_U(m))
+        }
+        0x13 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_LOAD16_U(m))
+        }
+        0x14 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_LOAD8_U(m))
+        }
+        0x15 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_LOAD16_U(m))
+        }
+        0x16 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_LOAD32_U(m))
+        }
+        0x17 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_STORE(m))
+        }
+        0x18 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_STORE(m))
+        }
+        0x19 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_STORE8(m))
+        }
+        0x1A => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_STORE16(m))
+        }
+        0x1B => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_STORE8(m))
+        }
+        0x1C => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_STORE16(m))
+        }
+        0x1D => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_STORE32(m))
+        }
+        0x1E => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW_ADD(m))
+        }
+        0x1F => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW_ADD(m))
+        }
+        0x20 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW8_ADD_U(m))
+        }
+        0x21 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW16_ADD_U(m))
+        }
+        0x22 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW8_ADD_U(m))
+        }
+        0x23 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW16_ADD_U(m))
+        }
+        0x24 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW32_ADD_U(m))
+        }
+        0x25 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW_SUB(m))
+        }
+        0x26 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW_SUB(m))
+        }
+        0x27 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW8_SUB_U(m))
+        }
+        0x28 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW16_SUB_U(m))
+        }
+        0x29 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW8_SUB_U(m))
+        }
+        0x2A => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW16_SUB_U(m))
+        }
+        0x2B => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW32_SUB_U(m))
+        }
+        0x2C => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW_AND(m))
+        }
+        0x2D => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW_AND(m))
+        }
+        0x2E => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW8_AND_U(m))
+        }
+        0x2F => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW16_AND_U(m))
+        }
+        0x30 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW8_AND_U(m))
+        }
+        0x31 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW16_AND_U(m))
+        }
+        0x32 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW32_AND_U(m))
+        }
+        0x33 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW_OR(m))
+        }
+        0x34 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW_OR(m))
+        }
+        0x35 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW8_OR_U(m))
+        }
+        0x36 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW16_OR_U(m))
+        }
+        0x37 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW8_OR_U(m))
+        }
+        0x38 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW16_OR_U(m))
+        }
+        0x39 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW32_OR_U(m))
+        }
+        0x3A => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW_XOR(m))
+        }
+        0x3B => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW_XOR(m))
+        }
+        0x3C => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW8_XOR_U(m))
+        }
+        0x3D => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW16_XOR_U(m))
+        }
+        0x3E => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW8_XOR_U(m))
+        }
+        0x3F => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW16_XOR_U(m))
+        }
+        0x40 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW32_XOR_U(m))
+        }
+        0x41 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW_XCHG(m))
+        }
+        0x42 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW_XCHG(m))
+        }
+        0x43 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW8_XCHG_U(m))
+        }
+        0x44 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW16_XCHG_U(m))
+        }
+        0x45 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW8_XCHG_U(m))
+        }
+        0x46 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW16_XCHG_U(m))
+        }
+        0x47 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW32_XCHG_U(m))
+        }
+        0x48 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW_CMPXCHG(m))
+        }
+        0x49 => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW_CMPXCHG(m))
+        }
+        0x4A => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW8_CMPXCHG_U(m))
+        }
+        0x4B => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I32_ATOMIC_RMW16_CMPXCHG_U(m))
+        }
+        0x4C => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW8_CMPXCHG_U(m))
+        }
+        0x4D => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW16_CMPXCHG_U(m))
+        }
+        0x4E => {
+            let (i, m) = take_memarg(i)?;
+            (i, Instruction::OP_I64_ATOMIC_RMW32_CMPXCHG_U(m))
+        }
+        _ => return invalid_opcode(i, 0xFE, m),
+    };
+
+    Ok(instr)
+}
+
+fn take_lane(i: &[u8]) -> IResult<&[u8], LaneIdx> {
+    let (i, lane) = take(1u8)(i)?;
+    Ok((i, lane[0]))
+}
+
 fn take_memarg(i: &[u8]) -> IResult<&[u8], MemArg> {
     let (i, n) = crate::take_leb_u32(i)?;
-    let (i, o) = crate::take_leb_u32(i)?;
+    let (i, o) = crate::take_leb_u64(i)?;
 
     Ok((
         i,
@@ -539,6 +1372,7 @@ fn take_memarg(i: &[u8]) -> IResult<&[u8], MemArg> {
 }
 
 #[cfg(test)]
+#[allow(clippy::vec_init_then_push)]
 mod test {
     use super::*;
 
@@ -620,7 +1454,7 @@ mod test {
         assert_eq!(
             instructions.1,
             Instruction::OP_BLOCK(
-                BlockType::FuncTy(-128),
+                BlockType::FuncTy(-128i64 as u32),
                 CodeBlock::new(&mut counter, vec![Instruction::OP_NOP, Instruction::OP_NOP])
             )
         );
@@ -830,4 +1664,93 @@ mod test {
             )
         );
     }
+
+    #[test]
+    fn test_instruction_vector() {
+        let mut counter = Counter::default();
+
+        // v128.const with 16 bytes
+        let mut payload = vec![0xFD, 0x0C];
+        payload.extend((0..16).collect::<Vec<u8>>());
+        let (i, instr) = parse_instr(&payload, &mut counter).unwrap();
+        assert!(i.is_empty());
+        assert_eq!(
+            Instruction::OP_V128_CONST(0x0F0E_0D0C_0B0A_0908_0706_0504_0302_0100),
+            instr
+        );
+
+        // The opcodes above 0x7F are LEB128 encoded
+        let payload = vec![0xFD, 0xAE, 0x01];
+        let (_, instr) = parse_instr(&payload, &mut counter).unwrap();
+        assert_eq!(Instruction::OP_I32X4_ADD, instr);
+
+        // v128.load32_lane with align, offset and lane
+        let payload = vec![0xFD, 0x56, 0x02, 0x10, 0x03];
+        let (_, instr) = parse_instr(&payload, &mut counter).unwrap();
+        assert_eq!(
+            Instruction::OP_V128_LOAD32_LANE(
+                MemArg {
+                    align: 2,
+                    offset: 16
+                },
+                3
+            ),
+            instr
+        );
+    }
+
+    #[test]
+    fn test_instruction_atomic() {
+        let mut counter = Counter::default();
+
+        // i32.atomic.rmw.cmpxchg with align and offset
+        let payload = vec![0xFE, 0x48, 0x02, 0x08];
+        let (i, instr) = parse_instr(&payload, &mut counter).unwrap();
+        assert!(i.is_empty());
+        assert_eq!(
+            Instruction::OP_I32_ATOMIC_RMW_CMPXCHG(MemArg {
+                align: 2,
+                offset: 8
+            }),
+            instr
+        );
+
+        // atomic.fence has a reserved zero byte
+        let payload = vec![0xFE, 0x03, 0x00];
+        let (i, instr) = parse_instr(&payload, &mut counter).unwrap();
+        assert!(i.is_empty());
+        assert_eq!(Instruction::OP_ATOMIC_FENCE, instr);
+    }
+
+    #[test]
+    fn test_instruction_try() {
+        let mut counter = Counter::default();
+
+        // try (throw 0) catch 0 nop catch_all (rethrow 0) end
+        let payload = vec![0x06, 0x40, 0x08, 0x00, 0x07, 0x00, 0x01, 0x19, 0x09, 0x00, 0x0b];
+        let (i, instr) = parse_instr(&payload, &mut counter).unwrap();
+        assert!(i.is_empty());
+
+        match instr {
+            Instruction::OP_TRY(BlockType::Empty, body, catches) => {
+                assert_eq!(1, body.get_instructions().len());
+                assert_eq!(&Instruction::OP_THROW(0), body.get_instructions()[0].get_instruction());
+
+                match catches.as_slice() {
+                    [Catch::Tag(0, tag_handler), Catch::All(all_handler)] => {
+                        assert_eq!(&Instruction::OP_NOP, tag_handler.get_instructions()[0].get_instruction());
+                        assert_eq!(&Instruction::OP_RETHROW(0), all_handler.get_instructions()[0].get_instruction());
+                    }
+                    _ => panic!("Unexpected catches {:?}", catches),
+                }
+            }
+            _ => panic!("Unexpected instruction {:?}", instr),
+        }
+
+        // try nop delegate 1
+        let payload = vec![0x06, 0x40, 0x01, 0x18, 0x01];
+        let (i, instr) = parse_instr(&payload, &mut counter).unwrap();
+        assert!(i.is_empty());
+        assert!(matches!(instr, Instruction::OP_TRY_DELEGATE(BlockType::Empty, _, 1)));
+    }
 }
@@ -11,7 +11,7 @@ mod leb128;
 use self::core::*;
 use self::leb128::*;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use byteorder::{ByteOrder, LittleEndian};
 use nom::bytes::complete::take;
 use nom::combinator::complete;
@@ -45,9 +45,13 @@ macro_rules! read_wasm {
 pub fn parse(content: Vec<u8>) -> Result<Module> {
     let slice = content.as_slice();
 
-    let parsed = parse_module(slice).expect("Parsing failed");
+    let (_, sections) = parse_module(slice).map_err(|err| match err {
+        nom::Err::Error((_, kind)) | nom::Err::Failure((_, kind)) => {
+            anyhow!("Parsing failed: {:?}", kind)
+        }
+        nom::Err::Incomplete(_) => anyhow!("Parsing failed: incomplete input"),
+    })?;
 
-    let (_, sections) = parsed;
     Ok(Module { sections })
 }
 
@@ -85,6 +89,8 @@ fn parse_section(i: &[u8]) -> IResult<&[u8], Section> {
         9 => parse_element_section(i, size, &mut counter)?,
         10 => parse_code_section(i, size, &mut counter)?,
         11 => parse_data_section(i, size, &mut counter)?,
+        12 => parse_data_count_section(i, size)?,
+        13 => parse_tag_section(i, size)?,
         _ => panic!("invalid section id"),
     };
 
@@ -153,10 +159,18 @@ fn parse_memory_section(i: &[u8], _size: u32) -> IResult<&[u8], Section> {
     Ok((i, Section::Memory(MemorySection { entries: mem })))
 }
 
-fn parse_global_section<'a, 'b>(
+fn parse_tag_section(i: &[u8], _size: u32) -> IResult<&[u8], Section> {
+    debug!("parse tag section");
+    let (i, times) = take_leb_u32(i)?;
+    let (i, types) = count(take_tagtype, times as usize)(i)?;
+
+    Ok((i, Section::Tag(TagSection { types })))
+}
+
+fn parse_global_section<'a>(
     i: &'a [u8],
     _size: u32,
-    counter: &'b mut Counter,
+    counter: &mut Counter,
 ) -> IResult<&'a [u8], Section> {
     debug!("parse global function");
     let (mut i, times) = take_leb_u32(i)?;
@@ -186,10 +200,10 @@ fn parse_start_section(i: &[u8], _size: u32) -> IResult<&[u8], Section> {
     Ok((i, Section::Start(StartSection { index: func_idx })))
 }
 
-fn parse_element_section<'a, 'b>(
+fn parse_element_section<'a>(
     i: &'a [u8],
     _size: u32,
-    counter: &'b mut Counter,
+    counter: &mut Counter,
 ) -> IResult<&'a [u8], Section> {
     debug!("parse_element_section");
     let (mut i, times) = take_leb_u32(i)?;
@@ -205,10 +219,10 @@ fn parse_element_section<'a, 'b>(
     Ok((i, Section::Element(ElementSection { entries: elements })))
 }
 
-fn parse_data_section<'a, 'b>(
+fn parse_data_section<'a>(
     i: &'a [u8],
     _size: u32,
-    counter: &'b mut Counter,
+    counter: &mut Counter,
 ) -> IResult<&'a [u8], Section> {
     debug!("parse_data_section");
     let (mut i, times) = take_leb_u32(i)?;
@@ -224,10 +238,17 @@ fn parse_data_section<'a, 'b>(
     Ok((i, Section::Data(DataSection { entries })))
 }
 
-fn parse_code_section<'a, 'b>(
+fn parse_data_count_section(i: &[u8], _size: u32) -> IResult<&[u8], Section> {
+    debug!("parse_data_count_section");
+    let (i, count) = take_leb_u32(i)?;
+
+    Ok((i, Section::DataCount(DataCountSection { count })))
+}
+
+fn parse_code_section<'a>(
     i: &'a [u8],
     _size: u32,
-    counter: &'b mut Counter,
+    counter: &mut Counter,
 ) -> IResult<&'a [u8], Section> {
     debug!("parse_code_section");
 
@@ -245,7 +266,7 @@ fn parse_code_section<'a, 'b>(
     Ok((i, Section::Code(CodeSection { entries: codes })))
 }
 
-fn take_code<'a, 'b>(i: &'a [u8], counter: &'b mut Counter) -> IResult<&'a [u8], FunctionBody> {
+fn take_code<'a>(i: &'a [u8], counter: &mut Counter) -> IResult<&'a [u8], FunctionBody> {
     debug!("parse_code");
 
     let (i, _size) = take_leb_u32(i)?;
@@ -254,7 +275,7 @@ fn take_code<'a, 'b>(i: &'a [u8], counter: &'b mut Counter) -> IResult<&'a [u8],
     Ok((i, k))
 }
 
-fn take_func<'a, 'b>(i: &'a [u8], counter: &'b mut Counter) -> IResult<&'a [u8], FunctionBody> {
+fn take_func<'a>(i: &'a [u8], counter: &mut Counter) -> IResult<&'a [u8], FunctionBody> {
     debug!("take_func");
 
     let (i, times) = take_leb_u32(i)?;
@@ -285,11 +306,28 @@ fn take_local(i: &[u8]) -> IResult<&[u8], LocalEntry> {
     Ok((i, LocalEntry { count: n, ty: t }))
 }
 
-fn take_data<'a, 'b>(i: &'a [u8], counter: &'b mut Counter) -> IResult<&'a [u8], DataSegment> {
+fn take_data<'a>(i: &'a [u8], counter: &mut Counter) -> IResult<&'a [u8], DataSegment> {
     debug!("take_data");
 
-    let (i, mem_idx) = take_leb_u32(i)?;
-    let (i, e) = take_expr(i, counter)?;
+    // https://webassembly.github.io/spec/core/binary/modules.html#data-section
+    let (i, flags) = take_leb_u32(i)?;
+
+    let (i, mode) = match flags {
+        0 | 2 => {
+            let (i, memory) = if flags == 2 { take_leb_u32(i)? } else { (i, 0) };
+            let (i, e) = take_expr(i, counter)?;
+
+            (
+                i,
+                DataMode::Active {
+                    memory,
+                    offset: InstructionWrapper::wrap_instructions(counter, e),
+                },
+            )
+        }
+        1 => (i, DataMode::Passive),
+        _ => panic!("Invalid data segment flags {}", flags),
+    };
 
     let (i, times) = take_leb_u32(i)?;
     let (i, b) = count(take(1u8), times as usize)(i)?;
@@ -297,29 +335,87 @@ fn take_data<'a, 'b>(i: &'a [u8], counter: &'b mut Counter) -> IResult<&'a [u8],
     Ok((
         i,
         DataSegment {
-            data: mem_idx,
-            offset: InstructionWrapper::wrap_instructions(counter, e),
+            mode,
             init: b.into_iter().map(|w| w[0]).collect(),
         },
     ))
 }
 
-fn take_elem<'a, 'b>(i: &'a [u8], counter: &'b mut Counter) -> IResult<&'a [u8], ElementSegment> {
+fn take_elem<'a>(i: &'a [u8], counter: &mut Counter) -> IResult<&'a [u8], ElementSegment> {
     debug!("take_elem");
 
-    let (i, table_idx) = take_leb_u32(i)?;
-    let (i, e) = take_expr(i, counter)?;
+    // https://webassembly.github.io/spec/core/binary/modules.html#element-section
+    // Bit 0 marks passive or declarative segments, bit 1 an explicit table index
+    // (or declarative segments) and bit 2 expressions instead of function indices.
+    let (i, flags) = take_leb_u32(i)?;
+
+    if flags > 7 {
+        panic!("Invalid element segment flags {}", flags);
+    }
+
+    let (i, mode) = if flags & 0b001 == 0 {
+        let (i, table) = if flags & 0b010 != 0 { take_leb_u32(i)? } else { (i, 0) };
+        let (i, e) = take_expr(i, counter)?;
+
+        (
+            i,
+            ElementMode::Active {
+                table,
+                offset: InstructionWrapper::wrap_instructions(counter, e),
+            },
+        )
+    } else if flags & 0b010 == 0 {
+        (i, ElementMode::Passive)
+    } else {
+        (i, ElementMode::Declarative)
+    };
+
+    // The element kind or reference type is only given with an explicit table or without mode.
+    // The only element kind 0x00 are functions.
+    let (i, ty) = match (flags & 0b011 != 0, flags & 0b100 != 0) {
+        (false, _) => (i, ValueType::FuncRef),
+        (true, false) => {
+            let (i, kind) = take(1u8)(i)?;
+            assert_eq!([0x00], kind);
+            (i, ValueType::FuncRef)
+        }
+        (true, true) => take_valtype(i)?,
+    };
+
     let (i, times) = take_leb_u32(i)?;
-    let (i, y_vec) = count(take_leb_u32, times as usize)(i)?;
 
-    Ok((
-        i,
-        ElementSegment {
-            table: table_idx,
-            offset: InstructionWrapper::wrap_instructions(counter, e),
-            init: y_vec,
-        },
-    ))
+    let (i, init) = if flags & 0b100 == 0 {
+        let (i, y_vec) = count(take_leb_u32, times as usize)(i)?;
+        (i, y_vec.into_iter().map(Some).collect())
+    } else {
+        count(take_elem_expr, times as usize)(i)?
+    };
+
+    Ok((i, ElementSegment { ty, mode, init }))
+}
+
+/// The expression of an element is a `ref.func` or a `ref.null`
+fn take_elem_expr(i: &[u8]) -> IResult<&[u8], Option<FuncIdx>> {
+    debug!("take_elem_expr");
+
+    let (i, instr) = take(1u8)(i)?;
+
+    let (i, func_idx) = match instr[0] {
+        0xD0 => {
+            let (i, _ref_type) = take(1u8)(i)?;
+            (i, None)
+        }
+        0xD2 => {
+            let (i, func_idx) = take_leb_u32(i)?;
+            (i, Some(func_idx))
+        }
+        _ => panic!("Invalid element expression {}", instr[0]),
+    };
+
+    let (i, e) = take(1u8)(i)?;
+    assert_eq!(e, END_INSTR);
+
+    Ok((i, func_idx))
 }
 
 fn take_export(i: &[u8]) -> IResult<&[u8], ExportEntry> {
@@ -331,7 +427,7 @@ fn take_export(i: &[u8]) -> IResult<&[u8], ExportEntry> {
     Ok((i, ExportEntry { name, kind }))
 }
 
-fn take_global<'a, 'b>(i: &'a [u8], counter: &'b mut Counter) -> IResult<&'a [u8], GlobalVariable> {
+fn take_global<'a>(i: &'a [u8], counter: &mut Counter) -> IResult<&'a [u8], GlobalVariable> {
     let (i, ty) = take_globaltype(i)?;
     let (i, e) = take_expr(i, counter)?;
 
@@ -344,9 +440,9 @@ fn take_global<'a, 'b>(i: &'a [u8], counter: &'b mut Counter) -> IResult<&'a [u8
     ))
 }
 
-pub(crate) fn take_expr<'a, 'b>(
+pub(crate) fn take_expr<'a>(
     mut i: &'a [u8],
-    counter: &'b mut Counter,
+    counter: &mut Counter,
 ) -> IResult<&'a [u8], Vec<Instruction>> {
     debug!("take expr");
 
@@ -394,21 +490,25 @@ fn take_import_desc(i: &[u8]) -> IResult<&[u8], ImportDesc> {
 
     let (i, desc) = match b[0] {
         0x00 => {
-            let (i, t) = take_leb_u32(&i)?;
+            let (i, t) = take_leb_u32(i)?;
             (i, ImportDesc::Function { ty: t })
         }
         0x01 => {
-            let (i, t) = take_tabletype(&i)?;
+            let (i, t) = take_tabletype(i)?;
             (i, ImportDesc::Table { ty: t })
         }
         0x02 => {
-            let (i, t) = take_memtype(&i)?;
+            let (i, t) = take_memtype(i)?;
             (i, ImportDesc::Memory { ty: t })
         }
         0x03 => {
-            let (i, t) = take_globaltype(&i)?;
+            let (i, t) = take_globaltype(i)?;
             (i, ImportDesc::Global { ty: t })
         }
+        0x04 => {
+            let (i, t) = take_tagtype(i)?;
+            (i, ImportDesc::Tag { ty: t })
+        }
         _ => panic!("desc failed"),
     };
 
@@ -422,21 +522,25 @@ fn take_desc(i: &[u8]) -> IResult<&[u8], ExternalKindType> {
 
     let (i, desc) = match b[0] {
         0x00 => {
-            let (i, t) = take_leb_u32(&i)?;
+            let (i, t) = take_leb_u32(i)?;
             (i, ExternalKindType::Function { ty: t })
         }
         0x01 => {
-            let (i, t) = take_leb_u32(&i)?;
+            let (i, t) = take_leb_u32(i)?;
             (i, ExternalKindType::Table { ty: t })
         }
         0x02 => {
-            let (i, t) = take_leb_u32(&i)?;
+            let (i, t) = take_leb_u32(i)?;
             (i, ExternalKindType::Memory { ty: t })
         }
         0x03 => {
-            let (i, t) = take_leb_u32(&i)?;
+            let (i, t) = take_leb_u32(i)?;
             (i, ExternalKindType::Global { ty: t })
         }
+        0x04 => {
+            let (i, t) = take_leb_u32(i)?;
+            (i, ExternalKindType::Tag { ty: t })
+        }
         _ => panic!("desc failed"),
     };
 
@@ -445,26 +549,36 @@ fn take_desc(i: &[u8]) -> IResult<&[u8], ExternalKindType> {
 
 fn take_memtype(i: &[u8]) -> IResult<&[u8], MemoryType> {
     debug!("take_memtype");
-    let (i, l) = take_limits(i)?;
+    let (i, (limits, index_type)) = take_limits(i)?;
 
-    Ok((i, MemoryType { limits: l }))
+    Ok((i, MemoryType { limits, index_type }))
 }
 
 fn take_tabletype(i: &[u8]) -> IResult<&[u8], TableType> {
     debug!("take_tabletype");
-    let (i, element_type) = take(1u8)(i)?;
-    assert_eq!(0x70, element_type[0]);
-    let (i, limits) = take_limits(i)?;
+    let (i, element_type) = take_valtype(i)?;
+    assert!(element_type.is_ref(), "Table must have a reference type");
+    let (i, (limits, index_type)) = take_limits(i)?;
+    assert_eq!(index_type, IndexType::I32, "Table must have 32-bit limits");
 
     Ok((
         i,
         TableType {
-            element_type: 0x70,
+            element_type,
             limits,
         },
     ))
 }
 
+/// Only exceptions are defined as tag attribute
+fn take_tagtype(i: &[u8]) -> IResult<&[u8], TypeIdx> {
+    debug!("take_tagtype");
+    let (i, attribute) = take(1u8)(i)?;
+    assert_eq!(attribute[0], 0x00, "Tag attribute must be exception");
+
+    take_leb_u32(i)
+}
+
 fn take_globaltype(i: &[u8]) -> IResult<&[u8], GlobalType> {
     debug!("take_globaltype");
     let (i, val) = take_valtype(i)?;
@@ -485,24 +599,47 @@ fn take_globaltype(i: &[u8]) -> IResult<&[u8], GlobalType> {
     ))
 }
 
-fn take_limits(i: &[u8]) -> IResult<&[u8], Limits> {
+/// The flags of the limits declare a maximum (0x01), a `shared` memory (0x02)
+/// and 64-bit limits of a memory with the index type `I64` (0x04)
+fn take_limits(i: &[u8]) -> IResult<&[u8], (Limits, IndexType)> {
     debug!("take_limits");
     let (i, n) = take(1u8)(i)?;
+    let flags = n[0];
 
-    Ok(match n[0] {
-        0x00 => {
-            let (i, n) = take_leb_u32(i)?;
+    assert!(flags <= 0x07, "Limit has wrong tag");
 
-            (i, Limits::Zero(n))
-        }
+    let index_type = if flags & 0x04 == 0 {
+        IndexType::I32
+    } else {
+        IndexType::I64
+    };
+
+    let (i, n) = take_bound(i, index_type)?;
+
+    let (i, limits) = match flags & 0x03 {
+        0x00 => (i, Limits::Zero(n)),
         0x01 => {
-            let (i, n) = take_leb_u32(i)?;
-            let (i, m) = take_leb_u32(i)?;
+            let (i, m) = take_bound(i, index_type)?;
 
             (i, Limits::One(n, m))
         }
-        _ => panic!("Limit has wrong tag"),
-    })
+        0x03 => {
+            let (i, m) = take_bound(i, index_type)?;
+
+            (i, Limits::Shared(n, m))
+        }
+        _ => panic!("Shared memory must have a maximum"),
+    };
+
+    Ok((i, (limits, index_type)))
+}
+
+/// The bounds of limits are encoded with the width of the index type
+fn take_bound(i: &[u8], index_type: IndexType) -> IResult<&[u8], u64> {
+    match index_type {
+        IndexType::I32 => take_leb_u32(i).map(|(i, n)| (i, n as u64)),
+        IndexType::I64 => take_leb_u64(i),
+    }
 }
 
 fn take_function_signature(i: &[u8]) -> IResult<&[u8], FunctionSignature> {
@@ -529,7 +666,7 @@ fn take_function_signature(i: &[u8]) -> IResult<&[u8], FunctionSignature> {
     ))
 }
 
-fn take_valtype(i: &[u8]) -> IResult<&[u8], ValueType> {
+pub(crate) fn take_valtype(i: &[u8]) -> IResult<&[u8], ValueType> {
     debug!("take_valtype");
     let (i, n) = take(1u8)(i)?;
 
@@ -543,7 +680,7 @@ fn take_blocktype(i: &[u8]) -> IResult<&[u8], BlockType> {
 
     let (i, bty) = match n[0] {
         0x40 => (u, BlockType::Empty),
-        0x7F | 0x7E | 0x7D | 0x7C => (u, BlockType::ValueType(n[0].into())),
+        0x7B..=0x7F | 0x70 | 0x6F => (u, BlockType::ValueType(n[0].into())),
         _ => {
             // This must be signed 33 bit
             // Weird, Page 96 spec
@@ -602,6 +739,23 @@ pub(crate) fn take_leb_u32(i: &[u8]) -> IResult<&[u8], u32> {
     }
 }
 
+pub(crate) fn take_leb_u64(i: &[u8]) -> IResult<&[u8], u64> {
+    debug!("take_leb_u64");
+    if i.len() >= 10 {
+        let (_, bytes) = take(10u8)(i)?;
+        let leb = read_u64_leb128(bytes);
+        let (i, _) = take(leb.1)(i)?; //skip the bytes, which contain leb
+
+        Ok((i, leb.0))
+    } else {
+        let (_, bytes) = take(i.len())(i)?;
+        let leb = read_u64_leb128(bytes);
+        let (i, _) = take(leb.1)(i)?; //skip the bytes, which contain leb
+
+        Ok((i, leb.0))
+    }
+}
+
 pub(crate) fn take_leb_i32(i: &[u8]) -> IResult<&[u8], i32> {
     debug!("take_leb_i32");
     if i.len() >= 5 {
@@ -765,6 +919,20 @@ mod tests {
         assert_eq!(n, -9223372036854775808);
     }
 
+    #[test]
+    fn test_take_limits() {
+        let (_, limits) = take_limits(&[0x01, 0x01, 0x02]).unwrap();
+        assert_eq!((Limits::One(1, 2), IndexType::I32), limits);
+
+        let (_, limits) = take_limits(&[0x03, 0x01, 0x02]).unwrap();
+        assert_eq!((Limits::Shared(1, 2), IndexType::I32), limits);
+
+        // The bounds of 64-bit memories can exceed `u32`
+        let (i, limits) = take_limits(&[0x05, 0x01, 0x80, 0x80, 0x80, 0x80, 0x20]).unwrap();
+        assert!(i.is_empty());
+        assert_eq!((Limits::One(1, 1 << 33), IndexType::I64), limits);
+    }
+
     #[test]
     fn test_empty_wasm() {
         test_file!("empty.wasm");
@@ -830,4 +998,26 @@ mod tests {
     fn test_global() {
         test_file!("global.wasm");
     }
+
+    #[test]
+    fn test_invalid_prefixed_instruction() {
+        // A module with a function, whose body is `instr`
+        let module = |instr: &[u8]| {
+            let mut body = vec![0x00];
+            body.extend_from_slice(instr);
+            body.push(0x0B);
+
+            let mut bytes = vec![0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+            bytes.extend_from_slice(&[0x01, 0x04, 0x01, 0x60, 0x00, 0x00]);
+            bytes.extend_from_slice(&[0x03, 0x02, 0x01, 0x00]);
+            bytes.extend_from_slice(&[0x0A, body.len() as u8 + 2, 0x01, body.len() as u8]);
+            bytes.extend_from_slice(&body);
+            bytes
+        };
+
+        assert!(parse(module(&[0x01])).is_ok());
+        assert!(parse(module(&[0xFC, 0x20])).is_err());
+        assert!(parse(module(&[0xFD, 0xAC, 0x02])).is_err());
+        assert!(parse(module(&[0xFE, 0x7F])).is_err());
+    }
 }